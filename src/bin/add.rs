@@ -0,0 +1,57 @@
+use std::env;
+use clap::{App, Arg, ArgMatches, SubCommand};
+use mdbook::book::add_chapter;
+use mdbook::errors::*;
+use mdbook::utils::process;
+use {get_book_dir, load_book};
+
+// Create clap subcommand arguments
+pub fn make_subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("add")
+        .about("Add a chapter to SUMMARY.md and create its file")
+        .arg(
+            Arg::with_name("title")
+                .required(true)
+                .help(
+                    "The chapter's title, with any parent chapters in front of it separated by \
+                     slashes{n}(e.g. \"Advanced Topics/Caching\")",
+                ),
+        )
+        .arg_from_usage(
+            "[dir] 'A directory for your book{n}(Defaults to Current Directory when omitted)'",
+        )
+        .arg_from_usage("--no-edit 'Skip opening the new chapter in $EDITOR'")
+}
+
+// Add command implementation
+pub fn execute(args: &ArgMatches) -> Result<()> {
+    let book_dir = get_book_dir(args);
+    let book = load_book(&book_dir, args)?;
+    let title = args.value_of("title").expect("the title is a required argument");
+
+    let src_dir = book.root.join(book.config.book.chapter_dir());
+    let chapter = add_chapter(&src_dir, title)?;
+    info!("Added {}", chapter.display());
+
+    if args.is_present("no-edit") {
+        return Ok(());
+    }
+
+    let editor = env::var("VISUAL").or_else(|_| env::var("EDITOR"));
+    if let Ok(editor) = editor {
+        if editor.trim().is_empty() {
+            return Ok(());
+        }
+
+        let status = process::command(&editor, &[])?
+            .arg(&chapter)
+            .status()
+            .chain_err(|| format!("Unable to run {}", editor))?;
+
+        if !status.success() {
+            warn!("{} exited with {}", editor, status);
+        }
+    }
+
+    Ok(())
+}
@@ -1,14 +1,28 @@
 use std::path::PathBuf;
-use clap::{App, ArgMatches, SubCommand};
-use mdbook::MDBook;
+use clap::{App, Arg, ArgMatches, SubCommand};
 use mdbook::errors::Result;
-use {get_book_dir, open};
+use mdbook::timings;
+use mdbook::versions;
+use mdbook::workspace::Workspace;
+use {config_args, get_book_dir, load_book, open, page_to_open};
 
 // Create clap subcommand arguments
 pub fn make_subcommand<'a, 'b>() -> App<'a, 'b> {
-    SubCommand::with_name("build")
+    let app = SubCommand::with_name("build")
         .about("Build the book from the markdown files")
-        .arg_from_usage("-o, --open 'Open the compiled book in a web browser'")
+        .arg(
+            Arg::with_name("open")
+                .short("o")
+                .long("open")
+                .value_name("file")
+                .takes_value(true)
+                .min_values(0)
+                .require_equals(true)
+                .help(
+                    "Open the compiled book in a web browser{n}\
+                     (Use --open=<file> to open the chapter rendered from that source file)",
+                ),
+        )
         .arg_from_usage(
             "-d, --dest-dir=[dest-dir] 'The output directory for your book{n}(Defaults to ./book \
              when omitted)'",
@@ -16,22 +30,102 @@ pub fn make_subcommand<'a, 'b>() -> App<'a, 'b> {
         .arg_from_usage(
             "[dir] 'A directory for your book{n}(Defaults to Current Directory when omitted)'",
         )
+        .arg_from_usage("--no-analytics 'Leave the analytics out of the HTML output'")
+        .arg_from_usage(
+            "--workspace 'Build every book listed in the directory's mdbook-workspace.toml'",
+        )
+        .arg_from_usage("--parallel 'Build the books of a workspace in parallel'")
+        .arg_from_usage(
+            "--versions 'Build every version listed in book.toml into its own directory'",
+        )
+        .arg_from_usage(
+            "--timings 'Show how long each stage of the build (and each chapter) took'",
+        );
+
+    config_args(
+        app,
+        "Treat unknown keys and invalid values in book.toml (and broken links, with \
+         build.check-links) as errors",
+    )
 }
 
 // Build command implementation
 pub fn execute(args: &ArgMatches) -> Result<()> {
     let book_dir = get_book_dir(args);
-    let mut book = MDBook::load(&book_dir)?;
+
+    if args.is_present("timings") {
+        timings::enable();
+    }
+
+    if args.is_present("workspace") {
+        build_workspace(book_dir, args)?;
+    } else if args.is_present("versions") {
+        build_versions(book_dir, args)?;
+    } else {
+        build_book(book_dir, args)?;
+    }
+
+    if args.is_present("timings") {
+        print!("{}", timings::report(&timings::take()));
+    }
+
+    Ok(())
+}
+
+fn build_book(book_dir: PathBuf, args: &ArgMatches) -> Result<()> {
+    let mut book = load_book(&book_dir, args)?;
 
     if let Some(dest_dir) = args.value_of("dest-dir") {
         book.config.build.build_dir = PathBuf::from(dest_dir);
     }
 
-    book.build()?;
+    book.update_mounts()?;
+    book.with_strict_links(args.is_present("strict")).build()?;
 
     if args.is_present("open") {
         // FIXME: What's the right behaviour if we don't use the HTML renderer?
-        open(book.build_dir_for("html").join("index.html"));
+        let page = page_to_open(&book, args)?;
+        open(book.build_dir_for("html").join(page));
+    }
+
+    Ok(())
+}
+
+fn build_versions(book_dir: PathBuf, args: &ArgMatches) -> Result<()> {
+    let book = load_book(&book_dir, args)?;
+
+    let build_dir = match args.value_of("dest-dir") {
+        Some(dest_dir) => PathBuf::from(dest_dir),
+        None => book.root.join(&book.config.build.build_dir),
+    };
+
+    versions::build_versions(&book.root, &book.config, &build_dir)?;
+
+    if args.is_present("open") {
+        open(build_dir.join("index.html"));
+    }
+
+    Ok(())
+}
+
+fn build_workspace(root: PathBuf, args: &ArgMatches) -> Result<()> {
+    let mut workspace = Workspace::load(root)?;
+
+    if let Some(dest_dir) = args.value_of("dest-dir") {
+        workspace.config.build_dir = PathBuf::from(dest_dir);
+    }
+    workspace.profile = args.value_of("profile").map(String::from);
+    if let Some(overrides) = args.values_of("set") {
+        workspace.overrides.extend(overrides.map(String::from));
+    }
+    if args.is_present("offline") {
+        workspace.overrides.push(String::from("build.offline=true"));
+    }
+
+    workspace.build(args.is_present("parallel"))?;
+
+    if args.is_present("open") {
+        open(workspace.build_dir().join("index.html"));
     }
 
     Ok(())
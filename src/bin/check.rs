@@ -0,0 +1,24 @@
+use clap::{App, ArgMatches, SubCommand};
+use mdbook::errors::Result;
+use {config_args, get_book_dir, load_book, STRICT_HELP};
+
+// Create clap subcommand arguments
+pub fn make_subcommand<'a, 'b>() -> App<'a, 'b> {
+    let app = SubCommand::with_name("check")
+        .about("Build the book and check that the links between chapters work")
+        .arg_from_usage(
+            "[dir] 'A directory for your book{n}(Defaults to Current Directory when omitted)'",
+        );
+
+    config_args(app, STRICT_HELP)
+}
+
+// Check command implementation
+pub fn execute(args: &ArgMatches) -> Result<()> {
+    let book_dir = get_book_dir(args);
+    let mut book = load_book(&book_dir, args)?;
+
+    // the links are checked as part of the build
+    book.config.build.check_links = true;
+    book.with_strict_links(true).build()
+}
@@ -1,7 +1,7 @@
 use std::fs;
-use std::path::PathBuf;
+use std::path::Path;
 use clap::{App, ArgMatches, SubCommand};
-use mdbook::MDBook;
+use mdbook::book::load_config;
 use mdbook::errors::*;
 use get_book_dir;
 
@@ -13,18 +13,75 @@ pub fn make_subcommand<'a, 'b>() -> App<'a, 'b> {
             "-d, --dest-dir=[dest-dir] 'The directory of built book{n}(Defaults to ./book when \
              omitted)'",
         )
+        .arg_from_usage(
+            "[dir] 'A directory for your book{n}(Defaults to Current Directory when omitted)'",
+        )
+        .arg_from_usage("--profile=[profile] 'Apply the overrides from this profile in book.toml'")
+        .arg_from_usage("--cache 'Delete the preprocessors' cache (build.cache-dir) as well'")
 }
 
 // Clean command implementation
 pub fn execute(args: &ArgMatches) -> ::mdbook::errors::Result<()> {
     let book_dir = get_book_dir(args);
-    let book = MDBook::load(&book_dir)?;
+    // only the configuration is needed, and loading the whole book would
+    // also update any mounted books
+    let config = load_config(&book_dir, args.value_of("profile"))?;
+
+    // Relative to the book's root, just like `mdbook build --dest-dir`. Any
+    // per-renderer and per-language directories live inside of it, unless a
+    // renderer has been given a `build-dir` of its own.
+    let mut dirs_to_remove = Vec::new();
+    match args.value_of("dest-dir") {
+        Some(dest_dir) => dirs_to_remove.push(book_dir.join(dest_dir)),
+        None => {
+            let build_dir = book_dir.join(&config.build.build_dir);
+
+            if let Some(outputs) = config.get("output").and_then(|o| o.as_table()) {
+                for name in outputs.keys() {
+                    let key = format!("output.{}.build-dir", name);
+                    if let Some(dir) = config.get(&key).and_then(|dir| dir.as_str()) {
+                        let dir = book_dir.join(dir);
+                        if !dir.starts_with(&build_dir) {
+                            dirs_to_remove.push(dir);
+                        }
+                    }
+                }
+            }
+            dirs_to_remove.push(build_dir);
+        }
+    }
+    if args.is_present("cache") {
+        dirs_to_remove.push(book_dir.join(&config.build.cache_dir));
+    }
 
-    let dir_to_remove = match args.value_of("dest-dir") {
-        Some(dest_dir) => PathBuf::from(dest_dir),
-        None => book.root.join(&book.config.build.build_dir),
-    };
-    fs::remove_dir_all(&dir_to_remove).chain_err(|| "Unable to remove the build directory")?;
+    let protected = [book_dir.clone(), book_dir.join(&config.book.src)];
+    for dir in dirs_to_remove {
+        if !dir.exists() {
+            info!("Nothing to clean, {} doesn't exist", dir.display());
+            continue;
+        }
+
+        if let Some(keep) = protected.iter().find(|keep| would_remove(&dir, keep)) {
+            let message = format!(
+                "Refusing to remove {} because that would remove {} as well",
+                dir.display(),
+                keep.display()
+            );
+            return Err(message.into());
+        }
+
+        info!("Removing {}", dir.display());
+        fs::remove_dir_all(&dir).chain_err(|| "Unable to remove the build directory")?;
+    }
 
     Ok(())
 }
+
+/// Does removing `dir` take `keep` with it, i.e. is `keep` inside of `dir`
+/// (or `dir` itself)?
+fn would_remove(dir: &Path, keep: &Path) -> bool {
+    match (dir.canonicalize(), keep.canonicalize()) {
+        (Ok(dir), Ok(keep)) => keep.starts_with(dir),
+        _ => false,
+    }
+}
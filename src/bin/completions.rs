@@ -0,0 +1,31 @@
+use std::io;
+use clap::{App, Arg, ArgMatches, Shell, SubCommand};
+use mdbook::errors::Result;
+use {create_clap_app, NAME};
+
+// Create clap subcommand arguments
+pub fn make_subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("completions")
+        .about("Print a shell completion script for mdbook")
+        .after_help(
+            "For example, to enable completions in bash:{n}    \
+             mdbook completions bash > /etc/bash_completion.d/mdbook",
+        )
+        .arg(
+            Arg::with_name("shell")
+                .required(true)
+                .possible_values(&Shell::variants())
+                .help("The shell to generate completions for"),
+        )
+}
+
+// Completions command implementation
+pub fn execute(args: &ArgMatches) -> Result<()> {
+    let shell: Shell = args.value_of("shell")
+        .expect("the shell is a required argument")
+        .parse()?;
+
+    create_clap_app().gen_completions_to(NAME, shell, &mut io::stdout());
+
+    Ok(())
+}
@@ -1,8 +1,10 @@
 use std::io;
 use std::io::Write;
-use clap::{App, ArgMatches, SubCommand};
+use std::process::Command;
+use clap::{App, Arg, ArgMatches, SubCommand};
+use tempdir::TempDir;
 use mdbook::MDBook;
-use mdbook::errors::Result;
+use mdbook::errors::*;
 use get_book_dir;
 
 // Create clap subcommand arguments
@@ -13,18 +15,54 @@ pub fn make_subcommand<'a, 'b>() -> App<'a, 'b> {
         .arg_from_usage("[dir] 'A directory for your book{n}(Defaults to Current Directory \
                          when omitted)'")
         .arg_from_usage("--theme 'Copies the default theme into your source folder'")
-        .arg_from_usage("--force 'skip confirmation prompts'")
+        .arg_from_usage("--title=[title] 'Sets the book title'")
+        .arg_from_usage(
+            "--template=[template] 'Scaffold the book from a template directory or git \
+             repository'",
+        )
+        .arg(
+            Arg::with_name("ignore")
+                .long("ignore")
+                .value_name("ignore")
+                .takes_value(true)
+                .possible_values(&["none", "git"])
+                .help("Creates a VCS ignore file (i.e. .gitignore), without asking"),
+        )
+        .arg_from_usage("--force 'Skip confirmation prompts and overwrite existing files'")
 }
 
 // Init command implementation
 pub fn execute(args: &ArgMatches) -> Result<()> {
     let book_dir = get_book_dir(args);
     let mut builder = MDBook::init(&book_dir);
+    let force = args.is_present("force");
+
+    builder.overwrite_existing(force);
+
+    // Keep a cloned template around until the book has been created
+    let mut _template_checkout = None;
+    if let Some(template) = args.value_of("template") {
+        if is_remote(template) {
+            let checkout = clone_template(template)?;
+            builder.with_template(checkout.path());
+            _template_checkout = Some(checkout);
+        } else {
+            builder.with_template(template);
+        }
+    }
+
+    if let Some(title) = args.value_of("title") {
+        let mut config = builder.config().clone();
+        config.book.title = Some(title.to_string());
+        builder.with_config(config);
+    }
 
     // If flag `--theme` is present, copy theme to src
     if args.is_present("theme") {
-        // Skip this if `--force` is present
-        if !args.is_present("force") {
+        // Skip the prompt if `--force` is present
+        if force {
+            builder.copy_theme(true);
+        } else {
             // Print warning
             println!();
             println!(
@@ -41,18 +79,45 @@ pub fn execute(args: &ArgMatches) -> Result<()> {
         }
     }
 
-    println!("\nDo you want a .gitignore to be created? (y/n)");
-
-    if confirm() {
-        builder.create_gitignore(true);
-    }
+    let create_gitignore = match args.value_of("ignore") {
+        Some(ignore) => ignore == "git",
+        None => {
+            println!("\nDo you want a .gitignore to be created? (y/n)");
+            confirm()
+        }
+    };
+    builder.create_gitignore(create_gitignore);
 
     builder.build()?;
-    println!("\nAll done, no errors...");
+    info!("All done, no errors...");
 
     Ok(())
 }
 
+/// Does the template look like something we need to `git clone`?
+fn is_remote(template: &str) -> bool {
+    template.starts_with("https://") || template.starts_with("http://")
+        || template.starts_with("git@") || template.starts_with("ssh://")
+        || template.ends_with(".git")
+}
+
+fn clone_template(url: &str) -> Result<TempDir> {
+    info!("Cloning the template from {}", url);
+    let checkout = TempDir::new("mdbook-template")?;
+
+    let output = Command::new("git")
+        .args(&["clone", "--depth", "1", url])
+        .arg(checkout.path())
+        .output()
+        .chain_err(|| "Unable to run git, is it installed?")?;
+
+    if !output.status.success() {
+        return Err(ErrorKind::Subprocess(format!("Unable to clone {}", url), output).into());
+    }
+
+    Ok(checkout)
+}
+
 // Simple function that user comfirmation
 fn confirm() -> bool {
     io::stdout().flush().unwrap();
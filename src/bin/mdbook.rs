@@ -7,21 +7,35 @@ extern crate error_chain;
 extern crate log;
 extern crate mdbook;
 extern crate open;
+#[macro_use]
+extern crate serde_json;
+extern crate tempdir;
 
 use std::env;
 use std::ffi::OsStr;
 use std::path::{Path, PathBuf};
 use std::io::Write;
-use clap::{App, AppSettings, ArgMatches};
+use clap::{App, AppSettings, Arg, ArgMatches};
 use chrono::Local;
 use log::LevelFilter;
 use env_logger::Builder;
+use mdbook::{BookItem, MDBook};
+use mdbook::book::load_config;
+use mdbook::config;
+use mdbook::diagnostics::{self, Diagnostic, Level};
+use mdbook::errors::{ErrorKind, Result};
 use mdbook::utils;
 
+pub mod add;
 pub mod build;
+pub mod check;
 pub mod clean;
+pub mod completions;
 pub mod init;
+pub mod stats;
+pub mod summary;
 pub mod test;
+pub mod xgettext;
 #[cfg(feature = "serve")]
 pub mod serve;
 #[cfg(feature = "watch")]
@@ -30,50 +44,137 @@ pub mod watch;
 const NAME: &'static str = "mdbook";
 
 fn main() {
-    init_logger();
+    let matches = create_clap_app().get_matches();
+
+    init_logger(verbosity(&matches));
+
+    let json = message_format(&matches) == "json";
+    if json {
+        diagnostics::set_sink(print_json);
+    }
+
+    // Check which subcomamnd the user ran...
+    let res = match matches.subcommand() {
+        ("init", Some(sub_matches)) => init::execute(sub_matches),
+        ("add", Some(sub_matches)) => add::execute(sub_matches),
+        ("build", Some(sub_matches)) => build::execute(sub_matches),
+        ("check", Some(sub_matches)) => check::execute(sub_matches),
+        ("clean", Some(sub_matches)) => clean::execute(sub_matches),
+        ("completions", Some(sub_matches)) => completions::execute(sub_matches),
+        #[cfg(feature = "watch")]
+        ("watch", Some(sub_matches)) => watch::execute(sub_matches),
+        #[cfg(feature = "serve")]
+        ("serve", Some(sub_matches)) => serve::execute(sub_matches),
+        ("stats", Some(sub_matches)) => stats::execute(sub_matches),
+        ("summary", Some(sub_matches)) => summary::execute(sub_matches),
+        ("test", Some(sub_matches)) => test::execute(sub_matches),
+        ("xgettext", Some(sub_matches)) => xgettext::execute(sub_matches),
+        (_, _) => unreachable!(),
+    };
+
+    if let Err(e) = res {
+        if json {
+            print_json(&Diagnostic::from(&e));
+        } else {
+            utils::log_backtrace(&e);
+        }
 
-    // Create a list of valid arguments and sub-commands
+        ::std::process::exit(101);
+    }
+}
+
+/// Create a list of valid arguments and sub-commands
+fn create_clap_app<'a, 'b>() -> App<'a, 'b> {
     let app = App::new(NAME)
                 .about("Create a book in form of a static website from markdown files")
                 .author("Mathieu David <mathieudavid@mathieudavid.org>")
                 // Get the version from our Cargo.toml using clap's crate_version!() macro
                 .version(concat!("v",crate_version!()))
                 .setting(AppSettings::SubcommandRequired)
+                .arg(
+                    Arg::with_name("verbose")
+                        .short("v")
+                        .long("verbose")
+                        .multiple(true)
+                        .global(true)
+                        .conflicts_with("quiet")
+                        .help("Log more details (-vv for even more)"),
+                )
+                .arg(
+                    Arg::with_name("quiet")
+                        .short("q")
+                        .long("quiet")
+                        .multiple(true)
+                        .global(true)
+                        .help("Only log warnings and errors (-qq for only errors)"),
+                )
+                .arg(
+                    Arg::with_name("message-format")
+                        .long("message-format")
+                        .value_name("format")
+                        .takes_value(true)
+                        .possible_values(&["human", "json"])
+                        .global(true)
+                        .help("Report problems with the book as human readable log \
+                               messages (the default) or as JSON lines on stdout"),
+                )
                 .after_help("For more information about a specific command, \
                              try `mdbook <command> --help`\n\
                              Source code for mdbook available \
                              at: https://github.com/rust-lang-nursery/mdBook")
                 .subcommand(init::make_subcommand())
+                .subcommand(add::make_subcommand())
                 .subcommand(build::make_subcommand())
                 .subcommand(test::make_subcommand())
-                .subcommand(clean::make_subcommand());
+                .subcommand(check::make_subcommand())
+                .subcommand(clean::make_subcommand())
+                .subcommand(stats::make_subcommand())
+                .subcommand(summary::make_subcommand())
+                .subcommand(xgettext::make_subcommand())
+                .subcommand(completions::make_subcommand());
 
     #[cfg(feature = "watch")]
     let app = app.subcommand(watch::make_subcommand());
     #[cfg(feature = "serve")]
     let app = app.subcommand(serve::make_subcommand());
 
-    // Check which subcomamnd the user ran...
-    let res = match app.get_matches().subcommand() {
-        ("init", Some(sub_matches)) => init::execute(sub_matches),
-        ("build", Some(sub_matches)) => build::execute(sub_matches),
-        ("clean", Some(sub_matches)) => clean::execute(sub_matches),
-        #[cfg(feature = "watch")]
-        ("watch", Some(sub_matches)) => watch::execute(sub_matches),
-        #[cfg(feature = "serve")]
-        ("serve", Some(sub_matches)) => serve::execute(sub_matches),
-        ("test", Some(sub_matches)) => test::execute(sub_matches),
-        (_, _) => unreachable!(),
-    };
+    app
+}
 
-    if let Err(e) = res {
-        utils::log_backtrace(&e);
+/// The log level asked for with `-v` or `-q`, if any. These can be given
+/// before or after the subcommand.
+fn verbosity(matches: &ArgMatches) -> Option<LevelFilter> {
+    let args = matches.subcommand().1.unwrap_or(matches);
 
-        ::std::process::exit(101);
+    match (args.occurrences_of("verbose"), args.occurrences_of("quiet")) {
+        (0, 0) => None,
+        (1, _) => Some(LevelFilter::Debug),
+        (_, 0) => Some(LevelFilter::Trace),
+        (_, 1) => Some(LevelFilter::Warn),
+        (_, _) => Some(LevelFilter::Error),
+    }
+}
+
+/// The `--message-format` asked for, which can also be given before or after
+/// the subcommand.
+fn message_format<'a>(matches: &'a ArgMatches) -> &'a str {
+    let args = matches.subcommand().1.unwrap_or(matches);
+
+    args.value_of("message-format").unwrap_or("human")
+}
+
+/// Print a diagnostic as a single line of JSON, for `--message-format=json`.
+fn print_json(diagnostic: &Diagnostic) {
+    match serde_json::to_string(diagnostic) {
+        Ok(line) => println!("{}", line),
+        Err(e) => error!("Unable to serialize \"{}\": {}", diagnostic, e),
     }
 }
 
-fn init_logger() {
+/// Log to stderr at the `Info` level, unless `RUST_LOG` or the verbosity
+/// flags say otherwise. Module-specific directives from `RUST_LOG` (e.g.
+/// `mdbook::renderer=trace`) still apply when a flag is given.
+fn init_logger(verbosity: Option<LevelFilter>) {
     let mut builder = Builder::new();
 
     builder.format(|formatter, record| {
@@ -84,11 +185,14 @@ fn init_logger() {
                  record.args())
     });
 
+    // default to logging at the Info level
+    builder.filter(None, LevelFilter::Info);
+
     if let Ok(var) = env::var("RUST_LOG") {
         builder.parse(&var);
-    } else {
-        // if no RUST_LOG provided, default to logging at the Info level
-        builder.filter(None, LevelFilter::Info);
+    }
+    if let Some(level) = verbosity {
+        builder.filter(None, level);
     }
 
     builder.init();
@@ -108,6 +212,107 @@ fn get_book_dir(args: &ArgMatches) -> PathBuf {
     }
 }
 
+/// What `--strict` does for the subcommands which don't check links.
+const STRICT_HELP: &str = "Treat unknown keys and invalid values in book.toml as errors";
+
+/// Add the arguments `load_book()` understands for adjusting the book's
+/// configuration: `--profile`, `--strict`, `--offline` and `--set`.
+fn config_args<'a, 'b>(app: App<'a, 'b>, strict_help: &'b str) -> App<'a, 'b> {
+    app.arg_from_usage("--profile=[profile] 'Apply the overrides from this profile in book.toml'")
+        .arg(Arg::with_name("strict").long("strict").help(strict_help))
+        .arg_from_usage(
+            "--offline 'Take remote includes from the cache instead of downloading them'",
+        )
+        .arg(
+            Arg::with_name("set")
+                .long("set")
+                .value_name("key=value")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .help("Override a book.toml key, e.g. output.html.curly-quotes=true"),
+        )
+}
+
+/// Load the book in `book_dir`, applying the configuration profile selected
+/// with `--profile`, any `--set` overrides, `--offline` and `--no-analytics`.
+///
+/// With `--strict`, any problems found in the `book.toml` are treated as
+/// errors instead of being logged as warnings.
+fn load_book(book_dir: &Path, args: &ArgMatches) -> Result<MDBook> {
+    let config_location = book_dir.join("book.toml");
+
+    if args.is_present("strict") && config_location.exists() {
+        let problems = config::validate_file(&config_location, args.value_of("profile"))?;
+
+        for problem in &problems {
+            diagnostics::report(
+                Diagnostic::new(Level::Error, problem.message.clone())
+                    .at(&config_location, problem.line),
+            );
+        }
+
+        if !problems.is_empty() {
+            let message = format!("found {} problem(s)", problems.len());
+            return Err(ErrorKind::Config(config_location, message).into());
+        }
+    }
+
+    let mut config = load_config(book_dir, args.value_of("profile"))?;
+
+    if let Some(overrides) = args.values_of("set") {
+        for assignment in overrides {
+            config.apply_override(assignment)?;
+        }
+    }
+
+    if args.is_present("offline") {
+        config.build.offline = true;
+    }
+
+    // only touch the HTML renderer's config when the book uses it, since
+    // that's what decides whether it runs
+    if args.is_present("no-analytics") && config.get("output.html").is_some() {
+        config.set("output.html.analytics.enabled", false)?;
+    }
+
+    MDBook::load_with_config(book_dir, config)
+}
+
+/// Figure out which page `--open` should show, relative to the HTML output
+/// directory. This is the book's index, unless a chapter's source file was
+/// given with `--open=<file>`.
+fn page_to_open(book: &MDBook, args: &ArgMatches) -> Result<PathBuf> {
+    let mut page = PathBuf::new();
+    if book.config.book.multilingual {
+        page.push(book.config.book.language_or_default());
+    }
+
+    let file = match args.value_of("open") {
+        Some(file) => Path::new(file),
+        None => {
+            page.push("index.html");
+            return Ok(page);
+        }
+    };
+
+    // accept paths relative to either the current directory or the chapters
+    let chapter_dir = book.root.join(book.config.book.chapter_dir());
+    let absolute = env::current_dir()?.join(file);
+    let chapter = absolute.strip_prefix(&chapter_dir).unwrap_or(file);
+
+    let is_chapter = book.iter().any(|item| match *item {
+        BookItem::Chapter(ref ch) => ch.path == chapter,
+        _ => false,
+    });
+    if !is_chapter {
+        return Err(format!("{} isn't one of the book's chapters", file.display()).into());
+    }
+
+    page.push(book.html_page_for(chapter));
+    Ok(page)
+}
+
 fn open<P: AsRef<OsStr>>(path: P) {
     if let Err(e) = open::that(path) {
         error!("Error opening web browser: {}", e);
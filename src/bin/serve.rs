@@ -3,13 +3,15 @@ extern crate staticfile;
 extern crate ws;
 
 use std;
+use std::cell::RefCell;
+use std::net::TcpListener;
 use self::iron::{status, AfterMiddleware, Chain, Iron, IronError, IronResult, Request, Response,
                  Set};
-use clap::{App, ArgMatches, SubCommand};
-use mdbook::MDBook;
+use clap::{App, Arg, ArgMatches, SubCommand};
+use mdbook::book::BuildState;
 use mdbook::utils;
 use mdbook::errors::*;
-use {get_book_dir, open};
+use {config_args, get_book_dir, load_book, open, page_to_open, STRICT_HELP};
 #[cfg(feature = "watch")]
 use watch;
 
@@ -17,45 +19,107 @@ struct ErrorRecover;
 
 // Create clap subcommand arguments
 pub fn make_subcommand<'a, 'b>() -> App<'a, 'b> {
-    SubCommand::with_name("serve")
+    let app = SubCommand::with_name("serve")
         .about("Serve the book at http://localhost:3000. Rebuild and reload on change.")
         .arg_from_usage(
             "[dir] 'A directory for your book{n}(Defaults to Current Directory when omitted)'",
         )
-        .arg_from_usage("-p, --port=[port] 'Use another port{n}(Defaults to 3000)'")
+        .arg_from_usage(
+            "-p, --port=[port] 'Use another port{n}(Defaults to 3000, or the next free port)'",
+        )
         .arg_from_usage(
             "-w, --websocket-port=[ws-port] 'Use another port for the websocket connection \
-             (livereload){n}(Defaults to 3001)'",
+             (livereload){n}(Defaults to 3001, or the next free port)'",
         )
         .arg_from_usage(
             "-i, --interface=[interface] 'Interface to listen on{n}(Defaults to localhost)'",
         )
+        .arg(
+            Arg::with_name("address")
+                .short("a")
+                .long("address")
+                .visible_alias("hostname")
+                .value_name("hostname")
+                .takes_value(true)
+                .help(
+                    "Hostname that the browser can reach the websocket server \
+                     from{n}(Defaults to the interface address)",
+                ),
+        )
         .arg_from_usage(
-            "-a, --address=[address] 'Address that the browser can reach the websocket server \
-             from{n}(Defaults to the interface address)'",
+            "--ws-url=[url] 'Full URL of the live-reload websocket, e.g. when it sits behind a \
+             proxy{n}(Overrides --hostname and --websocket-port)'",
         )
-        .arg_from_usage("-o, --open 'Open the book server in a web browser'")
+        .arg(
+            Arg::with_name("open")
+                .short("o")
+                .long("open")
+                .value_name("file")
+                .takes_value(true)
+                .min_values(0)
+                .require_equals(true)
+                .help(
+                    "Open the book server in a web browser{n}\
+                     (Use --open=<file> to open the chapter rendered from that source file)",
+                ),
+        )
+        .arg_from_usage("--no-analytics 'Leave the analytics out of the HTML output'")
+        .arg_from_usage(
+            "--exec=[command] 'Run this command after every successful rebuild{n}\
+             (Overrides build.hooks.after-rebuild in book.toml)'",
+        );
+
+    config_args(app, STRICT_HELP)
 }
 
 // Watch command implementation
 pub fn execute(args: &ArgMatches) -> Result<()> {
     let book_dir = get_book_dir(args);
-    let mut book = MDBook::load(&book_dir)?;
-
-    let port = args.value_of("port").unwrap_or("3000");
-    let ws_port = args.value_of("websocket-port").unwrap_or("3001");
-    let interface = args.value_of("interface").unwrap_or("localhost");
-    let public_address = args.value_of("address").unwrap_or(interface);
+    let mut book = load_book(&book_dir, args)?;
+
+    let serve_config = book.config
+        .html_config()
+        .map(|html| html.serve)
+        .unwrap_or_default();
+
+    let interface = args.value_of("interface")
+        .unwrap_or(&serve_config.interface)
+        .to_string();
+    let port = match args.value_of("port") {
+        Some(port) => port.parse().chain_err(|| "Invalid port")?,
+        None => serve_config.port,
+    };
+    let ws_port = match args.value_of("websocket-port") {
+        Some(port) => port.parse().chain_err(|| "Invalid websocket port")?,
+        None => serve_config.websocket_port,
+    };
+
+    let port = find_free_port(&interface, port, &[])?;
+    let ws_port = find_free_port(&interface, ws_port, &[port])?;
+
+    let interface = interface.as_str();
     let open_browser = args.is_present("open");
 
     let address = format!("{}:{}", interface, port);
     let ws_address = format!("{}:{}", interface, ws_port);
 
-    let livereload_url = format!("ws://{}:{}", public_address, ws_port);
+    let ws_url = args.value_of("ws-url")
+        .or(serve_config.ws_url.as_ref().map(|url| url.as_str()));
+    let livereload_url = match ws_url {
+        Some(url) => url.to_string(),
+        None => {
+            let hostname = args.value_of("address")
+                .or(serve_config.hostname.as_ref().map(|host| host.as_str()))
+                .unwrap_or(interface);
+            format!("ws://{}:{}", hostname, ws_port)
+        }
+    };
     book.config
         .set("output.html.livereload-url", &livereload_url)?;
 
-    book.build()?;
+    // only rebuild the chapters which changed from now on
+    let state = RefCell::new(BuildState::new());
+    book.build_incremental(&mut state.borrow_mut())?;
 
     let mut chain = Chain::new(staticfile::Static::new(book.build_dir_for("html")));
     chain.link_after(ErrorRecover);
@@ -76,36 +140,70 @@ pub fn execute(args: &ArgMatches) -> Result<()> {
     info!("Serving on: {}", serving_url);
 
     if open_browser {
-        open(serving_url);
+        let page = page_to_open(&book, args)?;
+        let page = page.to_string_lossy().replace('\\', "/");
+        open(format!("{}/{}", serving_url, page));
     }
 
     #[cfg(feature = "watch")]
-    watch::trigger_on_change(&mut book, move |path, book_dir| {
-        info!("File changed: {:?}", path);
+    watch::trigger_on_change(&mut book, move |paths, book_dir| {
+        info!("Files changed: {:?}", paths);
         info!("Building book...");
 
         // FIXME: This area is really ugly because we need to re-set livereload :(
 
         let livereload_url = livereload_url.clone();
 
-        let result = MDBook::load(&book_dir)
+        let result = load_book(book_dir, args)
             .and_then(move |mut b| {
                 b.config.set("output.html.livereload-url", &livereload_url)?;
+                watch::exec_after_rebuild(&mut b, args);
                 Ok(b)
             })
-            .and_then(|b| b.build());
-
-        if let Err(e) = result {
-            error!("Unable to load the book");
-            utils::log_backtrace(&e);
-        } else {
-            let _ = broadcaster.send("reload");
+            .and_then(|b| {
+                b.build_incremental(&mut state.borrow_mut())?;
+                Ok(b)
+            });
+
+        match result {
+            Ok(b) => {
+                let _ = broadcaster.send("reload");
+                watch::after_rebuild(&b);
+            }
+            Err(e) => {
+                error!("Unable to load the book");
+                utils::log_backtrace(&e);
+            }
         }
     });
 
     Ok(())
 }
 
+/// How many ports to try before giving up on finding a free one.
+const MAX_PORT_ATTEMPTS: u16 = 10;
+
+/// Find a port we can listen on, starting with `port` and moving on to the
+/// next one whenever it's already in use (or in `taken`).
+fn find_free_port(interface: &str, port: u16, taken: &[u16]) -> Result<u16> {
+    let last = port.saturating_add(MAX_PORT_ATTEMPTS - 1);
+
+    for candidate in port..=last {
+        if taken.contains(&candidate) {
+            continue;
+        }
+
+        if TcpListener::bind((interface, candidate)).is_ok() {
+            if candidate != port {
+                warn!("Port {} is already in use, using {} instead", port, candidate);
+            }
+            return Ok(candidate);
+        }
+    }
+
+    Err(format!("Unable to find a free port between {} and {}", port, last).into())
+}
+
 impl AfterMiddleware for ErrorRecover {
     fn catch(&self, _: &mut Request, err: IronError) -> IronResult<Response> {
         match err.response.status {
@@ -0,0 +1,127 @@
+use clap::{App, ArgMatches, SubCommand};
+use mdbook::BookItem;
+use mdbook::book::{Book, Stats, DEFAULT_WORDS_PER_MINUTE};
+use mdbook::errors::*;
+use serde_json;
+use {get_book_dir, load_book};
+
+// Create clap subcommand arguments
+pub fn make_subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("stats")
+        .about("Count the words, code blocks and images in each chapter")
+        .arg_from_usage(
+            "[dir] 'A directory for your book{n}(Defaults to Current Directory when omitted)'",
+        )
+        .arg_from_usage("--json 'Print the statistics as JSON instead of a table'")
+        .arg_from_usage("--profile=[profile] 'Apply the overrides from this profile in book.toml'")
+}
+
+struct ChapterStats {
+    name: String,
+    path: String,
+    stats: Stats,
+    reading_time: usize,
+}
+
+// Stats command implementation
+pub fn execute(args: &ArgMatches) -> Result<()> {
+    let book_dir = get_book_dir(args);
+    let book = load_book(&book_dir, args)?;
+
+    let words_per_minute = book.config
+        .html_config()
+        .map_or(DEFAULT_WORDS_PER_MINUTE, |html| html.words_per_minute);
+
+    // count what ends up in the rendered book, e.g. including included files
+    let chapters = chapter_stats(&book.preprocessed_book("html")?, words_per_minute);
+    let mut total = Stats::default();
+    for ch in &chapters {
+        total.add(&ch.stats);
+    }
+    let total_reading_time = total.reading_time(words_per_minute);
+
+    if args.is_present("json") {
+        let chapters: Vec<_> = chapters
+            .iter()
+            .map(|ch| {
+                json!({
+                    "name": ch.name,
+                    "path": ch.path,
+                    "words": ch.stats.words,
+                    "code_blocks": ch.stats.code_blocks,
+                    "images": ch.stats.images,
+                    "reading_time": ch.reading_time,
+                })
+            })
+            .collect();
+        let json = json!({
+            "chapters": chapters,
+            "total": {
+                "words": total.words,
+                "code_blocks": total.code_blocks,
+                "images": total.images,
+                "reading_time": total_reading_time,
+            },
+        });
+        let json = serde_json::to_string_pretty(&json).chain_err(|| "Unable to serialize")?;
+        println!("{}", json);
+        return Ok(());
+    }
+
+    let width = chapters
+        .iter()
+        .map(|ch| ch.name.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max("Chapter".len());
+
+    println!(
+        "{:<width$}  {:>7}  {:>5}  {:>6}  {:>7}",
+        "Chapter",
+        "Words",
+        "Code",
+        "Images",
+        "Minutes",
+        width = width
+    );
+    for ch in &chapters {
+        print_row(&ch.name, &ch.stats, ch.reading_time, width);
+    }
+    print_row("Total", &total, total_reading_time, width);
+
+    Ok(())
+}
+
+fn chapter_stats(book: &Book, words_per_minute: usize) -> Vec<ChapterStats> {
+    book.iter()
+        .filter_map(|item| match *item {
+            BookItem::Chapter(ref ch) => {
+                let stats = Stats::from_markdown(&ch.content);
+                let name = match ch.number {
+                    Some(ref number) => format!("{} {}", number, ch.name),
+                    None => ch.name.clone(),
+                };
+
+                Some(ChapterStats {
+                    name,
+                    path: ch.path.display().to_string(),
+                    stats,
+                    reading_time: stats.reading_time(words_per_minute),
+                })
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+fn print_row(name: &str, stats: &Stats, reading_time: usize, width: usize) {
+    println!(
+        "{:<width$}  {:>7}  {:>5}  {:>6}  {:>7}",
+        name,
+        stats.words,
+        stats.code_blocks,
+        stats.images,
+        reading_time,
+        width = width
+    );
+}
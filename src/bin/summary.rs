@@ -0,0 +1,40 @@
+use std::fs::File;
+use std::io::Write;
+use clap::{App, ArgMatches, SubCommand};
+use mdbook::book::{generate_summary, load_config};
+use mdbook::errors::*;
+use get_book_dir;
+
+// Create clap subcommand arguments
+pub fn make_subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("summary")
+        .about("Generate a SUMMARY.md from the layout of the source directory")
+        .arg_from_usage(
+            "[dir] 'A directory for your book{n}(Defaults to Current Directory when omitted)'",
+        )
+        .arg_from_usage(
+            "-w, --write 'Write the generated summary to SUMMARY.md instead of printing it'",
+        )
+        .arg_from_usage("--profile=[profile] 'Apply the overrides from this profile in book.toml'")
+}
+
+// Summary command implementation
+pub fn execute(args: &ArgMatches) -> Result<()> {
+    let book_dir = get_book_dir(args);
+    let config = load_config(&book_dir, args.value_of("profile"))?;
+    let src_dir = book_dir.join(config.book.chapter_dir());
+
+    let summary = generate_summary(&src_dir)?;
+
+    if args.is_present("write") {
+        let summary_md = src_dir.join("SUMMARY.md");
+        File::create(&summary_md)
+            .chain_err(|| "Unable to create SUMMARY.md")?
+            .write_all(summary.as_bytes())?;
+        info!("Wrote {}", summary_md.display());
+    } else {
+        print!("{}", summary);
+    }
+
+    Ok(())
+}
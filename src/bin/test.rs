@@ -1,15 +1,28 @@
-use clap::{App, ArgMatches, SubCommand};
-use mdbook::MDBook;
+use clap::{App, Arg, ArgMatches, SubCommand};
 use mdbook::errors::Result;
-use get_book_dir;
+use {config_args, get_book_dir, load_book, STRICT_HELP};
 
 // Create clap subcommand arguments
 pub fn make_subcommand<'a, 'b>() -> App<'a, 'b> {
-    SubCommand::with_name("test")
+    let app = SubCommand::with_name("test")
         .about("Test that code samples compile")
         .arg_from_usage(
             "-L, --library-path [DIR]... 'directory to add to crate search path'",
         )
+        .arg_from_usage(
+            "-c, --chapter=[chapter] 'Only test the chapter with this name or source file'",
+        )
+        .arg(
+            Arg::with_name("extern")
+                .long("extern")
+                .value_name("name=path")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .help("Make a pre-built crate available to code samples, e.g. serde=libserde.rlib"),
+        );
+
+    config_args(app, STRICT_HELP)
 }
 
 // test command implementation
@@ -17,10 +30,13 @@ pub fn execute(args: &ArgMatches) -> Result<()> {
     let library_paths: Vec<&str> = args.values_of("library-path")
                                        .map(|v| v.collect())
                                        .unwrap_or_default();
+    let externs: Vec<&str> = args.values_of("extern")
+                                 .map(|v| v.collect())
+                                 .unwrap_or_default();
     let book_dir = get_book_dir(args);
-    let mut book = MDBook::load(&book_dir)?;
+    let mut book = load_book(&book_dir, args)?;
 
-    book.test(library_paths)?;
+    book.test_with(library_paths, externs, args.value_of("chapter"))?;
 
     Ok(())
 }
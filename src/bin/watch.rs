@@ -1,60 +1,121 @@
+extern crate gitignore;
+extern crate glob;
 extern crate notify;
 
-use std::path::Path;
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
 use self::notify::Watcher;
-use std::time::Duration;
-use std::sync::mpsc::channel;
-use clap::{App, ArgMatches, SubCommand};
+use std::time::{Duration, SystemTime};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use clap::{App, Arg, ArgMatches, SubCommand};
 use mdbook::MDBook;
+use mdbook::book::BuildState;
 use mdbook::utils;
 use mdbook::errors::Result;
-use {get_book_dir, open};
+use {config_args, get_book_dir, load_book, open, page_to_open, STRICT_HELP};
 
 // Create clap subcommand arguments
 pub fn make_subcommand<'a, 'b>() -> App<'a, 'b> {
-    SubCommand::with_name("watch")
+    let app = SubCommand::with_name("watch")
         .about("Watch the files for changes")
-        .arg_from_usage("-o, --open 'Open the compiled book in a web browser'")
+        .arg(
+            Arg::with_name("open")
+                .short("o")
+                .long("open")
+                .value_name("file")
+                .takes_value(true)
+                .min_values(0)
+                .require_equals(true)
+                .help(
+                    "Open the compiled book in a web browser{n}\
+                     (Use --open=<file> to open the chapter rendered from that source file)",
+                ),
+        )
         .arg_from_usage(
             "[dir] 'A directory for your book{n}(Defaults to Current Directory when omitted)'",
         )
+        .arg_from_usage("--no-analytics 'Leave the analytics out of the HTML output'")
+        .arg_from_usage(
+            "--exec=[command] 'Run this command after every successful rebuild{n}\
+             (Overrides build.hooks.after-rebuild in book.toml)'",
+        );
+
+    config_args(app, STRICT_HELP)
 }
 
 // Watch command implementation
 pub fn execute(args: &ArgMatches) -> Result<()> {
     let book_dir = get_book_dir(args);
-    let book = MDBook::load(&book_dir)?;
+    let book = load_book(&book_dir, args)?;
+
+    // only rebuild the chapters which changed from now on
+    let state = RefCell::new(BuildState::new());
 
     if args.is_present("open") {
-        book.build()?;
-        open(book.build_dir_for("html").join("index.html"));
+        book.build_incremental(&mut state.borrow_mut())?;
+        let page = page_to_open(&book, args)?;
+        open(book.build_dir_for("html").join(page));
     }
 
-    trigger_on_change(&book, |path, book_dir| {
-        info!("File changed: {:?}\nBuilding book...\n", path);
-        let result = MDBook::load(&book_dir).and_then(|b| b.build());
+    trigger_on_change(&book, |paths, book_dir| {
+        info!("Files changed: {:?}\nBuilding book...\n", paths);
+        let result = load_book(book_dir, args).and_then(|mut b| {
+            exec_after_rebuild(&mut b, args);
+            b.build_incremental(&mut state.borrow_mut())?;
+            Ok(b)
+        });
 
-        if let Err(e) = result {
-            error!("Unable to build the book");
-            utils::log_backtrace(&e);
+        match result {
+            Ok(b) => after_rebuild(&b),
+            Err(e) => {
+                error!("Unable to build the book");
+                utils::log_backtrace(&e);
+            }
         }
     });
 
     Ok(())
 }
 
-/// Calls the closure when a book source file is changed, blocking indefinitely.
+/// Use the command given with `--exec` (if any) as the after-rebuild hook.
+pub fn exec_after_rebuild(book: &mut MDBook, args: &ArgMatches) {
+    if let Some(command) = args.value_of("exec") {
+        book.config.build.hooks.after_rebuild = Some(command.to_string());
+    }
+}
+
+/// Run the after-rebuild hooks once the book has been rebuilt. A failing hook
+/// is logged, and the book is watched as before.
+pub fn after_rebuild(book: &MDBook) {
+    if let Err(e) = book.after_rebuild() {
+        error!("Unable to run the after-rebuild hook");
+        utils::log_backtrace(&e);
+    }
+}
+
+/// How long notify waits before reporting an event, which lets it merge the
+/// several events editors tend to cause when saving a single file.
+const NOTIFY_DELAY_MS: u64 = 100;
+
+/// Calls the closure when book source files are changed, blocking indefinitely.
+///
+/// Changes are batched until none have come in for `build.watch-debounce`
+/// milliseconds, and anything changed while the closure runs is collected into
+/// the next batch, so a burst of changes results in a single call. A call
+/// isn't interrupted when more changes come in, it's followed by one more
+/// call for all of them instead. Events which queued up during a call for
+/// files that haven't changed since it started are dropped, as the closure
+/// already saw those changes.
 pub fn trigger_on_change<F>(book: &MDBook, closure: F)
 where
-    F: Fn(&Path, &Path),
+    F: Fn(&[PathBuf], &Path),
 {
     use self::notify::RecursiveMode::*;
-    use self::notify::DebouncedEvent::*;
 
     // Create a channel to receive the events.
     let (tx, rx) = channel();
 
-    let mut watcher = match notify::watcher(tx, Duration::from_secs(1)) {
+    let mut watcher = match notify::watcher(tx, Duration::from_millis(NOTIFY_DELAY_MS)) {
         Ok(w) => w,
         Err(e) => {
             error!("Error while trying to watch the files:\n\n\t{:?}", e);
@@ -73,15 +134,227 @@ where
     // Add the book.toml file to the watcher if it exists
     let _ = watcher.watch(book.root.join("book.toml"), NonRecursive);
 
+    let gitignore_path = book.root.join(".gitignore");
+    let ignore = IgnoreFilter::new(book, &gitignore_path);
+
+    let debounce = Duration::from_millis(book.config.build.watch_debounce);
+
     info!("Listening for changes...");
 
-    for event in rx.iter() {
-        debug!("Received filesystem event: {:?}", event);
-        match event {
-            Create(path) | Write(path) | Remove(path) | Rename(_, path) => {
-                closure(&path, &book.root);
+    let mut paths = Vec::new();
+
+    while next_batch(&rx, debounce, &ignore, &mut paths) {
+        if paths.is_empty() {
+            continue;
+        }
+
+        let started = SystemTime::now();
+        closure(&paths, &book.root);
+
+        // Whatever changed while the closure ran starts the next batch
+        paths = changed_during(&rx, &ignore, started);
+        if !paths.is_empty() {
+            debug!("Changed during the rebuild: {:?}", paths);
+        }
+    }
+}
+
+/// Adds the next batch of changes to `paths`: waits for a change (unless
+/// there already are some), then keeps collecting until things have been
+/// quiet for `debounce`. Returns `false` once the watcher has gone away.
+fn next_batch(
+    rx: &Receiver<notify::DebouncedEvent>,
+    debounce: Duration,
+    ignore: &IgnoreFilter,
+    paths: &mut Vec<PathBuf>,
+) -> bool {
+    if paths.is_empty() {
+        match rx.recv() {
+            Ok(event) => add_changed_path(event, ignore, paths),
+            Err(_) => return false,
+        }
+    }
+
+    loop {
+        match rx.recv_timeout(debounce) {
+            Ok(event) => add_changed_path(event, ignore, paths),
+            Err(RecvTimeoutError::Timeout) => return true,
+            Err(RecvTimeoutError::Disconnected) => return false,
+        }
+    }
+}
+
+/// The files changed since `started`, from the events which queued up in the
+/// meantime. The events for changes made before then are stale.
+fn changed_during(
+    rx: &Receiver<notify::DebouncedEvent>,
+    ignore: &IgnoreFilter,
+    started: SystemTime,
+) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    while let Ok(event) = rx.try_recv() {
+        add_changed_path(event, ignore, &mut paths);
+    }
+    paths.retain(|path| changed_since(path, started));
+    paths
+}
+
+/// Records the file touched by a filesystem event, unless it's ignored or
+/// already part of the batch.
+fn add_changed_path(
+    event: notify::DebouncedEvent,
+    ignore: &IgnoreFilter,
+    paths: &mut Vec<PathBuf>,
+) {
+    use self::notify::DebouncedEvent::*;
+
+    debug!("Received filesystem event: {:?}", event);
+    match event {
+        Create(path) | Write(path) | Remove(path) | Rename(_, path) => {
+            if ignore.is_ignored(&path) {
+                debug!("Ignoring change to {}", path.display());
+            } else if !paths.contains(&path) {
+                paths.push(path);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Was the file at `path` modified at or after `time`? Files which were
+/// removed (or can't be looked at) count as changed.
+fn changed_since(path: &Path, time: SystemTime) -> bool {
+    match path.metadata().and_then(|m| m.modified()) {
+        Ok(modified) => modified >= time,
+        Err(_) => true,
+    }
+}
+
+/// Decides which changed files aren't worth a rebuild: anything in the build
+/// directory, anything matched by the book's `.gitignore`, and anything
+/// matched by the `build.watch-ignore` globs.
+struct IgnoreFilter<'a> {
+    root: PathBuf,
+    build_dir: PathBuf,
+    gitignore: Option<gitignore::File<'a>>,
+    patterns: Vec<glob::Pattern>,
+}
+
+impl<'a> IgnoreFilter<'a> {
+    fn new(book: &MDBook, gitignore_path: &'a Path) -> IgnoreFilter<'a> {
+        let gitignore = if gitignore_path.exists() {
+            match gitignore::File::new(gitignore_path) {
+                Ok(file) => Some(file),
+                Err(e) => {
+                    warn!("Unable to read {}: {}", gitignore_path.display(), e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        let patterns = book.config
+            .build
+            .watch_ignore
+            .iter()
+            .filter_map(|pattern| match glob::Pattern::new(pattern) {
+                Ok(pattern) => Some(pattern),
+                Err(e) => {
+                    warn!("Invalid watch-ignore pattern \"{}\": {}", pattern, e);
+                    None
+                }
+            })
+            .collect();
+
+        IgnoreFilter {
+            root: book.root.clone(),
+            build_dir: book.root.join(&book.config.build.build_dir),
+            gitignore,
+            patterns,
+        }
+    }
+
+    fn is_ignored(&self, path: &Path) -> bool {
+        if path.starts_with(&self.build_dir) {
+            return true;
+        }
+
+        if let Some(ref gitignore) = self.gitignore {
+            // Fails for files which were removed in the meantime, those are
+            // better off triggering a rebuild anyway
+            if let Ok(true) = gitignore.is_excluded(path) {
+                return true;
             }
-            _ => {}
         }
+
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+        self.patterns
+            .iter()
+            .any(|pattern| pattern.matches_path(relative))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::notify::DebouncedEvent;
+    use std::fs::File;
+    use std::io::Write;
+    use std::thread;
+    use tempdir::TempDir;
+
+    fn ignore_build_dir(root: &Path) -> IgnoreFilter<'static> {
+        IgnoreFilter {
+            root: root.to_path_buf(),
+            build_dir: root.join("book"),
+            gitignore: None,
+            patterns: vec![glob::Pattern::new("*.swp").unwrap()],
+        }
+    }
+
+    #[test]
+    fn a_burst_of_changes_is_one_batch() {
+        let root = Path::new("/book");
+        let ignore = ignore_build_dir(root);
+        let (tx, rx) = channel();
+        tx.send(DebouncedEvent::Write(root.join("src/a.md"))).unwrap();
+        tx.send(DebouncedEvent::Write(root.join("src/b.md"))).unwrap();
+        tx.send(DebouncedEvent::Write(root.join("src/a.md"))).unwrap();
+        tx.send(DebouncedEvent::Create(root.join("book/a.html"))).unwrap();
+        tx.send(DebouncedEvent::Create(root.join(".a.md.swp"))).unwrap();
+        tx.send(DebouncedEvent::Rescan).unwrap();
+
+        let mut paths = Vec::new();
+        assert!(next_batch(&rx, Duration::from_millis(10), &ignore, &mut paths));
+        assert_eq!(paths, vec![root.join("src/a.md"), root.join("src/b.md")]);
+
+        drop(tx);
+        paths.clear();
+        assert!(!next_batch(&rx, Duration::from_millis(10), &ignore, &mut paths));
+    }
+
+    #[test]
+    fn only_files_changed_during_a_rebuild_make_the_next_batch() {
+        let temp = TempDir::new("mdbook").unwrap();
+        let ignore = ignore_build_dir(temp.path());
+        let seen = temp.path().join("seen.md");
+        let edited = temp.path().join("edited.md");
+        let removed = temp.path().join("removed.md");
+        File::create(&seen).unwrap();
+        File::create(&edited).unwrap();
+
+        // file modification times are less precise than the system clock
+        thread::sleep(Duration::from_millis(1100));
+        let started = SystemTime::now();
+        thread::sleep(Duration::from_millis(100));
+        File::create(&edited).unwrap().write_all(b"edited").unwrap();
+
+        let (tx, rx) = channel();
+        tx.send(DebouncedEvent::Write(seen)).unwrap();
+        tx.send(DebouncedEvent::Write(edited.clone())).unwrap();
+        tx.send(DebouncedEvent::Remove(removed.clone())).unwrap();
+
+        assert_eq!(changed_during(&rx, &ignore, started), vec![edited, removed]);
     }
 }
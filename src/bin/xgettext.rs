@@ -0,0 +1,53 @@
+use std::io::Write;
+use std::path::PathBuf;
+use clap::{App, ArgMatches, SubCommand};
+use mdbook::errors::*;
+use mdbook::preprocess::{xgettext, LinkPreprocessor, Preprocessor, PreprocessorContext};
+use mdbook::utils::fs::create_file;
+use {get_book_dir, load_book};
+
+// Create clap subcommand arguments
+pub fn make_subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("xgettext")
+        .about("Extract the book's text into a gettext template for translators")
+        .arg_from_usage(
+            "[dir] 'A directory for your book{n}(Defaults to Current Directory when omitted)'",
+        )
+        .arg_from_usage(
+            "-o, --output=[file] 'Where to write the template{n}(Defaults to messages.pot in \
+             the build.po-dir)'",
+        )
+        .arg_from_usage("--profile=[profile] 'Apply the overrides from this profile in book.toml'")
+}
+
+// Xgettext command implementation
+pub fn execute(args: &ArgMatches) -> Result<()> {
+    let book_dir = get_book_dir(args);
+    let book = load_book(&book_dir, args)?;
+
+    // The translations are applied after the links are expanded, so the
+    // included text has to be extracted as well.
+    let ctx = PreprocessorContext {
+        root: book.root.clone(),
+        config: book.config.clone(),
+        mounts: book.mounted_books().clone(),
+        build_info: None,
+    };
+    let mut chapters = book.book.clone();
+    LinkPreprocessor::new().run(&ctx, &mut chapters)?;
+
+    let pot = xgettext(
+        &chapters,
+        &book.config.book.chapter_dir(),
+        book.config.book.title.as_ref().map(|title| title.as_str()),
+    );
+
+    let output = match args.value_of("output") {
+        Some(output) => PathBuf::from(output),
+        None => book.root.join(&book.config.build.po_dir).join("messages.pot"),
+    };
+    create_file(&output)?.write_all(pot.as_bytes())?;
+    info!("Wrote {}", output.display());
+
+    Ok(())
+}
@@ -0,0 +1,305 @@
+//! Adding new chapters to a book, creating their files and inserting them into
+//! `SUMMARY.md`.
+
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use regex::Regex;
+
+use utils::fs::{create_file, file_to_string};
+use errors::*;
+
+/// Add a chapter to the book in `src_dir`, given the titles leading to it
+/// separated by slashes (e.g. `"Advanced Topics/Caching"`).
+///
+/// Any chapter along the way which doesn't exist yet is created, with its file
+/// placed next to its parent's. Returns the path of the new chapter's file.
+pub fn add_chapter(src_dir: &Path, title_path: &str) -> Result<PathBuf> {
+    let titles: Vec<&str> = title_path
+        .split('/')
+        .map(|title| title.trim())
+        .filter(|title| !title.is_empty())
+        .collect();
+    if titles.is_empty() {
+        bail!("No chapter title given");
+    }
+
+    let summary_path = src_dir.join("SUMMARY.md");
+    let mut summary = file_to_string(&summary_path)?;
+    let mut location = PathBuf::new();
+
+    for depth in 0..titles.len() {
+        location = match find_chapter(&summary, &titles[..depth + 1]) {
+            Some(entry) => PathBuf::from(entry.location),
+            None => {
+                let (new_summary, new_location) = insert_chapter(&summary, &titles[..depth + 1])?;
+                summary = new_summary;
+
+                let file = src_dir.join(&new_location);
+                if !file.exists() {
+                    debug!("Creating {}", file.display());
+                    let mut f = create_file(&file)?;
+                    writeln!(f, "# {}", titles[depth])?;
+                }
+                PathBuf::from(new_location)
+            }
+        };
+    }
+
+    File::create(&summary_path)
+        .chain_err(|| "Unable to update SUMMARY.md")?
+        .write_all(summary.as_bytes())?;
+
+    Ok(src_dir.join(location))
+}
+
+/// A chapter's entry in the list of numbered chapters.
+#[derive(Debug, Clone, PartialEq)]
+struct Entry {
+    line: usize,
+    indent: usize,
+    name: String,
+    location: String,
+}
+
+fn entries(summary: &str) -> Vec<Entry> {
+    lazy_static! {
+        static ref ENTRY: Regex = Regex::new(r"^(\s*)[-*+]\s*\[(.*?)\]\((.*?)\)").unwrap();
+    }
+
+    summary
+        .lines()
+        .enumerate()
+        .filter_map(|(line, text)| {
+            ENTRY.captures(text).map(|caps| Entry {
+                line,
+                indent: indentation(text),
+                name: caps[2].trim().to_string(),
+                location: caps[3].trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+/// The chapters directly below `parent` (or the top-level chapters if there
+/// is no parent).
+fn children(entries: &[Entry], parent: Option<&Entry>) -> Vec<Entry> {
+    let nested: Vec<&Entry> = match parent {
+        Some(parent) => {
+            let start = entries
+                .iter()
+                .position(|e| e == parent)
+                .map(|ix| ix + 1)
+                .unwrap_or(entries.len());
+            entries[start..]
+                .iter()
+                .take_while(|e| e.indent > parent.indent)
+                .collect()
+        }
+        None => entries.iter().collect(),
+    };
+
+    let indent = match nested.iter().map(|e| e.indent).min() {
+        Some(indent) => indent,
+        None => return Vec::new(),
+    };
+
+    nested
+        .into_iter()
+        .filter(|e| e.indent == indent)
+        .cloned()
+        .collect()
+}
+
+fn find_chapter(summary: &str, titles: &[&str]) -> Option<Entry> {
+    let entries = entries(summary);
+    let mut current: Option<Entry> = None;
+
+    for title in titles {
+        let found = children(&entries, current.as_ref())
+            .into_iter()
+            .find(|e| e.name == *title);
+
+        match found {
+            Some(entry) => current = Some(entry),
+            None => return None,
+        }
+    }
+
+    current
+}
+
+/// Insert the last of `titles` as a new chapter after its siblings, returning
+/// the updated summary and where the chapter's file should go.
+fn insert_chapter(summary: &str, titles: &[&str]) -> Result<(String, String)> {
+    let (name, parents) = titles.split_last().expect("there is always a title");
+    let entries = entries(summary);
+
+    let parent = if parents.is_empty() {
+        None
+    } else {
+        Some(find_chapter(summary, parents).chain_err(|| "The parent chapter doesn't exist")?)
+    };
+    let siblings = children(&entries, parent.as_ref());
+
+    let location = match parent {
+        Some(ref parent) => {
+            let parent_location = Path::new(&parent.location);
+            let dir = match parent_location.file_stem().and_then(|stem| stem.to_str()) {
+                Some("index") | Some("README") | None => {
+                    parent_location.parent().map(Path::to_path_buf).unwrap_or_default()
+                }
+                Some(stem) => parent_location.with_file_name(stem),
+            };
+            dir.join(format!("{}.md", slugify(name)))
+        }
+        None => PathBuf::from(format!("{}.md", slugify(name))),
+    };
+    let location = location.to_string_lossy().replace('\\', "/");
+
+    // indent the new line the same way as its siblings (or parent) are, be it
+    // with spaces or tabs
+    let mut lines: Vec<&str> = summary.lines().collect();
+    let leading_whitespace = |entry: &Entry| {
+        let line = lines[entry.line];
+        line[..line.len() - line.trim_left().len()].to_string()
+    };
+    let indent = match (siblings.first(), parent.as_ref()) {
+        (Some(sibling), _) => leading_whitespace(sibling),
+        (None, Some(parent)) => {
+            let uses_tabs = entries.iter().any(|e| lines[e.line].starts_with('\t'));
+            leading_whitespace(parent) + if uses_tabs { "\t" } else { "    " }
+        }
+        (None, None) => String::new(),
+    };
+    let new_line = format!("{}- [{}]({})", indent, name, location);
+
+    let insert_at = match siblings.last().or(parent.as_ref()) {
+        Some(last) => end_of_subtree(&lines, last) + 1,
+        None => lines.len(),
+    };
+    lines.insert(insert_at, &new_line);
+
+    let mut new_summary = lines.join("\n");
+    new_summary.push('\n');
+
+    Ok((new_summary, location))
+}
+
+/// How far a line is indented, counting a tab as four spaces.
+fn indentation(line: &str) -> usize {
+    line.chars()
+        .take_while(|c| c.is_whitespace())
+        .map(|c| if c == '\t' { 4 } else { 1 })
+        .sum()
+}
+
+/// The last line belonging to a chapter, including its nested chapters.
+fn end_of_subtree(lines: &[&str], entry: &Entry) -> usize {
+    let mut last = entry.line;
+
+    for (ix, line) in lines.iter().enumerate().skip(entry.line + 1) {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        if indentation(line) > entry.indent {
+            last = ix;
+        } else {
+            break;
+        }
+    }
+
+    last
+}
+
+fn slugify(name: &str) -> String {
+    let mut slug = String::new();
+
+    for c in name.chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('_') {
+            slug.push('_');
+        }
+    }
+
+    slug.trim_right_matches('_').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SUMMARY: &str = "# Summary
+
+[Introduction](intro.md)
+
+- [First Chapter](first/index.md)
+    - [Nested Chapter](first/nested.md)
+- [Advanced Topics](advanced.md)
+
+---
+
+[Conclusion](conclusion.md)
+";
+
+    #[test]
+    fn add_a_top_level_chapter() {
+        let (got, location) = insert_chapter(SUMMARY, &["Getting Started"]).unwrap();
+
+        assert_eq!(location, "getting_started.md");
+        assert!(got.contains(
+            "- [Advanced Topics](advanced.md)\n- [Getting Started](getting_started.md)\n\n---"
+        ));
+    }
+
+    #[test]
+    fn add_a_chapter_after_its_siblings() {
+        let (got, location) = insert_chapter(SUMMARY, &["First Chapter", "Another One"]).unwrap();
+
+        assert_eq!(location, "first/another_one.md");
+        assert!(got.contains(
+            "    - [Nested Chapter](first/nested.md)\n    - [Another One](first/another_one.md)\n\
+             - [Advanced Topics]"
+        ));
+    }
+
+    #[test]
+    fn add_the_first_nested_chapter() {
+        let (got, location) = insert_chapter(SUMMARY, &["Advanced Topics", "Caching"]).unwrap();
+
+        assert_eq!(location, "advanced/caching.md");
+        assert!(got.contains(
+            "- [Advanced Topics](advanced.md)\n    - [Caching](advanced/caching.md)\n"
+        ));
+    }
+
+    #[test]
+    fn tab_indented_summaries_stay_tab_indented() {
+        let summary = "- [First Chapter](first.md)\n\t- [Nested Chapter](first/nested.md)\n\
+                       \t\t- [Deeper](first/deeper.md)\n- [Second Chapter](second.md)\n";
+
+        let (got, _) = insert_chapter(summary, &["First Chapter", "Another One"]).unwrap();
+        assert!(got.contains(
+            "\t\t- [Deeper](first/deeper.md)\n\t- [Another One](first/another_one.md)\n\
+             - [Second Chapter]"
+        ));
+
+        let (got, _) = insert_chapter(summary, &["Second Chapter", "Nested"]).unwrap();
+        assert!(got.ends_with("- [Second Chapter](second.md)\n\t- [Nested](second/nested.md)\n"));
+    }
+
+    #[test]
+    fn find_nested_chapters() {
+        let got = find_chapter(SUMMARY, &["First Chapter", "Nested Chapter"]).unwrap();
+        assert_eq!(got.location, "first/nested.md");
+
+        assert!(find_chapter(SUMMARY, &["Nested Chapter"]).is_none());
+    }
+
+    #[test]
+    fn slugs_only_contain_letters_numbers_and_underscores() {
+        assert_eq!(slugify("Advanced Topics: Caching!"), "advanced_topics_caching");
+    }
+}
@@ -0,0 +1,565 @@
+//! Converting (a subset of) AsciiDoc into markdown, so `.adoc` chapters can
+//! sit alongside markdown ones.
+
+use std::collections::HashMap;
+use regex::{Captures, Regex};
+
+/// Convert AsciiDoc into markdown.
+///
+/// Only the common parts of AsciiDoc are understood: section titles,
+/// paragraphs with the usual inline formatting, links and cross references,
+/// images, lists, admonitions, simple tables and listing, literal, quote and
+/// passthrough blocks. Anything else is passed through as it is, so books
+/// needing more should configure a proper converter in `[build.converters]`.
+pub fn asciidoc_to_markdown(src: &str) -> String {
+    let mut converter = Converter::default();
+    converter.convert(src);
+    converter.out
+}
+
+#[derive(Debug, Default)]
+struct Converter {
+    out: String,
+    attributes: HashMap<String, String>,
+    /// The block attributes (e.g. `[source,rust]`) for the next block.
+    style: Option<String>,
+    /// The delimited blocks which are still open.
+    blocks: Vec<Block>,
+    quote_depth: usize,
+    /// Is the current paragraph an admonition (and so a block quote)?
+    admonition: bool,
+}
+
+#[derive(Debug)]
+struct Block {
+    delimiter: String,
+    quote: bool,
+    attribution: Option<String>,
+}
+
+impl Converter {
+    fn convert(&mut self, src: &str) {
+        lazy_static! {
+            static ref ATTRIBUTE_ENTRY: Regex = Regex::new(r"^:([\w-]+):\s*(.*)$").unwrap();
+            static ref ANCHOR: Regex = Regex::new(r"^\[\[([^\],]+)(?:,[^\]]*)?\]\]$").unwrap();
+            static ref BLOCK_ATTRIBUTES: Regex = Regex::new(r"^\[(.*)\]$").unwrap();
+            static ref BLOCK_TITLE: Regex = Regex::new(r"^\.([^.\s].*)$").unwrap();
+            static ref HEADING: Regex = Regex::new(r"^(={1,6})\s+(.+?)(?:\s+=+)?$").unwrap();
+            static ref BLOCK_IMAGE: Regex = Regex::new(r"^image::([^\[\s]+)\[([^\]]*)\]$").unwrap();
+            static ref UNORDERED: Regex = Regex::new(r"^\s*(\*{1,5}|-)\s+(.*)$").unwrap();
+            static ref ORDERED: Regex = Regex::new(r"^\s*(\.{1,5}|\d+\.)\s+(.*)$").unwrap();
+            static ref LABELED: Regex = Regex::new(r"^([^\s:][^:]*?)::(?:\s+(.*))?$").unwrap();
+            static ref ADMONITION: Regex =
+                Regex::new(r"^(NOTE|TIP|IMPORTANT|WARNING|CAUTION):\s+(.*)$").unwrap();
+        }
+
+        let mut lines = src.lines().map(|line| line.trim_right());
+        // the lines right after the document title are its author and revision
+        let mut in_header = false;
+
+        while let Some(line) = lines.next() {
+            if in_header {
+                if line.is_empty() {
+                    in_header = false;
+                } else if !line.starts_with(':') {
+                    continue;
+                }
+            }
+
+            if line.is_empty() {
+                self.end_paragraph();
+                self.push_blank();
+                continue;
+            }
+
+            if line == "////" {
+                for comment in &mut lines {
+                    if comment == "////" {
+                        break;
+                    }
+                }
+                continue;
+            }
+            if line.starts_with("//") {
+                continue;
+            }
+
+            if let Some(caps) = ATTRIBUTE_ENTRY.captures(line) {
+                self.attributes.insert(caps[1].to_string(), caps[2].to_string());
+                continue;
+            }
+            if let Some(caps) = ANCHOR.captures(line) {
+                self.push_line(&format!("<a id=\"{}\"></a>", &caps[1]));
+                self.push_blank();
+                continue;
+            }
+            if let Some(caps) = BLOCK_ATTRIBUTES.captures(line) {
+                self.style = Some(caps[1].to_string());
+                continue;
+            }
+            if let Some(caps) = BLOCK_TITLE.captures(line) {
+                let title = inline(&self.substitute(&caps[1]));
+                self.push_line(&format!("**{}**", title));
+                self.push_blank();
+                continue;
+            }
+
+            if is_delimiter(line) {
+                let style = self.style.take();
+                if line.starts_with('-') || line.starts_with('.') {
+                    self.verbatim_block(line, style, &mut lines);
+                } else if line.starts_with('+') {
+                    self.passthrough_block(line, &mut lines);
+                } else {
+                    self.toggle_block(line, style);
+                }
+                continue;
+            }
+            if line == "|===" {
+                let style = self.style.take();
+                self.table(style, &mut lines);
+                continue;
+            }
+
+            match line {
+                "'''" => {
+                    self.push_blank();
+                    self.push_line("***");
+                    self.push_blank();
+                    continue;
+                }
+                // page breaks and list continuations
+                "<<<" | "+" => continue,
+                _ => {}
+            }
+
+            let line = self.substitute(line);
+            let style = self.style.take();
+
+            if let Some(caps) = HEADING.captures(&line) {
+                if caps[1].len() == 1 && self.out.trim().is_empty() {
+                    in_header = true;
+                }
+                let heading = format!("{} {}", "#".repeat(caps[1].len()), inline(&caps[2]));
+                self.push_line(&heading);
+            } else if let Some(caps) = BLOCK_IMAGE.captures(&line) {
+                let alt = caps[2].split(',').next().unwrap_or("").trim();
+                self.push_line(&format!("![{}]({})", alt, &caps[1]));
+            } else if let Some(caps) = ADMONITION.captures(&line) {
+                let label = admonition_label(&caps[1]).expect("the regex only matches labels");
+                self.start_admonition(label, &inline(&caps[2]));
+            } else if let Some(label) = style.as_ref().and_then(|style| admonition_label(style)) {
+                self.start_admonition(label, &inline(&line));
+            } else if let Some(caps) = UNORDERED.captures(&line) {
+                let depth = if &caps[1] == "-" { 1 } else { caps[1].len() };
+                let item = format!("{}- {}", "    ".repeat(depth - 1), inline(&caps[2]));
+                self.push_line(&item);
+            } else if let Some(caps) = ORDERED.captures(&line) {
+                let depth = if caps[1].starts_with('.') { caps[1].len() } else { 1 };
+                let item = format!("{}1. {}", "    ".repeat(depth - 1), inline(&caps[2]));
+                self.push_line(&item);
+            } else if let Some(caps) = LABELED.captures(&line) {
+                let mut item = format!("- **{}**", inline(&caps[1]));
+                if let Some(definition) = caps.get(2) {
+                    item.push_str(": ");
+                    item.push_str(&inline(definition.as_str()));
+                }
+                self.push_line(&item);
+            } else {
+                let mut text = inline(&line);
+                if text.ends_with(" +") {
+                    // a hard line break
+                    let len = text.len();
+                    text.truncate(len - 2);
+                    text.push_str("  ");
+                }
+                self.push_line(&text);
+            }
+        }
+    }
+
+    /// Replace references to the document's attributes with their values.
+    fn substitute(&self, text: &str) -> String {
+        lazy_static! {
+            static ref REFERENCE: Regex = Regex::new(r"\{([\w-]+)\}").unwrap();
+        }
+
+        REFERENCE
+            .replace_all(text, |caps: &Captures| match self.attributes.get(&caps[1]) {
+                Some(value) => value.clone(),
+                None => caps[0].to_string(),
+            })
+            .into_owned()
+    }
+
+    fn push_line(&mut self, text: &str) {
+        for _ in 0..self.quote_depth {
+            self.out.push('>');
+            if !text.is_empty() {
+                self.out.push(' ');
+            }
+        }
+        self.out.push_str(text);
+        self.out.push('\n');
+    }
+
+    /// Separate two blocks, without piling up blank lines.
+    fn push_blank(&mut self) {
+        if self.quote_depth > 0 || !(self.out.is_empty() || self.out.ends_with("\n\n")) {
+            self.push_line("");
+        }
+    }
+
+    fn start_admonition(&mut self, label: &str, text: &str) {
+        self.quote_depth += 1;
+        self.admonition = true;
+        self.push_line(&format!("**{}:** {}", label, text));
+    }
+
+    fn end_paragraph(&mut self) {
+        if self.admonition {
+            self.admonition = false;
+            self.quote_depth -= 1;
+        }
+    }
+
+    /// Listing (`----`) and literal (`....`) blocks become code blocks.
+    fn verbatim_block<'s, I>(&mut self, delimiter: &str, style: Option<String>, lines: &mut I)
+    where
+        I: Iterator<Item = &'s str>,
+    {
+        let language = if delimiter.starts_with('.') {
+            String::from("text")
+        } else {
+            style.map(|style| source_language(&style)).unwrap_or_default()
+        };
+
+        self.push_line(&format!("```{}", language));
+        for line in lines {
+            if line == delimiter {
+                break;
+            }
+            self.push_line(line);
+        }
+        self.push_line("```");
+    }
+
+    /// Passthrough (`++++`) blocks are raw HTML.
+    fn passthrough_block<'s, I>(&mut self, delimiter: &str, lines: &mut I)
+    where
+        I: Iterator<Item = &'s str>,
+    {
+        self.push_blank();
+        for line in lines {
+            if line == delimiter {
+                break;
+            }
+            self.push_line(line);
+        }
+        self.push_blank();
+    }
+
+    /// Open or close a block whose contents are converted as usual: quotes,
+    /// examples and sidebars.
+    fn toggle_block(&mut self, delimiter: &str, style: Option<String>) {
+        self.end_paragraph();
+
+        let closes = self.blocks
+            .last()
+            .map(|block| block.delimiter == delimiter)
+            .unwrap_or(false);
+
+        if closes {
+            let block = self.blocks.pop().expect("There is a block to close");
+            if let Some(attribution) = block.attribution {
+                self.push_blank();
+                self.push_line(&format!("— {}", attribution));
+            }
+            if block.quote {
+                self.quote_depth -= 1;
+            }
+            self.push_blank();
+            return;
+        }
+
+        let style = style.unwrap_or_default();
+        let label = admonition_label(&style);
+        let is_quote = delimiter.starts_with('_');
+        // `[quote, author, source]`
+        let attribution = if is_quote {
+            let parts: Vec<&str> = style
+                .split(',')
+                .skip(1)
+                .map(|part| part.trim())
+                .filter(|part| !part.is_empty())
+                .collect();
+            if parts.is_empty() {
+                None
+            } else {
+                Some(parts.join(", "))
+            }
+        } else {
+            None
+        };
+
+        self.push_blank();
+        if is_quote || label.is_some() {
+            self.quote_depth += 1;
+        }
+        if let Some(label) = label {
+            self.push_line(&format!("**{}:**", label));
+        }
+
+        self.blocks.push(Block {
+            delimiter: delimiter.to_string(),
+            quote: is_quote || label.is_some(),
+            attribution,
+        });
+    }
+
+    /// Tables (`|===`) with one cell for each column.
+    fn table<'s, I>(&mut self, style: Option<String>, lines: &mut I)
+    where
+        I: Iterator<Item = &'s str>,
+    {
+        let mut rows = Vec::new();
+        for line in lines {
+            if line == "|===" {
+                break;
+            }
+            rows.push(line);
+        }
+
+        let first = match rows.iter().position(|row| !row.is_empty()) {
+            Some(ix) => ix,
+            None => return,
+        };
+        let columns = rows[first].split('|').skip(1).count();
+        if columns == 0 {
+            return;
+        }
+        // the first row is the header when it's followed by a blank line
+        let header = style.map(|style| style.contains("header")).unwrap_or(false)
+            || rows.get(first + 1).map(|row| row.is_empty()).unwrap_or(false);
+
+        let mut cells: Vec<String> = Vec::new();
+        for row in &rows[first..] {
+            if row.starts_with('|') {
+                let row_cells = row.split('|').skip(1);
+                cells.extend(row_cells.map(|cell| inline(&self.substitute(cell.trim()))));
+            } else if row.trim().is_empty() {
+                continue;
+            } else if let Some(cell) = cells.last_mut() {
+                // a cell continued on the next line
+                cell.push(' ');
+                cell.push_str(&inline(&self.substitute(row.trim())));
+            }
+        }
+
+        let mut table_rows = cells.chunks(columns);
+        let empty_header = vec![String::new(); columns];
+        let header_row = if header {
+            table_rows.next().unwrap_or(&empty_header[..])
+        } else {
+            &empty_header[..]
+        };
+
+        self.push_blank();
+        self.push_line(&format!("| {} |", header_row.join(" | ")));
+        self.push_line(&format!("|{}", "---|".repeat(columns)));
+        for row in table_rows {
+            self.push_line(&format!("| {} |", row.join(" | ")));
+        }
+        self.push_blank();
+    }
+}
+
+/// Is this line the start or end of a delimited block?
+fn is_delimiter(line: &str) -> bool {
+    let first = match line.chars().next() {
+        Some(first) => first,
+        None => return false,
+    };
+
+    line.len() >= 4 && "-.+_=*".contains(first) && line.chars().all(|c| c == first)
+}
+
+/// The language of a `[source,rust]` block.
+fn source_language(style: &str) -> String {
+    let mut parts = style.split(',').map(|part| part.trim());
+
+    match parts.next() {
+        Some("source") | Some("") => parts.next().unwrap_or("").to_string(),
+        _ => String::new(),
+    }
+}
+
+fn admonition_label(style: &str) -> Option<&'static str> {
+    match style.split(',').next().unwrap_or("").trim() {
+        "NOTE" => Some("Note"),
+        "TIP" => Some("Tip"),
+        "IMPORTANT" => Some("Important"),
+        "WARNING" => Some("Warning"),
+        "CAUTION" => Some("Caution"),
+        _ => None,
+    }
+}
+
+/// Convert the inline formatting of some text, leaving anything in
+/// backticks alone.
+fn inline(text: &str) -> String {
+    let mut converted = String::with_capacity(text.len());
+
+    for (i, part) in text.split('`').enumerate() {
+        if i > 0 {
+            converted.push('`');
+        }
+
+        if i % 2 == 0 {
+            converted.push_str(&inline_formatting(part));
+        } else if part.len() > 1 && part.starts_with('+') && part.ends_with('+') {
+            // `+literal+`
+            converted.push_str(&part[1..part.len() - 1]);
+        } else {
+            converted.push_str(part);
+        }
+    }
+
+    converted
+}
+
+fn inline_formatting(text: &str) -> String {
+    lazy_static! {
+        static ref XREF: Regex = Regex::new(r"xref:([^\s\[]+)\[([^\]]*)\]").unwrap();
+        static ref CROSS_REFERENCE: Regex = Regex::new(r"<<([^,>]+)(?:,\s*([^>]+))?>>").unwrap();
+        static ref IMAGE: Regex = Regex::new(r"image::?([^\s\[]+)\[([^\]]*)\]").unwrap();
+        static ref LINK: Regex = Regex::new(r"link:([^\s\[]+)\[([^\]]*)\]").unwrap();
+        static ref URL: Regex = Regex::new(r"((?:https?|ftp)://[^\s\[\]()]+)\[([^\]]*)\]").unwrap();
+        static ref BARE_URL: Regex = Regex::new(r"(^|\s)((?:https?|ftp)://[^\s\[\]<>]+)").unwrap();
+        static ref BOLD: Regex =
+            Regex::new(r"(^|[^\w*])\*([^*\s]|[^*\s][^*]*[^*\s])\*($|[^\w*])").unwrap();
+        static ref ITALIC: Regex = Regex::new(r"__([^_]+)__").unwrap();
+    }
+
+    let text = XREF.replace_all(text, |caps: &Captures| {
+        link_to(&caps[2], &caps[1], &reference(&caps[1]))
+    });
+    let text = CROSS_REFERENCE.replace_all(&text, |caps: &Captures| {
+        let label = caps.get(2).map(|m| m.as_str()).unwrap_or(&caps[1]);
+        link_to(label, label, &reference(&caps[1]))
+    });
+    let text = IMAGE.replace_all(&text, |caps: &Captures| {
+        let alt = caps[2].split(',').next().unwrap_or("").trim();
+        format!("![{}]({})", alt, &caps[1])
+    });
+    let text = LINK.replace_all(&text, |caps: &Captures| link_to(&caps[2], &caps[1], &caps[1]));
+    let text = URL.replace_all(&text, |caps: &Captures| link_to(&caps[2], &caps[1], &caps[1]));
+    let text = BARE_URL.replace_all(&text, |caps: &Captures| {
+        // trailing punctuation ends the sentence, not the URL
+        let url = caps[2].trim_right_matches(|c: char| ".,;:!?".contains(c));
+        format!("{}<{}>{}", &caps[1], url, &caps[2][url.len()..])
+    });
+    let text = BOLD.replace_all(&text, "${1}**${2}**${3}");
+    let text = ITALIC.replace_all(&text, "<em>${1}</em>");
+
+    text.into_owned()
+}
+
+fn link_to(text: &str, fallback: &str, dest: &str) -> String {
+    let text = if text.is_empty() { fallback } else { text };
+    format!("[{}]({})", text, dest)
+}
+
+/// Where a cross reference points, with references to other AsciiDoc files
+/// turned into links to the rendered chapters.
+fn reference(target: &str) -> String {
+    let (path, fragment) = match target.find('#') {
+        Some(ix) => (&target[..ix], &target[ix..]),
+        None if target.contains('.') => (target, ""),
+        None => return format!("#{}", target),
+    };
+
+    if path.ends_with(".adoc") {
+        format!("{}.html{}", &path[..path.len() - ".adoc".len()], fragment)
+    } else {
+        target.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convert_a_typical_chapter() {
+        let src = "= The Guide\n\
+                   Jane Doe <jane@example.com>\n\
+                   :version: 1.2\n\
+                   \n\
+                   == Installing\n\
+                   \n\
+                   // not rendered\n\
+                   Install *version {version}* from https://example.com[the website]\n\
+                   or see <<usage,below>> and xref:other.adoc#setup[the setup].\n\
+                   \n\
+                   * One\n\
+                   ** Nested\n\
+                   . First\n\
+                   \n\
+                   NOTE: Back up first.\n\
+                   Really.\n\
+                   \n\
+                   [source,rust]\n\
+                   ----\n\
+                   fn main() {\n\
+                   \n\
+                   }\n\
+                   ----\n";
+        let should_be = "# The Guide\n\
+                         \n\
+                         ## Installing\n\
+                         \n\
+                         Install **version 1.2** from [the website](https://example.com)\n\
+                         or see [below](#usage) and [the setup](other.html#setup).\n\
+                         \n\
+                         - One\n    - Nested\n\
+                         1. First\n\
+                         \n\
+                         > **Note:** Back up first.\n\
+                         > Really.\n\
+                         \n\
+                         ```rust\n\
+                         fn main() {\n\
+                         \n\
+                         }\n\
+                         ```\n";
+
+        assert_eq!(asciidoc_to_markdown(src), should_be);
+    }
+
+    #[test]
+    fn quotes_and_tables() {
+        let src = "[quote, Ada Lovelace]\n\
+                   ____\n\
+                   The engine *weaves* patterns.\n\
+                   ____\n\
+                   \n\
+                   |===\n\
+                   | Name | Value\n\
+                   \n\
+                   | a | 1\n\
+                   | b | 2\n\
+                   |===\n";
+        let should_be = "> The engine **weaves** patterns.\n\
+                         >\n\
+                         > — Ada Lovelace\n\
+                         \n\
+                         | Name | Value |\n\
+                         |---|---|\n\
+                         | a | 1 |\n\
+                         | b | 2 |\n\
+                         \n";
+
+        assert_eq!(asciidoc_to_markdown(src), should_be);
+    }
+}
@@ -0,0 +1,247 @@
+//! Generating a `SUMMARY.md` from the layout of the source directory, for
+//! books which would rather not maintain one by hand.
+
+use std::fs;
+use std::path::Path;
+use regex::Regex;
+
+use super::formats::{to_markdown, Converters, CHAPTER_EXTENSIONS};
+use utils::fs::file_to_string;
+use errors::*;
+
+/// Generate the contents of a `SUMMARY.md` for the chapters in `src_dir`.
+///
+/// - Every file mdBook can load a chapter from (markdown, notebooks,
+///   AsciiDoc, Org or reStructuredText) becomes a chapter, and every directory
+///   containing such files becomes a chapter with the files inside it nested
+///   below. A directory's own chapter is its `README` (or `index`) file.
+/// - The `README` (or `index`) file at the top of `src_dir` becomes an
+///   unnumbered introduction.
+/// - Entries are sorted by name, with a numeric prefix such as `01-` or `2_`
+///   ordering them numerically. The prefix is dropped from the titles.
+/// - A chapter's title is its first top-level heading, falling back to its
+///   file name.
+pub fn generate_summary(src_dir: &Path) -> Result<String> {
+    let mut summary = String::from("# Summary\n\n");
+
+    if let Some(index) = index_file(src_dir) {
+        let title = title_of(&src_dir.join(&index), "Introduction");
+        summary.push_str(&format!("[{}]({})\n\n", title, index));
+    }
+
+    add_chapters(src_dir, src_dir, 0, &mut summary)?;
+
+    Ok(summary)
+}
+
+fn add_chapters(src_dir: &Path, dir: &Path, depth: usize, summary: &mut String) -> Result<()> {
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(dir).chain_err(|| format!("Unable to read {}", dir.display()))? {
+        let path = entry?.path();
+        let name = match path.file_name().and_then(|name| name.to_str()) {
+            Some(name) => name.to_string(),
+            None => continue,
+        };
+
+        if name.starts_with('.') || name == "SUMMARY.md" {
+            continue;
+        }
+        if path.is_dir() {
+            if contains_chapters(&path) {
+                entries.push((name, path));
+            }
+        } else if is_chapter(&path) && !is_index(&path) {
+            entries.push((name, path));
+        }
+    }
+    entries.sort_by_key(|&(ref name, _)| sort_key(name));
+
+    let indent = "    ".repeat(depth);
+
+    for (name, path) in entries {
+        let relative = path.strip_prefix(src_dir)
+            .expect("the entry is inside the source directory");
+
+        if path.is_dir() {
+            let index = index_file(&path).unwrap_or_else(|| String::from("README.md"));
+            let location = relative.join(&index);
+            let title = title_of(&src_dir.join(&location), &title_from_name(&name));
+
+            summary.push_str(&format!("{}- [{}]({})\n", indent, title, to_link(&location)));
+            add_chapters(src_dir, &path, depth + 1, summary)?;
+        } else {
+            let title = title_of(&path, &title_from_name(&name));
+            summary.push_str(&format!("{}- [{}]({})\n", indent, title, to_link(relative)));
+        }
+    }
+
+    Ok(())
+}
+
+fn is_chapter(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map_or(false, |ext| CHAPTER_EXTENSIONS.contains(&ext))
+}
+
+fn is_index(path: &Path) -> bool {
+    let stem = path.file_stem().and_then(|stem| stem.to_str());
+    (stem == Some("README") || stem == Some("index")) && is_chapter(path)
+}
+
+fn index_file(dir: &Path) -> Option<String> {
+    for stem in &["README", "index"] {
+        for extension in CHAPTER_EXTENSIONS {
+            let name = format!("{}.{}", stem, extension);
+            if dir.join(&name).is_file() {
+                return Some(name);
+            }
+        }
+    }
+
+    None
+}
+
+fn contains_chapters(dir: &Path) -> bool {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return false,
+    };
+
+    entries.filter_map(|entry| entry.ok()).any(|entry| {
+        let path = entry.path();
+        if path.is_dir() {
+            contains_chapters(&path)
+        } else {
+            is_chapter(&path)
+        }
+    })
+}
+
+fn to_link(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
+/// Split off an ordering prefix like `01-` or `2_`.
+fn split_prefix(name: &str) -> (Option<u64>, &str) {
+    lazy_static! {
+        static ref PREFIX: Regex = Regex::new(r"^(\d+)[-_. ]+").unwrap();
+    }
+
+    match PREFIX.captures(name) {
+        Some(caps) => {
+            let rest = &name[caps[0].len()..];
+            (caps[1].parse().ok(), rest)
+        }
+        None => (None, name),
+    }
+}
+
+/// Entries with a numeric prefix come first, in numeric order, followed by
+/// the rest in alphabetical order.
+fn sort_key(name: &str) -> (u64, String) {
+    match split_prefix(name) {
+        (Some(number), _) => (number, name.to_string()),
+        (None, _) => (u64::max_value(), name.to_string()),
+    }
+}
+
+fn title_from_name(name: &str) -> String {
+    let (_, name) = split_prefix(name);
+    let name = match Path::new(name).file_stem().and_then(|stem| stem.to_str()) {
+        Some(stem) if is_chapter(Path::new(name)) => stem,
+        _ => name,
+    };
+    let name = name.replace(|c: char| c == '-' || c == '_', " ");
+
+    let mut chars = name.trim().chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// The chapter's first top-level heading, if it has one. Chapters in other
+/// formats are converted into markdown first, to find theirs.
+fn title_of(path: &Path, fallback: &str) -> String {
+    let content = file_to_string(path)
+        .and_then(|content| to_markdown(path, content, &Converters::default()))
+        .unwrap_or_default();
+
+    content
+        .lines()
+        .find(|line| line.starts_with("# "))
+        .map(|line| line[2..].trim().to_string())
+        .unwrap_or_else(|| fallback.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+    use utils::fs::write_files;
+
+    #[test]
+    fn titles_come_from_file_names_without_their_prefix() {
+        assert_eq!(title_from_name("02-getting-started.md"), "Getting started");
+        assert_eq!(title_from_name("advanced_topics"), "Advanced topics");
+        assert_eq!(title_from_name("3_notes.org"), "Notes");
+    }
+
+    #[test]
+    fn numeric_prefixes_sort_numerically() {
+        let mut names = vec!["appendix.md", "10-last.md", "2-second.md", "1-first.md"];
+        names.sort_by_key(|name| sort_key(name));
+
+        assert_eq!(names, vec!["1-first.md", "2-second.md", "10-last.md", "appendix.md"]);
+    }
+
+    #[test]
+    fn generate_a_summary_from_the_directory_layout() {
+        let temp = TempDir::new("mdbook").unwrap();
+        let files = vec![
+            ("README.md", "# Welcome\n"),
+            ("01-intro.md", "# Introduction\n\nSome text\n"),
+            ("02-guide/README.md", "# The Guide\n"),
+            ("02-guide/2-configuring.md", "No heading here\n"),
+            ("02-guide/1-installing.md", "# Installation\n"),
+            ("theme/book.css", "body {}\n"),
+            ("SUMMARY.md", "# Summary\n"),
+        ];
+        write_files(temp.path(), &files).unwrap();
+
+        let got = generate_summary(temp.path()).unwrap();
+
+        assert_eq!(
+            got,
+            "# Summary\n\n\
+             [Welcome](README.md)\n\n\
+             - [Introduction](01-intro.md)\n\
+             - [The Guide](02-guide/README.md)\n    \
+             - [Installation](02-guide/1-installing.md)\n    \
+             - [Configuring](02-guide/2-configuring.md)\n"
+        );
+    }
+
+    #[test]
+    fn chapters_in_other_formats_are_included() {
+        let temp = TempDir::new("mdbook").unwrap();
+        let files = vec![
+            ("1-intro.adoc", "= Introduction\n\nSome text\n"),
+            ("2-guide/index.rst", "The Guide\n=========\n"),
+            ("2-guide/notes.org", "#+TITLE: Notes\n"),
+            ("3-appendix.txt", "Not a chapter\n"),
+        ];
+        write_files(temp.path(), &files).unwrap();
+
+        let got = generate_summary(temp.path()).unwrap();
+
+        assert_eq!(
+            got,
+            "# Summary\n\n\
+             - [Introduction](1-intro.adoc)\n\
+             - [The Guide](2-guide/index.rst)\n    \
+             - [Notes](2-guide/notes.org)\n"
+        );
+    }
+}
@@ -3,31 +3,155 @@ use std::path::{Path, PathBuf};
 use std::collections::VecDeque;
 use std::fs::{self, File};
 use std::io::{Read, Write};
+use toml;
+use toml::value::{Table, Value};
 
-use super::summary::{parse_summary, Link, SectionNumber, Summary, SummaryItem};
-use config::BuildConfig;
+use super::summary::{mount_books, parse_summary, Link, SectionNumber, Summary, SummaryItem};
+use super::formats::{new_chapter, to_markdown, Converters};
+use super::mounts::MountedBooks;
+use config::{BuildConfig, SlugStyle};
 use errors::*;
 
 /// Load a book into memory from its `src/` directory.
 pub fn load_book<P: AsRef<Path>>(src_dir: P, cfg: &BuildConfig) -> Result<Book> {
-    let src_dir = src_dir.as_ref();
-    let summary_md = src_dir.join("SUMMARY.md");
+    load(
+        src_dir.as_ref(),
+        None,
+        &MountedBooks::default(),
+        cfg,
+        SlugStyle::default(),
+    )
+}
+
+/// Load a book into memory from its `src/` directory, using `summary_md` as
+/// the contents of its `SUMMARY.md`.
+pub fn load_book_with_summary<P: AsRef<Path>>(
+    src_dir: P,
+    summary_md: &str,
+    cfg: &BuildConfig,
+) -> Result<Book> {
+    load_with_summary(
+        src_dir.as_ref(),
+        None,
+        &MountedBooks::default(),
+        summary_md,
+        cfg,
+        SlugStyle::default(),
+    )
+}
+
+/// Load a translation of a book from its source directory.
+///
+/// Anything which hasn't been translated yet is taken from `fallback_dir`
+/// (usually the source directory of the book's main language). This includes
+/// the `SUMMARY.md`, so a translation only needs its own `SUMMARY.md` once its
+/// structure differs from the original.
+pub fn load_translation<P, Q>(src_dir: P, fallback_dir: Q, cfg: &BuildConfig) -> Result<Book>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    load(
+        src_dir.as_ref(),
+        Some(fallback_dir.as_ref()),
+        &MountedBooks::default(),
+        cfg,
+        SlugStyle::default(),
+    )
+}
+
+/// Load a book (or one of its translations) along with the books mounted in
+/// it, using `summary_md` as the contents of its `SUMMARY.md` if it's given.
+/// Converted chapters get the same heading IDs as the HTML renderer would
+/// give them with `slug_style`.
+pub(crate) fn load_with_mounts(
+    src_dir: &Path,
+    fallback_dir: Option<&Path>,
+    mounts: &MountedBooks,
+    summary_md: Option<&str>,
+    cfg: &BuildConfig,
+    slug_style: SlugStyle,
+) -> Result<Book> {
+    match summary_md {
+        Some(summary_md) => {
+            load_with_summary(src_dir, fallback_dir, mounts, summary_md, cfg, slug_style)
+        }
+        None => load(src_dir, fallback_dir, mounts, cfg, slug_style),
+    }
+}
+
+fn load(
+    src_dir: &Path,
+    fallback_dir: Option<&Path>,
+    mounts: &MountedBooks,
+    cfg: &BuildConfig,
+    slug_style: SlugStyle,
+) -> Result<Book> {
+    let summary_md = match fallback_dir {
+        Some(fallback_dir) if !src_dir.join("SUMMARY.md").exists() => {
+            debug!(
+                "{} has no SUMMARY.md, falling back to {}",
+                src_dir.display(),
+                fallback_dir.display()
+            );
+            fallback_dir.join("SUMMARY.md")
+        }
+        _ => src_dir.join("SUMMARY.md"),
+    };
 
     let mut summary_content = String::new();
     File::open(summary_md)
         .chain_err(|| "Couldn't open SUMMARY.md")?
         .read_to_string(&mut summary_content)?;
 
-    let summary = parse_summary(&summary_content).chain_err(|| "Summary parsing failed")?;
+    load_with_summary(src_dir, fallback_dir, mounts, &summary_content, cfg, slug_style)
+}
+
+fn load_with_summary(
+    src_dir: &Path,
+    fallback_dir: Option<&Path>,
+    mounts: &MountedBooks,
+    summary_content: &str,
+    cfg: &BuildConfig,
+    slug_style: SlugStyle,
+) -> Result<Book> {
+    let mut summary = parse_summary(summary_content)?;
+    mount_books(&mut summary, |name| load_mounted_summary(name, mounts))?;
 
     if cfg.create_missing {
-        create_missing(&src_dir, &summary).chain_err(|| "Unable to create missing chapters")?;
+        create_missing(src_dir, fallback_dir, mounts, &summary)
+            .chain_err(|| "Unable to create missing chapters")?;
     }
 
-    load_book_from_disk(&summary, src_dir)
+    let converters = Converters {
+        commands: cfg.converters.clone(),
+        slug_style: slug_style,
+    };
+    load_book_from_disk(&summary, src_dir, fallback_dir, mounts, &converters)
+}
+
+/// Load the `SUMMARY.md` of the book mounted as `name` from its own source
+/// directory.
+fn load_mounted_summary(name: &str, mounts: &MountedBooks) -> Result<Summary> {
+    let summary_md = match mounts.source_dir(name) {
+        Some(src_dir) => src_dir.join("SUMMARY.md"),
+        None => bail!("\"{}\" isn't listed in build.mounts", name),
+    };
+
+    let mut summary_content = String::new();
+    File::open(&summary_md)
+        .chain_err(|| format!("Couldn't open {}", summary_md.display()))?
+        .read_to_string(&mut summary_content)?;
+
+    parse_summary(&summary_content)
 }
 
-fn create_missing(src_dir: &Path, summary: &Summary) -> Result<()> {
+fn create_missing(
+    src_dir: &Path,
+    fallback_dir: Option<&Path>,
+    mounts: &MountedBooks,
+    summary: &Summary,
+) -> Result<()> {
     let mut items: Vec<_> = summary
         .prefix_chapters
         .iter()
@@ -39,8 +163,17 @@ fn create_missing(src_dir: &Path, summary: &Summary) -> Result<()> {
         let next = items.pop().expect("already checked");
 
         if let SummaryItem::Link(ref link) = *next {
+            // the chapters of mounted books belong to their own books
+            if mounts.mounted_path(&link.location).is_some() {
+                continue;
+            }
+
             let filename = src_dir.join(&link.location);
-            if !filename.exists() {
+            let translated_elsewhere = fallback_dir
+                .map(|dir| dir.join(&link.location).exists())
+                .unwrap_or(false);
+
+            if !filename.exists() && !translated_elsewhere {
                 if let Some(parent) = filename.parent() {
                     if !parent.exists() {
                         fs::create_dir_all(parent)?;
@@ -49,7 +182,7 @@ fn create_missing(src_dir: &Path, summary: &Summary) -> Result<()> {
                 debug!("Creating missing file {}", filename.display());
 
                 let mut f = File::create(&filename)?;
-                writeln!(f, "# {}", link.name)?;
+                f.write_all(new_chapter(&filename, &link.name).as_bytes())?;
             }
 
             items.extend(&link.nested_items);
@@ -80,6 +213,12 @@ impl Book {
         Default::default()
     }
 
+    /// The top-level items in the book. Chapters keep their nested items in
+    /// `sub_items`.
+    pub fn sections(&self) -> &[BookItem] {
+        &self.sections
+    }
+
     /// Get a depth-first iterator over the items in the book.
     pub fn iter(&self) -> BookItems {
         BookItems {
@@ -87,6 +226,33 @@ impl Book {
         }
     }
 
+    /// Get a depth-first iterator over the items in the book which also says
+    /// where each item is, i.e. how deeply it's nested and which chapter it's
+    /// nested in.
+    ///
+    /// ```rust
+    /// # use mdbook::book::{Book, BookItem, Chapter};
+    /// # let mut book = Book::new();
+    /// # book.push_item(Chapter::new("Intro", String::new(), "intro.md"));
+    /// for item in book.iter_with_context() {
+    ///     if let BookItem::Chapter(ref ch) = *item.item {
+    ///         let indent = "  ".repeat(item.depth);
+    ///         match item.parent {
+    ///             Some(parent) => println!("{}{} (in {})", indent, ch.name, parent.name),
+    ///             None => println!("{}{}", indent, ch.name),
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub fn iter_with_context(&self) -> BookItemsWithContext {
+        BookItemsWithContext {
+            items: self.sections
+                .iter()
+                .map(|item| BookItemRef::new(item, 0, None))
+                .collect(),
+        }
+    }
+
     /// Recursively apply a closure to each item in the book, allowing you to
     /// mutate them.
     ///
@@ -102,11 +268,77 @@ impl Book {
         for_each_mut(&mut func, &mut self.sections);
     }
 
+    /// Apply a closure to every chapter in the book, along with where the
+    /// chapter is in the book.
+    ///
+    /// Unlike `for_each_mut()`, chapters are visited in the order they appear
+    /// in the book (i.e. a chapter comes before its nested chapters), and
+    /// separators and part titles are skipped.
+    pub fn for_each_chapter_mut<F>(&mut self, mut func: F)
+    where
+        F: FnMut(&mut Chapter, &ChapterContext),
+    {
+        for_each_chapter_mut(&mut func, &mut self.sections, &mut Vec::new());
+    }
+
     /// Append a `BookItem` to the `Book`.
     pub fn push_item<I: Into<BookItem>>(&mut self, item: I) -> &mut Self {
         self.sections.push(item.into());
         self
     }
+
+    /// Number the chapters of every part from 1 again, instead of carrying
+    /// on from the previous part.
+    pub fn restart_numbering_at_parts(&mut self) {
+        let mut offset = 0;
+        let mut last = 0;
+
+        for item in &mut self.sections {
+            match *item {
+                BookItem::PartTitle(_) => offset = last,
+                BookItem::Chapter(ref mut ch) => {
+                    if let Some(first) = ch.number.as_ref().and_then(|n| n.first().cloned()) {
+                        last = first;
+                        shift_section_numbers(ch, offset);
+                    }
+                }
+                BookItem::Separator => {}
+            }
+        }
+    }
+}
+
+fn shift_section_numbers(ch: &mut Chapter, by: u32) {
+    if let Some(ref mut number) = ch.number {
+        number.0[0] -= by;
+    }
+
+    for item in &mut ch.sub_items {
+        if let BookItem::Chapter(ref mut sub) = *item {
+            shift_section_numbers(sub, by);
+        }
+    }
+}
+
+fn for_each_chapter_mut<F>(func: &mut F, items: &mut [BookItem], parents: &mut Vec<PathBuf>)
+where
+    F: FnMut(&mut Chapter, &ChapterContext),
+{
+    for item in items {
+        if let BookItem::Chapter(ref mut ch) = *item {
+            func(
+                ch,
+                &ChapterContext {
+                    depth: parents.len(),
+                    parents: &parents[..],
+                },
+            );
+
+            parents.push(ch.path.clone());
+            for_each_chapter_mut(func, &mut ch.sub_items, parents);
+            parents.pop();
+        }
+    }
 }
 
 pub fn for_each_mut<'a, F, I>(func: &mut F, items: I)
@@ -130,6 +362,8 @@ pub enum BookItem {
     Chapter(Chapter),
     /// A section separator.
     Separator,
+    /// The title of a part, grouping the numbered chapters which follow it.
+    PartTitle(String),
 }
 
 impl From<Chapter> for BookItem {
@@ -152,6 +386,20 @@ pub struct Chapter {
     pub sub_items: Vec<BookItem>,
     /// The chapter's location, relative to the `SUMMARY.md` file.
     pub path: PathBuf,
+    /// Arbitrary metadata taken from the chapter's front matter.
+    pub front_matter: Table,
+    /// How many lines of front matter were taken off the top of the
+    /// chapter's source, so line `n` of `content` is line
+    /// `n + front_matter_lines` of the file.
+    pub front_matter_lines: usize,
+    /// Hidden chapters are rendered like any other, but left out of the
+    /// book's navigation so they can only be reached by linking to them.
+    pub hidden: bool,
+    /// The file the chapter was loaded from, if that's somewhere other than
+    /// `path` in the source directory, e.g. for an untranslated chapter
+    /// taken from the main language.
+    #[serde(default)]
+    pub source_path: Option<PathBuf>,
 }
 
 impl Chapter {
@@ -169,8 +417,15 @@ impl Chapter {
 /// Use the provided `Summary` to load a `Book` from disk.
 ///
 /// You need to pass in the book's source directory because all the links in
-/// `SUMMARY.md` give the chapter locations relative to it.
-fn load_book_from_disk<P: AsRef<Path>>(summary: &Summary, src_dir: P) -> Result<Book> {
+/// `SUMMARY.md` give the chapter locations relative to it. Chapters which
+/// don't exist there are loaded from the `fallback_dir`, if there is one.
+fn load_book_from_disk<P: AsRef<Path>>(
+    summary: &Summary,
+    src_dir: P,
+    fallback_dir: Option<&Path>,
+    mounts: &MountedBooks,
+    converters: &Converters,
+) -> Result<Book> {
     debug!("Loading the book from disk");
     let src_dir = src_dir.as_ref();
 
@@ -183,54 +438,195 @@ fn load_book_from_disk<P: AsRef<Path>>(summary: &Summary, src_dir: P) -> Result<
     let mut chapters = Vec::new();
 
     for summary_item in summary_items {
-        let chapter = load_summary_item(summary_item, src_dir)?;
+        let chapter = load_summary_item(summary_item, src_dir, fallback_dir, mounts, converters)?;
         chapters.push(chapter);
     }
 
+    // chapters can hide themselves in their front matter, which the summary
+    // didn't know about when it numbered them
+    number_visible_chapters(&mut chapters, &[]);
+
     Ok(Book { sections: chapters })
 }
 
-fn load_summary_item<P: AsRef<Path>>(item: &SummaryItem, src_dir: P) -> Result<BookItem> {
+/// Number the numbered chapters which aren't hidden one after the other,
+/// nested below the `parent` section, and take the numbers of the hidden
+/// ones away.
+fn number_visible_chapters(items: &mut [BookItem], parent: &[u32]) {
+    let mut last = 0;
+
+    for item in items {
+        if let BookItem::Chapter(ref mut ch) = *item {
+            if ch.hidden {
+                remove_section_numbers(ch);
+            } else if ch.number.is_some() {
+                last += 1;
+                let mut number = parent.to_vec();
+                number.push(last);
+                number_visible_chapters(&mut ch.sub_items, &number);
+                ch.number = Some(SectionNumber(number));
+            }
+        }
+    }
+}
+
+fn remove_section_numbers(ch: &mut Chapter) {
+    ch.number = None;
+
+    for item in &mut ch.sub_items {
+        if let BookItem::Chapter(ref mut sub) = *item {
+            remove_section_numbers(sub);
+        }
+    }
+}
+
+fn load_summary_item<P: AsRef<Path>>(
+    item: &SummaryItem,
+    src_dir: P,
+    fallback_dir: Option<&Path>,
+    mounts: &MountedBooks,
+    converters: &Converters,
+) -> Result<BookItem> {
     match *item {
         SummaryItem::Separator => Ok(BookItem::Separator),
-        SummaryItem::Link(ref link) => load_chapter(link, src_dir).map(|c| BookItem::Chapter(c)),
+        SummaryItem::PartTitle(ref title) => Ok(BookItem::PartTitle(title.clone())),
+        SummaryItem::MountedBook(_, ref name) => bail!(
+            "The book \"{}\" can't be mounted inside another mounted book",
+            name
+        ),
+        SummaryItem::Link(ref link) => {
+            load_chapter(link, src_dir, fallback_dir, mounts, converters)
+                .map(|c| BookItem::Chapter(c))
+        }
     }
 }
 
-fn load_chapter<P: AsRef<Path>>(link: &Link, src_dir: P) -> Result<Chapter> {
+fn load_chapter<P: AsRef<Path>>(
+    link: &Link,
+    src_dir: P,
+    fallback_dir: Option<&Path>,
+    mounts: &MountedBooks,
+    converters: &Converters,
+) -> Result<Chapter> {
     debug!("Loading {} ({})", link.name, link.location.display());
     let src_dir = src_dir.as_ref();
 
-    let location = if link.location.is_absolute() {
-        link.location.clone()
-    } else {
-        src_dir.join(&link.location)
+    // untranslated chapters are taken from the fallback directory
+    let chapter_dir = match fallback_dir {
+        Some(fallback_dir)
+            if !link.location.is_absolute() && !src_dir.join(&link.location).exists() =>
+        {
+            debug!("\"{}\" hasn't been translated yet", link.name);
+            fallback_dir
+        }
+        _ => src_dir,
+    };
+
+    let mounted = mounts.mounted_path(&link.location);
+    let location = match mounted {
+        Some(ref mounted) => mounted.clone(),
+        None if link.location.is_absolute() => link.location.clone(),
+        None => chapter_dir.join(&link.location),
     };
 
-    let mut f = File::open(&location)
-        .chain_err(|| format!("Chapter file not found, {}", link.location.display()))?;
+    let load_error = || ErrorKind::ChapterLoad(link.name.clone(), location.clone());
+
+    let mut f = File::open(&location).chain_err(&load_error)?;
 
     let mut content = String::new();
-    f.read_to_string(&mut content)
-        .chain_err(|| format!("Unable to read \"{}\" ({})", link.name, location.display()))?;
+    f.read_to_string(&mut content).chain_err(&load_error)?;
+
+    let content = to_markdown(&location, content, converters).chain_err(&load_error)?;
+
+    let stripped = match mounted {
+        Some(_) => link.location.as_path(),
+        None => location
+            .strip_prefix(&chapter_dir)
+            .expect("Chapters are always inside a book"),
+    };
 
-    let stripped = location
-        .strip_prefix(&src_dir)
-        .expect("Chapters are always inside a book");
+    let (front_matter, body) = parse_front_matter(&content).chain_err(&load_error)?;
+    let front_matter_lines = content[..content.len() - body.len()].matches('\n').count();
 
-    let mut ch = Chapter::new(&link.name, content, stripped);
+    let hidden = match front_matter.get("hidden") {
+        Some(&Value::Boolean(hidden)) => hidden,
+        _ => false,
+    };
+
+    let mut ch = Chapter::new(&link.name, body.to_string(), stripped);
     ch.number = link.number.clone();
+    ch.front_matter = front_matter;
+    ch.front_matter_lines = front_matter_lines;
+    ch.hidden = link.hidden || hidden;
+    if mounted.is_none() && chapter_dir != src_dir {
+        ch.source_path = Some(location.clone());
+    }
 
-    let sub_items = link.nested_items
+    let mut sub_items = link.nested_items
         .iter()
-        .map(|i| load_summary_item(i, src_dir))
+        .map(|i| load_summary_item(i, src_dir, fallback_dir, mounts, converters))
         .collect::<Result<Vec<_>>>()?;
 
+    if ch.hidden {
+        // there's no way to reach the nested chapters from the navigation
+        // when their parent isn't in it
+        for_each_mut(
+            &mut |item: &mut BookItem| {
+                if let BookItem::Chapter(ref mut nested) = *item {
+                    nested.hidden = true;
+                }
+            },
+            &mut sub_items,
+        );
+    }
+
     ch.sub_items = sub_items;
 
     Ok(ch)
 }
 
+/// Split the front matter off the top of a chapter, returning it along with
+/// the rest of the chapter's contents.
+///
+/// Front matter is a TOML table surrounded by lines containing only `+++`:
+///
+/// ```text
+/// +++
+/// authors = ["Michael-F-Bryan"]
+/// tags = ["configuration"]
+/// +++
+///
+/// # Chapter 1
+/// ```
+fn parse_front_matter(content: &str) -> Result<(Table, &str)> {
+    const DELIMITER: &str = "+++";
+
+    let first_line_end = match content.find('\n') {
+        Some(ix) => ix,
+        None => return Ok((Table::new(), content)),
+    };
+
+    if content[..first_line_end].trim_right() != DELIMITER {
+        return Ok((Table::new(), content));
+    }
+
+    let rest = &content[first_line_end + 1..];
+    let mut offset = 0;
+
+    for line in rest.split('\n') {
+        if line.trim_right() == DELIMITER {
+            let front_matter = toml::from_str(&rest[..offset]).chain_err(|| "Invalid TOML")?;
+            let body_start = (offset + line.len() + 1).min(rest.len());
+
+            return Ok((front_matter, &rest[body_start..]));
+        }
+
+        offset += line.len() + 1;
+    }
+
+    bail!("The front matter is missing its closing \"{}\"", DELIMITER)
+}
+
 /// A depth-first iterator over the items in a book.
 ///
 /// # Note
@@ -260,6 +656,88 @@ impl<'a> Iterator for BookItems<'a> {
     }
 }
 
+/// An item in the book, along with where it is in the book.
+///
+/// # Note
+///
+/// This is created by the [`Book::iter_with_context()`] method.
+///
+/// [`Book::iter_with_context()`]: struct.Book.html#method.iter_with_context
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BookItemRef<'a> {
+    /// The item itself.
+    pub item: &'a BookItem,
+    /// How deeply the item is nested, with top-level items at 0.
+    pub depth: usize,
+    /// The item's section number, if it's a numbered chapter.
+    pub number: Option<&'a SectionNumber>,
+    /// The chapter this item is nested in.
+    pub parent: Option<&'a Chapter>,
+}
+
+impl<'a> BookItemRef<'a> {
+    fn new(item: &'a BookItem, depth: usize, parent: Option<&'a Chapter>) -> BookItemRef<'a> {
+        let number = match *item {
+            BookItem::Chapter(ref ch) => ch.number.as_ref(),
+            _ => None,
+        };
+
+        BookItemRef {
+            item,
+            depth,
+            number,
+            parent,
+        }
+    }
+}
+
+/// A depth-first iterator over a book's items and where they are.
+///
+/// # Note
+///
+/// This struct shouldn't be created directly, instead prefer the
+/// [`Book::iter_with_context()`] method.
+///
+/// [`Book::iter_with_context()`]: struct.Book.html#method.iter_with_context
+pub struct BookItemsWithContext<'a> {
+    items: VecDeque<BookItemRef<'a>>,
+}
+
+impl<'a> Iterator for BookItemsWithContext<'a> {
+    type Item = BookItemRef<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.items.pop_front();
+
+        if let Some(BookItemRef {
+            item: &BookItem::Chapter(ref ch),
+            depth,
+            ..
+        }) = item
+        {
+            for sub_item in ch.sub_items.iter().rev() {
+                self.items
+                    .push_front(BookItemRef::new(sub_item, depth + 1, Some(ch)));
+            }
+        }
+
+        item
+    }
+}
+
+/// Where a chapter is in the book, as given to the closure passed to
+/// [`Book::for_each_chapter_mut()`].
+///
+/// [`Book::for_each_chapter_mut()`]: struct.Book.html#method.for_each_chapter_mut
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChapterContext<'a> {
+    /// How deeply the chapter is nested, with top-level chapters at 0.
+    pub depth: usize,
+    /// The paths of the chapters this one is nested in, starting with the
+    /// top-level one.
+    pub parents: &'a [PathBuf],
+}
+
 impl Display for Chapter {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         if let Some(ref section_number) = self.number {
@@ -275,6 +753,7 @@ mod tests {
     use super::*;
     use tempdir::TempDir;
     use std::io::Write;
+    use utils::fs::write_files;
 
     const DUMMY_SRC: &'static str = "
 # Dummy Chapter
@@ -326,16 +805,117 @@ And here is some \
         let (link, temp_dir) = dummy_link();
         let should_be = Chapter::new("Chapter 1", DUMMY_SRC.to_string(), "chapter_1.md");
 
-        let got = load_chapter(&link, temp_dir.path()).unwrap();
+        let got = load_chapter(&link, temp_dir.path(), None, &MountedBooks::default(), &Converters::default()).unwrap();
         assert_eq!(got, should_be);
     }
 
+    #[test]
+    fn notebooks_are_loaded_as_markdown() {
+        let temp = TempDir::new("book").unwrap();
+        let chapter_path = temp.path().join("analysis.ipynb");
+        File::create(&chapter_path)
+            .unwrap()
+            .write_all(new_chapter(&chapter_path, "Analysis").as_bytes())
+            .unwrap();
+        let link = Link::new("Analysis", "analysis.ipynb");
+
+        let got = load_chapter(&link, temp.path(), None, &MountedBooks::default(), &Converters::default()).unwrap();
+
+        assert_eq!(got.content, "# Analysis\n\n");
+        assert_eq!(got.path, Path::new("analysis.ipynb"));
+    }
+
+    #[test]
+    fn chapters_remember_how_much_front_matter_they_had() {
+        let temp = TempDir::new("book").unwrap();
+        File::create(temp.path().join("chapter.md"))
+            .unwrap()
+            .write_all(b"+++\nhidden = false\n+++\n# Chapter\n")
+            .unwrap();
+        let link = Link::new("Chapter", "chapter.md");
+
+        let got = load_chapter(&link, temp.path(), None, &MountedBooks::default(), &Converters::default()).unwrap();
+
+        assert_eq!(got.content, "# Chapter\n");
+        assert_eq!(got.front_matter_lines, 3);
+    }
+
     #[test]
     fn cant_load_a_nonexistent_chapter() {
         let link = Link::new("Chapter 1", "/foo/bar/baz.md");
 
-        let got = load_chapter(&link, "");
-        assert!(got.is_err());
+        let got = load_chapter(&link, "", None, &MountedBooks::default(), &Converters::default()).unwrap_err();
+
+        match *got.kind() {
+            ErrorKind::ChapterLoad(ref name, ref file) => {
+                assert_eq!(name, "Chapter 1");
+                assert_eq!(file, Path::new("/foo/bar/baz.md"));
+            }
+            ref other => panic!("Unexpected error: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn front_matter_is_split_from_the_content() {
+        let src = "+++\ntags = [\"intro\"]\nauthor = \"Michael\"\n+++\n# Chapter 1\n";
+
+        let (front_matter, content) = parse_front_matter(src).unwrap();
+
+        assert_eq!(content, "# Chapter 1\n");
+        assert_eq!(front_matter["author"].as_str(), Some("Michael"));
+        assert_eq!(front_matter["tags"][0].as_str(), Some("intro"));
+    }
+
+    #[test]
+    fn chapters_without_front_matter_are_left_alone() {
+        let (front_matter, content) = parse_front_matter(DUMMY_SRC).unwrap();
+
+        assert!(front_matter.is_empty());
+        assert_eq!(content, DUMMY_SRC);
+    }
+
+    #[test]
+    fn unterminated_front_matter_is_an_error() {
+        let src = "+++\ntitle = \"Oops\"\n# Chapter 1\n";
+
+        assert!(parse_front_matter(src).is_err());
+    }
+
+    #[test]
+    fn untranslated_chapters_fall_back_to_the_original() {
+        let temp = TempDir::new("book").unwrap();
+        let en = temp.path().join("en");
+        let fr = temp.path().join("fr");
+        fs::create_dir_all(&en).unwrap();
+        fs::create_dir_all(&fr).unwrap();
+
+        File::create(en.join("SUMMARY.md"))
+            .unwrap()
+            .write_all(b"- [First](first.md)\n- [Second](second.md)\n")
+            .unwrap();
+        File::create(en.join("first.md"))
+            .unwrap()
+            .write_all(b"# First")
+            .unwrap();
+        File::create(en.join("second.md"))
+            .unwrap()
+            .write_all(b"# Second")
+            .unwrap();
+        File::create(fr.join("first.md"))
+            .unwrap()
+            .write_all(b"# Premier")
+            .unwrap();
+
+        let got = load_translation(&fr, &en, &BuildConfig::default()).unwrap();
+
+        let contents: Vec<_> = got.iter()
+            .filter_map(|item| match *item {
+                BookItem::Chapter(ref ch) => Some(ch.content.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(contents, vec!["# Premier", "# Second"]);
+        assert!(!fr.join("second.md").exists());
     }
 
     #[test]
@@ -348,6 +928,10 @@ And here is some \
             number: Some(SectionNumber(vec![1, 2])),
             path: PathBuf::from("second.md"),
             sub_items: Vec::new(),
+            front_matter: Table::new(),
+            hidden: false,
+            front_matter_lines: 0,
+            source_path: None,
         };
         let should_be = BookItem::Chapter(Chapter {
             name: String::from("Chapter 1"),
@@ -359,9 +943,14 @@ And here is some \
                 BookItem::Separator,
                 BookItem::Chapter(nested.clone()),
             ],
+            front_matter: Table::new(),
+            hidden: false,
+            front_matter_lines: 0,
+            source_path: None,
         });
 
-        let got = load_summary_item(&SummaryItem::Link(root), temp.path()).unwrap();
+        let got = load_summary_item(&SummaryItem::Link(root), temp.path(), None, &MountedBooks::default(), &Converters::default())
+            .unwrap();
         assert_eq!(got, should_be);
     }
 
@@ -383,7 +972,7 @@ And here is some \
             ],
         };
 
-        let got = load_book_from_disk(&summary, temp.path()).unwrap();
+        let got = load_book_from_disk(&summary, temp.path(), None, &MountedBooks::default(), &Converters::default()).unwrap();
 
         assert_eq!(got, should_be);
     }
@@ -430,6 +1019,10 @@ And here is some \
                             "Chapter_1/goodbye.md",
                         )),
                     ],
+                    front_matter: Table::new(),
+                    hidden: false,
+                    front_matter_lines: 0,
+                    source_path: None,
                 }),
                 BookItem::Separator,
             ],
@@ -455,6 +1048,70 @@ And here is some \
         assert_eq!(chapter_names, should_be);
     }
 
+    fn nested_book() -> Book {
+        let mut nested = Chapter::new("Nested", String::new(), "first/nested.md");
+        nested.number = Some(SectionNumber(vec![1, 1]));
+        let mut first = Chapter::new("First", String::new(), "first/index.md");
+        first.number = Some(SectionNumber(vec![1]));
+        first.sub_items = vec![BookItem::Chapter(nested), BookItem::Separator];
+
+        let mut book = Book::new();
+        book.push_item(first)
+            .push_item(Chapter::new("Appendix", String::new(), "appendix.md"));
+        book
+    }
+
+    #[test]
+    fn iterate_over_items_with_their_depth_and_parent() {
+        let book = nested_book();
+
+        let got: Vec<_> = book.iter_with_context()
+            .map(|item| {
+                let name = match *item.item {
+                    BookItem::Chapter(ref ch) => ch.name.clone(),
+                    _ => String::from("---"),
+                };
+                let parent = item.parent.map(|p| p.name.clone());
+                (name, item.depth, item.number.map(|n| n.to_string()), parent)
+            })
+            .collect();
+
+        let first = Some(String::from("First"));
+        assert_eq!(
+            got,
+            vec![
+                (String::from("First"), 0, Some(String::from("1.")), None),
+                (String::from("Nested"), 1, Some(String::from("1.1.")), first.clone()),
+                (String::from("---"), 1, None, first),
+                (String::from("Appendix"), 0, None, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn for_each_chapter_mut_says_where_the_chapter_is() {
+        let mut book = nested_book();
+
+        book.for_each_chapter_mut(|ch, ctx| {
+            ch.content = format!("depth {}, parents {:?}", ctx.depth, ctx.parents);
+        });
+
+        let got: Vec<_> = book.iter()
+            .filter_map(|item| match *item {
+                BookItem::Chapter(ref ch) => Some(ch.content.clone()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            got,
+            vec![
+                "depth 0, parents []",
+                "depth 1, parents [\"first/index.md\"]",
+                "depth 0, parents []",
+            ]
+        );
+    }
+
     #[test]
     fn for_each_mut_visits_all_items() {
         let mut book = Book {
@@ -477,6 +1134,10 @@ And here is some \
                             "Chapter_1/goodbye.md",
                         )),
                     ],
+                    front_matter: Table::new(),
+                    hidden: false,
+                    front_matter_lines: 0,
+                    source_path: None,
                 }),
                 BookItem::Separator,
             ],
@@ -504,7 +1165,7 @@ And here is some \
             ..Default::default()
         };
 
-        let got = load_book_from_disk(&summary, temp.path());
+        let got = load_book_from_disk(&summary, temp.path(), None, &MountedBooks::default(), &Converters::default());
         assert!(got.is_err());
     }
 
@@ -525,7 +1186,65 @@ And here is some \
             ..Default::default()
         };
 
-        let got = load_book_from_disk(&summary, temp.path());
+        let got = load_book_from_disk(&summary, temp.path(), None, &MountedBooks::default(), &Converters::default());
         assert!(got.is_err());
     }
+
+    #[test]
+    fn restart_the_numbering_of_every_part() {
+        let chapter = |name: &str, number: Vec<u32>, sub_items: Vec<BookItem>| {
+            BookItem::Chapter(Chapter {
+                name: name.to_string(),
+                number: Some(SectionNumber(number)),
+                sub_items,
+                ..Default::default()
+            })
+        };
+        let mut book = Book {
+            sections: vec![
+                BookItem::PartTitle(String::from("Part I")),
+                chapter("First", vec![1], Vec::new()),
+                chapter("Second", vec![2], Vec::new()),
+                BookItem::PartTitle(String::from("Part II")),
+                chapter("Third", vec![3], vec![chapter("Nested", vec![3, 1], Vec::new())]),
+            ],
+        };
+
+        book.restart_numbering_at_parts();
+
+        let numbers: Vec<String> = book.iter()
+            .filter_map(|item| match *item {
+                BookItem::Chapter(ref ch) => ch.number.as_ref().map(|n| n.to_string()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(numbers, vec!["1.", "2.", "1.", "1.1."]);
+    }
+
+    #[test]
+    fn chapters_hidden_by_their_front_matter_dont_use_up_a_number() {
+        let temp = TempDir::new("book").unwrap();
+        let files = [
+            ("egg.md", "+++\nhidden = true\n+++\n# Egg\n"),
+            ("yolk.md", "# Yolk\n"),
+            ("first.md", "# First\n"),
+            ("nested.md", "# Nested\n"),
+        ];
+        write_files(temp.path(), &files).unwrap();
+        let summary = "- [Egg](egg.md)\n  - [Yolk](yolk.md)\n\
+                       - [First](first.md)\n  - [Nested](nested.md)\n";
+
+        let book = load_book_with_summary(temp.path(), summary, &BuildConfig::default()).unwrap();
+
+        let numbers: Vec<Option<String>> = book.iter()
+            .filter_map(|item| match *item {
+                BookItem::Chapter(ref ch) => Some(ch.number.as_ref().map(|n| n.to_string())),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            numbers,
+            vec![None, None, Some(String::from("1.")), Some(String::from("1.1."))]
+        );
+    }
 }
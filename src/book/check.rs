@@ -0,0 +1,205 @@
+//! Making sure the links between chapters (and to their headings) actually go
+//! somewhere in the rendered book.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use pulldown_cmark::{Event, Parser, Tag};
+use regex::Regex;
+
+use super::{Book, BookItem, Chapter, MountedBooks};
+use utils::fs::file_to_string;
+use utils::links::{find_line, is_external, normalize};
+
+/// A link which doesn't point at anything in the rendered book.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BrokenLink {
+    /// The source file of the chapter containing the link, relative to the
+    /// source directory.
+    pub chapter: PathBuf,
+    /// The line the link is on, if we could find it.
+    pub line: Option<usize>,
+    /// The link's destination, as written.
+    pub link: String,
+    /// Why the link is broken.
+    pub reason: String,
+}
+
+impl fmt::Display for BrokenLink {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.chapter.display())?;
+        if let Some(line) = self.line {
+            write!(f, ":{}", line)?;
+        }
+        write!(f, ": broken link to \"{}\" ({})", self.link, self.reason)
+    }
+}
+
+/// Check the links in every chapter against the HTML rendered into
+/// `html_dir`, using the chapter sources in `src_dir` (or in the books mounted
+/// in it) to find out which line each link is on.
+pub fn check_links(
+    book: &Book,
+    src_dir: &Path,
+    mounts: &MountedBooks,
+    html_dir: &Path,
+) -> Vec<BrokenLink> {
+    let mut anchors = HashMap::new();
+    let mut broken = Vec::new();
+
+    for item in book.iter() {
+        if let BookItem::Chapter(ref ch) = *item {
+            if ch.path.as_os_str().is_empty() {
+                continue;
+            }
+
+            let source = file_to_string(mounts.source_path(src_dir, &ch.path)).unwrap_or_default();
+
+            for link in links(ch) {
+                if let Some(reason) = check_link(ch, &link, html_dir, &mut anchors) {
+                    broken.push(BrokenLink {
+                        chapter: ch.path.clone(),
+                        line: find_line(&source, &link),
+                        link,
+                        reason,
+                    });
+                }
+            }
+        }
+    }
+
+    broken
+}
+
+/// All the link destinations in a chapter which point inside the book.
+fn links(ch: &Chapter) -> Vec<String> {
+    Parser::new(&ch.content)
+        .filter_map(|event| match event {
+            Event::Start(Tag::Link(dest, _)) => Some(dest.into_owned()),
+            _ => None,
+        })
+        .filter(|dest| !dest.is_empty() && !is_external(dest))
+        .collect()
+}
+
+/// Returns why a link is broken, or `None` if it's fine.
+fn check_link(
+    ch: &Chapter,
+    link: &str,
+    html_dir: &Path,
+    anchors: &mut HashMap<PathBuf, Vec<String>>,
+) -> Option<String> {
+    let (path, anchor) = match link.find('#') {
+        Some(ix) => (&link[..ix], Some(&link[ix + 1..])),
+        None => (link, None),
+    };
+
+    // Links are relative to the book's root (the theme sets a `<base>`),
+    // except for links to somewhere in the same page.
+    let target = if path.is_empty() {
+        ch.path.with_extension("html")
+    } else {
+        match normalize(Path::new(path.trim_left_matches('/'))) {
+            Some(target) => target,
+            None => return Some(String::from("it points outside the book")),
+        }
+    };
+
+    let mut full_path = html_dir.join(&target);
+    if !full_path.exists() && is_rendered_readme(&target) {
+        // the `index` preprocessor renders a README.md as the directory's
+        // index.html
+        full_path.set_file_name("index.html");
+    }
+    if !full_path.exists() {
+        return Some(format!("{} doesn't exist", target.display()));
+    }
+
+    // with pretty URLs `foo.html` only redirects to `foo/index.html`
+    let pretty_path = full_path.with_extension("").join("index.html");
+    if pretty_path.exists() {
+        full_path = pretty_path;
+    }
+
+    let anchor = match anchor {
+        Some(anchor) if !anchor.is_empty() => anchor,
+        _ => return None,
+    };
+    if full_path.extension().and_then(|ext| ext.to_str()) != Some("html") {
+        return None;
+    }
+
+    let ids = anchors
+        .entry(full_path.clone())
+        .or_insert_with(|| ids_in(&file_to_string(&full_path).unwrap_or_default()));
+
+    if ids.iter().any(|id| id == anchor) {
+        None
+    } else {
+        Some(format!("{} has no \"{}\" heading", target.display(), anchor))
+    }
+}
+
+fn is_rendered_readme(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .map(|name| name.eq_ignore_ascii_case("README.html"))
+        .unwrap_or(false)
+}
+
+/// All the element ids in a rendered page.
+fn ids_in(html: &str) -> Vec<String> {
+    lazy_static! {
+        static ref ID: Regex = Regex::new(r#"\sid="([^"]*)""#).unwrap();
+    }
+
+    ID.captures_iter(html).map(|caps| caps[1].to_string()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{self, File};
+    use std::io::Write;
+    use tempdir::TempDir;
+
+    #[test]
+    fn find_the_ids_in_a_page() {
+        let html = r#"<a class="header" href="x.html#intro" id="intro"><h1>Intro</h1></a>
+                      <div id="sidebar" class="sidebar"></div>"#;
+
+        assert_eq!(ids_in(html), vec!["intro", "sidebar"]);
+    }
+
+    #[test]
+    fn report_broken_links_with_their_line() {
+        let temp = TempDir::new("mdbook").unwrap();
+        let src = temp.path().join("src");
+        let html = temp.path().join("book");
+        fs::create_dir_all(src.join("nested")).unwrap();
+        fs::create_dir_all(html.join("nested")).unwrap();
+
+        let content = "# Chapter\n\n[ok](nested/other.html#usage)\n[missing](gone.html)\n\
+                       [bad anchor](nested/other.html#nope)\n[external](https://example.com)\n";
+        File::create(src.join("chapter.md"))
+            .unwrap()
+            .write_all(content.as_bytes())
+            .unwrap();
+        File::create(html.join("chapter.html")).unwrap();
+        File::create(html.join("nested/other.html"))
+            .unwrap()
+            .write_all(br#"<a class="header" href="nested/other.html#usage" id="usage">"#)
+            .unwrap();
+
+        let mut book = Book::new();
+        book.push_item(Chapter::new("Chapter", content.to_string(), "chapter.md"));
+
+        let got = check_links(&book, &src, &MountedBooks::default(), &html);
+
+        assert_eq!(got.len(), 2);
+        assert_eq!(got[0].link, "gone.html");
+        assert_eq!(got[0].line, Some(4));
+        assert_eq!(got[1].link, "nested/other.html#nope");
+        assert_eq!(got[1].line, Some(5));
+    }
+}
@@ -0,0 +1,125 @@
+//! Finding the fenced code blocks in a chapter, so they can be tested.
+
+/// A fenced code block.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CodeBlock {
+    /// The first word of the info string, e.g. `python` for ```` ```python ````.
+    pub language: String,
+    /// The rest of the info string's attributes, e.g. `ignore`.
+    pub attributes: Vec<String>,
+    /// The (1-based) line the opening fence is on.
+    pub line: usize,
+    /// The block's contents.
+    pub code: String,
+}
+
+/// Get all the fenced code blocks in some markdown, in the order they appear.
+///
+/// Blocks without an info string are skipped, seeing as we wouldn't know what
+/// to do with them anyway.
+pub fn code_blocks(content: &str) -> Vec<CodeBlock> {
+    let mut blocks = Vec::new();
+    let mut current: Option<(&str, CodeBlock)> = None;
+
+    for (i, line) in content.lines().enumerate() {
+        let trimmed = line.trim_left();
+
+        let mut finished = false;
+        if let Some((fence, ref mut block)) = current {
+            if is_closing_fence(trimmed, fence) {
+                finished = true;
+            } else {
+                block.code.push_str(line);
+                block.code.push('\n');
+            }
+        } else if let Some(fence) = opening_fence(trimmed) {
+            let mut words = trimmed[fence.len()..]
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|word| !word.is_empty())
+                .map(String::from);
+
+            let language = words.next().unwrap_or_default();
+            let block = CodeBlock {
+                language,
+                attributes: words.collect(),
+                line: i + 1,
+                code: String::new(),
+            };
+            current = Some((fence, block));
+        }
+
+        if finished {
+            if let Some((_, block)) = current.take() {
+                if !block.language.is_empty() {
+                    blocks.push(block);
+                }
+            }
+        }
+    }
+
+    blocks
+}
+
+pub(crate) fn opening_fence(line: &str) -> Option<&str> {
+    for fence in &['`', '~'] {
+        let len = line.len() - line.trim_left_matches(*fence).len();
+        if len >= 3 {
+            return Some(&line[..len]);
+        }
+    }
+
+    None
+}
+
+/// A closing fence is made of the same character as the opening one, at
+/// least as many times, and nothing else.
+pub(crate) fn is_closing_fence(line: &str, fence: &str) -> bool {
+    let rest = line.trim_left_matches(&fence[..1]);
+    line.len() - rest.len() >= fence.len() && rest.trim().is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_the_code_blocks() {
+        let src = "# Chapter\n\n```python\nprint(1)\n```\n\nSome text\n\n~~~bash,ignore\necho hi\n\
+                   ~~~\n\n```\nno language\n```\n";
+
+        let got = code_blocks(src);
+
+        assert_eq!(
+            got,
+            vec![
+                CodeBlock {
+                    language: String::from("python"),
+                    attributes: Vec::new(),
+                    line: 3,
+                    code: String::from("print(1)\n"),
+                },
+                CodeBlock {
+                    language: String::from("bash"),
+                    attributes: vec![String::from("ignore")],
+                    line: 9,
+                    code: String::from("echo hi\n"),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn longer_fences_can_contain_shorter_ones() {
+        let src = "````markdown\n```rust\nfn main() {}\n```\n````\n";
+
+        let got = code_blocks(src);
+
+        assert_eq!(got.len(), 1);
+        assert_eq!(got[0].code, "```rust\nfn main() {}\n```\n");
+
+        let src = "```rust\nfn main() {}\n`````\n\n```bash\necho hi\n```\n";
+        let got = code_blocks(src);
+        assert_eq!(got.len(), 2);
+        assert_eq!(got[0].code, "fn main() {}\n");
+    }
+}
@@ -0,0 +1,143 @@
+//! Chapters written in something other than markdown, which are converted
+//! into markdown when the book is loaded.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use super::{Book, BookItem, MountedBooks};
+use super::asciidoc::asciidoc_to_markdown;
+use super::notebook::{empty_notebook, notebook_to_markdown};
+use super::org::org_to_markdown;
+use super::rst::rst_to_markdown;
+use config::SlugStyle;
+use utils::process::{self, pipe_through};
+use errors::*;
+
+/// The extensions of the files mdBook can load chapters from without a
+/// `build.converters` command.
+pub const CHAPTER_EXTENSIONS: &[&str] = &["md", "ipynb", "adoc", "asciidoc", "org", "rst"];
+
+/// The source files of the chapters which were converted from another format,
+/// which renderers shouldn't copy into the rendered book. Other files with the
+/// same extensions (e.g. a notebook which is only there to be downloaded) are
+/// copied like any other file.
+pub fn converted_chapters(book: &Book, src_dir: &Path, mounts: &MountedBooks) -> Vec<PathBuf> {
+    book.iter()
+        .filter_map(|item| match *item {
+            BookItem::Chapter(ref ch) if ch.path.extension().map_or(false, |e| e != "md") => {
+                Some(mounts.source_path(src_dir, &ch.path))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// How chapters are converted into markdown.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Converters {
+    /// The commands of `build.converters`, keyed by file extension.
+    pub commands: BTreeMap<String, String>,
+    /// How the book turns headings into IDs, so links to a section of a
+    /// converted chapter point at the right place.
+    pub slug_style: SlugStyle,
+}
+
+/// Convert the contents of the chapter at `path` into markdown.
+///
+/// A command in `converters` (keyed by file extension) takes precedence over
+/// mdBook's own conversion, so a more complete converter can be used.
+pub fn to_markdown(path: &Path, content: String, converters: &Converters) -> Result<String> {
+    let extension = path.extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default();
+
+    if let Some(command) = converters.commands.get(extension) {
+        return run_converter(command, path, &content);
+    }
+
+    match extension {
+        "ipynb" => notebook_to_markdown(&content),
+        "adoc" | "asciidoc" => Ok(asciidoc_to_markdown(&content)),
+        "org" => Ok(org_to_markdown(&content, converters.slug_style)),
+        "rst" => Ok(rst_to_markdown(&content, converters.slug_style)),
+        _ => Ok(content),
+    }
+}
+
+/// The initial contents of a chapter which doesn't exist yet.
+pub fn new_chapter(path: &Path, title: &str) -> String {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("ipynb") => empty_notebook(title),
+        Some("adoc") | Some("asciidoc") => format!("= {}\n", title),
+        Some("org") => format!("#+TITLE: {}\n", title),
+        Some("rst") => format!("{}\n{}\n", title, "=".repeat(title.chars().count())),
+        _ => format!("# {}\n", title),
+    }
+}
+
+/// Run a converter from the chapter's directory, giving it the chapter on
+/// stdin and reading the markdown it prints.
+fn run_converter(command: &str, path: &Path, content: &str) -> Result<String> {
+    let mut cmd = process::command(command, &[])
+        .chain_err(|| format!("Invalid converter for {}", path.display()))?;
+
+    if let Some(dir) = path.parent() {
+        if !dir.as_os_str().is_empty() {
+            cmd.current_dir(dir);
+        }
+    }
+
+    debug!("Converting {} with {:?}", path.display(), cmd);
+    let output = pipe_through(&mut cmd, content.as_bytes().to_vec())
+        .chain_err(|| format!("Unable to run the converter \"{}\"", command))?;
+
+    if !output.status.success() {
+        bail!(ErrorKind::Subprocess(
+            format!("The converter \"{}\" failed", command),
+            output
+        ));
+    }
+
+    String::from_utf8(output.stdout)
+        .chain_err(|| format!("The converter \"{}\" printed invalid UTF-8", command))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use book::Chapter;
+
+    #[test]
+    fn markdown_is_left_alone() {
+        let content = "= Not a heading\n".to_string();
+
+        let got = to_markdown(Path::new("chapter.md"), content, &Converters::default());
+
+        assert_eq!(got.unwrap(), "= Not a heading\n");
+    }
+
+    #[test]
+    fn only_the_converted_chapters_are_left_out_of_the_build() {
+        let mut book = Book::new();
+        book.push_item(Chapter::new("Intro", String::new(), "intro.md"));
+        book.push_item(Chapter::new("Notebook", String::new(), "analysis.ipynb"));
+        let src_dir = Path::new("/book/src");
+
+        let got = converted_chapters(&book, src_dir, &MountedBooks::default());
+
+        assert_eq!(got, vec![src_dir.join("analysis.ipynb")]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn a_configured_converter_takes_precedence() {
+        let mut converters = Converters::default();
+        converters
+            .commands
+            .insert("adoc".to_string(), "tr '=' '#'".to_string());
+
+        let got = to_markdown(Path::new("chapter.adoc"), "= Title\n".to_string(), &converters);
+
+        assert_eq!(got.unwrap(), "# Title\n");
+    }
+}
@@ -0,0 +1,94 @@
+//! Running user-supplied code at various points of the build.
+
+use std::fmt::{self, Display, Formatter};
+use std::path::Path;
+
+use super::Book;
+use config::Config;
+use utils::process;
+use errors::*;
+
+/// The points of a build where hooks are run.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BuildStage {
+    /// Once, before anything is built. Changes made to the source directory
+    /// at this point are picked up by the build.
+    BeforeBuild,
+    /// For each renderer, once the book has been preprocessed for it.
+    AfterPreprocess,
+    /// For each renderer, once it has rendered the book.
+    AfterRender,
+    /// Once `mdbook watch` or `mdbook serve` has rebuilt the book after a
+    /// change.
+    AfterRebuild,
+}
+
+impl Display for BuildStage {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let name = match *self {
+            BuildStage::BeforeBuild => "before-build",
+            BuildStage::AfterPreprocess => "after-preprocess",
+            BuildStage::AfterRender => "after-render",
+            BuildStage::AfterRebuild => "after-rebuild",
+        };
+
+        f.write_str(name)
+    }
+}
+
+/// Everything a hook gets to know about the build.
+#[derive(Debug)]
+pub struct HookContext<'a> {
+    /// The stage of the build the hook is run at.
+    pub stage: BuildStage,
+    /// The book's root directory.
+    pub root: &'a Path,
+    /// The configuration the book is built with.
+    pub config: &'a Config,
+    /// The book, as it will be (or was) given to the renderer.
+    pub book: &'a Book,
+    /// The renderer's name, unless this is the `BeforeBuild` or
+    /// `AfterRebuild` stage.
+    pub renderer: Option<&'a str>,
+    /// The directory being rendered into. For the `BeforeBuild` and
+    /// `AfterRebuild` stages this is the book's build directory.
+    pub destination: &'a Path,
+}
+
+/// A callback registered with `MDBook::with_hook()`.
+pub type Hook = Box<Fn(&HookContext) -> Result<()>>;
+
+/// The command configured for a stage in `[build.hooks]`, if there is one.
+pub(crate) fn configured_command<'a>(ctx: &HookContext<'a>) -> Option<&'a String> {
+    let hooks = &ctx.config.build.hooks;
+
+    match ctx.stage {
+        BuildStage::BeforeBuild => hooks.before_build.as_ref(),
+        BuildStage::AfterPreprocess => hooks.after_preprocess.as_ref(),
+        BuildStage::AfterRender => hooks.after_render.as_ref(),
+        BuildStage::AfterRebuild => hooks.after_rebuild.as_ref(),
+    }
+}
+
+/// Run a hook's command from the book's root directory, substituting in the
+/// `{destination}` and `{renderer}`.
+pub(crate) fn run_command(command: &str, ctx: &HookContext) -> Result<()> {
+    let destination = ctx.destination.to_string_lossy();
+    let renderer = ctx.renderer.unwrap_or_default();
+
+    let mut cmd = process::command(
+        command,
+        &[("{destination}", &*destination), ("{renderer}", renderer)],
+    ).chain_err(|| format!("Invalid {} hook", ctx.stage))?;
+    cmd.current_dir(ctx.root);
+
+    debug!("Running the {} hook: {:?}", ctx.stage, cmd);
+    let status = cmd.status()
+        .chain_err(|| format!("Unable to run the {} hook (\"{}\")", ctx.stage, command))?;
+
+    if !status.success() {
+        bail!("The {} hook (\"{}\") failed with {}", ctx.stage, command, status);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,304 @@
+//! Rebuilding only the chapters which changed since the previous build, for
+//! `mdbook watch` and `mdbook serve`.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::hash::{Hash, Hasher};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use super::{Book, BookItem, MDBook, MountedBooks};
+use cache::Cache;
+use preprocess::{included_files, readme_renames, Included};
+use utils::fs::file_to_string;
+
+/// What was built last time, used by `MDBook::build_incremental()` to figure
+/// out what needs rebuilding.
+///
+/// A fresh `BuildState` always results in a full build.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct BuildState {
+    /// Covers everything which affects every page: the book's structure, its
+    /// configuration, the theme and the renderers.
+    pub(crate) fingerprint: Option<u64>,
+    /// A hash of each chapter's source and includes, by the chapter's path.
+    pub(crate) chapters: HashMap<PathBuf, u64>,
+    /// Each renderer's preprocessed chapter contents, by the chapter's path.
+    pub(crate) preprocessed: HashMap<String, HashMap<PathBuf, String>>,
+}
+
+impl BuildState {
+    /// Create a `BuildState` for a book which hasn't been built yet.
+    pub fn new() -> BuildState {
+        BuildState::default()
+    }
+}
+
+/// Can this book be built incrementally? If not, it's always built in full.
+pub(crate) fn is_supported(md: &MDBook) -> bool {
+    let hooks = &md.config.build.hooks;
+    let has_hooks = !md.hooks.is_empty() || hooks.before_build.is_some()
+        || hooks.after_preprocess.is_some() || hooks.after_render.is_some();
+
+    !md.config.book.multilingual && !has_hooks
+        && md.renderers.iter().all(|r| r.supports_incremental())
+}
+
+/// Hash everything which, when changed, means every chapter needs to be
+/// rebuilt.
+pub(crate) fn fingerprint(md: &MDBook) -> u64 {
+    let mut hasher = DefaultHasher::new();
+
+    format!("{:?}", md.config).hash(&mut hasher);
+    for renderer in &md.renderers {
+        renderer.name().hash(&mut hasher);
+    }
+
+    // the table of contents shown on every page
+    for item in md.book.iter_with_context() {
+        item.depth.hash(&mut hasher);
+        match *item.item {
+            BookItem::Chapter(ref ch) => {
+                ch.name.hash(&mut hasher);
+                ch.path.hash(&mut hasher);
+                ch.hidden.hash(&mut hasher);
+                format!("{:?}", ch.number).hash(&mut hasher);
+            }
+            BookItem::PartTitle(ref title) => title.hash(&mut hasher),
+            BookItem::Separator => "---".hash(&mut hasher),
+        }
+    }
+
+    hash_dir(&md.theme_dir(), &mut hasher);
+    hash_dir(&md.source_dir().join("theme"), &mut hasher);
+
+    hasher.finish()
+}
+
+/// Hash each chapter's content along with everything it includes, where
+/// `remote_includes` is the cache the remote includes are downloaded to.
+pub(crate) fn chapter_hashes(
+    book: &Book,
+    src_dir: &Path,
+    mounts: &MountedBooks,
+    remote_includes: &Cache,
+) -> HashMap<PathBuf, u64> {
+    book.iter()
+        .filter_map(|item| match *item {
+            BookItem::Chapter(ref ch) => Some(ch),
+            _ => None,
+        })
+        .map(|ch| {
+            let mut hasher = DefaultHasher::new();
+            ch.content.hash(&mut hasher);
+            format!("{:?}", ch.front_matter).hash(&mut hasher);
+
+            let source = mounts.source_path(src_dir, &ch.path);
+            let base = source.parent().unwrap_or(src_dir);
+            for included in included_files(&ch.content, base) {
+                // a missing include is hashed as empty, so creating it later
+                // (or downloading it) still counts as a change
+                let content = match included {
+                    Included::File(ref file) => file_to_string(file).ok(),
+                    Included::Remote(ref url) => remote_includes.get(url),
+                };
+                content.unwrap_or_default().hash(&mut hasher);
+            }
+
+            (ch.path.clone(), hasher.finish())
+        })
+        .collect()
+}
+
+/// The paths of the chapters whose hash differs from last time, in book
+/// order.
+pub(crate) fn changed_chapters(
+    book: &Book,
+    previous: &HashMap<PathBuf, u64>,
+    current: &HashMap<PathBuf, u64>,
+) -> Vec<PathBuf> {
+    book.iter()
+        .filter_map(|item| match *item {
+            BookItem::Chapter(ref ch) => Some(&ch.path),
+            _ => None,
+        })
+        .filter(|path| previous.get(*path) != current.get(*path))
+        .cloned()
+        .collect()
+}
+
+/// Blank out the chapters which didn't change, so preprocessors don't spend
+/// any time on them.
+pub(crate) fn blank_unchanged(book: &mut Book, changed: &[PathBuf]) {
+    book.for_each_chapter_mut(|ch, _| {
+        if !changed.contains(&ch.path) {
+            ch.content.clear();
+        }
+    });
+}
+
+/// The paths the changed chapters can have after preprocessing. The index
+/// preprocessor renames a `README.md` to `index.md`, so both are included.
+pub(crate) fn preprocessed_paths(book: &Book, changed: &[PathBuf]) -> Vec<PathBuf> {
+    let renames = readme_renames(book);
+    let mut paths = changed.to_vec();
+    paths.extend(changed.iter().filter_map(|path| renames.get(path)).cloned());
+    paths
+}
+
+/// Put back the preprocessed contents of the chapters which didn't change.
+/// A chapter without previous contents (e.g. one a preprocessor added) is
+/// left as it is and counted as changed, so the (preprocessed) paths of the
+/// changed chapters are returned.
+pub(crate) fn restore_unchanged(
+    book: &mut Book,
+    changed: &[PathBuf],
+    previous: &HashMap<PathBuf, String>,
+) -> Vec<PathBuf> {
+    let mut changed_paths = Vec::new();
+
+    book.for_each_chapter_mut(|ch, _| {
+        match previous.get(&ch.path) {
+            Some(content) if !changed.contains(&ch.path) => ch.content = content.clone(),
+            _ => changed_paths.push(ch.path.clone()),
+        }
+    });
+
+    changed_paths
+}
+
+/// The contents of each chapter, by its path.
+pub(crate) fn chapter_contents(book: &Book) -> HashMap<PathBuf, String> {
+    book.iter()
+        .filter_map(|item| match *item {
+            BookItem::Chapter(ref ch) => Some((ch.path.clone(), ch.content.clone())),
+            _ => None,
+        })
+        .collect()
+}
+
+fn hash_dir(dir: &Path, hasher: &mut DefaultHasher) {
+    let mut entries: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries.filter_map(|e| e.ok()).map(|e| e.path()).collect(),
+        Err(_) => return,
+    };
+    entries.sort();
+
+    for path in entries {
+        path.hash(hasher);
+        if path.is_dir() {
+            hash_dir(&path, hasher);
+        } else {
+            let mut content = Vec::new();
+            if File::open(&path).and_then(|mut f| f.read_to_end(&mut content)).is_ok() {
+                content.hash(hasher);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use book::Chapter;
+
+    fn book_with(contents: &[&str]) -> Book {
+        let mut book = Book::new();
+        for (i, content) in contents.iter().enumerate() {
+            let name = format!("Chapter {}", i);
+            book.push_item(Chapter::new(&name, content.to_string(), format!("{}.md", i)));
+        }
+        book
+    }
+
+    #[test]
+    fn only_edited_chapters_are_changed() {
+        let src_dir = Path::new("/does/not/exist");
+        let mounts = MountedBooks::default();
+        let cache = Cache::new(src_dir.join("cache"));
+        let book = book_with(&["one", "2", "three"]);
+        let previous = book_with(&["one", "two", "three"]);
+        let before = chapter_hashes(&previous, src_dir, &mounts, &cache);
+        let after = chapter_hashes(&book, src_dir, &mounts, &cache);
+
+        assert_eq!(changed_chapters(&book, &before, &after), vec![PathBuf::from("1.md")]);
+        assert_eq!(changed_chapters(&book, &HashMap::new(), &after).len(), 3);
+    }
+
+    #[test]
+    fn chapters_are_matched_by_path_when_the_book_is_reordered() {
+        let src_dir = Path::new("/does/not/exist");
+        let mounts = MountedBooks::default();
+        let cache = Cache::new(src_dir.join("cache"));
+        let mut before = Book::new();
+        before.push_item(Chapter::new("A", String::from("a"), "a.md"));
+        before.push_item(Chapter::new("B", String::from("b"), "b.md"));
+        let mut after = Book::new();
+        after.push_item(Chapter::new("New", String::from("new"), "new.md"));
+        after.push_item(Chapter::new("B", String::from("b"), "b.md"));
+        after.push_item(Chapter::new("A", String::from("a"), "a.md"));
+
+        let previous = chapter_hashes(&before, src_dir, &mounts, &cache);
+        let current = chapter_hashes(&after, src_dir, &mounts, &cache);
+        let changed = changed_chapters(&after, &previous, &current);
+        assert_eq!(changed, vec![PathBuf::from("new.md")]);
+
+        let mut preprocessed = HashMap::new();
+        preprocessed.insert(PathBuf::from("a.md"), String::from("A"));
+        preprocessed.insert(PathBuf::from("b.md"), String::from("B"));
+        blank_unchanged(&mut after, &changed);
+        restore_unchanged(&mut after, &changed, &preprocessed);
+
+        let contents = chapter_contents(&after);
+        assert_eq!(contents[Path::new("new.md")], "new");
+        assert_eq!(contents[Path::new("b.md")], "B");
+        assert_eq!(contents[Path::new("a.md")], "A");
+    }
+
+    #[test]
+    fn unchanged_chapters_get_their_previous_contents_back() {
+        let mut book = book_with(&["one", "two"]);
+        let mut previous = HashMap::new();
+        previous.insert(PathBuf::from("0.md"), String::from("ONE"));
+        previous.insert(PathBuf::from("1.md"), String::from("TWO"));
+        let changed = vec![PathBuf::from("1.md")];
+
+        blank_unchanged(&mut book, &changed);
+        assert_eq!(chapter_contents(&book)[Path::new("0.md")], "");
+
+        let changed = restore_unchanged(&mut book, &changed, &previous);
+
+        assert_eq!(changed, vec![PathBuf::from("1.md")]);
+        let contents = chapter_contents(&book);
+        assert_eq!(contents[Path::new("0.md")], "ONE");
+        assert_eq!(contents[Path::new("1.md")], "two");
+    }
+
+    #[test]
+    fn a_changed_readme_is_still_changed_after_being_renamed() {
+        let mut book = Book::new();
+        book.push_item(Chapter::new("Intro", String::from("new"), "README.md"));
+        book.push_item(Chapter::new("Other", String::from("other"), "other.md"));
+        let changed = vec![PathBuf::from("README.md")];
+        let paths = preprocessed_paths(&book, &changed);
+        assert_eq!(paths, vec![PathBuf::from("README.md"), PathBuf::from("index.md")]);
+
+        // what the index preprocessor does
+        book.for_each_mut(|item| {
+            if let BookItem::Chapter(ref mut ch) = *item {
+                if ch.path == Path::new("README.md") {
+                    ch.path = PathBuf::from("index.md");
+                }
+            }
+        });
+        let mut previous = HashMap::new();
+        previous.insert(PathBuf::from("index.md"), String::from("old"));
+        previous.insert(PathBuf::from("other.md"), String::from("OTHER"));
+
+        let changed = restore_unchanged(&mut book, &paths, &previous);
+
+        assert_eq!(changed, vec![PathBuf::from("index.md")]);
+        assert_eq!(chapter_contents(&book)[Path::new("index.md")], "new");
+    }
+}
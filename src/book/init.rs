@@ -1,11 +1,12 @@
 use std::fs::{self, File};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::io::Write;
 use toml;
 
 use config::Config;
 use super::MDBook;
 use theme;
+use utils::fs::file_to_string;
 use errors::*;
 
 /// A helper for setting up a new book and its directory structure.
@@ -15,6 +16,8 @@ pub struct BookBuilder {
     create_gitignore: bool,
     config: Config,
     copy_theme: bool,
+    overwrite: bool,
+    template: Option<PathBuf>,
 }
 
 impl BookBuilder {
@@ -26,6 +29,8 @@ impl BookBuilder {
             create_gitignore: false,
             config: Config::default(),
             copy_theme: false,
+            overwrite: false,
+            template: None,
         }
     }
 
@@ -53,8 +58,31 @@ impl BookBuilder {
         self
     }
 
+    /// Should existing files (e.g. a `book.toml` or the theme) be overwritten?
+    /// When this is `false` (the default), building fails instead.
+    ///
+    /// An existing `SUMMARY.md` is always kept, with any chapters missing from
+    /// it being created.
+    pub fn overwrite_existing(&mut self, overwrite: bool) -> &mut BookBuilder {
+        self.overwrite = overwrite;
+        self
+    }
+
+    /// Scaffold the book from a template directory instead of the stub
+    /// content. Everything in the template (e.g. a `book.toml`, `SUMMARY.md`,
+    /// starter chapters or a theme) is copied into the new book.
+    ///
+    /// If the template has a `book.toml`, it replaces the `Config` given to
+    /// the `BookBuilder`, apart from the book's title. The file is copied as
+    /// it is, so any comments in it are kept.
+    pub fn with_template<P: Into<PathBuf>>(&mut self, template: P) -> &mut BookBuilder {
+        self.template = Some(template.into());
+        self
+    }
+
     /// Generate the actual book. This will:
     ///
+    /// - Copy across the template (if applicable)
     /// - Create the directory structure.
     /// - Stub out some dummy chapters and the `SUMMARY.md`.
     /// - Create a `.gitignore` (if applicable)
@@ -62,11 +90,45 @@ impl BookBuilder {
     /// - Generate a `book.toml` file,
     /// - Then load the book so we can build it or run tests.
     pub fn build(&self) -> Result<MDBook> {
-        info!("Creating a new book with stub content");
+        match self.template {
+            Some(ref template) => {
+                info!("Creating a new book from the template in {}", template.display());
+
+                let mut builder = self.clone();
+                builder.config = self.template_config(template)?;
+                builder.build_book()
+            }
+            None => {
+                info!("Creating a new book with stub content");
+                self.build_book()
+            }
+        }
+    }
+
+    fn build_book(&self) -> Result<MDBook> {
+        if !self.overwrite {
+            let existing: Vec<_> = self.files_to_write()?
+                .into_iter()
+                .filter(|f| f.exists())
+                .map(|f| f.display().to_string())
+                .collect();
+
+            if !existing.is_empty() {
+                bail!(
+                    "Refusing to overwrite existing files ({}), use --force to overwrite them",
+                    existing.join(", ")
+                );
+            }
+        }
 
         self.create_directory_structure()
             .chain_err(|| "Unable to create directory structure")?;
 
+        if let Some(ref template) = self.template {
+            self.copy_across_template(template)
+                .chain_err(|| "Unable to copy across the template")?;
+        }
+
         self.create_stub_files()
             .chain_err(|| "Unable to create stub files")?;
 
@@ -82,23 +144,94 @@ impl BookBuilder {
 
         self.write_book_toml()?;
 
-        match MDBook::load(&self.root) {
-            Ok(book) => Ok(book),
-            Err(e) => {
-                error!("{}", e);
+        // a template can still make for a book which doesn't load
+        MDBook::load(&self.root).chain_err(|| "Unable to load the new book")
+    }
 
-                panic!(
-                    "The BookBuilder should always create a valid book. If you are seeing this it \
-                     is a bug and should be reported."
-                );
+    /// The files we'd write to, other than the stub chapters.
+    fn files_to_write(&self) -> Result<Vec<PathBuf>> {
+        let mut files = vec![self.root.join("book.toml")];
+
+        if let Some(ref template) = self.template {
+            for file in template_files(template)? {
+                let file = self.root.join(file);
+                if !files.contains(&file) {
+                    files.push(file);
+                }
             }
         }
+
+        if self.create_gitignore {
+            files.push(self.root.join(".gitignore"));
+        }
+
+        if self.copy_theme {
+            let themedir = self.theme_dir();
+            files.extend(theme_files().into_iter().map(|(name, _)| themedir.join(name)));
+        }
+
+        Ok(files)
+    }
+
+    /// The config from the template's `book.toml` (if it has one), keeping the
+    /// title we were asked to use.
+    fn template_config(&self, template: &Path) -> Result<Config> {
+        let book_toml = template.join("book.toml");
+        if !book_toml.exists() {
+            return Ok(self.config.clone());
+        }
+
+        let mut config = Config::from_disk(&book_toml)
+            .chain_err(|| format!("Unable to load {}", book_toml.display()))?;
+        if self.config.book.title.is_some() {
+            config.book.title = self.config.book.title.clone();
+        }
+
+        Ok(config)
+    }
+
+    fn copy_across_template(&self, template: &Path) -> Result<()> {
+        debug!("Copying the template");
+
+        for file in template_files(template)? {
+            let dest = self.root.join(&file);
+            if let Some(parent) = dest.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::copy(template.join(&file), &dest)?;
+        }
+
+        Ok(())
+    }
+
+    fn theme_dir(&self) -> PathBuf {
+        let themedir = self.config
+            .html_config()
+            .and_then(|html| html.theme)
+            .unwrap_or_else(|| self.config.book.src.join("theme"));
+        self.root.join(themedir)
     }
 
     fn write_book_toml(&self) -> Result<()> {
         debug!("Writing book.toml");
         let book_toml = self.root.join("book.toml");
-        let cfg = toml::to_vec(&self.config).chain_err(|| "Unable to serialize the config")?;
+        let from_template = self.template
+            .as_ref()
+            .map_or(false, |template| template.join("book.toml").exists());
+
+        let cfg = if from_template {
+            // the template's `book.toml` has been copied across already, so
+            // only the title needs changing
+            let copied = Config::from_disk(&book_toml)?;
+            match self.config.book.title {
+                Some(ref title) if copied.book.title.as_ref() != Some(title) => {
+                    set_title(&file_to_string(&book_toml)?, title).into_bytes()
+                }
+                _ => return Ok(()),
+            }
+        } else {
+            toml::to_vec(&self.config).chain_err(|| "Unable to serialize the config")?
+        };
 
         File::create(book_toml)
             .chain_err(|| "Couldn't create book.toml")?
@@ -110,37 +243,20 @@ impl BookBuilder {
     fn copy_across_theme(&self) -> Result<()> {
         debug!("Copying theme");
 
-        let themedir = self.config
-            .html_config()
-            .and_then(|html| html.theme)
-            .unwrap_or_else(|| self.config.book.src.join("theme"));
-        let themedir = self.root.join(themedir);
+        let themedir = self.theme_dir();
 
         if !themedir.exists() {
             debug!(
                 "{} does not exist, creating the directory",
                 themedir.display()
             );
-            fs::create_dir(&themedir)?;
+            fs::create_dir_all(&themedir)?;
         }
 
-        let mut index = File::create(themedir.join("index.hbs"))?;
-        index.write_all(theme::INDEX)?;
-
-        let mut css = File::create(themedir.join("book.css"))?;
-        css.write_all(theme::CSS)?;
-
-        let mut favicon = File::create(themedir.join("favicon.png"))?;
-        favicon.write_all(theme::FAVICON)?;
-
-        let mut js = File::create(themedir.join("book.js"))?;
-        js.write_all(theme::JS)?;
-
-        let mut highlight_css = File::create(themedir.join("highlight.css"))?;
-        highlight_css.write_all(theme::HIGHLIGHT_CSS)?;
-
-        let mut highlight_js = File::create(themedir.join("highlight.js"))?;
-        highlight_js.write_all(theme::HIGHLIGHT_JS)?;
+        for (name, content) in theme_files() {
+            let mut f = File::create(themedir.join(name))?;
+            f.write_all(content)?;
+        }
 
         Ok(())
     }
@@ -151,6 +267,7 @@ impl BookBuilder {
         let mut f = File::create(self.root.join(".gitignore"))?;
 
         writeln!(f, "{}", self.config.build.build_dir.display())?;
+        writeln!(f, "{}", self.config.build.cache_dir.display())?;
 
         Ok(())
     }
@@ -160,6 +277,12 @@ impl BookBuilder {
         let src_dir = self.root.join(&self.config.book.src);
 
         let summary = src_dir.join("SUMMARY.md");
+        if summary.exists() {
+            // the missing chapters get created when the book is loaded
+            debug!("{} already exists, keeping it", summary.display());
+            return Ok(());
+        }
+
         let mut f = File::create(&summary).chain_err(|| "Unable to create SUMMARY.md")?;
         writeln!(f, "# Summary")?;
         writeln!(f, "")?;
@@ -185,3 +308,92 @@ impl BookBuilder {
         Ok(())
     }
 }
+
+/// The theme files which get copied into a book so they can be customized.
+fn theme_files() -> Vec<(&'static str, &'static [u8])> {
+    vec![
+        ("index.hbs", theme::INDEX),
+        ("header.hbs", theme::HEADER),
+        ("comments.hbs", theme::COMMENTS),
+        ("sidebar.hbs", theme::SIDEBAR),
+        ("menu.hbs", theme::MENU),
+        ("footer.hbs", theme::FOOTER),
+        ("scripts.hbs", theme::SCRIPTS),
+        ("book.css", theme::CSS),
+        ("favicon.png", theme::FAVICON),
+        ("book.js", theme::JS),
+        ("highlight.css", theme::HIGHLIGHT_CSS),
+        ("highlight.js", theme::HIGHLIGHT_JS),
+        ("tomorrow-night.css", theme::TOMORROW_NIGHT_CSS),
+        ("ayu-highlight.css", theme::AYU_HIGHLIGHT_CSS),
+        ("clipboard.min.js", theme::CLIPBOARD_JS),
+        ("sw.js", theme::SERVICE_WORKER),
+        ("notes.js", theme::NOTES_JS),
+    ]
+}
+
+/// Set the `title` in the `[book]` table of a `book.toml`, leaving the rest of
+/// it (comments included) alone.
+fn set_title(book_toml: &str, title: &str) -> String {
+    let title_line = format!("title = {}", toml::Value::String(title.to_string()));
+    let mut lines: Vec<String> = book_toml.lines().map(String::from).collect();
+
+    let book_table = lines.iter().position(|line| line.trim() == "[book]");
+    match book_table {
+        Some(start) => {
+            let end = lines[start + 1..]
+                .iter()
+                .position(|line| line.trim_left().starts_with('['))
+                .map_or(lines.len(), |i| start + 1 + i);
+            let existing = (start + 1..end).find(|&i| {
+                let line = lines[i].trim_left();
+                line.starts_with("title") && line["title".len()..].trim_left().starts_with('=')
+            });
+
+            match existing {
+                Some(i) => lines[i] = title_line,
+                None => lines.insert(start + 1, title_line),
+            }
+        }
+        None => {
+            lines.push(String::new());
+            lines.push(String::from("[book]"));
+            lines.push(title_line);
+        }
+    }
+
+    let mut book_toml = lines.join("\n");
+    book_toml.push('\n');
+    book_toml
+}
+
+/// All the files in a template directory, relative to it. Version control
+/// directories (e.g. `.git/`) are skipped.
+fn template_files(template: &Path) -> Result<Vec<PathBuf>> {
+    fn walk(root: &Path, dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+
+            if path.is_dir() {
+                let name = path.file_name().and_then(|name| name.to_str());
+                if name != Some(".git") && name != Some(".hg") {
+                    walk(root, &path, files)?;
+                }
+            } else if let Ok(relative) = path.strip_prefix(root) {
+                files.push(relative.to_path_buf());
+            }
+        }
+
+        Ok(())
+    }
+
+    if !template.is_dir() {
+        bail!("The template {} isn't a directory", template.display());
+    }
+
+    let mut files = Vec::new();
+    walk(template, template, &mut files)?;
+    files.sort();
+
+    Ok(files)
+}
@@ -6,25 +6,57 @@
 //! [1]: ../index.html
 
 mod summary;
+mod add;
+mod asciidoc;
+mod auto_summary;
 mod book;
+mod check;
+mod code_blocks;
+mod formats;
+mod hooks;
+mod incremental;
 mod init;
-
-pub use self::book::{load_book, Book, BookItem, BookItems, Chapter};
+mod mounts;
+mod notebook;
+mod org;
+mod outline;
+mod rst;
+mod stats;
+
+pub use self::add::add_chapter;
+pub use self::auto_summary::generate_summary;
+pub use self::book::{load_book, load_book_with_summary, load_translation, Book, BookItem,
+                     BookItemRef, BookItems, BookItemsWithContext, Chapter, ChapterContext};
 pub use self::summary::{parse_summary, Link, SectionNumber, Summary, SummaryItem};
 pub use self::init::BookBuilder;
-
-use std::path::PathBuf;
+pub use self::outline::{load_outline, ChapterInfo, Outline};
+pub use self::check::BrokenLink;
+pub use self::hooks::{BuildStage, Hook, HookContext};
+pub use self::incremental::BuildState;
+pub use self::mounts::MountedBooks;
+pub use self::stats::{Stats, DEFAULT_WORDS_PER_MINUTE};
+pub(crate) use self::code_blocks::{is_closing_fence, opening_fence};
+pub(crate) use self::formats::converted_chapters;
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 use std::io::Write;
 use std::process::Command;
+use glob::Pattern;
+use regex::{Captures, Regex};
 use tempdir::TempDir;
 use toml::Value;
 
+use timings;
 use utils;
-use renderer::{CmdRenderer, HtmlHandlebars, RenderContext, Renderer};
-use preprocess::{LinkPreprocessor, Preprocessor, PreprocessorContext};
+use renderer::{html_output_path, CmdRenderer, HtmlHandlebars, RenderContext, Renderer};
+use preprocess::{readme_renames, BuildInfoPreprocessor, GettextPreprocessor, IndexPreprocessor,
+                 LinkPreprocessor, Preprocessor, PreprocessorContext};
 use errors::*;
 
-use config::Config;
+use config::{self, Config, LanguageConfig};
+use diagnostics::{self, Diagnostic, Level};
 
 /// The object used to manage and build a book.
 pub struct MDBook {
@@ -34,42 +66,34 @@ pub struct MDBook {
     pub config: Config,
     /// A representation of the book's contents in memory.
     pub book: Book,
+    /// The books mounted as parts of this one.
+    mounts: MountedBooks,
     renderers: Vec<Box<Renderer>>,
 
     /// List of pre-processors to be run on the book
     preprocessors: Vec<Box<Preprocessor>>,
+
+    /// Callbacks to run at the various stages of the build.
+    hooks: Vec<(BuildStage, Hook)>,
+
+    /// Whether broken links fail the build instead of being warned about.
+    strict_links: bool,
 }
 
 impl MDBook {
     /// Load a book from its root directory on disk.
     pub fn load<P: Into<PathBuf>>(book_root: P) -> Result<MDBook> {
         let book_root = book_root.into();
-        let config_location = book_root.join("book.toml");
-
-        // the book.json file is no longer used, so we should emit a warning to
-        // let people know to migrate to book.toml
-        if book_root.join("book.json").exists() {
-            warn!("It appears you are still using book.json for configuration.");
-            warn!("This format is no longer used, so you should migrate to the");
-            warn!("book.toml format.");
-            warn!("Check the user guide for migration information:");
-            warn!("\thttps://rust-lang-nursery.github.io/mdBook/format/config.html");
-        }
-
-        let mut config = if config_location.exists() {
-            debug!("Loading config from {}", config_location.display());
-            Config::from_disk(&config_location)?
-        } else {
-            Config::default()
-        };
+        let config = load_config(&book_root, None)?;
 
-        config.update_from_env();
+        MDBook::load_with_config(book_root, config)
+    }
 
-        if log_enabled!(::log::Level::Trace) {
-            for line in format!("Config: {:#?}", config).lines() {
-                trace!("{}", line);
-            }
-        }
+    /// Load a book from its root directory on disk, applying the overrides
+    /// from the `[profile.<name>]` table of its `book.toml`.
+    pub fn load_with_profile<P: Into<PathBuf>>(book_root: P, profile: &str) -> Result<MDBook> {
+        let book_root = book_root.into();
+        let config = load_config(&book_root, Some(profile))?;
 
         MDBook::load_with_config(book_root, config)
     }
@@ -78,8 +102,12 @@ impl MDBook {
     pub fn load_with_config<P: Into<PathBuf>>(book_root: P, config: Config) -> Result<MDBook> {
         let root = book_root.into();
 
-        let src_dir = root.join(&config.book.src);
-        let book = book::load_book(&src_dir, &config.build)?;
+        mounts::sync_mounts(&root, &config, false)?;
+        let mounts = MountedBooks::new(&root, &config)?;
+        let book = timings::time(
+            || String::from("load"),
+            || load_chapters(&root, &config, &mounts),
+        )?;
 
         let renderers = determine_renderers(&config);
         let preprocessors = determine_preprocessors(&config)?;
@@ -88,11 +116,31 @@ impl MDBook {
             root,
             config,
             book,
+            mounts,
             renderers,
             preprocessors,
+            hooks: Vec::new(),
+            strict_links: false,
         })
     }
 
+    /// Fetch the repositories of the books mounted from git, in case the
+    /// branches they follow have moved on, and reload the book.
+    ///
+    /// Loading a book only fetches a repository when the revision it needs
+    /// is missing, so rebuilds don't go over the network.
+    pub fn update_mounts(&mut self) -> Result<()> {
+        if self.config.build.mounts.values().all(|m| m.git.is_none()) {
+            return Ok(());
+        }
+
+        mounts::sync_mounts(&self.root, &self.config, true)?;
+        self.mounts = MountedBooks::new(&self.root, &self.config)?;
+        self.book = load_chapters(&self.root, &self.config, &self.mounts)?;
+
+        Ok(())
+    }
+
     /// Returns a flat depth-first iterator over the elements of the book,
     /// it returns an [BookItem enum](bookitem.html):
     /// `(section: String, bookitem: &BookItem)`
@@ -108,6 +156,7 @@ impl MDBook {
     ///     match *item {
     ///         BookItem::Chapter(ref chapter) => {},
     ///         BookItem::Separator => {},
+    ///         BookItem::PartTitle(ref title) => {},
     ///     }
     /// }
     ///
@@ -148,88 +197,572 @@ impl MDBook {
     /// Tells the renderer to build our book and put it in the build directory.
     pub fn build(&self) -> Result<()> {
         info!("Book building has started");
+        self.check_build_dirs()?;
 
-        let mut preprocessed_book = self.book.clone();
-        let preprocess_ctx = PreprocessorContext::new(self.root.clone(), self.config.clone());
+        let build_dir = self.root.join(&self.config.build.build_dir);
+        let ctx = HookContext {
+            stage: BuildStage::BeforeBuild,
+            root: &self.root,
+            config: &self.config,
+            book: &self.book,
+            renderer: None,
+            destination: &build_dir,
+        };
+        let book = if self.run_hooks(&ctx)? {
+            // the hooks may have added or changed chapters
+            debug!("Reloading the book after the before-build hooks");
+            Cow::Owned(load_chapters(&self.root, &self.config, &self.mounts)?)
+        } else {
+            Cow::Borrowed(&self.book)
+        };
 
-        for preprocessor in &self.preprocessors {
-            debug!("Running the {} preprocessor.", preprocessor.name());
-            preprocessor.run(&preprocess_ctx, &mut preprocessed_book)?;
+        let build_info = utils::BuildInfo::collect(&self.root)?;
+        if self.config.book.multilingual {
+            self.build_translations(&book, &build_info)
+        } else {
+            self.build_book(&book, &self.config, None, &build_info)
         }
+    }
+
+    /// Run the `after-rebuild` hooks, which `mdbook watch` and `mdbook serve`
+    /// do once they've rebuilt the book after a change.
+    pub fn after_rebuild(&self) -> Result<()> {
+        let build_dir = self.root.join(&self.config.build.build_dir);
+        let ctx = HookContext {
+            stage: BuildStage::AfterRebuild,
+            root: &self.root,
+            config: &self.config,
+            book: &self.book,
+            renderer: None,
+            destination: &build_dir,
+        };
+        self.run_hooks(&ctx)?;
+
+        Ok(())
+    }
+
+    /// Run the command and callbacks registered for a stage of the build,
+    /// returning whether there were any.
+    fn run_hooks(&self, ctx: &HookContext) -> Result<bool> {
+        let mut ran = false;
+
+        if let Some(command) = hooks::configured_command(ctx) {
+            timings::time(
+                || format!("{} hook", ctx.stage),
+                || hooks::run_command(command, ctx),
+            )?;
+            ran = true;
+        }
+
+        for &(stage, ref hook) in &self.hooks {
+            if stage == ctx.stage {
+                hook(ctx).chain_err(|| format!("The {} hook failed", stage))?;
+                ran = true;
+            }
+        }
+
+        Ok(ran)
+    }
+
+    /// Make sure no two renderers share a build directory, seeing as each of
+    /// them clears out its directory before rendering into it.
+    fn check_build_dirs(&self) -> Result<()> {
+        let dirs: Vec<(&str, PathBuf)> = self.renderers
+            .iter()
+            .map(|r| (r.name(), self.build_dir_for(r.name())))
+            .collect();
+
+        for (i, &(first, ref first_dir)) in dirs.iter().enumerate() {
+            for &(second, ref second_dir) in &dirs[i + 1..] {
+                if first_dir.starts_with(second_dir) || second_dir.starts_with(first_dir) {
+                    bail!(
+                        "The \"{}\" and \"{}\" renderers would clobber each other's output \
+                         ({} and {}), set a different `build-dir` for one of them",
+                        first,
+                        second,
+                        first_dir.display(),
+                        second_dir.display()
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check the links between the chapters of the book which was just
+    /// rendered to HTML (and to their headings), with `build.check-links`.
+    /// Broken links are reported as warnings, or fail the build when
+    /// `strict_links` is set.
+    fn check_rendered_links(&self, ctx: &RenderContext, src_dir: &Path) -> Result<()> {
+        if !ctx.config.build.check_links {
+            return Ok(());
+        }
+
+        let broken = timings::time(
+            || String::from("check links"),
+            || check::check_links(&ctx.book, src_dir, &self.mounts, &ctx.destination),
+        );
+
+        let level = if self.strict_links {
+            Level::Error
+        } else {
+            Level::Warning
+        };
+        for link in &broken {
+            let message = format!("broken link to \"{}\" ({})", link.link, link.reason);
+            let source = self.mounts.source_path(src_dir, &link.chapter);
+            diagnostics::report(Diagnostic::new(level, message).at(source, link.line));
+        }
+
+        if self.strict_links && !broken.is_empty() {
+            bail!("Found {} broken link(s)", broken.len());
+        }
+
+        Ok(())
+    }
+
+    /// Build every language of a multilingual book, each into its own
+    /// subdirectory of the build directory.
+    fn build_translations(&self, main_book: &Book, build_info: &utils::BuildInfo) -> Result<()> {
+        let main_language = self.config.book.language_or_default().to_string();
+        let src_dir = self.source_dir();
+
+        let mut languages = self.config.languages();
+        languages
+            .entry(main_language.clone())
+            .or_insert_with(LanguageConfig::default);
+
+        for (code, language) in &languages {
+            info!("Building the \"{}\" translation", code);
+
+            let book = if *code == main_language {
+                main_book.clone()
+            } else {
+                let mut book = book::load_with_mounts(
+                    &src_dir.join(code),
+                    Some(&src_dir.join(&main_language)),
+                    &self.mounts,
+                    None,
+                    &self.config.build,
+                    slug_style(&self.config),
+                )?;
+                if self.config.book.restart_part_numbering {
+                    book.restart_numbering_at_parts();
+                }
+                book
+            };
+
+            let mut config = self.config.clone();
+            config.set("language", &languages)?;
+            config.book.language = Some(code.clone());
+            if language.title.is_some() {
+                config.book.title = language.title.clone();
+            }
+            if language.description.is_some() {
+                config.book.description = language.description.clone();
+            }
+
+            self.build_book(&book, &config, Some(code.as_str()), build_info)?;
+        }
+
+        if self.renderers.iter().any(|r| r.name() == "html") {
+            // send readers who end up at the root to the main language
+            let index = self.build_dir_for("html").join("index.html");
+            let mut f = utils::fs::create_file(&index)?;
+            writeln!(
+                f,
+                "<!DOCTYPE html>\n<meta charset=\"utf-8\">\n\
+                 <meta http-equiv=\"refresh\" content=\"0; url={}/index.html\">",
+                main_language
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn build_book(
+        &self,
+        book: &Book,
+        config: &Config,
+        subdirectory: Option<&str>,
+        build_info: &utils::BuildInfo,
+    ) -> Result<()> {
+        let mut preprocess_ctx =
+            PreprocessorContext::new(self.root.clone(), config.clone(), self.mounts.clone());
+        preprocess_ctx.build_info = Some(build_info.clone());
 
         for renderer in &self.renderers {
+            // each renderer gets its own copy of the book, seeing as not
+            // every preprocessor supports every renderer
+            let mut preprocessed_book = book.clone();
+            self.preprocess(&preprocess_ctx, renderer.name(), &mut preprocessed_book)?;
+
+            let mut build_dir = self.build_dir_for(renderer.name());
+            if let Some(subdirectory) = subdirectory {
+                build_dir.push(subdirectory);
+            }
+
+            // the hooks borrow the book from the `RenderContext`, so big
+            // books aren't held in memory twice
+            let mut render_ctx =
+                RenderContext::new(self.root.clone(), preprocessed_book, config.clone(), build_dir);
+            render_ctx.mounts = self.mounts.clone();
+            render_ctx.build_info = Some(build_info.clone());
+
+            let mut ctx = HookContext {
+                stage: BuildStage::AfterPreprocess,
+                root: &self.root,
+                config,
+                book: &render_ctx.book,
+                renderer: Some(renderer.name()),
+                destination: &render_ctx.destination,
+            };
+            self.run_hooks(&ctx)?;
+
             info!("Running the {} backend", renderer.name());
-            self.run_renderer(&preprocessed_book, renderer.as_ref())?;
+            self.run_renderer(&render_ctx, renderer.as_ref())?;
+            if renderer.name() == "html" {
+                let mut src_dir = self.source_dir();
+                if let Some(subdirectory) = subdirectory {
+                    src_dir.push(subdirectory);
+                }
+                self.check_rendered_links(&render_ctx, &src_dir)?;
+            }
+
+            ctx.stage = BuildStage::AfterRender;
+            self.run_hooks(&ctx)?;
+        }
+
+        Ok(())
+    }
+
+    /// The book the way `renderer` gets to see it, i.e. after running the
+    /// preprocessors which support that renderer.
+    pub fn preprocessed_book(&self, renderer: &str) -> Result<Book> {
+        let ctx = PreprocessorContext::new(
+            self.root.clone(),
+            self.config.clone(),
+            self.mounts.clone(),
+        );
+        let mut book = self.book.clone();
+        self.preprocess(&ctx, renderer, &mut book)?;
+
+        Ok(book)
+    }
+
+    /// Run the preprocessors which support a renderer over the book.
+    fn preprocess(&self, ctx: &PreprocessorContext, renderer: &str, book: &mut Book) -> Result<()> {
+        for preprocessor in &self.preprocessors {
+            if preprocessor.supports_renderer(renderer) {
+                debug!("Running the {} preprocessor.", preprocessor.name());
+                timings::time(
+                    || format!("preprocess {} ({})", preprocessor.name(), renderer),
+                    || preprocessor.run(ctx, book),
+                ).chain_err(|| ErrorKind::Preprocessor(preprocessor.name().to_string()))?;
+            } else {
+                debug!(
+                    "Skipping the {} preprocessor, it doesn't support the {} renderer",
+                    preprocessor.name(),
+                    renderer
+                );
+            }
         }
 
         Ok(())
     }
 
-    fn run_renderer(&self, preprocessed_book: &Book, renderer: &Renderer) -> Result<()> {
+    /// Run a renderer, clearing out its build directory first unless only
+    /// some of the chapters are being rendered.
+    fn run_renderer(&self, ctx: &RenderContext, renderer: &Renderer) -> Result<()> {
         let name = renderer.name();
-        let build_dir = self.build_dir_for(name);
-        if build_dir.exists() {
+        if ctx.changed_chapters.is_none() && ctx.destination.exists() {
             debug!(
                 "Cleaning build dir for the \"{}\" renderer ({})",
                 name,
-                build_dir.display()
+                ctx.destination.display()
             );
 
-            utils::fs::remove_dir_content(&build_dir)
+            utils::fs::remove_dir_content(&ctx.destination)
                 .chain_err(|| "Unable to clear output directory")?;
         }
 
-        let render_context = RenderContext::new(
+        timings::time(|| format!("render {}", name), || renderer.render(ctx))
+            .chain_err(|| ErrorKind::Renderer(name.to_string()))
+    }
+
+    /// Build the book like `build()`, but only preprocess and render the
+    /// chapters which changed since the last time it was built with the same
+    /// `state`. `mdbook watch` and `mdbook serve` use this to speed up
+    /// rebuilds.
+    ///
+    /// Everything is rebuilt when the `SUMMARY.md`, the configuration or the
+    /// theme changed. Books which are multilingual, use build hooks or use a
+    /// renderer which doesn't support incremental builds are always built in
+    /// full.
+    pub fn build_incremental(&self, state: &mut BuildState) -> Result<()> {
+        if !incremental::is_supported(self) {
+            *state = BuildState::new();
+            return self.build();
+        }
+
+        let fingerprint = incremental::fingerprint(self);
+        let mut preprocess_ctx = PreprocessorContext::new(
             self.root.clone(),
-            preprocessed_book.clone(),
             self.config.clone(),
-            build_dir,
+            self.mounts.clone(),
         );
+        preprocess_ctx.build_info = Some(utils::BuildInfo::collect(&self.root)?);
+        let hashes = incremental::chapter_hashes(
+            &self.book,
+            &self.source_dir(),
+            &self.mounts,
+            &preprocess_ctx.cache("remote-includes"),
+        );
+        let changed = if state.fingerprint == Some(fingerprint) {
+            Some(incremental::changed_chapters(&self.book, &state.chapters, &hashes))
+        } else {
+            None
+        };
 
-        renderer
-            .render(&render_context)
-            .chain_err(|| "Rendering failed")
+        if changed.as_ref().map_or(false, |changed| changed.is_empty()) {
+            info!("No chapters changed, nothing to rebuild");
+            return Ok(());
+        }
+
+        match changed {
+            Some(ref changed) => info!("Rebuilding {} changed chapter(s)", changed.len()),
+            None => info!("Book building has started"),
+        }
+
+        // only trust the state again once everything was rendered
+        state.fingerprint = None;
+        self.check_build_dirs()?;
+
+        for renderer in &self.renderers {
+            let name = renderer.name();
+            let mut book = self.book.clone();
+
+            let previous = match (changed.as_ref(), state.preprocessed.remove(name)) {
+                (Some(changed), Some(previous)) => Some((changed, previous)),
+                _ => None,
+            };
+
+            if let Some((ref changed, _)) = previous {
+                incremental::blank_unchanged(&mut book, changed);
+            }
+            self.preprocess(&preprocess_ctx, name, &mut book)?;
+            let changed_paths = previous.map(|(changed, previous)| {
+                let changed = incremental::preprocessed_paths(&self.book, changed);
+                incremental::restore_unchanged(&mut book, &changed, &previous)
+            });
+
+            state
+                .preprocessed
+                .insert(name.to_string(), incremental::chapter_contents(&book));
+
+            let mut ctx = RenderContext::new(
+                self.root.clone(),
+                book,
+                self.config.clone(),
+                self.build_dir_for(name),
+            );
+            ctx.changed_chapters = changed_paths;
+            ctx.mounts = self.mounts.clone();
+            ctx.build_info = preprocess_ctx.build_info.clone();
+
+            info!("Running the {} backend", name);
+            self.run_renderer(&ctx, renderer.as_ref())?;
+            if name == "html" {
+                self.check_rendered_links(&ctx, &self.source_dir())?;
+            }
+        }
+
+        state.fingerprint = Some(fingerprint);
+        state.chapters = hashes;
+
+        Ok(())
     }
 
-    /// You can change the default renderer to another one by using this method.
-    /// The only requirement is for your renderer to implement the [`Renderer`
-    /// trait](../renderer/trait.Renderer.html)
+    /// Register a renderer to be run when building the book. The only
+    /// requirement is for your renderer to implement the [`Renderer`
+    /// trait](../renderer/trait.Renderer.html).
+    ///
+    /// A renderer with the same name as one which is already registered
+    /// (e.g. `"html"`, for the built-in HTML renderer) replaces it.
     pub fn with_renderer<R: Renderer + 'static>(&mut self, renderer: R) -> &mut Self {
+        self.renderers.retain(|r| r.name() != renderer.name());
         self.renderers.push(Box::new(renderer));
         self
     }
 
-    /// Register a [`Preprocessor`](../preprocess/trait.Preprocessor.html) to be used when rendering the book.
-    pub fn with_preprecessor<P: Preprocessor + 'static>(&mut self, preprocessor: P) -> &mut Self {
+    /// Stop the renderer with the given name from being run, e.g. to only
+    /// use a custom renderer instead of the default HTML one.
+    pub fn without_renderer(&mut self, name: &str) -> &mut Self {
+        self.renderers.retain(|r| r.name() != name);
+        self
+    }
+
+    /// The names of the renderers which will be run when building the book,
+    /// in the order they're run.
+    pub fn renderer_names(&self) -> Vec<&str> {
+        self.renderers.iter().map(|r| r.name()).collect()
+    }
+
+    /// Register a [`Preprocessor`](../preprocess/trait.Preprocessor.html) to be
+    /// used when rendering the book. It runs after the ones from `book.toml`.
+    ///
+    /// The registration methods can be chained:
+    ///
+    /// ```rust,no_run
+    /// # extern crate mdbook;
+    /// # use mdbook::MDBook;
+    /// # use mdbook::preprocess::BuildInfoPreprocessor;
+    /// # fn run() -> mdbook::errors::Result<()> {
+    /// MDBook::load("/path/to/book")?
+    ///     .with_preprocessor(BuildInfoPreprocessor::new())
+    ///     .with_build_dir("/tmp/my-book")
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// # fn main() { run().unwrap() }
+    /// ```
+    pub fn with_preprocessor<P: Preprocessor + 'static>(&mut self, preprocessor: P) -> &mut Self {
         self.preprocessors.push(Box::new(preprocessor));
         self
     }
 
+    #[doc(hidden)]
+    #[deprecated(note = "use `with_preprocessor()` instead")]
+    pub fn with_preprecessor<P: Preprocessor + 'static>(&mut self, preprocessor: P) -> &mut Self {
+        self.with_preprocessor(preprocessor)
+    }
+
+    /// Register a callback to be run at a particular stage of the build, after
+    /// the command for that stage in `[build.hooks]` (if any).
+    ///
+    /// ```rust,no_run
+    /// # extern crate mdbook;
+    /// # use mdbook::MDBook;
+    /// # use mdbook::book::BuildStage;
+    /// # fn run() -> mdbook::errors::Result<()> {
+    /// MDBook::load("/path/to/book")?
+    ///     .with_hook(BuildStage::AfterRender, |ctx| {
+    ///         println!("Rendered into {}", ctx.destination.display());
+    ///         Ok(())
+    ///     })
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// # fn main() { run().unwrap() }
+    /// ```
+    pub fn with_hook<F>(&mut self, stage: BuildStage, hook: F) -> &mut Self
+    where
+        F: Fn(&HookContext) -> Result<()> + 'static,
+    {
+        self.hooks.push((stage, Box::new(hook)));
+        self
+    }
+
+    /// Fail the build when `build.check-links` finds a broken link, instead
+    /// of only warning about it. `mdbook build --strict` and `mdbook check`
+    /// turn this on.
+    pub fn with_strict_links(&mut self, strict: bool) -> &mut Self {
+        self.strict_links = strict;
+        self
+    }
+
+    /// Put the rendered book somewhere other than the `build.build-dir` from
+    /// `book.toml`. Relative paths are relative to the book's root.
+    pub fn with_build_dir<P: Into<PathBuf>>(&mut self, build_dir: P) -> &mut Self {
+        self.config.build.build_dir = build_dir.into();
+        self
+    }
+
     /// Run `rustdoc` tests on the book, linking against the provided libraries.
     pub fn test(&mut self, library_paths: Vec<&str>) -> Result<()> {
+        self.test_with(library_paths, Vec::new(), None)
+    }
+
+    /// Run `rustdoc` tests on the book, linking against the provided libraries
+    /// and making the given `name=path` crates available to code examples.
+    ///
+    /// If a `chapter` is given, only the chapter with that name or source file
+    /// (relative to the book's root or source directory) is tested.
+    pub fn test_with(
+        &mut self,
+        library_paths: Vec<&str>,
+        externs: Vec<&str>,
+        chapter: Option<&str>,
+    ) -> Result<()> {
+        for ext in &externs {
+            if !ext.contains('=') {
+                bail!("Externs should be given as name=path, got \"{}\"", ext);
+            }
+        }
+
         let library_args: Vec<&str> = (0..library_paths.len())
             .map(|_| "-L")
             .zip(library_paths.into_iter())
             .flat_map(|x| vec![x.0, x.1])
+            .chain(externs.into_iter().flat_map(|ext| vec!["--extern", ext]))
             .collect();
 
+        let mut skipped = Vec::new();
+        for pattern in &self.config.build.test_skip {
+            let pattern = Pattern::new(pattern)
+                .chain_err(|| format!("Invalid test-skip pattern \"{}\"", pattern))?;
+            skipped.push(pattern);
+        }
+
+        let chapter_dir = self.config.book.chapter_dir();
+        let selected = chapter.map(|chapter| {
+            let path = Path::new(chapter);
+            let path = path.strip_prefix(&chapter_dir).unwrap_or(path);
+            (chapter, path.to_path_buf())
+        });
+        let mut found = selected.is_none();
+
+        let commands: BTreeMap<String, String> = match self.config.get("test.commands") {
+            Some(_) => self.config.get_deserialized("test.commands")?,
+            None => BTreeMap::new(),
+        };
+        let mut failures = 0;
+
         let temp_dir = TempDir::new("mdbook")?;
 
-        let preprocess_context = PreprocessorContext::new(self.root.clone(), self.config.clone());
+        let preprocess_context = PreprocessorContext::new(
+            self.root.clone(),
+            self.config.clone(),
+            self.mounts.clone(),
+        );
 
         LinkPreprocessor::new().run(&preprocess_context, &mut self.book)?;
 
         for item in self.iter() {
             if let BookItem::Chapter(ref ch) = *item {
                 if !ch.path.as_os_str().is_empty() {
-                    let path = self.source_dir().join(&ch.path);
-                    let content = utils::fs::file_to_string(&path)?;
+                    if let Some((ref name, ref path)) = selected {
+                        if !ch.name.eq_ignore_ascii_case(name) && ch.path != *path {
+                            continue;
+                        }
+                        found = true;
+                    }
+
+                    let path = self.root.join(&chapter_dir).join(&ch.path);
+
+                    if is_test_skipped(ch, &skipped) {
+                        info!("Skipping file: {:?}", path);
+                        continue;
+                    }
+
                     info!("Testing file: {:?}", path);
 
                     // write preprocessed file to tempdir
-                    let path = temp_dir.path().join(&ch.path);
+                    // rustdoc only treats `.md` files as markdown
+                    let path = temp_dir.path().join(ch.path.with_extension("md"));
                     let mut tmpf = utils::fs::create_file(&path)?;
-                    tmpf.write_all(content.as_bytes())?;
+                    tmpf.write_all(ignore_skipped_blocks(&ch.content).as_bytes())?;
 
                     let output = Command::new("rustdoc")
                         .arg(&path)
@@ -243,9 +776,20 @@ impl MDBook {
                             output
                         ));
                     }
+
+                    failures += test_with_commands(ch, &commands, temp_dir.path())?.len();
                 }
             }
         }
+
+        if failures > 0 {
+            bail!("{} code block(s) failed their test command", failures);
+        }
+
+        if !found {
+            bail!("No chapter called \"{}\" was found", chapter.unwrap_or_default());
+        }
+
         Ok(())
     }
 
@@ -273,7 +817,14 @@ impl MDBook {
     ///   - latex/
     ///     - my_awesome_book.tex
     ///
+    /// A renderer can also be given a directory of its own (relative to the
+    /// book's root) with the `build-dir` key in its `[output.X]` table.
     pub fn build_dir_for(&self, backend_name: &str) -> PathBuf {
+        let key = format!("output.{}.build-dir", backend_name);
+        if let Some(dir) = self.config.get(&key).and_then(|dir| dir.as_str()) {
+            return self.root.join(dir);
+        }
+
         let build_dir = self.root.join(&self.config.build.build_dir);
 
         if self.renderers.len() <= 1 {
@@ -283,6 +834,29 @@ impl MDBook {
         }
     }
 
+    /// Where the HTML renderer puts the page for the chapter at `path`,
+    /// relative to its build directory. This takes the index preprocessor
+    /// renaming a `README.md` to `index.md` and `output.html.pretty-urls`
+    /// into account.
+    pub fn html_page_for(&self, path: &Path) -> PathBuf {
+        let renamed = if self.preprocessors.iter().any(|p| p.name() == "index") {
+            readme_renames(&self.book).remove(path)
+        } else {
+            None
+        };
+        let path = renamed.as_ref().map(|p| p.as_path()).unwrap_or(path);
+        let pretty_urls = self.config
+            .html_config()
+            .map_or(false, |html| html.pretty_urls);
+
+        html_output_path(path, pretty_urls)
+    }
+
+    /// The books mounted as parts of this one, see `build.mounts`.
+    pub fn mounted_books(&self) -> &MountedBooks {
+        &self.mounts
+    }
+
     /// Get the directory containing this book's source files.
     pub fn source_dir(&self) -> PathBuf {
         self.root.join(&self.config.book.src)
@@ -298,6 +872,90 @@ impl MDBook {
     }
 }
 
+/// Load the chapters of the book in `root` from its source directory, and
+/// the ones of the mounted books from theirs.
+fn load_chapters(root: &Path, config: &Config, mounts: &MountedBooks) -> Result<Book> {
+    let src_dir = root.join(config.book.chapter_dir());
+
+    let summary = if config.book.auto_summary {
+        Some(generate_summary(&src_dir)?)
+    } else {
+        None
+    };
+    let summary = summary.as_ref().map(|summary| summary.as_str());
+    let mut book = book::load_with_mounts(
+        &src_dir,
+        None,
+        mounts,
+        summary,
+        &config.build,
+        slug_style(config),
+    )?;
+    if config.book.restart_part_numbering {
+        book.restart_numbering_at_parts();
+    }
+
+    Ok(book)
+}
+
+/// The `output.html.slug-style` the book's headings get their IDs with.
+fn slug_style(config: &Config) -> config::SlugStyle {
+    config
+        .html_config()
+        .map(|html| html.slug_style)
+        .unwrap_or_default()
+}
+
+/// Load the `book.toml` from a book's root directory (falling back to the
+/// defaults if there isn't one), then apply the requested profile and any
+/// overrides from the environment.
+///
+/// This is what `MDBook::load()` uses under the hood. Use it when you need to
+/// tweak the `Config` before handing it to `MDBook::load_with_config()`.
+pub fn load_config(book_root: &Path, profile: Option<&str>) -> Result<Config> {
+    let config_location = book_root.join("book.toml");
+
+    // the book.json file is no longer used, so we should emit a warning to
+    // let people know to migrate to book.toml
+    if book_root.join("book.json").exists() {
+        warn!("It appears you are still using book.json for configuration.");
+        warn!("This format is no longer used, so you should migrate to the");
+        warn!("book.toml format.");
+        warn!("Check the user guide for migration information:");
+        warn!("\thttps://rust-lang-nursery.github.io/mdBook/format/config.html");
+    }
+
+    let mut config = if config_location.exists() {
+        debug!("Loading config from {}", config_location.display());
+        let config = Config::from_disk(&config_location)?;
+
+        for problem in config::validate_file(&config_location, profile)? {
+            diagnostics::report(
+                Diagnostic::new(Level::Warning, problem.message)
+                    .at(&config_location, problem.line),
+            );
+        }
+
+        config
+    } else {
+        Config::default()
+    };
+
+    if let Some(profile) = profile {
+        config.apply_profile(profile)?;
+    }
+
+    config.update_from_env();
+
+    if log_enabled!(::log::Level::Trace) {
+        for line in format!("Config: {:#?}", config).lines() {
+            trace!("{}", line);
+        }
+    }
+
+    Ok(config)
+}
+
 /// Look at the `Config` and try to figure out what renderers to use.
 fn determine_renderers(config: &Config) -> Vec<Box<Renderer>> {
     let mut renderers: Vec<Box<Renderer>> = Vec::new();
@@ -323,15 +981,21 @@ fn determine_renderers(config: &Config) -> Vec<Box<Renderer>> {
 }
 
 fn default_preprocessors() -> Vec<Box<Preprocessor>> {
-    vec![Box::new(LinkPreprocessor::new())]
+    vec![
+        Box::new(LinkPreprocessor::new()),
+        Box::new(GettextPreprocessor::new()),
+        Box::new(IndexPreprocessor::new()),
+    ]
 }
 
 /// Look at the `MDBook` and try to figure out what preprocessors to run.
 fn determine_preprocessors(config: &Config) -> Result<Vec<Box<Preprocessor>>> {
     let preprocess_list = match config.build.preprocess {
         Some(ref p) => p,
-        // If no preprocessor field is set, default to the LinkPreprocessor. This allows you
-        // to disable the LinkPreprocessor by setting "preprocess" to an empty list.
+        // If no preprocessor field is set, default to the LinkPreprocessor, the
+        // GettextPreprocessor (after the links, so included text is translated too)
+        // and the IndexPreprocessor. This allows you to disable them by setting
+        // "preprocess" to an empty list.
         None => return Ok(default_preprocessors()),
     };
 
@@ -339,7 +1003,10 @@ fn determine_preprocessors(config: &Config) -> Result<Vec<Box<Preprocessor>>> {
 
     for key in preprocess_list {
         match key.as_ref() {
+            "gettext" => preprocessors.push(Box::new(GettextPreprocessor::new())),
             "links" => preprocessors.push(Box::new(LinkPreprocessor::new())),
+            "build-info" => preprocessors.push(Box::new(BuildInfoPreprocessor::new())),
+            "index" => preprocessors.push(Box::new(IndexPreprocessor::new())),
             _ => bail!("{:?} is not a recognised preprocessor", key),
         }
     }
@@ -360,11 +1027,119 @@ fn interpret_custom_renderer(key: &str, table: &Value) -> Box<Renderer> {
     Box::new(CmdRenderer::new(key.to_string(), command.to_string()))
 }
 
+/// Should `mdbook test` leave this chapter alone, either because its front
+/// matter says `test = false` or because it matches one of the `test-skip`
+/// patterns?
+fn is_test_skipped(ch: &Chapter, skipped: &[Pattern]) -> bool {
+    if let Some(&Value::Boolean(false)) = ch.front_matter.get("test") {
+        return true;
+    }
+
+    skipped.iter().any(|pattern| pattern.matches_path(&ch.path))
+}
+
+/// Run the `test.commands` for the other languages' code blocks in a chapter,
+/// logging the failures and returning the lines of the source file the
+/// failing blocks start on.
+fn test_with_commands(
+    ch: &Chapter,
+    commands: &BTreeMap<String, String>,
+    temp_dir: &Path,
+) -> Result<Vec<usize>> {
+    let mut failures = Vec::new();
+
+    for (i, block) in code_blocks::code_blocks(&ch.content).into_iter().enumerate() {
+        let command = match commands.get(&block.language) {
+            Some(command) => command,
+            None => continue,
+        };
+        if block.attributes.iter().any(|attr| attr == "ignore" || attr == "mdbook-skip") {
+            continue;
+        }
+
+        let file = temp_dir.join(format!("block-{}.{}", i, block.language));
+        utils::fs::create_file(&file)?.write_all(block.code.as_bytes())?;
+        let file = file.to_string_lossy();
+
+        let mut cmd = utils::process::command(command, &[("{file}", &*file)])
+            .chain_err(|| format!("Invalid test command for \"{}\"", block.language))?;
+
+        let output = cmd.output()
+            .chain_err(|| format!("Unable to run \"{}\"", command))?;
+
+        if !output.status.success() {
+            let line = block.line + ch.front_matter_lines;
+            failures.push(line);
+            error!(
+                "The {} block on line {} of \"{}\" ({}) failed:\n{}{}",
+                block.language,
+                line,
+                ch.name,
+                ch.path.display(),
+                String::from_utf8_lossy(&output.stdout),
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
+    }
+
+    Ok(failures)
+}
+
+/// Turn the `mdbook-skip` attribute of code blocks into rustdoc's `ignore`, so
+/// the block is still highlighted as Rust but never compiled.
+fn ignore_skipped_blocks(content: &str) -> Cow<str> {
+    lazy_static! {
+        static ref SKIP: Regex =
+            Regex::new(r"(?m)^(\s*(?:```|~~~)[^\n]*?)\bmdbook-skip\b").unwrap();
+    }
+
+    SKIP.replace_all(content, |caps: &Captures| format!("{}ignore", &caps[1]))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use toml::value::{Table, Value};
 
+    #[test]
+    fn chapters_can_opt_out_of_testing() {
+        let mut ch = Chapter::new("Intro", String::new(), "intro.md");
+        assert!(!is_test_skipped(&ch, &[]));
+
+        let skipped = vec![Pattern::new("broken/*.md").unwrap()];
+        let broken = Chapter::new("Broken", String::new(), "broken/example.md");
+        assert!(is_test_skipped(&broken, &skipped));
+        assert!(!is_test_skipped(&ch, &skipped));
+
+        ch.front_matter.insert("test".to_string(), Value::Boolean(false));
+        assert!(is_test_skipped(&ch, &[]));
+    }
+
+    #[test]
+    fn failing_blocks_are_reported_by_their_line_in_the_source_file() {
+        let temp = TempDir::new("mdbook").unwrap();
+        let mut ch = Chapter::new(
+            "Scripts",
+            String::from("```sh\nexit 0\n```\n\n```sh\nexit 1\n```\n\n```sh,ignore\nexit 1\n```\n"),
+            "scripts.md",
+        );
+        ch.front_matter_lines = 3;
+        let mut commands = BTreeMap::new();
+        commands.insert(String::from("sh"), String::from("sh \"{file}\""));
+
+        let failures = test_with_commands(&ch, &commands, temp.path()).unwrap();
+
+        assert_eq!(failures, vec![8]);
+    }
+
+    #[test]
+    fn mdbook_skip_blocks_are_ignored_by_rustdoc() {
+        let src = "```rust,mdbook-skip\nfn main() {}\n```\n\n```rust\nfn main() {}\n```";
+        let should_be = "```rust,ignore\nfn main() {}\n```\n\n```rust\nfn main() {}\n```";
+
+        assert_eq!(ignore_skipped_blocks(src), should_be);
+    }
+
     #[test]
     fn config_defaults_to_html_renderer_if_empty() {
         let cfg = Config::default();
@@ -404,7 +1179,7 @@ mod tests {
     }
 
     #[test]
-    fn config_defaults_to_link_preprocessor_if_not_set() {
+    fn config_defaults_to_link_gettext_and_index_preprocessors_if_not_set() {
         let cfg = Config::default();
 
         // make sure we haven't got anything in the `output` table
@@ -413,8 +1188,10 @@ mod tests {
         let got = determine_preprocessors(&cfg);
 
         assert!(got.is_ok());
-        assert_eq!(got.as_ref().unwrap().len(), 1);
+        assert_eq!(got.as_ref().unwrap().len(), 3);
         assert_eq!(got.as_ref().unwrap()[0].name(), "links");
+        assert_eq!(got.as_ref().unwrap()[1].name(), "gettext");
+        assert_eq!(got.as_ref().unwrap()[2].name(), "index");
     }
 
     #[test]
@@ -0,0 +1,294 @@
+//! Books mounted as parts of another book, from a local directory or a git
+//! repository.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+
+use super::load_config;
+use config::{Config, MountConfig};
+use errors::*;
+
+/// The source directories of the books in `build.mounts`, keyed by the name
+/// they're mounted as.
+///
+/// The chapters of a mounted book are loaded straight from its own source
+/// directory (which, for a book from git, is in a checkout in the cache
+/// directory), while their paths in the book start with the name of the
+/// mount.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MountedBooks {
+    sources: BTreeMap<String, PathBuf>,
+}
+
+impl MountedBooks {
+    /// Find the source directories of the books mounted in the book at
+    /// `root`, loading each mounted book's configuration. This doesn't clone
+    /// or fetch anything, see `sync_mounts()` for that.
+    ///
+    /// `MDBook` does this once when loading the book, and hands the result to
+    /// the preprocessors and renderers.
+    pub fn new(root: &Path, config: &Config) -> Result<MountedBooks> {
+        let mut sources = BTreeMap::new();
+
+        for (name, mount) in &config.build.mounts {
+            check_name(name)?;
+            let book_root = mounted_book_root(root, name, mount, config)
+                .chain_err(|| format!("Unable to find the book mounted as \"{}\"", name))?;
+            let mounted = load_config(&book_root, None)?;
+            sources.insert(name.clone(), book_root.join(mounted.book.chapter_dir()));
+        }
+
+        Ok(MountedBooks { sources })
+    }
+
+    /// The source directory of the book mounted as `name`.
+    pub fn source_dir(&self, name: &str) -> Option<&Path> {
+        self.sources.get(name).map(|dir| dir.as_path())
+    }
+
+    /// The mounted books' names and source directories.
+    pub fn iter(&self) -> ::std::collections::btree_map::Iter<String, PathBuf> {
+        self.sources.iter()
+    }
+
+    /// Where the source file of the chapter at `path` is, if the path starts
+    /// with the name of a mounted book.
+    pub fn mounted_path(&self, path: &Path) -> Option<PathBuf> {
+        let mut components = path.components();
+        match components.next() {
+            Some(Component::Normal(first)) => first
+                .to_str()
+                .and_then(|name| self.source_dir(name))
+                .map(|dir| dir.join(components.as_path())),
+            _ => None,
+        }
+    }
+
+    /// Where the source file of the chapter at `path` is, which is in
+    /// `src_dir` unless it belongs to a mounted book.
+    pub fn source_path(&self, src_dir: &Path, path: &Path) -> PathBuf {
+        self.mounted_path(path).unwrap_or_else(|| src_dir.join(path))
+    }
+}
+
+/// Make sure the books in `build.mounts` which come from git repositories are
+/// checked out at the right revision in the cache directory.
+///
+/// A repository is only fetched when the revision to check out is missing
+/// from it, or when `fetch` is set (e.g. for an explicit `mdbook build`), so
+/// rebuilding the book doesn't go over the network every time.
+pub fn sync_mounts(root: &Path, config: &Config, fetch: bool) -> Result<()> {
+    for (name, mount) in &config.build.mounts {
+        check_name(name)?;
+        if let Some(ref url) = mount.git {
+            let checkout = checkout_dir(root, name, config);
+            let rev = mount.rev.as_ref().map(|rev| rev.as_str()).unwrap_or("origin/HEAD");
+            update_checkout(url, rev, &checkout, fetch, config.build.offline)
+                .chain_err(|| format!("Unable to get the book mounted as \"{}\"", name))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Mounts end up as directories in the book, so their names can't be paths.
+fn check_name(name: &str) -> Result<()> {
+    let mut components = Path::new(name).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(_)), None) => Ok(()),
+        _ => bail!("The mount \"{}\" has to be a plain directory name", name),
+    }
+}
+
+/// The root directory of a mounted book.
+fn mounted_book_root(
+    root: &Path,
+    name: &str,
+    mount: &MountConfig,
+    config: &Config,
+) -> Result<PathBuf> {
+    let path = mount.path.clone().unwrap_or_default();
+
+    match mount.git {
+        Some(_) => Ok(checkout_dir(root, name, config).join(path)),
+        None if mount.path.is_some() => Ok(root.join(path)),
+        None => bail!("Either \"path\" or \"git\" has to be set"),
+    }
+}
+
+/// Where the repository of a book mounted from git is checked out.
+fn checkout_dir(root: &Path, name: &str, config: &Config) -> PathBuf {
+    root.join(&config.build.cache_dir).join("mounts").join(name)
+}
+
+/// Clone the repository at `url` into `dir` (or fetch it when asked to, or
+/// when it doesn't have `rev` yet) and check out `rev`.
+///
+/// A branch is checked out from `origin`, since the local branch made by the
+/// clone doesn't move when fetching. Tags and commits are used as they are.
+fn update_checkout(url: &str, rev: &str, dir: &Path, fetch: bool, offline: bool) -> Result<()> {
+    if !dir.join(".git").exists() {
+        if offline {
+            bail!("{} hasn't been cloned yet and mdBook is offline", url);
+        }
+
+        if let Some(parent) = dir.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        info!("Cloning {}", url);
+        let mut clone = Command::new("git");
+        clone.args(&["clone", "--quiet", url]).arg(dir);
+        run_git(clone)?;
+    } else if !offline && (fetch || !has_revision(rev, dir)) {
+        info!("Fetching {}", url);
+        let mut fetch = Command::new("git");
+        fetch
+            .args(&["fetch", "--quiet", "--tags", "origin"])
+            .current_dir(dir);
+        run_git(fetch)?;
+    }
+
+    let remote_branch = format!("origin/{}", rev);
+    let rev = if !rev.starts_with("origin/") && has_revision(&remote_branch, dir) {
+        remote_branch.as_str()
+    } else {
+        rev
+    };
+
+    let mut checkout = Command::new("git");
+    checkout
+        .args(&["checkout", "--quiet", "--detach", rev])
+        .current_dir(dir);
+    run_git(checkout)
+}
+
+/// Does the repository in `dir` have the commit `rev` refers to?
+fn has_revision(rev: &str, dir: &Path) -> bool {
+    Command::new("git")
+        .args(&["rev-parse", "--quiet", "--verify"])
+        .arg(format!("{}^{{commit}}", rev))
+        .current_dir(dir)
+        .output()
+        .map(|output| output.status.success())
+        .unwrap_or(false)
+}
+
+fn run_git(mut cmd: Command) -> Result<()> {
+    debug!("Running {:?}", cmd);
+    let output = cmd.output()
+        .chain_err(|| "Unable to run git, which is needed for books mounted from git")?;
+
+    if !output.status.success() {
+        bail!(ErrorKind::Subprocess(format!("{:?} failed", cmd), output));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::io::Write;
+    use tempdir::TempDir;
+
+    #[test]
+    fn mounted_chapters_are_found_in_the_mounted_book() {
+        let temp = TempDir::new("mdbook").unwrap();
+        let guide_src = temp.path().join("guide").join("src");
+        fs::create_dir_all(&guide_src).unwrap();
+        File::create(guide_src.join("SUMMARY.md"))
+            .unwrap()
+            .write_all(b"- [Usage](usage.md)\n")
+            .unwrap();
+
+        let root = temp.path().join("umbrella");
+        let mut config = Config::default();
+        config.build.mounts.insert(
+            "guide".to_string(),
+            MountConfig {
+                path: Some(PathBuf::from("../guide")),
+                ..Default::default()
+            },
+        );
+
+        sync_mounts(&root, &config, false).unwrap();
+        let mounts = MountedBooks::new(&root, &config).unwrap();
+
+        let src_dir = root.join("src");
+        assert_eq!(
+            mounts.source_path(&src_dir, Path::new("guide/usage.md")),
+            root.join("../guide").join("src").join("usage.md")
+        );
+        assert_eq!(
+            mounts.source_path(&src_dir, Path::new("guidelines.md")),
+            src_dir.join("guidelines.md")
+        );
+        assert!(!src_dir.join("guide").exists());
+    }
+
+    #[test]
+    fn mounts_must_be_plain_directory_names() {
+        let temp = TempDir::new("mdbook").unwrap();
+        let mut config = Config::default();
+        config.build.mounts.insert(
+            "../outside".to_string(),
+            MountConfig {
+                path: Some(PathBuf::from("guide")),
+                ..Default::default()
+            },
+        );
+
+        assert!(MountedBooks::new(temp.path(), &config).is_err());
+    }
+
+    fn git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .args(&["-c", "user.name=mdBook", "-c", "user.email=mdbook@example.com"])
+            .args(args)
+            .current_dir(dir)
+            .status()
+            .unwrap();
+        assert!(status.success(), "git {:?} failed", args);
+    }
+
+    fn commit_chapter(repo: &Path, content: &str) {
+        File::create(repo.join("src").join("usage.md"))
+            .unwrap()
+            .write_all(content.as_bytes())
+            .unwrap();
+        git(repo, &["add", "."]);
+        git(repo, &["commit", "--quiet", "-m", content]);
+    }
+
+    #[test]
+    fn fetching_moves_a_mounted_branch_forward() {
+        let temp = TempDir::new("mdbook").unwrap();
+        let upstream = temp.path().join("upstream");
+        fs::create_dir_all(upstream.join("src")).unwrap();
+        git(&upstream, &["init", "--quiet"]);
+        git(&upstream, &["checkout", "--quiet", "-b", "main"]);
+        commit_chapter(&upstream, "first");
+
+        let root = temp.path().join("umbrella");
+        let mut config = Config::default();
+        config.build.mounts.insert(
+            "guide".to_string(),
+            MountConfig {
+                git: Some(upstream.to_string_lossy().into_owned()),
+                rev: Some("main".to_string()),
+                ..Default::default()
+            },
+        );
+        sync_mounts(&root, &config, false).unwrap();
+        let usage = checkout_dir(&root, "guide", &config).join("src").join("usage.md");
+        assert_eq!(::utils::fs::file_to_string(&usage).unwrap(), "first");
+
+        commit_chapter(&upstream, "second");
+        sync_mounts(&root, &config, true).unwrap();
+
+        assert_eq!(::utils::fs::file_to_string(&usage).unwrap(), "second");
+    }
+}
@@ -0,0 +1,281 @@
+//! Converting Jupyter notebooks (`.ipynb` files) into markdown, so they can
+//! be used as chapters.
+
+use std::collections::BTreeMap;
+use regex::Regex;
+use serde_json::{self, Value};
+
+use utils::escape_html;
+use errors::*;
+
+/// The (nbformat 4) notebook file format, or at least the parts of it which
+/// end up in a chapter.
+#[derive(Debug, Deserialize)]
+struct Notebook {
+    cells: Vec<Cell>,
+    #[serde(default)]
+    metadata: NotebookMetadata,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct NotebookMetadata {
+    language_info: Option<LanguageInfo>,
+    kernelspec: Option<KernelSpec>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LanguageInfo {
+    name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct KernelSpec {
+    language: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "cell_type", rename_all = "lowercase")]
+enum Cell {
+    Markdown { source: MultiLine },
+    Code {
+        source: MultiLine,
+        #[serde(default)]
+        outputs: Vec<Output>,
+    },
+    Raw { source: MultiLine },
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "output_type", rename_all = "snake_case")]
+enum Output {
+    Stream { name: String, text: MultiLine },
+    ExecuteResult { data: BTreeMap<String, Value> },
+    DisplayData { data: BTreeMap<String, Value> },
+    Error { traceback: Vec<String> },
+}
+
+/// Notebooks store text either as a single string or as a list of lines.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum MultiLine {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl MultiLine {
+    fn text(&self) -> String {
+        match *self {
+            MultiLine::One(ref text) => text.clone(),
+            MultiLine::Many(ref lines) => lines.concat(),
+        }
+    }
+}
+
+/// Convert a notebook into markdown.
+///
+/// Markdown cells are kept as they are and code cells become code blocks in
+/// the notebook's language, followed by the outputs saved with the notebook.
+/// Images are embedded as `data:` URLs.
+pub fn notebook_to_markdown(json: &str) -> Result<String> {
+    let notebook: Notebook = serde_json::from_str(json).chain_err(|| "Invalid notebook")?;
+
+    let NotebookMetadata {
+        language_info,
+        kernelspec,
+    } = notebook.metadata;
+    let language = language_info
+        .and_then(|info| info.name)
+        .or_else(|| kernelspec.and_then(|spec| spec.language))
+        .unwrap_or_default();
+
+    let mut markdown = String::new();
+
+    for cell in &notebook.cells {
+        match *cell {
+            Cell::Markdown { ref source } => push_block(&mut markdown, &source.text()),
+            Cell::Code {
+                ref source,
+                ref outputs,
+            } => {
+                let source = source.text();
+                if !source.trim().is_empty() {
+                    let block = format!("```{}\n{}\n```", language, source.trim_right());
+                    push_block(&mut markdown, &block);
+                }
+
+                for output in outputs {
+                    if let Some(output) = render_output(output) {
+                        push_block(&mut markdown, &output);
+                    }
+                }
+            }
+            // raw cells are meant for nbconvert's other output formats
+            Cell::Raw { .. } => {}
+        }
+    }
+
+    Ok(markdown)
+}
+
+/// The notebook for a chapter which doesn't exist yet.
+pub fn empty_notebook(title: &str) -> String {
+    let notebook = json!({
+        "cells": [
+            {
+                "cell_type": "markdown",
+                "metadata": {},
+                "source": [format!("# {}", title)],
+            }
+        ],
+        "metadata": {},
+        "nbformat": 4,
+        "nbformat_minor": 2,
+    });
+
+    serde_json::to_string_pretty(&notebook).expect("A notebook is always valid JSON")
+}
+
+fn push_block(markdown: &mut String, block: &str) {
+    markdown.push_str(block.trim_right());
+    markdown.push_str("\n\n");
+}
+
+fn render_output(output: &Output) -> Option<String> {
+    match *output {
+        Output::Stream { ref name, ref text } => {
+            let class = if name == "stderr" {
+                "notebook-output notebook-stderr"
+            } else {
+                "notebook-output"
+            };
+            Some(pre(class, &text.text()))
+        }
+        Output::ExecuteResult { ref data } | Output::DisplayData { ref data } => {
+            render_data(data)
+        }
+        Output::Error { ref traceback } => {
+            lazy_static! {
+                static ref ANSI_ESCAPE: Regex = Regex::new(r"\x1b\[[0-9;]*[a-zA-Z]").unwrap();
+            }
+            let traceback = ANSI_ESCAPE.replace_all(&traceback.join("\n"), "").into_owned();
+            Some(pre("notebook-output notebook-error", &traceback))
+        }
+    }
+}
+
+/// Render the richest of the representations of some output which can go
+/// in a web page.
+fn render_data(data: &BTreeMap<String, Value>) -> Option<String> {
+    for mime in &["image/png", "image/jpeg", "image/gif"] {
+        if let Some(image) = data.get(*mime).map(value_text) {
+            let image: String = image.chars().filter(|c| !c.is_whitespace()).collect();
+            return Some(format!(
+                "<img class=\"notebook-output\" src=\"data:{};base64,{}\">",
+                mime, image
+            ));
+        }
+    }
+
+    for mime in &["image/svg+xml", "text/html"] {
+        if let Some(html) = data.get(*mime).map(value_text) {
+            // a blank line would end the HTML block
+            let html: Vec<&str> = html.lines().filter(|line| !line.trim().is_empty()).collect();
+            return Some(format!(
+                "<div class=\"notebook-output\">\n{}\n</div>",
+                html.join("\n")
+            ));
+        }
+    }
+
+    if let Some(text) = data.get("text/markdown").map(value_text) {
+        return Some(text);
+    }
+
+    data.get("text/plain")
+        .map(value_text)
+        .map(|text| pre("notebook-output", &text))
+}
+
+fn value_text(value: &Value) -> String {
+    match *value {
+        Value::String(ref text) => text.clone(),
+        Value::Array(ref lines) => lines.iter().filter_map(|line| line.as_str()).collect(),
+        ref other => other.to_string(),
+    }
+}
+
+fn pre(class: &str, text: &str) -> String {
+    // a blank line would end the HTML block, so the newlines are encoded and
+    // the whole block stays on one line
+    let text = escape_html(text.trim_right()).replace('\n', "&#10;");
+    format!("<pre class=\"{}\">{}</pre>", class, text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utils::render_markdown;
+
+    #[test]
+    fn cells_become_markdown_and_code_blocks() {
+        let notebook = r##"{
+            "cells": [
+                {"cell_type": "markdown", "metadata": {}, "source": ["# Plotting\n", "\n", "Some *text*."]},
+                {"cell_type": "code", "metadata": {}, "execution_count": 1, "source": "print(1 < 2)",
+                 "outputs": [{"output_type": "stream", "name": "stdout", "text": ["True\n"]}]},
+                {"cell_type": "code", "metadata": {}, "execution_count": 2, "source": ["x"],
+                 "outputs": [{"output_type": "execute_result", "execution_count": 2, "metadata": {},
+                              "data": {"text/plain": ["42"], "image/png": "iVBOR\nw0K"}}]},
+                {"cell_type": "raw", "metadata": {}, "source": "\\LaTeX only"}
+            ],
+            "metadata": {"language_info": {"name": "python", "version": "3.6.4"}},
+            "nbformat": 4,
+            "nbformat_minor": 2
+        }"##;
+
+        let should_be = "# Plotting\n\nSome *text*.\n\n\
+                         ```python\nprint(1 < 2)\n```\n\n\
+                         <pre class=\"notebook-output\">True</pre>\n\n\
+                         ```python\nx\n```\n\n\
+                         <img class=\"notebook-output\" src=\"data:image/png;base64,iVBORw0K\">\n\n";
+
+        assert_eq!(notebook_to_markdown(notebook).unwrap(), should_be);
+    }
+
+    #[test]
+    fn errors_lose_their_terminal_colours() {
+        let output = Output::Error {
+            traceback: vec![
+                "\u{1b}[0;31mZeroDivisionError\u{1b}[0m: division by zero".to_string(),
+            ],
+        };
+
+        assert_eq!(
+            render_output(&output).unwrap(),
+            "<pre class=\"notebook-output notebook-error\">ZeroDivisionError: division by zero</pre>"
+        );
+    }
+
+    #[test]
+    fn output_with_blank_lines_stays_in_one_block() {
+        let output = Output::Stream {
+            name: String::from("stdout"),
+            text: MultiLine::One(String::from("First paragraph\n\nSecond <one>\n")),
+        };
+        let markdown = render_output(&output).unwrap();
+
+        assert_eq!(
+            markdown,
+            "<pre class=\"notebook-output\">First paragraph&#10;&#10;Second &lt;one&gt;</pre>"
+        );
+        // rather than a paragraph starting after the blank line
+        assert_eq!(render_markdown(&markdown, false).trim_right(), markdown);
+    }
+
+    #[test]
+    fn an_empty_notebook_can_be_converted() {
+        let got = notebook_to_markdown(&empty_notebook("Analysis")).unwrap();
+
+        assert_eq!(got, "# Analysis\n\n");
+    }
+}
@@ -0,0 +1,424 @@
+//! Converting (a subset of) Org-mode into markdown, so `.org` chapters can sit
+//! alongside markdown ones.
+
+#[allow(unused_imports)] use std::ascii::AsciiExt;
+use regex::{self, Captures, Regex};
+
+use config::SlugStyle;
+use utils::slugify;
+
+/// Convert an Org document into markdown.
+///
+/// Headings, paragraphs with the usual emphasis markers, links, lists
+/// (including checkboxes and description lists), tables, fixed-width lines,
+/// horizontal rules and `src`, `example`, `quote` and `export html` blocks
+/// are converted. Keywords other than `#+TITLE`, comments and drawers are
+/// left out.
+pub fn org_to_markdown(src: &str, slug_style: SlugStyle) -> String {
+    lazy_static! {
+        static ref BLOCK_BEGIN: Regex = Regex::new(r"(?i)^#\+begin_(\w+)(?:\s+(.*))?$").unwrap();
+        static ref KEYWORD: Regex = Regex::new(r"^#\+(\w+):\s*(.*)$").unwrap();
+        static ref DRAWER: Regex = Regex::new(r"^:[\w-]+:$").unwrap();
+        static ref HEADING: Regex = Regex::new(r"^(\*+)\s+(.*?)(?:\s+:[\w@#%:]+:)?$").unwrap();
+        static ref RULE: Regex = Regex::new(r"^-{5,}$").unwrap();
+        static ref LIST_ITEM: Regex = Regex::new(r"^(\s*)([-+]|\d+[.)])\s+(.*)$").unwrap();
+        static ref CHECKBOX: Regex = Regex::new(r"^\[([ xX-])\]\s+(.*)$").unwrap();
+        static ref DESCRIPTION: Regex = Regex::new(r"^(.*?)\s+::(?:\s+(.*))?$").unwrap();
+    }
+
+    let lines: Vec<&str> = src.lines().map(|line| line.trim_right()).collect();
+    let mut out = String::new();
+    // headings move down a level when the document has a title
+    let mut heading_offset = 0;
+    let mut in_list = false;
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+        let trimmed = line.trim_left();
+        i += 1;
+
+        if trimmed.is_empty() {
+            push_blank(&mut out);
+            continue;
+        }
+
+        if let Some(caps) = BLOCK_BEGIN.captures(trimmed) {
+            let kind = caps[1].to_ascii_lowercase();
+            let args = caps.get(2).map(|m| m.as_str().trim()).unwrap_or("");
+            let end = format!("#+end_{}", kind);
+
+            let start = i;
+            while i < lines.len() && lines[i].trim().to_ascii_lowercase() != end {
+                i += 1;
+            }
+            let body = &lines[start..i];
+            // skip the end of the block
+            i += 1;
+
+            push_block(&mut out, &kind, args, body, slug_style);
+            in_list = false;
+            continue;
+        }
+
+        if trimmed == ":" || trimmed.starts_with(": ") {
+            // consecutive fixed-width lines make up a single block
+            let mut fixed = vec![&trimmed[1..]];
+            while i < lines.len() {
+                let next = lines[i].trim_left();
+                if next != ":" && !next.starts_with(": ") {
+                    break;
+                }
+                fixed.push(&next[1..]);
+                i += 1;
+            }
+
+            push_line(&mut out, "```text");
+            for line in fixed {
+                let line = if line.starts_with(' ') { &line[1..] } else { line };
+                push_line(&mut out, line);
+            }
+            push_line(&mut out, "```");
+            continue;
+        }
+
+        if DRAWER.is_match(trimmed) {
+            while i < lines.len() && !lines[i].trim().eq_ignore_ascii_case(":end:") {
+                i += 1;
+            }
+            i += 1;
+            continue;
+        }
+
+        if let Some(caps) = KEYWORD.captures(trimmed) {
+            if caps[1].eq_ignore_ascii_case("title") {
+                push_line(&mut out, &format!("# {}", inline(&caps[2], slug_style)));
+                heading_offset = 1;
+            }
+            continue;
+        }
+        if trimmed == "#" || trimmed.starts_with("# ") {
+            // a comment
+            continue;
+        }
+
+        if trimmed.starts_with('|') {
+            let start = i - 1;
+            while i < lines.len() && lines[i].trim_left().starts_with('|') {
+                i += 1;
+            }
+            push_table(&mut out, &lines[start..i], slug_style);
+            in_list = false;
+            continue;
+        }
+
+        if let Some(caps) = HEADING.captures(line) {
+            let level = (caps[1].len() + heading_offset).min(6);
+            push_line(
+                &mut out,
+                &format!("{} {}", "#".repeat(level), inline(&caps[2], slug_style)),
+            );
+            in_list = false;
+        } else if RULE.is_match(trimmed) {
+            push_blank(&mut out);
+            push_line(&mut out, "***");
+            push_blank(&mut out);
+            in_list = false;
+        } else if let Some(caps) = LIST_ITEM.captures(line) {
+            let bullet = if caps[2].starts_with(|c: char| c.is_digit(10)) {
+                "1."
+            } else {
+                "-"
+            };
+
+            let mut item = caps[3].to_string();
+            if let Some(checkbox) = CHECKBOX.captures(&caps[3]) {
+                let mark = if checkbox[1].eq_ignore_ascii_case("x") { "x" } else { " " };
+                item = format!("[{}] {}", mark, &checkbox[2]);
+            }
+            let item = match DESCRIPTION.captures(&item) {
+                Some(description) => match description.get(2) {
+                    Some(definition) => format!(
+                        "**{}**: {}",
+                        inline(&description[1], slug_style),
+                        inline(definition.as_str(), slug_style)
+                    ),
+                    None => format!("**{}**", inline(&description[1], slug_style)),
+                },
+                None => inline(&item, slug_style),
+            };
+
+            push_line(&mut out, &format!("{}{} {}", &caps[1], bullet, item));
+            in_list = true;
+        } else if in_list && line.starts_with(char::is_whitespace) {
+            // the continuation of a list item
+            push_line(&mut out, &inline(line, slug_style));
+        } else {
+            push_line(&mut out, &inline(trimmed, slug_style));
+            in_list = false;
+        }
+    }
+
+    out
+}
+
+fn push_line(out: &mut String, line: &str) {
+    out.push_str(line);
+    out.push('\n');
+}
+
+/// Separate two blocks, without piling up blank lines.
+fn push_blank(out: &mut String) {
+    if !(out.is_empty() || out.ends_with("\n\n")) {
+        out.push('\n');
+    }
+}
+
+fn push_block(out: &mut String, kind: &str, args: &str, body: &[&str], slug_style: SlugStyle) {
+    match kind {
+        "src" | "example" => {
+            let language = if kind == "src" {
+                args.split_whitespace().next().unwrap_or("")
+            } else {
+                "text"
+            };
+
+            push_line(out, &format!("```{}", language));
+            for line in body {
+                push_line(out, line);
+            }
+            push_line(out, "```");
+        }
+        "quote" | "verse" => {
+            push_blank(out);
+            for line in org_to_markdown(&body.join("\n"), slug_style).lines() {
+                if line.is_empty() {
+                    push_line(out, ">");
+                } else {
+                    push_line(out, &format!("> {}", line));
+                }
+            }
+            push_blank(out);
+        }
+        "export" => {
+            // only HTML ends up in an HTML page
+            if args.eq_ignore_ascii_case("html") {
+                push_blank(out);
+                for line in body {
+                    push_line(out, line);
+                }
+                push_blank(out);
+            }
+        }
+        _ => {
+            push_blank(out);
+            out.push_str(&org_to_markdown(&body.join("\n"), slug_style));
+            push_blank(out);
+        }
+    }
+}
+
+fn push_table(out: &mut String, lines: &[&str], slug_style: SlugStyle) {
+    let mut rows: Vec<Vec<String>> = Vec::new();
+    let mut header = false;
+
+    for line in lines {
+        let line = line.trim();
+        if line.starts_with("|-") {
+            // a separator after the first row makes it the header
+            header = header || rows.len() == 1;
+            continue;
+        }
+
+        let line = line.trim_left_matches('|').trim_right_matches('|');
+        rows.push(line.split('|').map(|cell| inline(cell.trim(), slug_style)).collect());
+    }
+
+    let columns = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+    if columns == 0 {
+        return;
+    }
+    for row in &mut rows {
+        row.resize(columns, String::new());
+    }
+
+    let mut rows = rows.into_iter();
+    let header_row = if header {
+        rows.next().expect("The header is there")
+    } else {
+        vec![String::new(); columns]
+    };
+
+    push_blank(out);
+    push_line(out, &format!("| {} |", header_row.join(" | ")));
+    push_line(out, &format!("|{}", "---|".repeat(columns)));
+    for row in rows {
+        push_line(out, &format!("| {} |", row.join(" | ")));
+    }
+    push_blank(out);
+}
+
+/// A regex for text surrounded by one of Org's emphasis markers.
+fn emphasis(marker: char) -> Regex {
+    let marker = regex::escape(&marker.to_string());
+    let pattern = format!(
+        r#"(^|[\s\-({{'"])({m})([^\s{m}]|[^\s{m}][^{m}]*?[^\s{m}]){m}($|[\s\-.,:!?;'")}}\[\]])"#,
+        m = marker
+    );
+
+    Regex::new(&pattern).expect("The emphasis pattern is valid")
+}
+
+/// Convert the inline markup of some text.
+fn inline(text: &str, slug_style: SlugStyle) -> String {
+    lazy_static! {
+        static ref VERBATIM: Regex = emphasis('=');
+        static ref CODE: Regex = emphasis('~');
+        static ref LINK: Regex = Regex::new(r"\[\[([^\]]+)\](?:\[([^\]]+)\])?\]").unwrap();
+        static ref BOLD: Regex = emphasis('*');
+        static ref ITALIC: Regex = emphasis('/');
+        static ref UNDERLINE: Regex = emphasis('_');
+        static ref STRIKETHROUGH: Regex = emphasis('+');
+    }
+
+    let text = VERBATIM.replace_all(text, "${1}`${3}`${4}");
+    let text = CODE.replace_all(&text, "${1}`${3}`${4}");
+
+    let mut converted = String::with_capacity(text.len());
+    for (i, part) in text.split('`').enumerate() {
+        if i > 0 {
+            converted.push('`');
+        }
+        if i % 2 == 1 {
+            converted.push_str(part);
+            continue;
+        }
+
+        let part = LINK.replace_all(part, |caps: &Captures| {
+            link(&caps[1], caps.get(2).map(|m| m.as_str()), slug_style)
+        });
+        let part = BOLD.replace_all(&part, "${1}**${3}**${4}");
+        let part = ITALIC.replace_all(&part, "${1}*${3}*${4}");
+        let part = UNDERLINE.replace_all(&part, "${1}<u>${3}</u>${4}");
+        let part = STRIKETHROUGH.replace_all(&part, "${1}<del>${3}</del>${4}");
+        converted.push_str(&part);
+    }
+
+    converted
+}
+
+fn link(target: &str, description: Option<&str>, slug_style: SlugStyle) -> String {
+    let path = if target.starts_with("file:") {
+        &target["file:".len()..]
+    } else {
+        target
+    };
+    // search options (`file:notes.org::*Heading`) are dropped
+    let path = match path.find("::") {
+        Some(ix) => &path[..ix],
+        None => path,
+    };
+
+    let dest = if path.starts_with('*') {
+        // a link to a heading, with the ID the heading gets in the book
+        format!("#{}", slugify(path[1..].trim(), slug_style))
+    } else if path.ends_with(".org") {
+        format!("{}.html", &path[..path.len() - ".org".len()])
+    } else {
+        path.to_string()
+    };
+
+    match description {
+        Some(description) => format!("[{}]({})", description, dest),
+        None if is_image(&dest) => format!("![]({})", dest),
+        None => format!("[{}]({})", path.trim_left_matches('*'), dest),
+    }
+}
+
+fn is_image(path: &str) -> bool {
+    let path = path.to_ascii_lowercase();
+
+    [".png", ".jpg", ".jpeg", ".gif", ".svg", ".webp"]
+        .iter()
+        .any(|ext| path.ends_with(ext))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convert_a_typical_chapter() {
+        let src = "#+TITLE: Org Notes\n\
+                   #+AUTHOR: Jane\n\
+                   \n\
+                   * Setup                                     :intro:\n\
+                   Install =mdbook= with *cargo* and read [[https://example.com][the /docs/]].\n\
+                   See [[file:other.org][the other chapter]] and [[file:diagram.png]].\n\
+                   \n\
+                   - [X] one\n  - nested\n\
+                   - term :: definition\n\
+                   \n\
+                   #+BEGIN_SRC rust\n\
+                   fn main() {}\n\
+                   #+END_SRC\n";
+        let should_be = "# Org Notes\n\
+                         \n\
+                         ## Setup\n\
+                         Install `mdbook` with **cargo** and read [the *docs*](https://example.com).\n\
+                         See [the other chapter](other.html) and ![](diagram.png).\n\
+                         \n\
+                         - [x] one\n  - nested\n\
+                         - **term**: definition\n\
+                         \n\
+                         ```rust\n\
+                         fn main() {}\n\
+                         ```\n";
+
+        assert_eq!(org_to_markdown(src, SlugStyle::Github), should_be);
+    }
+
+    #[test]
+    fn tables_quotes_and_fixed_width_lines() {
+        let src = "* Results\n\
+                   :PROPERTIES:\n\
+                   :ID: 1234\n\
+                   :END:\n\
+                   | Name | Value |\n\
+                   |------+-------|\n\
+                   | a    | 1     |\n\
+                   \n\
+                   #+begin_quote\n\
+                   Quoted /text/.\n\
+                   #+end_quote\n\
+                   \n\
+                   : $ mdbook build\n";
+        let should_be = "# Results\n\
+                         \n\
+                         | Name | Value |\n\
+                         |---|---|\n\
+                         | a | 1 |\n\
+                         \n\
+                         > Quoted *text*.\n\
+                         \n\
+                         ```text\n\
+                         $ mdbook build\n\
+                         ```\n";
+
+        assert_eq!(org_to_markdown(src, SlugStyle::Github), should_be);
+    }
+
+    #[test]
+    fn heading_links_use_the_configured_slug_style() {
+        let src = "See [[*Über Größe]].";
+
+        assert_eq!(
+            org_to_markdown(src, SlugStyle::Github),
+            "See [Über Größe](#Über-größe).\n"
+        );
+        assert_eq!(
+            org_to_markdown(src, SlugStyle::Ascii),
+            "See [Über Größe](#uber-grosse).\n"
+        );
+    }
+}
@@ -0,0 +1,160 @@
+//! Inspecting a book's structure without building it.
+
+use std::path::{Path, PathBuf};
+
+use super::{load_chapters, load_config, mounts, Book, BookItem, MountedBooks, SectionNumber};
+use config::Config;
+use errors::*;
+
+/// A book's structure, loaded without running any preprocessors or
+/// renderers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Outline {
+    /// The book's root directory.
+    pub root: PathBuf,
+    /// The directory containing the book's `SUMMARY.md` and chapters.
+    pub src_dir: PathBuf,
+    /// The configuration from the book's `book.toml`.
+    pub config: Config,
+    /// The book itself, as it would be handed to the preprocessors.
+    pub book: Book,
+    /// Every chapter in the book, in the order they appear in the summary.
+    pub chapters: Vec<ChapterInfo>,
+}
+
+/// What there is to know about a chapter's place in the book.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChapterInfo {
+    /// The chapter's name, as given in the summary.
+    pub name: String,
+    /// The chapter's file, relative to the source directory.
+    pub path: PathBuf,
+    /// The chapter's file on disk.
+    pub source_path: PathBuf,
+    /// The chapter's section number, if it has one.
+    pub number: Option<SectionNumber>,
+    /// How deeply the chapter is nested, with top-level chapters at 0.
+    pub depth: usize,
+    /// The index (into `Outline::chapters`) of the chapter this one is
+    /// nested in.
+    pub parent: Option<usize>,
+    /// The title of the part the chapter belongs to.
+    pub part: Option<String>,
+    /// Whether the chapter is left out of the book's navigation.
+    pub hidden: bool,
+}
+
+/// Load the `SUMMARY.md` and chapters of the book in `book_root`, along with
+/// each chapter's position in the book, without rendering anything.
+///
+/// Unlike `MDBook::load()` this never creates missing chapters, so it's safe
+/// to use from linters and other tools which only want to look at a book.
+pub fn load_outline<P: AsRef<Path>>(book_root: P) -> Result<Outline> {
+    let root = book_root.as_ref().to_path_buf();
+    let config = load_config(&root, None)?;
+
+    let mut without_creating = config.clone();
+    without_creating.build.create_missing = false;
+    mounts::sync_mounts(&root, &config, false)?;
+    let mounts = MountedBooks::new(&root, &config)?;
+    let book = load_chapters(&root, &without_creating, &mounts)?;
+
+    let src_dir = root.join(config.book.chapter_dir());
+    let mut chapters = Vec::new();
+    let mut part = None;
+    collect_chapters(book.sections(), &src_dir, &mounts, None, 0, &mut part, &mut chapters);
+
+    Ok(Outline {
+        root,
+        src_dir,
+        config,
+        book,
+        chapters,
+    })
+}
+
+fn collect_chapters(
+    items: &[BookItem],
+    src_dir: &Path,
+    mounts: &MountedBooks,
+    parent: Option<usize>,
+    depth: usize,
+    part: &mut Option<String>,
+    chapters: &mut Vec<ChapterInfo>,
+) {
+    for item in items {
+        match *item {
+            BookItem::Chapter(ref ch) => {
+                let index = chapters.len();
+                chapters.push(ChapterInfo {
+                    name: ch.name.clone(),
+                    path: ch.path.clone(),
+                    source_path: mounts.source_path(src_dir, &ch.path),
+                    number: ch.number.clone(),
+                    depth,
+                    parent,
+                    part: part.clone(),
+                    hidden: ch.hidden,
+                });
+
+                collect_chapters(
+                    &ch.sub_items,
+                    src_dir,
+                    mounts,
+                    Some(index),
+                    depth + 1,
+                    part,
+                    chapters,
+                );
+            }
+            BookItem::PartTitle(ref title) => *part = Some(title.clone()),
+            BookItem::Separator => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use book::Chapter;
+
+    #[test]
+    fn chapters_know_their_parent_depth_and_part() {
+        let mut nested = Chapter::new("Nested", String::new(), "first/nested.md");
+        nested.number = Some(SectionNumber(vec![1, 1]));
+        let mut first = Chapter::new("First", String::new(), "first/index.md");
+        first.number = Some(SectionNumber(vec![1]));
+        first.sub_items.push(BookItem::Chapter(nested));
+
+        let items = vec![
+            BookItem::Chapter(Chapter::new("Intro", String::new(), "intro.md")),
+            BookItem::PartTitle(String::from("Part I")),
+            BookItem::Chapter(first),
+        ];
+        let mut chapters = Vec::new();
+
+        collect_chapters(
+            &items,
+            Path::new("/src"),
+            &MountedBooks::default(),
+            None,
+            0,
+            &mut None,
+            &mut chapters,
+        );
+
+        let got: Vec<_> = chapters
+            .iter()
+            .map(|ch| (ch.name.as_str(), ch.depth, ch.parent, ch.part.clone()))
+            .collect();
+        assert_eq!(
+            got,
+            vec![
+                ("Intro", 0, None, None),
+                ("First", 0, None, Some(String::from("Part I"))),
+                ("Nested", 1, Some(1), Some(String::from("Part I"))),
+            ]
+        );
+        assert_eq!(chapters[2].source_path, PathBuf::from("/src/first/nested.md"));
+    }
+}
@@ -0,0 +1,676 @@
+//! Converting (a subset of) reStructuredText into markdown, so `.rst` chapters
+//! (e.g. from Sphinx documentation) can sit alongside markdown ones.
+
+#[allow(unused_imports)] use std::ascii::AsciiExt;
+use std::collections::HashMap;
+use regex::{Captures, Regex};
+
+use config::SlugStyle;
+use utils::slugify;
+
+/// Convert a reStructuredText document into markdown.
+///
+/// Section titles, paragraphs with the usual inline markup, hyperlinks
+/// (including named targets and Sphinx's `:doc:` and `:ref:` roles), lists,
+/// definition and field lists, literal blocks, block quotes, simple tables
+/// and the common directives (`code-block`, admonitions, `image`, `figure`,
+/// `math` and `raw`) are converted. Grid tables are kept as preformatted
+/// text, comments are left out and so are directives which only make sense
+/// for Sphinx (like `toctree`).
+pub fn rst_to_markdown(src: &str, slug_style: SlugStyle) -> String {
+    lazy_static! {
+        static ref TARGET: Regex = Regex::new(r"^\s*\.\. _([^:]+):\s+(\S+)$").unwrap();
+    }
+
+    let lines: Vec<&str> = src.lines().map(|line| line.trim_right()).collect();
+
+    let mut targets = HashMap::new();
+    for line in &lines {
+        if let Some(caps) = TARGET.captures(line) {
+            targets.insert(caps[1].to_lowercase(), caps[2].to_string());
+        }
+    }
+
+    let mut converter = Converter {
+        targets,
+        title_styles: Vec::new(),
+        slug_style,
+    };
+    converter.convert(&lines)
+}
+
+struct Converter {
+    /// The URLs of the document's named hyperlink targets.
+    targets: HashMap<String, String>,
+    /// The adornments of the section titles, in the order they were first
+    /// used, which decides their levels.
+    title_styles: Vec<(char, bool)>,
+    /// How the book turns section titles into IDs.
+    slug_style: SlugStyle,
+}
+
+impl Converter {
+    fn convert(&mut self, lines: &[&str]) -> String {
+        lazy_static! {
+            static ref DIRECTIVE: Regex = Regex::new(r"^\.\.\s+([\w:-]+)::\s*(.*)$").unwrap();
+            static ref INTERNAL_TARGET: Regex = Regex::new(r"^\.\. _([^:]+):$").unwrap();
+            static ref SIMPLE_TABLE: Regex = Regex::new(r"^=+( +=+)+$").unwrap();
+            static ref LIST_ITEM: Regex =
+                Regex::new(r"^(\s*)([-*+]|\d+[.)]|#\.|\(\d+\))\s+(.*)$").unwrap();
+            static ref FIELD: Regex = Regex::new(r"^:([^:`]+):(?:\s+(.*))?$").unwrap();
+        }
+
+        let mut out = String::new();
+        let mut in_list = false;
+        let mut i = 0;
+
+        while i < lines.len() {
+            let line = lines[i];
+            let next = lines.get(i + 1).cloned().unwrap_or("");
+
+            if line.is_empty() {
+                push_blank(&mut out);
+                i += 1;
+                continue;
+            }
+
+            // a section title with an overline
+            if is_adornment(line) && !next.is_empty() && lines.get(i + 2) == Some(&line) {
+                let level = self.title_level(line, true);
+                push_heading(&mut out, level, &self.inline(next.trim()));
+                i += 3;
+                in_list = false;
+                continue;
+            }
+            // a section title with an underline
+            if !line.starts_with(' ') && is_adornment(next)
+                && next.chars().count() >= line.chars().count()
+            {
+                let level = self.title_level(next, false);
+                push_heading(&mut out, level, &self.inline(line));
+                i += 2;
+                in_list = false;
+                continue;
+            }
+            if is_adornment(line) && line.len() >= 4 {
+                // a transition
+                push_blank(&mut out);
+                push_line(&mut out, "***");
+                push_blank(&mut out);
+                i += 1;
+                continue;
+            }
+
+            if SIMPLE_TABLE.is_match(line) {
+                i = self.simple_table(&mut out, lines, i);
+                in_list = false;
+                continue;
+            }
+            if line.starts_with("+-") && line.ends_with('+') {
+                // grid tables are too much work for too little gain
+                push_line(&mut out, "```text");
+                while i < lines.len() && (lines[i].starts_with('+') || lines[i].starts_with('|')) {
+                    push_line(&mut out, lines[i]);
+                    i += 1;
+                }
+                push_line(&mut out, "```");
+                continue;
+            }
+
+            if line == ".." || line.starts_with(".. ") {
+                let body_end = indented_block_end(lines, i + 1);
+                let body = dedent(&lines[i + 1..body_end]);
+
+                if let Some(caps) = DIRECTIVE.captures(line) {
+                    let directive = self.directive(&caps[1], caps[2].trim(), &body);
+                    push_blank(&mut out);
+                    out.push_str(&directive);
+                    push_blank(&mut out);
+                } else if let Some(caps) = INTERNAL_TARGET.captures(line) {
+                    let id = anchor(&caps[1], self.slug_style);
+                    push_line(&mut out, &format!("<a id=\"{}\"></a>", id));
+                    push_blank(&mut out);
+                }
+                // anything else is a comment, or a target which was collected
+                // up front
+
+                i = body_end;
+                in_list = false;
+                continue;
+            }
+
+            if let Some(caps) = LIST_ITEM.captures(line) {
+                let bullet = if caps[2].starts_with(|c: char| "-*+".contains(c)) {
+                    "-"
+                } else {
+                    "1."
+                };
+                let item = format!("{}{} {}", &caps[1], bullet, self.inline(&caps[3]));
+                i = push_paragraph_line(&mut out, lines, i, &item);
+                in_list = true;
+                continue;
+            }
+
+            if line.starts_with(' ') {
+                if in_list {
+                    // the continuation of a list item
+                    let text = format!("{}{}", leading_space(line), self.inline(line.trim_left()));
+                    i = push_paragraph_line(&mut out, lines, i, &text);
+                } else {
+                    let end = indented_block_end(lines, i);
+                    let quote = self.convert(&dedent(&lines[i..end]));
+                    push_blank(&mut out);
+                    push_quote(&mut out, &quote);
+                    push_blank(&mut out);
+                    i = end;
+                }
+                continue;
+            }
+            in_list = false;
+
+            if let Some(caps) = FIELD.captures(line) {
+                let value = caps.get(2).map(|m| self.inline(m.as_str())).unwrap_or_default();
+                push_line(&mut out, &format!("- **{}**: {}", &caps[1], value));
+                i += 1;
+                continue;
+            }
+
+            // a term, with its definition indented on the following lines
+            let starts_paragraph = i == 0 || lines[i - 1].is_empty();
+            if starts_paragraph && next.starts_with(' ') && !next.trim().is_empty() {
+                let end = indented_block_end(lines, i + 1);
+                let definition = self.convert(&dedent(&lines[i + 1..end]));
+                let definition = definition.trim();
+
+                push_line(&mut out, &format!("- **{}**", self.inline(line)));
+                for line in definition.lines() {
+                    if line.is_empty() {
+                        push_line(&mut out, "");
+                    } else {
+                        push_line(&mut out, &format!("  {}", line));
+                    }
+                }
+                i = end;
+                continue;
+            }
+
+            let text = self.inline(line);
+            i = push_paragraph_line(&mut out, lines, i, &text);
+        }
+
+        out
+    }
+
+    fn title_level(&mut self, adornment: &str, overline: bool) -> usize {
+        let style = (adornment.chars().next().unwrap_or('='), overline);
+
+        match self.title_styles.iter().position(|s| *s == style) {
+            Some(ix) => ix + 1,
+            None => {
+                self.title_styles.push(style);
+                self.title_styles.len()
+            }
+        }
+    }
+
+    fn directive(&mut self, name: &str, argument: &str, body: &[&str]) -> String {
+        lazy_static! {
+            static ref OPTION: Regex = Regex::new(r"^:([\w-]+):\s*(.*)$").unwrap();
+        }
+
+        // the options come first in the body
+        let mut options = HashMap::new();
+        let mut content_start = 0;
+        for line in body {
+            match OPTION.captures(line) {
+                Some(caps) => options.insert(caps[1].to_string(), caps[2].to_string()),
+                None => break,
+            };
+            content_start += 1;
+        }
+        let content = dedent(&body[content_start..]);
+        let content = trim_blank_lines(&content);
+
+        let mut out = String::new();
+
+        match name {
+            "code-block" | "code" | "sourcecode" => {
+                push_line(&mut out, &format!("```{}", argument));
+                for line in content {
+                    push_line(&mut out, line);
+                }
+                push_line(&mut out, "```");
+            }
+            "image" | "figure" => {
+                let alt = options.get("alt").map(|alt| alt.as_str()).unwrap_or("");
+                push_line(&mut out, &format!("![{}]({})", alt, argument));
+                if !content.is_empty() {
+                    // the figure's caption
+                    push_blank(&mut out);
+                    out.push_str(&self.convert(content));
+                }
+            }
+            "math" => {
+                push_line(&mut out, "\\\\[");
+                if !argument.is_empty() {
+                    push_line(&mut out, argument);
+                }
+                for line in content {
+                    push_line(&mut out, line);
+                }
+                push_line(&mut out, "\\\\]");
+            }
+            "raw" => if argument.eq_ignore_ascii_case("html") {
+                for line in content {
+                    push_line(&mut out, line);
+                }
+            },
+            "toctree" | "contents" | "index" | "highlight" | "meta" | "only" => {}
+            _ => {
+                let label = match name {
+                    "admonition" => Some(argument.to_string()),
+                    "note" | "tip" | "hint" | "important" | "warning" | "caution"
+                    | "attention" | "danger" | "error" | "seealso" => {
+                        let mut label = name.to_string();
+                        label[..1].make_ascii_uppercase();
+                        Some(label)
+                    }
+                    _ => None,
+                };
+
+                match label {
+                    Some(label) => {
+                        // an admonition's text can start on the directive's line
+                        let mut text: Vec<&str> = Vec::new();
+                        if name != "admonition" && !argument.is_empty() {
+                            text.push(argument);
+                        }
+                        text.extend(content);
+
+                        let converted = self.convert(&text);
+                        let admonition = format!("**{}:** {}", label, converted.trim());
+                        push_quote(&mut out, &admonition);
+                    }
+                    None => {
+                        debug!("Keeping the contents of the unknown \"{}\" directive", name);
+                        out.push_str(&self.convert(content));
+                    }
+                }
+            }
+        }
+
+        out
+    }
+
+    fn simple_table(&mut self, out: &mut String, lines: &[&str], start: usize) -> usize {
+        let border = lines[start];
+        let columns = column_spans(border);
+
+        let mut rows: Vec<Vec<String>> = Vec::new();
+        let mut header_rows = 0;
+        let mut i = start + 1;
+
+        while i < lines.len() {
+            let line = lines[i];
+            i += 1;
+
+            if line.starts_with('=') && line.chars().all(|c| c == '=' || c == ' ') {
+                // the border after the header, or the end of the table
+                let last = i >= lines.len() || lines[i].is_empty();
+                if last {
+                    break;
+                }
+                header_rows = rows.len();
+                continue;
+            }
+            if line.is_empty() {
+                continue;
+            }
+
+            let cells = columns
+                .iter()
+                .enumerate()
+                .map(|(n, &(from, to))| {
+                    // the last column's text can go past the border
+                    let to = if n + 1 == columns.len() { line.len() } else { to };
+                    let cell = line.get(from.min(line.len())..to.min(line.len())).unwrap_or("");
+                    self.inline(cell.trim())
+                })
+                .collect();
+            rows.push(cells);
+        }
+
+        let empty_header = vec![String::new(); columns.len()];
+        let header = if header_rows == 1 {
+            &rows[0]
+        } else {
+            &empty_header
+        };
+
+        push_blank(out);
+        push_line(out, &format!("| {} |", header.join(" | ")));
+        push_line(out, &format!("|{}", "---|".repeat(columns.len())));
+        for row in &rows[header_rows.min(1)..] {
+            push_line(out, &format!("| {} |", row.join(" | ")));
+        }
+        push_blank(out);
+
+        i
+    }
+
+    /// Convert the inline markup of some text.
+    fn inline(&self, text: &str) -> String {
+        lazy_static! {
+            static ref LITERAL: Regex = Regex::new(r"``(.+?)``").unwrap();
+            static ref ROLE: Regex = Regex::new(r":([\w:+-]+):`([^`]+)`").unwrap();
+            static ref EMBEDDED_LINK: Regex = Regex::new(r"`([^`<]*?)\s*<([^`>]+)>`__?").unwrap();
+            static ref NAMED_REFERENCE: Regex = Regex::new(r"`([^`]+)`__?").unwrap();
+            static ref WORD_REFERENCE: Regex =
+                Regex::new(r"\b(\w[\w-]*)_(\s|$|[.,;:!?)])").unwrap();
+            static ref INTERPRETED: Regex = Regex::new(r"`([^`]+)`").unwrap();
+            static ref BARE_URL: Regex = Regex::new(r"(^|\s)((?:https?|ftp)://[^\s<>]+)").unwrap();
+            static ref PLACEHOLDER: Regex = Regex::new("\u{1a}(\\d+)\u{1a}").unwrap();
+        }
+
+        // anything already converted is kept out of the way of the other
+        // substitutions
+        let mut converted: Vec<String> = Vec::new();
+        let text = {
+            let mut hold = |markdown: String| {
+                converted.push(markdown);
+                format!("\u{1a}{}\u{1a}", converted.len() - 1)
+            };
+
+            let text = LITERAL.replace_all(text, |caps: &Captures| hold(format!("`{}`", &caps[1])));
+            let text = ROLE.replace_all(&text, |caps: &Captures| {
+                hold(role(&caps[1], &caps[2], self.slug_style))
+            });
+            let text = EMBEDDED_LINK.replace_all(&text, |caps: &Captures| {
+                let label = if caps[1].is_empty() { &caps[2] } else { &caps[1] };
+                hold(format!("[{}]({})", label, &caps[2]))
+            });
+            let text = NAMED_REFERENCE.replace_all(&text, |caps: &Captures| {
+                let dest = match self.targets.get(&caps[1].to_lowercase()) {
+                    Some(url) => url.clone(),
+                    // most likely a section title
+                    None => format!("#{}", anchor(&caps[1], self.slug_style)),
+                };
+                hold(format!("[{}]({})", &caps[1], dest))
+            });
+            let text = WORD_REFERENCE.replace_all(&text, |caps: &Captures| {
+                match self.targets.get(&caps[1].to_lowercase()) {
+                    Some(url) => {
+                        let link = hold(format!("[{}]({})", &caps[1], url));
+                        format!("{}{}", link, &caps[2])
+                    }
+                    None => caps[0].to_string(),
+                }
+            });
+            let text = INTERPRETED.replace_all(&text, |caps: &Captures| {
+                hold(format!("*{}*", &caps[1]))
+            });
+            let text = BARE_URL.replace_all(&text, |caps: &Captures| {
+                // trailing punctuation ends the sentence, not the URL
+                let url = caps[2].trim_right_matches(|c: char| ".,;:!?)".contains(c));
+                format!("{}{}{}", &caps[1], hold(format!("<{}>", url)), &caps[2][url.len()..])
+            });
+            text.into_owned()
+        };
+
+        PLACEHOLDER
+            .replace_all(&text, |caps: &Captures| {
+                let ix: usize = caps[1].parse().expect("Placeholders are numbers");
+                converted[ix].clone()
+            })
+            .into_owned()
+    }
+}
+
+/// Push a line of a paragraph. A paragraph ending in `::` is followed by
+/// a literal block, which is pushed too. Returns the index of the next
+/// line to convert.
+fn push_paragraph_line(out: &mut String, lines: &[&str], i: usize, text: &str) -> usize {
+    if !text.ends_with("::") {
+        push_line(out, text);
+        return i + 1;
+    }
+
+    // "Paragraph::" becomes "Paragraph:", while " ::" disappears
+    let text = &text[..text.len() - 1];
+    let text = if text.trim() == ":" {
+        ""
+    } else if text.ends_with(" :") {
+        text[..text.len() - 2].trim_right()
+    } else {
+        text
+    };
+    if !text.is_empty() {
+        push_line(out, text);
+    }
+
+    let mut start = i + 1;
+    while start < lines.len() && lines[start].is_empty() {
+        start += 1;
+    }
+    let end = indented_block_end(lines, start);
+    if start == end {
+        return start;
+    }
+
+    push_blank(out);
+    push_line(out, "```text");
+    for line in dedent(&lines[start..end]) {
+        push_line(out, line);
+    }
+    push_line(out, "```");
+    end
+}
+
+/// Convert an interpreted text role (e.g. `` :doc:`intro` ``).
+fn role(name: &str, text: &str, slug_style: SlugStyle) -> String {
+    lazy_static! {
+        static ref EXPLICIT_TITLE: Regex = Regex::new(r"^(.*?)\s*<([^>]+)>$").unwrap();
+    }
+
+    let (title, target) = match EXPLICIT_TITLE.captures(text) {
+        Some(caps) => (caps[1].to_string(), caps[2].to_string()),
+        None => (text.to_string(), text.to_string()),
+    };
+
+    match name {
+        "doc" => format!("[{}]({}.html)", title, target.trim_left_matches('/')),
+        "ref" => format!("[{}](#{})", title, anchor(&target, slug_style)),
+        "math" => format!("\\\\({}\\\\)", text),
+        "emphasis" => format!("*{}*", text),
+        "strong" => format!("**{}**", text),
+        "sub" | "subscript" => format!("<sub>{}</sub>", text),
+        "sup" | "superscript" => format!("<sup>{}</sup>", text),
+        _ => format!("`{}`", text),
+    }
+}
+
+/// The ID of the section (or target) with this name, the same way the book
+/// gives its headings IDs.
+fn anchor(name: &str, slug_style: SlugStyle) -> String {
+    slugify(name.trim(), slug_style)
+}
+
+/// Is this line made up of a single punctuation character, like the lines
+/// around section titles?
+fn is_adornment(line: &str) -> bool {
+    const ADORNMENTS: &str = "=-`:'\"~^_*+#<>.";
+
+    let first = match line.chars().next() {
+        Some(first) => first,
+        None => return false,
+    };
+
+    line.len() >= 2 && ADORNMENTS.contains(first) && line.chars().all(|c| c == first)
+}
+
+/// The end of the block of lines (and blank lines) indented further than the
+/// line before `start`.
+fn indented_block_end(lines: &[&str], start: usize) -> usize {
+    let mut end = start;
+    let mut last_text = start;
+
+    while end < lines.len() && (lines[end].is_empty() || lines[end].starts_with(' ')) {
+        end += 1;
+        if !lines[end - 1].is_empty() {
+            last_text = end;
+        }
+    }
+
+    // trailing blank lines belong to whatever comes next
+    last_text
+}
+
+/// Remove the indentation which all the lines have in common.
+fn dedent<'a>(lines: &[&'a str]) -> Vec<&'a str> {
+    let indent = lines
+        .iter()
+        .filter(|line| !line.is_empty())
+        .map(|line| line.len() - line.trim_left().len())
+        .min()
+        .unwrap_or(0);
+
+    lines
+        .iter()
+        .map(|line| if line.len() >= indent { &line[indent..] } else { "" })
+        .collect()
+}
+
+fn trim_blank_lines<'a, 'b>(lines: &'b [&'a str]) -> &'b [&'a str] {
+    let start = lines.iter().position(|line| !line.is_empty()).unwrap_or(lines.len());
+    let end = lines.iter().rposition(|line| !line.is_empty()).map(|ix| ix + 1).unwrap_or(start);
+    &lines[start..end.max(start)]
+}
+
+fn leading_space(line: &str) -> &str {
+    &line[..line.len() - line.trim_left().len()]
+}
+
+/// Where each column of a simple table starts and ends, from its border.
+fn column_spans(border: &str) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    let mut start = None;
+
+    for (ix, c) in border.char_indices() {
+        match (c, start) {
+            ('=', None) => start = Some(ix),
+            (' ', Some(from)) => {
+                spans.push((from, ix));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(from) = start {
+        spans.push((from, border.len()));
+    }
+
+    spans
+}
+
+fn push_line(out: &mut String, line: &str) {
+    out.push_str(line);
+    out.push('\n');
+}
+
+/// Separate two blocks, without piling up blank lines.
+fn push_blank(out: &mut String) {
+    if !(out.is_empty() || out.ends_with("\n\n")) {
+        out.push('\n');
+    }
+}
+
+fn push_heading(out: &mut String, level: usize, text: &str) {
+    push_line(out, &format!("{} {}", "#".repeat(level.min(6)), text));
+}
+
+fn push_quote(out: &mut String, markdown: &str) {
+    for line in markdown.trim().lines() {
+        if line.is_empty() {
+            push_line(out, ">");
+        } else {
+            push_line(out, &format!("> {}", line));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convert_a_typical_chapter() {
+        let src = "=========\n\
+                   The Guide\n\
+                   =========\n\
+                   \n\
+                   Installing\n\
+                   ----------\n\
+                   \n\
+                   .. A comment\n   spanning two lines\n\
+                   \n\
+                   Read the ``README`` and `the website <https://example.com>`_, or\n\
+                   :doc:`the intro <intro>` and Python_.\n\
+                   \n\
+                   .. _Python: https://python.org\n\
+                   \n\
+                   * One\n\
+                   * Two\n\
+                   \n\
+                   Run this::\n\
+                   \n    $ mdbook build\n\
+                   \n\
+                   .. note:: Back up first.\n\
+                   \n\
+                   .. code-block:: rust\n   :linenos:\n\
+                   \n   fn main() {}\n";
+        let should_be = "# The Guide\n\
+                         \n\
+                         ## Installing\n\
+                         \n\
+                         Read the `README` and [the website](https://example.com), or\n\
+                         [the intro](intro.html) and [Python](https://python.org).\n\
+                         \n\
+                         - One\n\
+                         - Two\n\
+                         \n\
+                         Run this:\n\
+                         \n\
+                         ```text\n\
+                         $ mdbook build\n\
+                         ```\n\
+                         \n\
+                         > **Note:** Back up first.\n\
+                         \n\
+                         ```rust\n\
+                         fn main() {}\n\
+                         ```\n\
+                         \n";
+
+        assert_eq!(rst_to_markdown(src, SlugStyle::Github), should_be);
+    }
+
+    #[test]
+    fn simple_tables_and_definition_lists() {
+        let src = "=====  =====\n\
+                   Name   Value\n\
+                   =====  =====\n\
+                   a      1\n\
+                   b      *two*\n\
+                   =====  =====\n\
+                   \n\
+                   term\n    The definition.\n";
+        let should_be = "| Name | Value |\n\
+                         |---|---|\n\
+                         | a | 1 |\n\
+                         | b | *two* |\n\
+                         \n\
+                         - **term**\n  The definition.\n";
+
+        assert_eq!(rst_to_markdown(src, SlugStyle::Github), should_be);
+    }
+}
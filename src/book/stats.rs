@@ -0,0 +1,94 @@
+//! Counting the words, code blocks and images in a chapter.
+
+use pulldown_cmark::{Event, Parser, Tag};
+
+/// How many words a reader gets through per minute, if not told otherwise.
+pub const DEFAULT_WORDS_PER_MINUTE: usize = 200;
+
+/// Some statistics about a piece of markdown.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Serialize)]
+pub struct Stats {
+    /// The number of words, not counting the contents of code blocks.
+    pub words: usize,
+    /// The number of code blocks.
+    pub code_blocks: usize,
+    /// The number of images.
+    pub images: usize,
+}
+
+impl Stats {
+    /// Gather the statistics for some markdown.
+    pub fn from_markdown(content: &str) -> Stats {
+        let mut stats = Stats::default();
+        let mut in_code_block = false;
+
+        for event in Parser::new(content) {
+            match event {
+                Event::Start(Tag::CodeBlock(_)) => {
+                    in_code_block = true;
+                    stats.code_blocks += 1;
+                }
+                Event::End(Tag::CodeBlock(_)) => in_code_block = false,
+                Event::Start(Tag::Image(..)) => stats.images += 1,
+                Event::Text(ref text) if !in_code_block => {
+                    stats.words += text.split_whitespace().count();
+                }
+                _ => {}
+            }
+        }
+
+        stats
+    }
+
+    /// Roughly how many minutes it takes to read this, rounded up.
+    pub fn reading_time(&self, words_per_minute: usize) -> usize {
+        let words_per_minute = if words_per_minute == 0 {
+            DEFAULT_WORDS_PER_MINUTE
+        } else {
+            words_per_minute
+        };
+
+        (self.words + words_per_minute - 1) / words_per_minute
+    }
+
+    /// Add another set of statistics to this one.
+    pub fn add(&mut self, other: &Stats) {
+        self.words += other.words;
+        self.code_blocks += other.code_blocks;
+        self.images += other.images;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn count_words_code_blocks_and_images() {
+        let src = "# A Title\n\nSome `inline code` here.\n\n```rust\nlet not_words = 1;\n```\n\n\
+                   ![An image](img.png)";
+
+        let got = Stats::from_markdown(src);
+
+        assert_eq!(
+            got,
+            Stats {
+                words: 8,
+                code_blocks: 1,
+                images: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn reading_time_is_rounded_up() {
+        let stats = Stats {
+            words: 450,
+            ..Default::default()
+        };
+
+        assert_eq!(stats.reading_time(200), 3);
+        assert_eq!(stats.reading_time(450), 1);
+        assert_eq!(Stats::default().reading_time(200), 0);
+    }
+}
@@ -1,5 +1,6 @@
 use std::fmt::{self, Display, Formatter};
 use std::iter::FromIterator;
+use std::mem;
 use std::ops::{Deref, DerefMut};
 use std::path::{Path, PathBuf};
 use memchr::{self, Memchr};
@@ -37,6 +38,24 @@ use errors::*;
 /// You can either use - or * to indicate a numbered chapter, the parser doesn't
 /// care but you'll probably want to stay consistent.
 ///
+/// **Part Title:** The numbered chapters can be grouped into parts, each
+/// starting with an (unnumbered) top-level heading. Chapter numbers carry on
+/// from one part to the next.
+///
+/// ```markdown
+/// # Part I: The Basics
+///
+/// - [Title of the Chapter](relative/path/to/markdown.md)
+/// ```
+///
+/// **Mounted Book:** A part title which links to `mount:<name>` is replaced by
+/// the chapters of the book mounted as `<name>` (see `build.mounts`), which
+/// keep being numbered from where this book got to.
+///
+/// ```markdown
+/// # [The API Guide](mount:api-guide)
+/// ```
+///
 /// **Suffix Chapter:** After the numbered chapters you can add a couple of
 /// non-numbered chapters. They are the same as prefix chapters but come after
 /// the numbered chapters instead of before.
@@ -74,6 +93,8 @@ pub struct Link {
     pub location: PathBuf,
     /// The section number, if this chapter is in the numbered section.
     pub number: Option<SectionNumber>,
+    /// Whether the chapter is left out of the book's navigation.
+    pub hidden: bool,
     /// Any nested items this chapter may contain.
     pub nested_items: Vec<SummaryItem>,
 }
@@ -85,6 +106,7 @@ impl Link {
             name: name.into(),
             location: location.as_ref().to_path_buf(),
             number: None,
+            hidden: false,
             nested_items: Vec::new(),
         }
     }
@@ -96,6 +118,7 @@ impl Default for Link {
             name: String::new(),
             location: PathBuf::new(),
             number: None,
+            hidden: false,
             nested_items: Vec::new(),
         }
     }
@@ -108,6 +131,12 @@ pub enum SummaryItem {
     Link(Link),
     /// A separator (`---`).
     Separator,
+    /// The title of a part, grouping the numbered chapters which follow it.
+    PartTitle(String),
+    /// A book mounted as a part, given by the part's title and the name of
+    /// the mount. These are replaced by the mounted book's chapters when the
+    /// book is loaded.
+    MountedBook(String, String),
 }
 
 impl SummaryItem {
@@ -125,6 +154,60 @@ impl From<Link> for SummaryItem {
     }
 }
 
+/// The scheme of the links to mounted books in part titles.
+const MOUNT_PREFIX: &str = "mount:";
+
+/// Replace the books mounted in a summary's numbered chapters with their own
+/// chapters, relocated into the directory named after the mount. The mounted
+/// chapters carry on the numbering from the chapters before them.
+///
+/// `load` gets the `Summary` of a mounted book from the name of its mount.
+pub(crate) fn mount_books<F>(summary: &mut Summary, mut load: F) -> Result<()>
+where
+    F: FnMut(&str) -> Result<Summary>,
+{
+    let items = mem::replace(&mut summary.numbered_chapters, Vec::new());
+    let mut shift = 0;
+
+    for item in items {
+        match item {
+            SummaryItem::MountedBook(title, name) => {
+                let mounted = load(&name)
+                    .chain_err(|| format!("Unable to mount the book \"{}\"", name))?;
+
+                let mut chapters = mounted.prefix_chapters;
+                chapters.extend(mounted.numbered_chapters);
+                chapters.extend(mounted.suffix_chapters);
+                relocate(&mut chapters, Path::new(&name));
+
+                let previous_chapters = count_numbered(&summary.numbered_chapters);
+                update_section_numbers(&mut chapters, 0, previous_chapters as u32);
+                shift += count_numbered(&chapters) as u32;
+
+                summary.numbered_chapters.push(SummaryItem::PartTitle(title));
+                summary.numbered_chapters.extend(chapters);
+            }
+            other => {
+                summary.numbered_chapters.push(other);
+                let last = summary.numbered_chapters.len() - 1;
+                update_section_numbers(&mut summary.numbered_chapters[last..], 0, shift);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Move the chapters of a list of `SummaryItem`s into `dir`.
+fn relocate(items: &mut [SummaryItem], dir: &Path) {
+    for item in items {
+        if let SummaryItem::Link(ref mut link) = *item {
+            link.location = dir.join(&link.location);
+            relocate(&mut link.nested_items, dir);
+        }
+    }
+}
+
 /// A recursive descent (-ish) parser for a `SUMMARY.md`.
 ///
 ///
@@ -139,7 +222,9 @@ impl From<Link> for SummaryItem {
 ///                     | EPSILON
 /// prefix_chapters   ::= item*
 /// suffix_chapters   ::= item*
-/// numbered_chapters ::= dotted_item+
+/// numbered_chapters ::= (part_title | dotted_item)+
+/// part_title        ::= "# " TEXT
+///                     | "# " "[" TEXT "]" "(mount:" TEXT ")"
 /// dotted_item       ::= INDENT* DOT_POINT item
 /// item              ::= link
 ///                     | separator
@@ -154,6 +239,9 @@ impl From<Link> for SummaryItem {
 struct SummaryParser<'a> {
     src: &'a str,
     stream: pulldown_cmark::Parser<'a>,
+    /// An event which was read too early and should be handed out again by
+    /// `next_event()`.
+    back: Option<Event<'a>>,
 }
 
 /// Reads `Events` from the provided stream until the corresponding
@@ -200,32 +288,43 @@ impl<'a> SummaryParser<'a> {
         SummaryParser {
             src: text,
             stream: pulldown_parser,
+            back: None,
         }
     }
 
     /// Get the current line and column to give the user more useful error
     /// messages.
+    ///
+    /// The parser may already have read past the end of the offending line,
+    /// so any trailing whitespace is skipped to keep us on the line which
+    /// caused the problem.
     fn current_location(&self) -> (usize, usize) {
-        let byte_offset = self.stream.get_offset();
+        let byte_offset = self.src[..self.stream.get_offset()].trim_right().len();
 
         let previous_text = self.src[..byte_offset].as_bytes();
         let line = Memchr::new(b'\n', previous_text).count() + 1;
-        let start_of_line = memchr::memrchr(b'\n', previous_text).unwrap_or(0);
-        let col = self.src[start_of_line..byte_offset].chars().count();
+        let start_of_line = memchr::memrchr(b'\n', previous_text)
+            .map(|ix| ix + 1)
+            .unwrap_or(0);
+        let col = self.src[start_of_line..byte_offset].chars().count() + 1;
 
         (line, col)
     }
 
+    /// The text of a particular line (counting from 1).
+    fn line_text(&self, line: usize) -> &str {
+        self.src.lines().nth(line - 1).unwrap_or("")
+    }
+
     /// Parse the text the `SummaryParser` was created with.
     fn parse(mut self) -> Result<Summary> {
         let title = self.parse_title();
 
-        let prefix_chapters = self.parse_affix(true)
-            .chain_err(|| "There was an error parsing the prefix chapters")?;
-        let numbered_chapters = self.parse_numbered()
-            .chain_err(|| "There was an error parsing the numbered chapters")?;
-        let suffix_chapters = self.parse_affix(false)
-            .chain_err(|| "There was an error parsing the suffix chapters")?;
+        // the `ParseError`s already say where the problem is, so they're
+        // passed on as-is for callers to match on
+        let prefix_chapters = self.parse_affix(true)?;
+        let numbered_chapters = self.parse_numbered()?;
+        let suffix_chapters = self.parse_affix(false)?;
 
         Ok(Summary {
             title,
@@ -237,6 +336,9 @@ impl<'a> SummaryParser<'a> {
 
     /// Parse the affix chapters. This expects the first event (start of
     /// paragraph) to have already been consumed by the previous parser.
+    ///
+    /// The prefix chapters end at the first list or part title, which is left
+    /// for `parse_numbered()`.
     fn parse_affix(&mut self, is_prefix: bool) -> Result<Vec<SummaryItem>> {
         let mut items = Vec::new();
         debug!(
@@ -246,17 +348,27 @@ impl<'a> SummaryParser<'a> {
 
         loop {
             match self.next_event() {
-                Some(Event::Start(Tag::List(..))) => {
+                Some(event @ Event::Start(Tag::List(..)))
+                | Some(event @ Event::Start(Tag::Header(1))) => {
                     if is_prefix {
                         // we've finished prefix chapters and are at the start
                         // of the numbered section.
+                        self.back = Some(event);
                         break;
                     } else {
-                        bail!(self.parse_error("Suffix chapters cannot be followed by a list"));
+                        bail!(self.parse_error(
+                            "Suffix chapters cannot be followed by a list or part title",
+                            Some(String::from(
+                                "Move the suffix chapters after the last numbered chapter, \
+                                 or turn them into list items (\"- [Title](chapter.md)\") \
+                                 if they should be numbered",
+                            )),
+                        ));
                     }
                 }
-                Some(Event::Start(Tag::Link(href, _))) => {
-                    let link = self.parse_link(href.to_string())?;
+                Some(Event::Start(Tag::Link(href, title))) => {
+                    let mut link = self.parse_link(href.to_string())?;
+                    link.hidden = is_hidden(&title);
                     items.push(SummaryItem::Link(link));
                 }
                 Some(Event::Start(Tag::Rule)) => items.push(SummaryItem::Separator),
@@ -273,41 +385,69 @@ impl<'a> SummaryParser<'a> {
         let name = stringify_events(link_content);
 
         if href.is_empty() {
-            Err(self.parse_error("You can't have an empty link."))
+            let hint = format!(
+                "Point the link at the chapter's file, e.g. \"[{}](chapter.md)\"",
+                name
+            );
+            Err(self.parse_error("You can't have an empty link.", Some(hint)))
         } else {
             Ok(Link {
                 name: name,
                 location: PathBuf::from(href.to_string()),
                 number: None,
+                hidden: false,
                 nested_items: Vec::new(),
             })
         }
     }
 
-    /// Parse the numbered chapters. This assumes the opening list tag has
-    /// already been consumed by a previous parser.
+    /// Parse the numbered chapters and the part titles between them, stopping
+    /// at the first paragraph (the start of the suffix chapters).
     fn parse_numbered(&mut self) -> Result<Vec<SummaryItem>> {
         let mut items = Vec::new();
         let root_number = SectionNumber::default();
 
-        // we need to do this funny loop-match-if-let dance because a rule will
-        // close off any currently running list. Therefore we try to read the
-        // list items before the rule, then if we encounter a rule we'll add a
-        // separator and try to resume parsing numbered chapters if we start a
-        // list immediately afterwards.
-        //
-        // If you can think of a better way to do this then please make a PR :)
-
+        // A rule or part title closes off any currently running list, so we
+        // read one list at a time and keep going for as long as there are
+        // more lists, rules and part titles.
         loop {
-            let mut bunch_of_items = self.parse_nested_numbered(&root_number)?;
-
-            // if we've resumed after something like a rule the root sections
-            // will be numbered from 1. We need to manually go back and update
-            // them
-            update_section_numbers(&mut bunch_of_items, 0, items.len() as u32);
-            items.extend(bunch_of_items);
-
             match self.next_event() {
+                Some(Event::Start(Tag::List(..))) => {
+                    let mut bunch_of_items = self.parse_nested_numbered(&root_number)?;
+
+                    // if we've resumed after something like a rule the root
+                    // sections will be numbered from 1. We need to manually go
+                    // back and update them
+                    let previous_chapters = count_numbered(&items);
+                    update_section_numbers(&mut bunch_of_items, 0, previous_chapters as u32);
+                    items.extend(bunch_of_items);
+                }
+                Some(Event::Start(Tag::Header(1))) => {
+                    let title = collect_events!(self.stream, end Tag::Header(1));
+                    let mount = title
+                        .iter()
+                        .filter_map(|event| match *event {
+                            Event::Start(Tag::Link(ref href, _))
+                                if href.starts_with(MOUNT_PREFIX) =>
+                            {
+                                Some(href[MOUNT_PREFIX.len()..].to_string())
+                            }
+                            _ => None,
+                        })
+                        .next();
+                    let title = stringify_events(title);
+
+                    match mount {
+                        Some(name) => {
+                            debug!("Found mounted book: {} ({})", title, name);
+                            items.push(SummaryItem::MountedBook(title, name));
+                        }
+                        None => {
+                            debug!("Found part title: {}", title);
+                            items.push(SummaryItem::PartTitle(title));
+                        }
+                    }
+                }
                 Some(Event::Start(Tag::Paragraph)) => {
                     // we're starting the suffix chapters
                     break;
@@ -333,12 +473,6 @@ impl<'a> SummaryParser<'a> {
                             }
                         }
                     }
-
-                    if let Some(Event::Start(Tag::List(..))) = self.next_event() {
-                        continue;
-                    } else {
-                        break;
-                    }
                 }
                 Some(_) => {
                     // something else... ignore
@@ -355,7 +489,7 @@ impl<'a> SummaryParser<'a> {
     }
 
     fn next_event(&mut self) -> Option<Event<'a>> {
-        let next = self.stream.next();
+        let next = self.back.take().or_else(|| self.stream.next());
         trace!("Next event: {:?}", next);
 
         next
@@ -368,18 +502,32 @@ impl<'a> SummaryParser<'a> {
         loop {
             match self.next_event() {
                 Some(Event::Start(Tag::Item)) => {
-                    let item = self.parse_nested_item(parent, items.len())?;
+                    let item = self.parse_nested_item(parent, count_numbered(&items))?;
                     items.push(item);
                 }
                 Some(Event::Start(Tag::List(..))) => {
                     // recurse to parse the nested list
-                    let (_, last_item) = get_last_link(&mut items)?;
-                    let last_item_number = last_item
-                        .number
-                        .as_ref()
-                        .expect("All numbered chapters have numbers");
-
-                    let sub_items = self.parse_nested_numbered(last_item_number)?;
+                    let (_, last_item) = match get_last_link(&mut items) {
+                        Ok(last) => last,
+                        Err(_) => bail!(self.parse_error(
+                            "A nested list has to come after the chapter it belongs to",
+                            Some(String::from(
+                                "Check the indentation of this line, it is probably indented \
+                                 further than the chapter above it",
+                            )),
+                        )),
+                    };
+
+                    // hidden chapters aren't numbered, and neither is anything
+                    // nested inside of them
+                    let sub_items = match last_item.number.clone() {
+                        Some(number) => self.parse_nested_numbered(&number)?,
+                        None => {
+                            let mut sub_items = self.parse_nested_numbered(parent)?;
+                            hide_all(&mut sub_items);
+                            sub_items
+                        }
+                    };
 
                     last_item.nested_items = sub_items;
                 }
@@ -400,8 +548,14 @@ impl<'a> SummaryParser<'a> {
         loop {
             match self.next_event() {
                 Some(Event::Start(Tag::Paragraph)) => continue,
-                Some(Event::Start(Tag::Link(href, _))) => {
+                Some(Event::Start(Tag::Link(href, title))) => {
                     let mut link = self.parse_link(href.to_string())?;
+                    link.hidden = is_hidden(&title);
+
+                    if link.hidden {
+                        trace!("Found hidden chapter: {}", link.name);
+                        return Ok(SummaryItem::Link(link));
+                    }
 
                     let mut number = parent.clone();
                     number.0.push(num_existing_items as u32 + 1);
@@ -417,30 +571,107 @@ impl<'a> SummaryParser<'a> {
                     return Ok(SummaryItem::Link(link));
                 }
                 other => {
-                    warn!("Expected a start of a link, actually got {:?}", other);
+                    debug!("Expected a start of a link, actually got {:?}", other);
+                    let (line, _) = self.current_location();
+                    let hint = suggest_link_fix(self.line_text(line));
                     bail!(self.parse_error(
-                        "The link items for nested chapters must only contain a hyperlink"
+                        "The link items for nested chapters must only contain a hyperlink",
+                        hint,
                     ));
                 }
             }
         }
     }
 
-    fn parse_error<D: Display>(&self, msg: D) -> Error {
+    /// Create a `ParseError` for the current location, quoting the offending
+    /// line and adding a suggestion on how to fix it (if we have one).
+    fn parse_error<D: Display>(&self, msg: D, hint: Option<String>) -> Error {
         let (line, col) = self.current_location();
 
-        ErrorKind::ParseError(line, col, msg.to_string()).into()
+        let mut message = format!("{}\n\n{:>5} | {}", msg, line, self.line_text(line));
+        if let Some(hint) = hint {
+            message.push_str(&format!("\n\nhelp: {}", hint));
+        }
+
+        ErrorKind::ParseError(line, col, message).into()
     }
 
     /// Try to parse the title line.
     fn parse_title(&mut self) -> Option<String> {
-        if let Some(Event::Start(Tag::Header(1))) = self.next_event() {
-            debug!("Found a h1 in the SUMMARY");
+        match self.next_event() {
+            Some(Event::Start(Tag::Header(1))) => {
+                debug!("Found a h1 in the SUMMARY");
 
-            let tags = collect_events!(self.stream, end Tag::Header(1));
-            Some(stringify_events(tags))
-        } else {
-            None
+                let tags = collect_events!(self.stream, end Tag::Header(1));
+                Some(stringify_events(tags))
+            }
+            other => {
+                self.back = other;
+                None
+            }
+        }
+    }
+}
+
+/// Guess what's wrong with a list item which should have been a link.
+fn suggest_link_fix(line: &str) -> Option<String> {
+    let text = line.trim()
+        .trim_left_matches(|c: char| c == '-' || c == '*' || c == '+')
+        .trim();
+    if text.is_empty() {
+        return None;
+    }
+
+    let hint = match (text.find('['), text.find(']')) {
+        (None, _) => format!(
+            "Turn the item into a link to the chapter's file, e.g. \"- [{}](chapter.md)\"",
+            text
+        ),
+        (Some(_), None) => String::from("The link is missing its closing \"]\""),
+        (Some(_), Some(close)) => {
+            let rest = &text[close + 1..];
+            if rest.trim_left().starts_with('(') && !rest.starts_with('(') {
+                String::from("Remove the space between the \"]\" and the \"(\"")
+            } else if rest.starts_with('(') && !rest.contains(')') {
+                String::from("The link is missing its closing \")\"")
+            } else if !rest.starts_with('(') {
+                String::from(
+                    "Add the chapter's file after the title, e.g. \"[Title](chapter.md)\"",
+                )
+            } else {
+                String::from("Remove any text outside of the link")
+            }
+        }
+    };
+
+    Some(hint)
+}
+
+/// A link's title marks it as hidden, e.g.
+/// `[Easter Egg](easter-egg.md "hidden")`.
+fn is_hidden(title: &str) -> bool {
+    title.trim().eq_ignore_ascii_case("hidden")
+}
+
+/// The number of numbered chapters in a list of `SummaryItem`s, not counting
+/// nested ones.
+fn count_numbered(items: &[SummaryItem]) -> usize {
+    items
+        .iter()
+        .filter(|item| match **item {
+            SummaryItem::Link(ref link) => link.number.is_some(),
+            _ => false,
+        })
+        .count()
+}
+
+/// Hide a list of `SummaryItem`s and everything nested inside them.
+fn hide_all(items: &mut [SummaryItem]) {
+    for item in items {
+        if let SummaryItem::Link(ref mut link) = *item {
+            link.hidden = true;
+            link.number = None;
+            hide_all(&mut link.nested_items);
         }
     }
 }
@@ -689,7 +920,6 @@ mod tests {
         let should_be = vec![SummaryItem::Link(link)];
 
         let mut parser = SummaryParser::new(src);
-        let _ = parser.stream.next();
 
         let got = parser.parse_numbered().unwrap();
 
@@ -705,11 +935,13 @@ mod tests {
                 name: String::from("First"),
                 location: PathBuf::from("./first.md"),
                 number: Some(SectionNumber(vec![1])),
+                hidden: false,
                 nested_items: vec![
                     SummaryItem::Link(Link {
                         name: String::from("Nested"),
                         location: PathBuf::from("./nested.md"),
                         number: Some(SectionNumber(vec![1, 1])),
+                        hidden: false,
                         nested_items: Vec::new(),
                     }),
                 ],
@@ -718,12 +950,12 @@ mod tests {
                 name: String::from("Second"),
                 location: PathBuf::from("./second.md"),
                 number: Some(SectionNumber(vec![2])),
+                hidden: false,
                 nested_items: Vec::new(),
             }),
         ];
 
         let mut parser = SummaryParser::new(src);
-        let _ = parser.stream.next();
 
         let got = parser.parse_numbered().unwrap();
 
@@ -742,18 +974,19 @@ mod tests {
                 name: String::from("First"),
                 location: PathBuf::from("./first.md"),
                 number: Some(SectionNumber(vec![1])),
+                hidden: false,
                 nested_items: Vec::new(),
             }),
             SummaryItem::Link(Link {
                 name: String::from("Second"),
                 location: PathBuf::from("./second.md"),
                 number: Some(SectionNumber(vec![2])),
+                hidden: false,
                 nested_items: Vec::new(),
             }),
         ];
 
         let mut parser = SummaryParser::new(src);
-        let _ = parser.stream.next();
 
         let got = parser.parse_numbered().unwrap();
 
@@ -764,9 +997,181 @@ mod tests {
     fn an_empty_link_location_is_an_error() {
         let src = "- [Empty]()\n";
         let mut parser = SummaryParser::new(src);
-        parser.stream.next();
 
         let got = parser.parse_numbered();
         assert!(got.is_err());
     }
+
+    #[test]
+    fn parse_errors_quote_the_offending_line() {
+        let src = "# Summary\n\n- [First](first.md)\n- Second\n";
+        let parser = SummaryParser::new(src);
+
+        let got = parser.parse().unwrap_err();
+        match *got.kind() {
+            ErrorKind::ParseError(line, _, _) => assert_eq!(line, 4),
+            ref other => panic!("Unexpected error: {:?}", other),
+        }
+        let cause = got.to_string();
+
+        assert!(cause.starts_with("Error at line 4, column"), "{}", cause);
+        assert!(cause.contains("    4 | - Second"), "{}", cause);
+        assert!(cause.contains("help: Turn the item into a link"), "{}", cause);
+    }
+
+    #[test]
+    fn suggest_fixes_for_broken_links() {
+        let inputs = vec![
+            ("- [Second](second.md", "missing its closing \")\""),
+            ("- [Second (second.md)", "missing its closing \"]\""),
+            ("- [Second] (second.md)", "Remove the space"),
+            ("- [Second]", "Add the chapter's file"),
+        ];
+
+        for (line, should_be) in inputs {
+            let got = suggest_link_fix(line).unwrap();
+            assert!(got.contains(should_be), "{:?} gave {:?}", line, got);
+        }
+    }
+
+    #[test]
+    fn parse_part_titles_between_numbered_chapters() {
+        let src = "# Summary\n\n[Intro](intro.md)\n\n# Part I\n\n- [First](first.md)\n\n\
+                   # Part II\n\n- [Second](second.md)\n\n[Outro](outro.md)\n";
+
+        let got = parse_summary(src).unwrap();
+
+        assert_eq!(got.prefix_chapters.len(), 1);
+        assert_eq!(
+            got.numbered_chapters,
+            vec![
+                SummaryItem::PartTitle(String::from("Part I")),
+                SummaryItem::Link(Link {
+                    name: String::from("First"),
+                    location: PathBuf::from("first.md"),
+                    number: Some(SectionNumber(vec![1])),
+                    hidden: false,
+                    nested_items: Vec::new(),
+                }),
+                SummaryItem::PartTitle(String::from("Part II")),
+                SummaryItem::Link(Link {
+                    name: String::from("Second"),
+                    location: PathBuf::from("second.md"),
+                    number: Some(SectionNumber(vec![2])),
+                    hidden: false,
+                    nested_items: Vec::new(),
+                }),
+            ]
+        );
+        assert_eq!(got.suffix_chapters.len(), 1);
+    }
+
+    #[test]
+    fn separators_between_numbered_chapters_keep_the_numbering_going() {
+        let src = "- [First](./first.md)\n  - [Nested](./nested.md)\n\n---\n\n\
+                   - [Second](./second.md)\n";
+
+        let mut parser = SummaryParser::new(src);
+        let got = parser.parse_numbered().unwrap();
+
+        assert_eq!(got.len(), 3);
+        assert_eq!(got[1], SummaryItem::Separator);
+        match got[2] {
+            SummaryItem::Link(ref link) => {
+                assert_eq!(link.number, Some(SectionNumber(vec![2])));
+            }
+            ref other => panic!("Expected a link, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_a_mounted_book() {
+        let src = "- [First](./first.md)\n\n# [The API Guide](mount:api-guide)\n";
+
+        let got = parse_summary(src).unwrap();
+
+        assert_eq!(
+            got.numbered_chapters[1],
+            SummaryItem::MountedBook(String::from("The API Guide"), String::from("api-guide"))
+        );
+    }
+
+    #[test]
+    fn mounted_books_carry_on_the_numbering() {
+        let src = "- [First](first.md)\n\n# [Guide](mount:guide)\n\n# Part II\n\n\
+                   - [Second](second.md)\n";
+        let mut summary = parse_summary(src).unwrap();
+
+        mount_books(&mut summary, |name| {
+            assert_eq!(name, "guide");
+            parse_summary("[Preface](preface.md)\n\n- [One](one.md)\n  - [Nested](nested.md)\n\
+                           - [Two](two.md)\n")
+        }).unwrap();
+
+        let got: Vec<_> = summary
+            .numbered_chapters
+            .iter()
+            .map(|item| match *item {
+                SummaryItem::Link(ref link) => format!(
+                    "{} {}",
+                    link.number.as_ref().map(|n| n.to_string()).unwrap_or_default(),
+                    link.location.display()
+                ),
+                SummaryItem::PartTitle(ref title) => format!("# {}", title),
+                ref other => panic!("Unexpected {:?}", other),
+            })
+            .collect();
+        assert_eq!(
+            got,
+            vec![
+                "1. first.md",
+                "# Guide",
+                " guide/preface.md",
+                "2. guide/one.md",
+                "3. guide/two.md",
+                "# Part II",
+                "4. second.md",
+            ]
+        );
+        match summary.numbered_chapters[3] {
+            SummaryItem::Link(ref one) => match one.nested_items[0] {
+                SummaryItem::Link(ref nested) => {
+                    assert_eq!(nested.location, PathBuf::from("guide/nested.md"));
+                    assert_eq!(nested.number, Some(SectionNumber(vec![2, 1])));
+                }
+                ref other => panic!("Expected a link, got {:?}", other),
+            },
+            ref other => panic!("Expected a link, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn hidden_chapters_are_not_numbered() {
+        let src = "- [First](./first.md)\n- [Egg](./egg.md \"hidden\")\n  - [Yolk](./yolk.md)\n\
+                   - [Second](./second.md)\n";
+
+        let mut parser = SummaryParser::new(src);
+        let got = parser.parse_numbered().unwrap();
+
+        let links: Vec<&Link> = got.iter()
+            .filter_map(|item| match *item {
+                SummaryItem::Link(ref link) => Some(link),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(links.len(), 3);
+
+        assert!(links[1].hidden);
+        assert_eq!(links[1].number, None);
+        match links[1].nested_items[0] {
+            SummaryItem::Link(ref yolk) => {
+                assert!(yolk.hidden);
+                assert_eq!(yolk.number, None);
+            }
+            ref other => panic!("Expected a link, got {:?}", other),
+        }
+
+        assert!(!links[2].hidden);
+        assert_eq!(links[2].number, Some(SectionNumber(vec![2])));
+    }
 }
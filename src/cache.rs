@@ -0,0 +1,178 @@
+//! A cache on disk for the results of expensive work, like rendering diagrams
+//! or highlighting code, which survives between builds.
+//!
+//! Entries are keyed by a hash of whatever they were computed from, so a
+//! changed input simply misses the cache. Preprocessors get a `Cache` of their
+//! own from [`PreprocessorContext::cache()`]:
+//!
+//! ```rust,no_run
+//! # extern crate mdbook;
+//! # use mdbook::book::Book;
+//! # use mdbook::errors::*;
+//! # use mdbook::preprocess::PreprocessorContext;
+//! # fn render_diagram(_src: &str) -> Result<String> { unimplemented!() }
+//! fn run(ctx: &PreprocessorContext, book: &mut Book) -> Result<()> {
+//!     let cache = ctx.cache("diagrams");
+//!     let svg = cache.get_or_insert_with("graph { a -- b }", render_diagram)?;
+//!     # let _ = (book, svg);
+//!     Ok(())
+//! }
+//! # fn main() {}
+//! ```
+//!
+//! [`PreprocessorContext::cache()`]: ../preprocess/struct.PreprocessorContext.html#method.cache
+
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use sha2::{Digest, Sha256};
+use tempdir::TempDir;
+
+use errors::*;
+use utils::fs::create_file;
+
+/// A directory of cached values, keyed by the hash of their input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cache {
+    dir: PathBuf,
+}
+
+impl Cache {
+    /// Create a `Cache` which keeps its entries in `dir`. The directory is
+    /// only created once something is inserted.
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Cache {
+        Cache { dir: dir.into() }
+    }
+
+    /// The directory the entries are kept in.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The key an input is stored under. This is the SHA-256 of its contents,
+    /// so it stays the same between builds and `mdbook` versions, and two
+    /// different inputs never end up with the same entry.
+    pub fn key(input: &str) -> String {
+        format!("{:x}", Sha256::digest(input.as_bytes()))
+    }
+
+    /// Look up the value previously cached for `input`.
+    pub fn get(&self, input: &str) -> Option<String> {
+        let path = self.path_for(input);
+        let mut value = String::new();
+
+        match File::open(&path).and_then(|mut f| f.read_to_string(&mut value)) {
+            Ok(_) => {
+                trace!("Cache hit for {}", path.display());
+                Some(value)
+            }
+            Err(_) => None,
+        }
+    }
+
+    /// How long ago the value for `input` was cached, if it was.
+    pub fn age(&self, input: &str) -> Option<Duration> {
+        fs::metadata(self.path_for(input))
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+    }
+
+    /// Cache the `value` computed from `input`.
+    pub fn insert(&self, input: &str, value: &str) -> Result<()> {
+        let path = self.path_for(input);
+
+        // write to a temporary file of this write's own first, so a build
+        // running in parallel never sees half an entry (or writes to the
+        // same temporary file)
+        fs::create_dir_all(&self.dir)
+            .chain_err(|| format!("Unable to create {}", self.dir.display()))?;
+        let temp_dir = TempDir::new_in(&self.dir, "entry")
+            .chain_err(|| format!("Unable to write the cache entry {}", path.display()))?;
+        let temp = temp_dir.path().join("value");
+        create_file(&temp)?.write_all(value.as_bytes())?;
+        fs::rename(&temp, &path)
+            .chain_err(|| format!("Unable to write the cache entry {}", path.display()))?;
+
+        Ok(())
+    }
+
+    /// Get the value cached for `input`, computing and caching it with `f` if
+    /// there isn't one yet.
+    pub fn get_or_insert_with<F>(&self, input: &str, f: F) -> Result<String>
+    where
+        F: FnOnce(&str) -> Result<String>,
+    {
+        if let Some(value) = self.get(input) {
+            return Ok(value);
+        }
+
+        let value = f(input)?;
+        self.insert(input, &value)?;
+        Ok(value)
+    }
+
+    /// Remove every entry.
+    pub fn clear(&self) -> Result<()> {
+        if self.dir.exists() {
+            fs::remove_dir_all(&self.dir)
+                .chain_err(|| format!("Unable to remove {}", self.dir.display()))?;
+        }
+
+        Ok(())
+    }
+
+    fn path_for(&self, input: &str) -> PathBuf {
+        self.dir.join(Cache::key(input))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn keys_are_stable() {
+        assert_eq!(
+            Cache::key(""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            Cache::key("a"),
+            "ca978112ca1bbdcafac231b39a23dc4da786eff8147c4e72b9807785afee48bb"
+        );
+        assert_ne!(Cache::key("graph { a -- b }"), Cache::key("graph { a -- c }"));
+    }
+
+    #[test]
+    fn values_are_only_computed_once() {
+        let temp = TempDir::new("mdbook").unwrap();
+        let cache = Cache::new(temp.path().join("diagrams"));
+        let mut calls = 0;
+
+        for _ in 0..2 {
+            let got = cache
+                .get_or_insert_with("input", |input| {
+                    calls += 1;
+                    Ok(input.to_uppercase())
+                })
+                .unwrap();
+            assert_eq!(got, "INPUT");
+        }
+        assert_eq!(calls, 1);
+
+        // a new `Cache` for the same directory sees the same entries
+        let cache = Cache::new(temp.path().join("diagrams"));
+        assert_eq!(cache.get("input"), Some(String::from("INPUT")));
+        assert_eq!(cache.get("something else"), None);
+
+        // nothing but the entry is left behind
+        let entries = fs::read_dir(cache.dir()).unwrap().count();
+        assert_eq!(entries, 1);
+
+        cache.clear().unwrap();
+        assert_eq!(cache.get("input"), None);
+    }
+}
@@ -54,15 +54,20 @@ use std::path::{Path, PathBuf};
 use std::fs::File;
 use std::io::Read;
 use std::env;
+use std::collections::BTreeMap;
+use std::collections::btree_map::Entry;
+use std::fmt::{self, Display, Formatter};
 use toml::{self, Value};
 use toml::value::Table;
 use toml_query::read::TomlValueReadExt;
 use toml_query::insert::TomlValueInsertExt;
 use toml_query::delete::TomlValueDeleteExt;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde::de::DeserializeOwned;
 use serde_json;
 
 use errors::*;
+use book::DEFAULT_WORDS_PER_MINUTE;
 
 /// The overall configuration object for MDBook, essentially an in-memory
 /// representation of `book.toml`.
@@ -82,14 +87,18 @@ impl Config {
     }
 
     /// Load the configuration file from disk.
+    ///
+    /// If the file has a top-level `extends` key, the file it points to
+    /// (relative to the extending file) is loaded first and then this file's
+    /// values are merged on top of it. This lets several books share a common
+    /// base configuration.
     pub fn from_disk<P: AsRef<Path>>(config_file: P) -> Result<Config> {
-        let mut buffer = String::new();
-        File::open(config_file)
-            .chain_err(|| "Unable to open the configuration file")?
-            .read_to_string(&mut buffer)
-            .chain_err(|| "Couldn't read the file")?;
+        let config_file = config_file.as_ref();
+        let raw = load_raw(config_file, &mut Vec::new())?;
 
-        Config::from_str(&buffer)
+        raw.try_into().map_err(|e| {
+            ErrorKind::Config(config_file.to_path_buf(), e.to_string()).into()
+        })
     }
 
     /// Updates the `Config` from the available environment variables.
@@ -118,9 +127,12 @@ impl Config {
     /// > when building the book with something like
     /// >
     /// > ```text
-    /// > $ export MDBOOK_BOOK="{'title': 'My Awesome Book', authors: ['Michael-F-Bryan']}"
+    /// > $ export MDBOOK_BOOK='{"title": "My Awesome Book", "authors": ["Michael-F-Bryan"]}'
     /// > $ mdbook build
     /// > ```
+    /// >
+    /// > Keys which aren't mentioned in the override keep their original
+    /// > value.
     ///
     /// The latter case may be useful in situations where `mdbook` is invoked
     /// from a script or CI, where it sometimes isn't possible to update the
@@ -135,10 +147,31 @@ impl Config {
 
         for (key, value) in overrides {
             trace!("{} => {}", key, value);
-            let parsed_value = serde_json::from_str(&value)
-                .unwrap_or_else(|_| serde_json::Value::String(value.to_string()));
+            if let Err(e) = self.set(&key, parse_override(&value)) {
+                warn!("Ignoring the environment variable which overrides \"{}\": {}", key, e);
+            }
+        }
+    }
 
-            self.set(key, parsed_value).expect("unreachable");
+    /// Apply a `key=value` override, as passed to `--set` on the command line.
+    ///
+    /// Just like with environment variables, the value is parsed as JSON,
+    /// falling back to a string if that fails. This means
+    /// `output.html.curly-quotes=true` sets a boolean while
+    /// `output.html.site-url=https://example.com/book/` sets a string.
+    pub fn apply_override(&mut self, assignment: &str) -> Result<()> {
+        let mut parts = assignment.splitn(2, '=');
+        let key = parts.next().unwrap_or_default().trim();
+
+        match parts.next() {
+            Some(value) if !key.is_empty() => {
+                debug!("Overriding {} with {:?}", key, value);
+                self.set(key, parse_override(value))
+            }
+            _ => bail!(
+                "Expected an override of the form \"key=value\", found {:?}",
+                assignment
+            ),
         }
     }
 
@@ -162,6 +195,17 @@ impl Config {
         }
     }
 
+    /// The languages of a multilingual book, indexed by their language code.
+    pub fn languages(&self) -> BTreeMap<String, LanguageConfig> {
+        self.get_deserialized("language").unwrap_or_default()
+    }
+
+    /// The versions of a versioned book, in the order they're listed in the
+    /// version switcher.
+    pub fn versions(&self) -> Vec<VersionConfig> {
+        self.get_deserialized("version").unwrap_or_default()
+    }
+
     /// Convenience method for getting the html renderer's configuration.
     ///
     /// # Note
@@ -190,18 +234,29 @@ impl Config {
 
     /// Set a config key, clobbering any existing values along the way.
     ///
-    /// The only way this can fail is if we can't serialize `value` into a
-    /// `toml::Value`.
+    /// This fails if `value` can't be serialized into a `toml::Value`, or if
+    /// it's the wrong type for a `book.*` or `build.*` key.
     pub fn set<S: Serialize, I: AsRef<str>>(&mut self, index: I, value: S) -> Result<()> {
         let index = index.as_ref();
 
         let value =
             Value::try_from(value).chain_err(|| "Unable to represent the item as a JSON Value")?;
 
-        if index.starts_with("book.") {
-            self.book.update_value(&index[5..], value);
+        if index == "book" || index == "build" {
+            // merge the table into the existing section so a partial override
+            // (e.g. `MDBOOK_BOOK='{"title": "..."}'`) leaves other keys alone
+            match value {
+                Value::Table(table) => {
+                    for (key, item) in table {
+                        self.set(format!("{}.{}", index, key), item)?;
+                    }
+                }
+                _ => bail!("The \"{}\" table can only be overridden by a table", index),
+            }
+        } else if index.starts_with("book.") {
+            self.book.update_value(&index[5..], value)?;
         } else if index.starts_with("build.") {
-            self.build.update_value(&index[6..], value);
+            self.build.update_value(&index[6..], value)?;
         } else {
             self.rest.insert(index, value)?;
         }
@@ -209,6 +264,54 @@ impl Config {
         Ok(())
     }
 
+    /// Apply the overrides from the `[profile.<name>]` table, clobbering the
+    /// corresponding keys in the rest of the config.
+    ///
+    /// For example, selecting the `release` profile with the following
+    /// `book.toml` will set `output.html.google-analytics` while leaving the
+    /// rest of the `[output.html]` table untouched.
+    ///
+    /// ```toml
+    /// [output.html]
+    /// curly-quotes = true
+    ///
+    /// [profile.release.output.html]
+    /// google-analytics = "123456"
+    /// ```
+    pub fn apply_profile(&mut self, name: &str) -> Result<()> {
+        let overrides = match self.get(&format!("profile.{}", name)) {
+            Some(&Value::Table(ref table)) => table.clone(),
+            Some(_) => bail!("The \"{}\" profile should be a table", name),
+            None => bail!("The \"{}\" profile doesn't exist", name),
+        };
+
+        debug!("Applying the \"{}\" profile", name);
+
+        for (key, value) in overrides {
+            self.merge_value(&key, value)?;
+        }
+
+        Ok(())
+    }
+
+    /// Recursively `set()` every leaf value of `value`, so tables are merged
+    /// into the existing config instead of replacing it wholesale.
+    fn merge_value(&mut self, key: &str, value: Value) -> Result<()> {
+        match value {
+            Value::Table(table) => {
+                for (nested_key, nested_value) in table {
+                    self.merge_value(&format!("{}.{}", key, nested_key), nested_value)?;
+                }
+            }
+            other => {
+                trace!("{} => {}", key, other);
+                self.set(key, other)?;
+            }
+        }
+
+        Ok(())
+    }
+
     fn from_legacy(mut table: Value) -> Config {
         let mut cfg = Config::default();
 
@@ -270,22 +373,26 @@ impl<'de> Deserialize<'de> for Config {
         let mut table = match raw {
             Value::Table(t) => t,
             _ => {
-                use serde::de::Error;
                 return Err(D::Error::custom(
                     "A config file should always be a toml table",
                 ));
             }
         };
 
-        let book: BookConfig = table
-            .remove("book")
-            .and_then(|value| value.try_into().ok())
-            .unwrap_or_default();
+        use serde::de::Error;
+        let book: BookConfig = match table.remove("book") {
+            Some(value) => value
+                .try_into()
+                .map_err(|e| D::Error::custom(format!("Invalid [book] table, {}", e)))?,
+            None => BookConfig::default(),
+        };
 
-        let build: BuildConfig = table
-            .remove("build")
-            .and_then(|value| value.try_into().ok())
-            .unwrap_or_default();
+        let build: BuildConfig = match table.remove("build") {
+            Some(value) => value
+                .try_into()
+                .map_err(|e| D::Error::custom(format!("Invalid [build] table, {}", e)))?,
+            None => BuildConfig::default(),
+        };
 
         Ok(Config {
             book: book,
@@ -308,7 +415,15 @@ impl Serialize for Config {
             }
         };
 
+        let build_config = match Value::try_from(self.build.clone()) {
+            Ok(cfg) => cfg,
+            Err(_) => {
+                return Err(S::Error::custom("Unable to serialize the BuildConfig"));
+            }
+        };
+
         table.insert("book", book_config).expect("unreachable");
+        table.insert("build", build_config).expect("unreachable");
         table.serialize(s)
     }
 }
@@ -325,6 +440,67 @@ fn parse_env(key: &str) -> Option<String> {
     }
 }
 
+/// Read a config file as a raw `toml::Value`, recursively resolving any
+/// `extends` keys. `seen` keeps track of the files already visited so we can
+/// bail out when a file (indirectly) extends itself.
+fn load_raw(config_file: &Path, seen: &mut Vec<PathBuf>) -> Result<Value> {
+    let canonical = config_file
+        .canonicalize()
+        .chain_err(|| format!("Unable to open the configuration file {}", config_file.display()))?;
+
+    if seen.contains(&canonical) {
+        bail!("{} extends itself", config_file.display());
+    }
+    seen.push(canonical);
+
+    let mut buffer = String::new();
+    File::open(config_file)
+        .chain_err(|| "Unable to open the configuration file")?
+        .read_to_string(&mut buffer)
+        .chain_err(|| "Couldn't read the file")?;
+
+    let mut raw: Value = toml::from_str(&buffer)
+        .map_err(|e| Error::from(ErrorKind::Config(config_file.to_path_buf(), e.to_string())))?;
+
+    let base = match raw.as_table_mut().and_then(|table| table.remove("extends")) {
+        Some(Value::String(base)) => base,
+        Some(_) => bail!("\"extends\" should be the path to another configuration file"),
+        None => return Ok(raw),
+    };
+
+    let base_file = config_file
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .join(base);
+    debug!("{} extends {}", config_file.display(), base_file.display());
+
+    let mut merged = load_raw(&base_file, seen)?;
+    merge_into(&mut merged, raw);
+
+    Ok(merged)
+}
+
+/// Recursively merge `overrides` into `base`. Tables are merged key by key,
+/// while any other value (including arrays) replaces what was there before.
+fn merge_into(base: &mut Value, overrides: Value) {
+    match (base, overrides) {
+        (&mut Value::Table(ref mut base), Value::Table(overrides)) => for (key, value) in overrides {
+            match base.entry(key) {
+                Entry::Occupied(mut entry) => merge_into(entry.get_mut(), value),
+                Entry::Vacant(entry) => {
+                    entry.insert(value);
+                }
+            }
+        },
+        (base, overrides) => *base = overrides,
+    }
+}
+
+/// Parse an overridden value as JSON, falling back to a plain string.
+fn parse_override(value: &str) -> serde_json::Value {
+    serde_json::from_str(value).unwrap_or_else(|_| serde_json::Value::String(value.to_string()))
+}
+
 fn is_legacy_format(table: &Value) -> bool {
     let legacy_items = [
         "title",
@@ -343,6 +519,327 @@ fn is_legacy_format(table: &Value) -> bool {
     false
 }
 
+/// A problem found while validating a `book.toml`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    /// The (1-based) line the problem was found on, if it could be located.
+    pub line: Option<usize>,
+    /// A human readable description of the problem.
+    pub message: String,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: {}", line, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// Check the contents of a `book.toml` for unknown keys and values of the
+/// wrong type, both of which `Config::from_str()` silently ignores.
+///
+/// Tables which mdbook doesn't know about are assumed to belong to a plugin or
+/// alternate backend, so they are only reported when their name looks like a
+/// typo of a known table (e.g. `[output.hmtl]`).
+pub fn validate(src: &str) -> Result<Vec<Diagnostic>> {
+    let raw: Value = toml::from_str(src).chain_err(|| "Invalid configuration file")?;
+
+    Ok(validate_value(src, &raw))
+}
+
+/// Check a `book.toml` the way it's actually used, i.e. merged with the files
+/// it `extends` and with the `profile` (if any) applied on top.
+///
+/// The problems are located in the `book.toml` itself, so a problem which
+/// comes from another file or a profile is reported without a line number.
+pub fn validate_file(config_file: &Path, profile: Option<&str>) -> Result<Vec<Diagnostic>> {
+    let mut src = String::new();
+    File::open(config_file)
+        .chain_err(|| "Unable to open the configuration file")?
+        .read_to_string(&mut src)
+        .chain_err(|| "Couldn't read the file")?;
+
+    let mut raw = load_raw(config_file, &mut Vec::new())?;
+    if let Some(profile) = profile {
+        let overrides = raw.get("profile").and_then(|p| p.get(profile)).cloned();
+        if let Some(overrides) = overrides {
+            merge_into(&mut raw, overrides);
+        }
+    }
+
+    Ok(validate_value(&src, &raw))
+}
+
+fn validate_value(src: &str, raw: &Value) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    // the legacy format gets its own set of warnings when it's loaded
+    if is_legacy_format(raw) {
+        return diagnostics;
+    }
+
+    let table = match raw.as_table() {
+        Some(table) => table,
+        None => return diagnostics,
+    };
+
+    for (key, value) in table {
+        match key.as_str() {
+            "book" => check_section::<BookConfig>(src, "book", value, &mut diagnostics),
+            "build" => {
+                check_section::<BuildConfig>(src, "build", value, &mut diagnostics);
+                check_map::<MountConfig>(src, "build", "mounts", value, &mut diagnostics);
+            }
+            "output" => check_outputs(src, value, &mut diagnostics),
+            "language" => check_languages(src, value, &mut diagnostics),
+            "version" => check_versions(src, value, &mut diagnostics),
+            "profile" | "test" => {}
+            other => if let Some(suggestion) = closest_match(other, KNOWN_TABLES) {
+                diagnostics.push(Diagnostic {
+                    line: find_line(src, other, None),
+                    message: format!("unknown table `{}`, did you mean `{}`?", other, suggestion),
+                });
+            },
+        }
+    }
+
+    diagnostics
+}
+
+const KNOWN_TABLES: &[&str] = &[
+    "book", "build", "language", "output", "profile", "test", "version",
+];
+
+fn check_outputs(src: &str, outputs: &Value, diagnostics: &mut Vec<Diagnostic>) {
+    let outputs = match outputs.as_table() {
+        Some(outputs) => outputs,
+        None => return,
+    };
+
+    for (name, value) in outputs {
+        let path = format!("output.{}", name);
+
+        if name == "html" {
+            check_section::<HtmlConfig>(src, &path, value, diagnostics);
+            check_optional::<WebAppConfig>(src, &path, "web-app", value, diagnostics);
+            check_optional::<SocialCardsConfig>(src, &path, "social-cards", value, diagnostics);
+            check_optional::<ThemeColorsConfig>(src, &path, "theme-colors", value, diagnostics);
+            check_optional::<RobotsConfig>(src, &path, "robots", value, diagnostics);
+            check_map::<AssetProcessorConfig>(
+                src,
+                &path,
+                "asset-processors",
+                value,
+                diagnostics,
+            );
+        } else if value.get("command").is_none() {
+            // a renderer without a command is probably a misspelled builtin
+            if let Some(suggestion) = closest_match(name, &["html"]) {
+                diagnostics.push(Diagnostic {
+                    line: find_line(src, &path, None),
+                    message: format!(
+                        "unknown renderer `{}`, did you mean `output.{}`?",
+                        path, suggestion
+                    ),
+                });
+            }
+        }
+    }
+}
+
+fn check_languages(src: &str, languages: &Value, diagnostics: &mut Vec<Diagnostic>) {
+    if let Some(languages) = languages.as_table() {
+        for (code, language) in languages {
+            let path = format!("language.{}", code);
+            check_section::<LanguageConfig>(src, &path, language, diagnostics);
+        }
+    }
+}
+
+fn check_versions(src: &str, versions: &Value, diagnostics: &mut Vec<Diagnostic>) {
+    if let Some(versions) = versions.as_array() {
+        for version in versions {
+            check_section::<VersionConfig>(src, "version", version, diagnostics);
+        }
+    }
+}
+
+/// Check a table against the fields of the struct it gets deserialized into,
+/// using the struct's default value to figure out which keys are allowed.
+fn check_section<T>(src: &str, path: &str, value: &Value, diagnostics: &mut Vec<Diagnostic>)
+where
+    T: Default + Serialize + DeserializeOwned,
+{
+    let known = serde_json::to_value(T::default()).expect("unreachable");
+    check_keys(src, path, value, &known, diagnostics);
+
+    if let Err(e) = value.clone().try_into::<T>() {
+        diagnostics.push(Diagnostic {
+            line: find_line(src, path, None),
+            message: format!("invalid value in `[{}]`: {}", path, e),
+        });
+    }
+}
+
+/// Check the keys of an optional table, which `check_section()` can't see
+/// into because it's `None` by default.
+fn check_optional<T>(
+    src: &str,
+    parent: &str,
+    key: &str,
+    value: &Value,
+    diagnostics: &mut Vec<Diagnostic>,
+) where
+    T: Default + Serialize,
+{
+    if let Some(table) = value.get(key) {
+        let known = serde_json::to_value(T::default()).expect("unreachable");
+        check_keys(src, &format!("{}.{}", parent, key), table, &known, diagnostics);
+    }
+}
+
+/// Check the keys of every table in a map of tables, which is empty (and so
+/// allows any key) by default.
+fn check_map<T>(
+    src: &str,
+    parent: &str,
+    key: &str,
+    value: &Value,
+    diagnostics: &mut Vec<Diagnostic>,
+) where
+    T: Default + Serialize,
+{
+    if let Some(entries) = value.get(key).and_then(|map| map.as_table()) {
+        let known = serde_json::to_value(T::default()).expect("unreachable");
+
+        for (name, table) in entries {
+            let path = format!("{}.{}.{}", parent, key, name);
+            check_keys(src, &path, table, &known, diagnostics);
+        }
+    }
+}
+
+fn check_keys(
+    src: &str,
+    path: &str,
+    value: &Value,
+    known: &serde_json::Value,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let (table, known) = match (value.as_table(), known.as_object()) {
+        (Some(table), Some(known)) => (table, known),
+        _ => return,
+    };
+
+    // an empty object is a map with arbitrary keys, not a struct
+    if known.is_empty() {
+        return;
+    }
+
+    for (key, item) in table {
+        match known.get(key) {
+            Some(nested) => {
+                let nested_path = format!("{}.{}", path, key);
+                check_keys(src, &nested_path, item, nested, diagnostics);
+            }
+            None => {
+                let candidates: Vec<&str> = known.keys().map(|k| k.as_str()).collect();
+                let mut message = format!("unknown key `{}` in `[{}]`", key, path);
+
+                if let Some(suggestion) = closest_match(key, &candidates) {
+                    message.push_str(&format!(", did you mean `{}`?", suggestion));
+                }
+
+                diagnostics.push(Diagnostic {
+                    line: find_line(src, path, Some(key)),
+                    message,
+                });
+            }
+        }
+    }
+}
+
+/// Find the closest candidate to `word`, provided it's close enough to
+/// plausibly be a typo.
+fn closest_match<'a>(word: &str, candidates: &[&'a str]) -> Option<&'a str> {
+    candidates
+        .iter()
+        .map(|candidate| (edit_distance(word, candidate), *candidate))
+        .filter(|&(distance, candidate)| {
+            let max_distance = if candidate.len() <= 4 { 1 } else { 2 };
+            distance > 0 && distance <= max_distance
+        })
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// The number of insertions, deletions, substitutions and transpositions
+/// needed to turn `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+
+    for i in 1..a.len() + 1 {
+        for j in 1..b.len() + 1 {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+
+    d[a.len()][b.len()]
+}
+
+/// Find the line a table header (or a key inside that table) is declared on.
+fn find_line(src: &str, table: &str, key: Option<&str>) -> Option<usize> {
+    let mut current_table = String::new();
+
+    for (i, line) in src.lines().enumerate() {
+        let line = line.trim();
+
+        if line.starts_with('[') {
+            current_table = line.trim_matches(|c| c == '[')
+                .split(']')
+                .next()
+                .unwrap_or_default()
+                .split('.')
+                .map(|part| part.trim().trim_matches('"'))
+                .collect::<Vec<_>>()
+                .join(".");
+
+            let in_table =
+                current_table == table || current_table.starts_with(&format!("{}.", table));
+            if key.is_none() && in_table {
+                return Some(i + 1);
+            }
+        } else if let Some(key) = key {
+            if current_table == table && line.contains('=') {
+                let name = line.split('=').next().unwrap_or_default().trim();
+                if name.trim_matches('"') == key {
+                    return Some(i + 1);
+                }
+            }
+        }
+    }
+
+    None
+}
+
 /// Configuration options which are specific to the book and required for
 /// loading it from disk.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -358,6 +855,41 @@ pub struct BookConfig {
     pub src: PathBuf,
     /// Does this book support more than one language?
     pub multilingual: bool,
+    /// The book's main language (e.g. `en`). In a multilingual book, this is
+    /// where untranslated chapters are taken from.
+    pub language: Option<String>,
+    /// Generate the book's structure from the layout of the source directory
+    /// instead of reading it from `SUMMARY.md`.
+    pub auto_summary: bool,
+    /// Number the chapters of every part from 1 again, instead of carrying on
+    /// from the previous part.
+    pub restart_part_numbering: bool,
+    /// How many levels of nested chapters get a section number. Chapters
+    /// nested any deeper are shown without one.
+    pub number_depth: Option<usize>,
+    /// The markdown extensions to enable.
+    pub markdown: MarkdownConfig,
+    /// The version of the book being built, which is shown as the current
+    /// one by the version switcher of a versioned book.
+    pub version: Option<String>,
+}
+
+impl BookConfig {
+    /// The language the book is written in, defaulting to English (`en`).
+    pub fn language_or_default(&self) -> &str {
+        self.language.as_ref().map(|l| l.as_str()).unwrap_or("en")
+    }
+
+    /// The directory containing the book's chapters, relative to the book's
+    /// root. For a multilingual book this is the subdirectory of `src` named
+    /// after the book's language.
+    pub fn chapter_dir(&self) -> PathBuf {
+        if self.multilingual {
+            self.src.join(self.language_or_default())
+        } else {
+            self.src.clone()
+        }
+    }
 }
 
 impl Default for BookConfig {
@@ -368,10 +900,106 @@ impl Default for BookConfig {
             description: None,
             src: PathBuf::from("src"),
             multilingual: false,
+            language: None,
+            auto_summary: false,
+            restart_part_numbering: false,
+            number_depth: None,
+            markdown: MarkdownConfig::default(),
+            version: None,
         }
     }
 }
 
+/// The markdown extensions a book uses, taken from its `[book.markdown]`
+/// table.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct MarkdownConfig {
+    /// Render tables.
+    pub tables: bool,
+    /// Render text between `~~` as struck through.
+    pub strikethrough: bool,
+    /// Render list items starting with `[ ]` or `[x]` as checkboxes.
+    pub task_lists: bool,
+    /// Render terms followed by lines starting with `: ` as definition lists.
+    pub definition_lists: bool,
+    /// Render text between `^`s as superscript.
+    pub superscript: bool,
+    /// Render text between single `~`s as subscript.
+    pub subscript: bool,
+    /// What to do with the raw HTML in chapters.
+    pub raw_html: RawHtml,
+    /// The tags kept when sanitizing raw HTML, instead of the default ones.
+    pub allowed_tags: Option<Vec<String>>,
+    /// The attributes kept when sanitizing raw HTML, instead of the default
+    /// ones.
+    pub allowed_attributes: Option<Vec<String>>,
+}
+
+impl Default for MarkdownConfig {
+    fn default() -> MarkdownConfig {
+        MarkdownConfig {
+            tables: true,
+            strikethrough: false,
+            task_lists: false,
+            definition_lists: false,
+            superscript: false,
+            subscript: false,
+            raw_html: RawHtml::default(),
+            allowed_tags: None,
+            allowed_attributes: None,
+        }
+    }
+}
+
+/// What happens to the raw HTML in a book's chapters.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RawHtml {
+    /// Pass it through untouched (the default).
+    Allow,
+    /// Only keep the allowed tags and attributes, dropping everything else
+    /// (including scripts and event handlers).
+    Sanitize,
+    /// Drop every tag, keeping only the text.
+    Strip,
+}
+
+impl Default for RawHtml {
+    fn default() -> RawHtml {
+        RawHtml::Allow
+    }
+}
+
+/// Settings for one of the languages of a multilingual book, taken from its
+/// `[language.<code>]` table.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct LanguageConfig {
+    /// The name shown in the language switcher. Defaults to the language code.
+    pub name: Option<String>,
+    /// The book's title in this language.
+    pub title: Option<String>,
+    /// The book's description in this language.
+    pub description: Option<String>,
+}
+
+/// A version of the book, built from a git revision by
+/// `mdbook build --versions`.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct VersionConfig {
+    /// The name shown in the version switcher, which is also the directory
+    /// the version is built into.
+    pub name: String,
+    /// The git revision (e.g. a tag or branch) to build the version from.
+    /// Defaults to the working tree.
+    pub rev: Option<String>,
+    /// Whether this is the version which is also built into `latest/`.
+    /// Defaults to the first version listed.
+    pub latest: bool,
+}
+
 /// Configuration for the build procedure.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(default, rename_all = "kebab-case")]
@@ -383,7 +1011,40 @@ pub struct BuildConfig {
     pub create_missing: bool,
     /// Which preprocessors should be applied
     pub preprocess: Option<Vec<String>>,
-
+    /// Glob patterns, relative to the book's root directory, for files which
+    /// shouldn't trigger a rebuild in `mdbook watch` and `mdbook serve`.
+    pub watch_ignore: Vec<String>,
+    /// How long (in milliseconds) `mdbook watch` and `mdbook serve` wait for
+    /// more changes before rebuilding, so saving several files at once only
+    /// triggers a single rebuild. Defaults to `1000`.
+    pub watch_debounce: u64,
+    /// Glob patterns, relative to the source directory, for chapters whose
+    /// code examples `mdbook test` shouldn't run.
+    pub test_skip: Vec<String>,
+    /// Should `mdbook build`, `mdbook watch` and `mdbook serve` check the
+    /// links between chapters (and to their headings) once the book has been
+    /// rendered to HTML? Defaults to `true`.
+    pub check_links: bool,
+    /// Commands to run at various points of the build.
+    pub hooks: HooksConfig,
+    /// Where preprocessors cache the results of expensive work between
+    /// builds, relative to the book's root directory. Defaults to
+    /// `.mdbook-cache`.
+    pub cache_dir: PathBuf,
+    /// Commands which convert chapters written in other formats into
+    /// markdown, keyed by the extension of the chapters' files. A command
+    /// is given the chapter on stdin and prints the markdown.
+    pub converters: BTreeMap<String, String>,
+    /// Build without network access, taking remote includes from the cache
+    /// instead of downloading them.
+    pub offline: bool,
+    /// Other books which can be mounted as parts of this one, keyed by the
+    /// name `SUMMARY.md` refers to them by.
+    pub mounts: BTreeMap<String, MountConfig>,
+    /// The directory with the gettext translations of the book (named after
+    /// their language, e.g. `fr.po`), relative to the book's root. Defaults
+    /// to `po`.
+    pub po_dir: PathBuf,
 }
 
 impl Default for BuildConfig {
@@ -392,12 +1053,56 @@ impl Default for BuildConfig {
             build_dir: PathBuf::from("book"),
             create_missing: true,
             preprocess: None,
+            watch_ignore: Vec::new(),
+            watch_debounce: 1000,
+            test_skip: Vec::new(),
+            check_links: true,
+            hooks: HooksConfig::default(),
+            cache_dir: PathBuf::from(".mdbook-cache"),
+            converters: BTreeMap::new(),
+            offline: false,
+            mounts: BTreeMap::new(),
+            po_dir: PathBuf::from("po"),
         }
     }
 }
 
+/// Commands which are run at various points of the build, from the book's
+/// root directory. Any `{destination}` and `{renderer}` in a command are
+/// replaced with the directory being rendered into and the renderer's name.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct HooksConfig {
+    /// Run once before anything is built. Chapters it creates or changes are
+    /// picked up by the build.
+    pub before_build: Option<String>,
+    /// Run for each renderer, once the book has been preprocessed for it.
+    pub after_preprocess: Option<String>,
+    /// Run for each renderer, once it has rendered the book.
+    pub after_render: Option<String>,
+    /// Run by `mdbook watch` and `mdbook serve` after every successful
+    /// rebuild.
+    pub after_rebuild: Option<String>,
+}
+
+/// Where to find a book which is mounted as a part of another. Its chapters
+/// are loaded from its own source directory, and end up in the directory
+/// named after the mount in the rendered book.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct MountConfig {
+    /// The mounted book's root directory, relative to the book's root or, for
+    /// a book in a git repository, to the top of the repository.
+    pub path: Option<PathBuf>,
+    /// The URL of a git repository to clone the mounted book from.
+    pub git: Option<String>,
+    /// The revision of the git repository to check out, e.g. a tag, a commit
+    /// or `origin/<branch>`. Defaults to the repository's default branch.
+    pub rev: Option<String>,
+}
+
 /// Configuration for the HTML renderer.
-#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(default, rename_all = "kebab-case")]
 pub struct HtmlConfig {
     /// The theme directory, if specified.
@@ -408,6 +1113,8 @@ pub struct HtmlConfig {
     pub mathjax_support: bool,
     /// An optional google analytics code.
     pub google_analytics: Option<String>,
+    /// The web analytics added to every page.
+    pub analytics: AnalyticsConfig,
     /// Additional CSS stylesheets to include in the rendered page's `<head>`.
     pub additional_css: Vec<PathBuf>,
     /// Additional JS scripts to include at the bottom of the rendered page's 
@@ -425,6 +1132,378 @@ pub struct HtmlConfig {
     pub livereload_url: Option<String>,
     /// Should section labels be rendered?
     pub no_section_label: bool,
+    /// Should section labels be prepended to the page title?
+    pub section_label_in_title: bool,
+    /// The string used to separate the parts of a section label (e.g. `"."`
+    /// gives `1.2.`, while `"-"` gives `1-2`).
+    pub section_label_separator: String,
+    /// The keys used for navigating the book.
+    pub keyboard_shortcuts: KeyboardShortcuts,
+    /// Arbitrary values made available to the theme's templates as `vars`.
+    pub vars: BTreeMap<String, serde_json::Value>,
+    /// Settings for `mdbook serve`.
+    pub serve: ServeConfig,
+    /// Show the estimated reading time under each chapter's title?
+    pub reading_time: bool,
+    /// How many words a reader gets through per minute, used to estimate the
+    /// reading time. Defaults to `200`.
+    pub words_per_minute: usize,
+    /// Render every chapter to an `index.html` in a directory of its own, so
+    /// its URL doesn't need the `.html` extension.
+    pub pretty_urls: bool,
+    /// Where to put the rendered HTML, relative to the book's root, instead
+    /// of the `build.build-dir`.
+    pub build_dir: Option<PathBuf>,
+    /// Warn about images and other files copied from the source directory
+    /// which are bigger than this many kilobytes.
+    pub max_asset_size: Option<u64>,
+    /// How the IDs of headings are made from their text.
+    pub slug_style: SlugStyle,
+    /// Commands which transform the markdown of each chapter after it's
+    /// parsed, before it's turned into HTML.
+    pub event_filters: Vec<String>,
+    /// Put the markup of local SVG images straight into the page instead of
+    /// linking to them.
+    pub inline_svg: bool,
+    /// Find the people who contributed to each chapter from the git history
+    /// of its source file, for the templates.
+    pub contributors: bool,
+    /// List each chapter's contributors at the bottom of its page.
+    pub contributor_byline: bool,
+    /// A file with the HTML of a comment system (e.g. utterances or giscus),
+    /// relative to the book's root, which is rendered after each chapter.
+    pub comments: Option<PathBuf>,
+    /// Make sure the pages don't load anything from other sites, using local
+    /// copies of the fonts and scripts and leaving out the analytics.
+    pub privacy: bool,
+    /// Add a service worker which keeps the chapters a reader visited
+    /// available offline.
+    pub service_worker: bool,
+    /// Have the service worker download the whole book straight away, so all
+    /// of it can be read offline.
+    pub precache_book: bool,
+    /// Settings for the web app manifest, which lets readers install the
+    /// book on their devices. There's no manifest unless this is given.
+    pub web_app: Option<WebAppConfig>,
+    /// The URL the book is published at (e.g. `https://example.com/book/`),
+    /// for the links which have to be absolute.
+    pub site_url: Option<String>,
+    /// Settings for the preview images shown for links to the chapters on
+    /// social media. There are none unless this is given.
+    pub social_cards: Option<SocialCardsConfig>,
+    /// Let readers highlight text and add notes to it, which are kept in
+    /// their browser.
+    pub reader_notes: bool,
+    /// The font size the book starts with, as a percentage of the browser's
+    /// default. Readers can change it with the A- and A+ buttons.
+    pub font_size: u32,
+    /// Colors which replace the ones of the built-in themes.
+    pub theme_colors: Option<ThemeColorsConfig>,
+    /// How the pages look when printed.
+    pub print: PrintConfig,
+    /// The width of the sidebar, which readers can change by dragging its
+    /// edge.
+    pub sidebar: SidebarConfig,
+    /// The rules written to `robots.txt`, if the book should have one.
+    pub robots: Option<RobotsConfig>,
+    /// Link the part titles in the sidebar to a page listing the part's
+    /// chapters.
+    pub part_pages: bool,
+    /// Where the "next" and "previous" links go at the edge of a part.
+    pub part_navigation: PartNavigation,
+    /// The URL of the book's git repository, which themes can link to.
+    pub git_repository_url: Option<String>,
+    /// The URL of a chapter's source file, with `{url}` standing for
+    /// `git-repository-url` and `{path}` for the file's path in the
+    /// repository. Defaults to `{url}/blob/master/{path}`.
+    pub source_url_template: Option<String>,
+    /// Commands which process the files matching a pattern (e.g. `*.scss`)
+    /// as they are copied to the build directory.
+    pub asset_processors: BTreeMap<String, AssetProcessorConfig>,
+}
+
+impl Default for HtmlConfig {
+    fn default() -> HtmlConfig {
+        HtmlConfig {
+            theme: None,
+            curly_quotes: false,
+            mathjax_support: false,
+            google_analytics: None,
+            analytics: AnalyticsConfig::default(),
+            additional_css: Vec::new(),
+            additional_js: Vec::new(),
+            playpen: Playpen::default(),
+            livereload_url: None,
+            no_section_label: false,
+            section_label_in_title: false,
+            section_label_separator: String::from("."),
+            keyboard_shortcuts: KeyboardShortcuts::default(),
+            vars: BTreeMap::new(),
+            serve: ServeConfig::default(),
+            reading_time: false,
+            words_per_minute: DEFAULT_WORDS_PER_MINUTE,
+            pretty_urls: false,
+            build_dir: None,
+            max_asset_size: None,
+            slug_style: SlugStyle::default(),
+            event_filters: Vec::new(),
+            inline_svg: false,
+            contributors: false,
+            contributor_byline: false,
+            comments: None,
+            privacy: false,
+            service_worker: false,
+            precache_book: false,
+            web_app: None,
+            site_url: None,
+            social_cards: None,
+            reader_notes: false,
+            font_size: 100,
+            theme_colors: None,
+            print: PrintConfig::default(),
+            sidebar: SidebarConfig::default(),
+            robots: None,
+            part_pages: false,
+            part_navigation: PartNavigation::default(),
+            git_repository_url: None,
+            source_url_template: None,
+            asset_processors: BTreeMap::new(),
+        }
+    }
+}
+
+/// How the ID of a heading (which is what links to it point at) is made from
+/// its text.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SlugStyle {
+    /// Lowercase, keeping only letters, digits, `-` and `_`, with whitespace
+    /// turned into `-`, the same as GitHub does.
+    Github,
+    /// Like `Github`, but accented letters are transliterated to ASCII and
+    /// any other non-ASCII characters are dropped.
+    Ascii,
+    /// The heading's text as it is, apart from whitespace turning into `-`
+    /// and dropping the characters which can't be used in a URL fragment.
+    Unicode,
+}
+
+impl Default for SlugStyle {
+    fn default() -> SlugStyle {
+        SlugStyle::Github
+    }
+}
+
+/// A command which turns a file of the book into the one put in the build
+/// directory.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct AssetProcessorConfig {
+    /// The command, with `{input}` standing for the file and `{output}` for
+    /// where its output goes.
+    pub command: String,
+    /// The extension of the output, e.g. `css`. Defaults to the extension
+    /// of the file.
+    pub extension: Option<String>,
+}
+
+/// Where the "next" link of the last chapter of a part goes, and the
+/// "previous" link of the first one.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PartNavigation {
+    /// To the chapter next to it, as if there were no parts.
+    Continue,
+    /// Nowhere, the links stop at the edges of the part.
+    Stop,
+    /// To the page of the part, which needs `part-pages`.
+    PartPage,
+}
+
+impl PartNavigation {
+    /// The name of the setting, as it is written in `book.toml`.
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            PartNavigation::Continue => "continue",
+            PartNavigation::Stop => "stop",
+            PartNavigation::PartPage => "part-page",
+        }
+    }
+}
+
+impl Default for PartNavigation {
+    fn default() -> PartNavigation {
+        PartNavigation::Continue
+    }
+}
+
+/// Configuration for the preview images of the chapters on social media.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct SocialCardsConfig {
+    /// The built-in theme whose colors the cards use. Defaults to `light`.
+    pub theme: String,
+    /// The command which turns the SVG of a card into a PNG image, with
+    /// `{input}` and `{output}` standing for the two files. Defaults to
+    /// `rsvg-convert`.
+    pub converter: String,
+}
+
+impl Default for SocialCardsConfig {
+    fn default() -> SocialCardsConfig {
+        SocialCardsConfig {
+            theme: String::from("light"),
+            converter: String::from("rsvg-convert --output {output} {input}"),
+        }
+    }
+}
+
+/// The main colors of the book, for rebranding it without a custom theme.
+/// Each of them is a CSS color, e.g. `#c0392b`, and applies to every theme.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct ThemeColorsConfig {
+    /// The color of links and of the current chapter in the sidebar.
+    pub accent: Option<String>,
+    /// The background of the sidebar.
+    pub sidebar_background: Option<String>,
+    /// The background of code blocks and inline code.
+    pub code_background: Option<String>,
+}
+
+/// Configuration for printing the book.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct PrintConfig {
+    /// Start every chapter on a new page, and keep code blocks and tables
+    /// on a single page where possible. Defaults to `true`.
+    pub page_breaks: bool,
+    /// Print the URL of links to other sites after them. Defaults to `true`.
+    pub expand_links: bool,
+}
+
+impl Default for PrintConfig {
+    fn default() -> PrintConfig {
+        PrintConfig {
+            page_breaks: true,
+            expand_links: true,
+        }
+    }
+}
+
+/// Configuration for the width of the sidebar, in pixels.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct SidebarConfig {
+    /// The width the sidebar starts with. Defaults to `300`.
+    pub width: u32,
+    /// How narrow readers can make the sidebar. Defaults to `150`.
+    pub min_width: u32,
+    /// How wide readers can make the sidebar. Defaults to `600`.
+    pub max_width: u32,
+}
+
+impl Default for SidebarConfig {
+    fn default() -> SidebarConfig {
+        SidebarConfig {
+            width: 300,
+            min_width: 150,
+            max_width: 600,
+        }
+    }
+}
+
+/// Configuration for the book's `robots.txt`.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct RobotsConfig {
+    /// The paths crawlers should stay away from, e.g. `/drafts/`.
+    pub disallow: Vec<String>,
+    /// The URL of the site's sitemap.
+    pub sitemap: Option<String>,
+}
+
+/// Configuration for the web app manifest of the HTML output.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct WebAppConfig {
+    /// The name of the app. Defaults to the book's title.
+    pub name: Option<String>,
+    /// A shorter name, for where there's little room (e.g. under the icon on
+    /// a home screen). Defaults to the name.
+    pub short_name: Option<String>,
+    /// The color of the browser's toolbar. Defaults to `#ffffff`.
+    pub theme_color: String,
+    /// The color of the splash screen shown while the book loads. Defaults to
+    /// `#ffffff`.
+    pub background_color: String,
+    /// How much of the browser to show around the book, e.g. `standalone` or
+    /// `browser`. Defaults to `standalone`.
+    pub display: String,
+    /// The app's icons, relative to the source directory. Defaults to the
+    /// favicon.
+    pub icons: Vec<WebAppIcon>,
+}
+
+impl Default for WebAppConfig {
+    fn default() -> WebAppConfig {
+        WebAppConfig {
+            name: None,
+            short_name: None,
+            theme_color: String::from("#ffffff"),
+            background_color: String::from("#ffffff"),
+            display: String::from("standalone"),
+            icons: Vec::new(),
+        }
+    }
+}
+
+/// One of the icons of the web app manifest.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct WebAppIcon {
+    /// The image, relative to the source directory.
+    pub src: String,
+    /// The sizes the image is meant for, e.g. `192x192`. PNG images get
+    /// their actual size by default.
+    pub sizes: Option<String>,
+    /// What the icon is meant for, e.g. `maskable`.
+    pub purpose: Option<String>,
+}
+
+/// Configuration for the web analytics added to every page of the HTML
+/// output.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct AnalyticsConfig {
+    /// The analytics service to use.
+    pub provider: Option<AnalyticsProvider>,
+    /// The ID the service knows the book by, e.g. the measurement ID for
+    /// Google Analytics or the site's domain for Plausible.
+    pub id: Option<String>,
+    /// Whether to add the analytics at all, so they can be left out of a
+    /// build (e.g. `mdbook build --no-analytics`). Defaults to `true`.
+    pub enabled: bool,
+}
+
+impl Default for AnalyticsConfig {
+    fn default() -> AnalyticsConfig {
+        AnalyticsConfig {
+            provider: None,
+            id: None,
+            enabled: true,
+        }
+    }
+}
+
+/// A web analytics service.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AnalyticsProvider {
+    /// [Google Analytics](https://analytics.google.com), using `gtag.js`.
+    Google,
+    /// [Plausible](https://plausible.io).
+    Plausible,
 }
 
 /// Configuration for tweaking how the the HTML renderer handles the playpen.
@@ -437,6 +1516,14 @@ pub struct Playpen {
     pub editor: PathBuf,
     /// Should playpen snippets be editable? Defaults to `false`.
     pub editable: bool,
+    /// The base URL of the playground used to run code snippets. Defaults to
+    /// `https://play.rust-lang.org`.
+    pub url: String,
+    /// The editor theme to use for editable snippets. When unset, the theme
+    /// follows the book's light or dark theme.
+    pub editor_theme: Option<String>,
+    /// Should long lines be wrapped in editable snippets? Defaults to `false`.
+    pub line_wrapping: bool,
 }
 
 impl Default for Playpen {
@@ -444,6 +1531,76 @@ impl Default for Playpen {
         Playpen {
             editor: PathBuf::from("ace"),
             editable: false,
+            url: String::from("https://play.rust-lang.org"),
+            editor_theme: None,
+            line_wrapping: false,
+        }
+    }
+}
+
+/// Configuration for the web server started by `mdbook serve`. Options given
+/// on the command line take precedence.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct ServeConfig {
+    /// The interface to listen on. Defaults to `localhost`.
+    pub interface: String,
+    /// The port to serve the book on. Defaults to `3000`, or the next free
+    /// port if it's already in use.
+    pub port: u16,
+    /// The port used for the live-reload websocket. Defaults to `3001`, or the
+    /// next free port if it's already in use.
+    pub websocket_port: u16,
+    /// The hostname browsers use to reach the live-reload websocket, for when
+    /// the interface (e.g. `0.0.0.0` inside a container) isn't reachable from
+    /// them. Defaults to the interface.
+    pub hostname: Option<String>,
+    /// The full URL of the live-reload websocket (e.g. `wss://example.com/ws`
+    /// behind a reverse proxy). Takes precedence over `hostname`.
+    pub ws_url: Option<String>,
+}
+
+impl Default for ServeConfig {
+    fn default() -> ServeConfig {
+        ServeConfig {
+            interface: String::from("localhost"),
+            port: 3000,
+            websocket_port: 3001,
+            hostname: None,
+            ws_url: None,
+        }
+    }
+}
+
+/// The keyboard shortcuts available to readers, mapping each action to the
+/// list of keys (as given by `KeyboardEvent.key`) which trigger it. An empty
+/// list disables the action.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct KeyboardShortcuts {
+    /// Go to the previous chapter. Defaults to `["ArrowLeft"]`.
+    pub previous: Vec<String>,
+    /// Go to the next chapter. Defaults to `["ArrowRight"]`.
+    pub next: Vec<String>,
+    /// Show or hide the sidebar. Disabled by default.
+    pub toggle_sidebar: Vec<String>,
+    /// Whether the shortcuts work at all. Turning them off leaves the arrow
+    /// keys to widgets embedded in the book. Defaults to `true`.
+    pub enabled: bool,
+    /// CSS selectors of the parts of the pages where the shortcuts don't
+    /// apply, e.g. `.my-widget`. Text inputs and editable code are always
+    /// left alone.
+    pub ignore: Vec<String>,
+}
+
+impl Default for KeyboardShortcuts {
+    fn default() -> KeyboardShortcuts {
+        KeyboardShortcuts {
+            previous: vec![String::from("ArrowLeft")],
+            next: vec![String::from("ArrowRight")],
+            toggle_sidebar: Vec::new(),
+            enabled: true,
+            ignore: Vec::new(),
         }
     }
 }
@@ -454,20 +1611,16 @@ impl Default for Playpen {
 /// This is definitely not the most performant way to do things, which means you
 /// should probably keep it away from tight loops...
 trait Updateable<'de>: Serialize + Deserialize<'de> {
-    fn update_value<S: Serialize>(&mut self, key: &str, value: S) {
+    fn update_value<S: Serialize>(&mut self, key: &str, value: S) -> Result<()> {
         let mut raw = Value::try_from(&self).expect("unreachable");
+        let value =
+            Value::try_from(value).chain_err(|| "Unable to represent the item as a TOML Value")?;
+        raw.insert(key, value)
+            .map_err(|e| Error::from(format!("Unable to set \"{}\", {}", key, e)))?;
 
-        {
-            if let Ok(value) = Value::try_from(value) {
-                let _ = raw.insert(key, value);
-            } else {
-                return;
-            }
-        }
-
-        if let Ok(updated) = raw.try_into() {
-            *self = updated;
-        }
+        *self = raw.try_into()
+            .map_err(|e| Error::from(format!("Invalid value for \"{}\", {}", key, e)))?;
+        Ok(())
     }
 }
 
@@ -480,6 +1633,9 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::fs;
+    use std::io::Write;
+    use tempdir::TempDir;
 
     const COMPLEX_CONFIG: &'static str = r#"
         [book]
@@ -503,6 +1659,19 @@ mod tests {
         [output.html.playpen]
         editable = true
         editor = "ace"
+        url = "https://play.example.com"
+        line-wrapping = true
+
+        [output.html.keyboard-shortcuts]
+        next = ["n", "ArrowRight"]
+        toggle-sidebar = ["t"]
+
+        [output.html.vars]
+        company = "ACME"
+
+        [output.html.serve]
+        interface = "0.0.0.0"
+        port = 8000
         "#;
 
     #[test]
@@ -522,10 +1691,20 @@ mod tests {
             create_missing: false,
             preprocess: Some(vec!["first_preprocessor".to_string(),
                                   "second_preprocessor".to_string()]),
+            ..Default::default()
         };
         let playpen_should_be = Playpen {
             editable: true,
             editor: PathBuf::from("ace"),
+            url: String::from("https://play.example.com"),
+            line_wrapping: true,
+            ..Default::default()
+        };
+        let keyboard_shortcuts_should_be = KeyboardShortcuts {
+            previous: vec![String::from("ArrowLeft")],
+            next: vec![String::from("n"), String::from("ArrowRight")],
+            toggle_sidebar: vec![String::from("t")],
+            ..Default::default()
         };
         let html_should_be = HtmlConfig {
             curly_quotes: true,
@@ -533,6 +1712,15 @@ mod tests {
             additional_css: vec![PathBuf::from("./foo/bar/baz.css")],
             theme: Some(PathBuf::from("./themedir")),
             playpen: playpen_should_be,
+            keyboard_shortcuts: keyboard_shortcuts_should_be,
+            vars: vec![(String::from("company"), json!("ACME"))]
+                .into_iter()
+                .collect(),
+            serve: ServeConfig {
+                interface: String::from("0.0.0.0"),
+                port: 8000,
+                ..Default::default()
+            },
             ..Default::default()
         };
 
@@ -624,6 +1812,7 @@ mod tests {
             build_dir: PathBuf::from("my-book"),
             create_missing: true,
             preprocess: None,
+            ..Default::default()
         };
 
         let html_should_be = HtmlConfig {
@@ -654,6 +1843,181 @@ mod tests {
         assert_eq!(got, value);
     }
 
+    #[test]
+    fn setting_a_book_or_build_key_to_the_wrong_type_is_an_error() {
+        let mut cfg = Config::default();
+
+        assert!(cfg.set("build.watch-debounce", "soon").is_err());
+        assert!(cfg.set("book.title", vec![1, 2]).is_err());
+        assert_eq!(cfg.build.watch_debounce, 1000);
+
+        cfg.set("build.watch-debounce", 250).unwrap();
+        assert_eq!(cfg.build.watch_debounce, 250);
+    }
+
+    #[test]
+    fn a_config_can_extend_another_file() {
+        let temp = TempDir::new("mdbook").unwrap();
+        let book_dir = temp.path().join("book");
+        fs::create_dir(&book_dir).unwrap();
+
+        let common = r#"
+            [book]
+            authors = ["Michael-F-Bryan"]
+
+            [output.html]
+            curly-quotes = true
+            google-analytics = "123456"
+            "#;
+        let book = r#"
+            extends = "../common.toml"
+
+            [book]
+            title = "Some Book"
+
+            [output.html]
+            google-analytics = "654321"
+            "#;
+        File::create(temp.path().join("common.toml"))
+            .unwrap()
+            .write_all(common.as_bytes())
+            .unwrap();
+        File::create(book_dir.join("book.toml"))
+            .unwrap()
+            .write_all(book.as_bytes())
+            .unwrap();
+
+        let cfg = Config::from_disk(book_dir.join("book.toml")).unwrap();
+
+        assert_eq!(cfg.book.title, Some(String::from("Some Book")));
+        assert_eq!(cfg.book.authors, vec![String::from("Michael-F-Bryan")]);
+        let html = cfg.html_config().unwrap();
+        assert!(html.curly_quotes);
+        assert_eq!(html.google_analytics, Some(String::from("654321")));
+        assert!(cfg.get("extends").is_none());
+    }
+
+    #[test]
+    fn a_config_cant_extend_itself() {
+        let temp = TempDir::new("mdbook").unwrap();
+        let config_file = temp.path().join("book.toml");
+        File::create(&config_file)
+            .unwrap()
+            .write_all(b"extends = \"book.toml\"")
+            .unwrap();
+
+        assert!(Config::from_disk(&config_file).is_err());
+    }
+
+    #[test]
+    fn invalid_config_files_say_which_file_is_broken() {
+        let temp = TempDir::new("mdbook").unwrap();
+        let config_file = temp.path().join("book.toml");
+        File::create(&config_file)
+            .unwrap()
+            .write_all(b"[book]\ntitle = 42\n")
+            .unwrap();
+
+        let got = Config::from_disk(&config_file).unwrap_err();
+
+        match *got.kind() {
+            ErrorKind::Config(ref file, _) => assert_eq!(file, &config_file),
+            ref other => panic!("Unexpected error: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn apply_command_line_overrides() {
+        let mut cfg = Config::from_str(COMPLEX_CONFIG).unwrap();
+
+        cfg.apply_override("output.html.site-url=https://example.com/book/")
+            .unwrap();
+        cfg.apply_override("output.html.curly-quotes=false").unwrap();
+        cfg.apply_override("book.title=Another Book").unwrap();
+
+        let html = cfg.html_config().unwrap();
+        assert!(!html.curly_quotes);
+        assert_eq!(html.google_analytics, Some(String::from("123456")));
+        assert_eq!(
+            cfg.get_deserialized::<String, _>("output.html.site-url").unwrap(),
+            "https://example.com/book/"
+        );
+        assert_eq!(cfg.book.title, Some(String::from("Another Book")));
+    }
+
+    #[test]
+    fn overrides_need_a_key_and_a_value() {
+        let mut cfg = Config::default();
+
+        assert!(cfg.apply_override("output.html.curly-quotes").is_err());
+        assert!(cfg.apply_override("=true").is_err());
+    }
+
+    #[test]
+    fn overriding_the_book_table_merges_with_existing_values() {
+        let src = r#"
+        [book]
+        title = "Some Book"
+        description = "A completely useless book"
+        "#;
+        let mut cfg = Config::from_str(src).unwrap();
+        let value = json!({"title": "Something else", "authors": ["Michael-F-Bryan"]});
+
+        cfg.set("book", value).unwrap();
+
+        assert_eq!(cfg.book.title, Some(String::from("Something else")));
+        assert_eq!(cfg.book.authors, vec![String::from("Michael-F-Bryan")]);
+        assert_eq!(
+            cfg.book.description,
+            Some(String::from("A completely useless book"))
+        );
+        assert!(cfg.get("book").is_none());
+    }
+
+    #[test]
+    fn the_build_table_cant_be_overridden_by_a_scalar() {
+        let mut cfg = Config::default();
+
+        assert!(cfg.set("build", "oops").is_err());
+        assert_eq!(cfg.build, BuildConfig::default());
+    }
+
+    #[test]
+    fn profiles_override_the_base_config() {
+        let src = r#"
+        [book]
+        title = "Some Book"
+
+        [build]
+        create-missing = true
+
+        [output.html]
+        curly-quotes = true
+
+        [profile.release.build]
+        create-missing = false
+
+        [profile.release.output.html]
+        google-analytics = "123456"
+        "#;
+        let mut cfg = Config::from_str(src).unwrap();
+
+        cfg.apply_profile("release").unwrap();
+
+        assert_eq!(cfg.book.title, Some(String::from("Some Book")));
+        assert!(!cfg.build.create_missing);
+        let html = cfg.html_config().unwrap();
+        assert!(html.curly_quotes);
+        assert_eq!(html.google_analytics, Some(String::from("123456")));
+    }
+
+    #[test]
+    fn applying_a_missing_profile_is_an_error() {
+        let mut cfg = Config::default();
+
+        assert!(cfg.apply_profile("release").is_err());
+    }
+
     #[test]
     fn parse_env_vars() {
         let inputs = vec![
@@ -726,4 +2090,146 @@ mod tests {
 
         assert_eq!(cfg.book.title, Some(should_be));
     }
+
+    #[test]
+    fn env_vars_which_cant_be_applied_are_skipped() {
+        let mut cfg = Config::default();
+
+        env::set_var("MDBOOK_BUILD", "not-a-table");
+        cfg.update_from_env();
+        env::remove_var("MDBOOK_BUILD");
+
+        assert_eq!(cfg.build, BuildConfig::default());
+    }
+
+    #[test]
+    fn a_valid_config_has_no_diagnostics() {
+        let diagnostics = validate(COMPLEX_CONFIG).unwrap();
+
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+    }
+
+    #[test]
+    fn unknown_keys_are_reported_with_their_line() {
+        let src = r#"
+        [book]
+        titel = "Some Book"
+
+        [output.html.playpen]
+        editble = true
+        "#;
+
+        let diagnostics = validate(src).unwrap();
+
+        assert_eq!(
+            diagnostics,
+            vec![
+                Diagnostic {
+                    line: Some(3),
+                    message: String::from("unknown key `titel` in `[book]`, did you mean `title`?"),
+                },
+                Diagnostic {
+                    line: Some(6),
+                    message: String::from(
+                        "unknown key `editble` in `[output.html.playpen]`, did you mean `editable`?",
+                    ),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_keys_in_optional_tables_and_maps_are_reported() {
+        let src = r#"
+        [build.mounts.api]
+        pth = "../api"
+
+        [output.html.web-app]
+        nmae = "Book"
+
+        [output.html.asset-processors.scss]
+        comand = "sassc {input}"
+        "#;
+
+        let diagnostics = validate(src).unwrap();
+        let lines: Vec<_> = diagnostics.iter().map(|d| d.line).collect();
+
+        assert_eq!(lines, vec![Some(3), Some(6), Some(9)]);
+        assert!(diagnostics[1].message.contains("`[output.html.web-app]`"));
+    }
+
+    #[test]
+    fn the_whole_merged_config_is_validated() {
+        let temp = TempDir::new("mdbook").unwrap();
+        let common = r#"
+            [output.html]
+            curly-qoutes = true
+            "#;
+        let book = r#"
+            extends = "common.toml"
+
+            [profile.release.build]
+            ofline = true
+            "#;
+        File::create(temp.path().join("common.toml"))
+            .unwrap()
+            .write_all(common.as_bytes())
+            .unwrap();
+        let config_file = temp.path().join("book.toml");
+        File::create(&config_file)
+            .unwrap()
+            .write_all(book.as_bytes())
+            .unwrap();
+
+        assert!(validate(book).unwrap().is_empty());
+
+        let diagnostics = validate_file(&config_file, None).unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].message.contains("`curly-qoutes`"));
+
+        let diagnostics = validate_file(&config_file, Some("release")).unwrap();
+        assert_eq!(diagnostics.len(), 2);
+    }
+
+    #[test]
+    fn misspelled_renderers_are_reported() {
+        let src = r#"
+        [output.hmtl]
+        curly-quotes = true
+
+        [output.epub]
+        command = "mdbook-epub"
+
+        [my-plugin]
+        foo = "bar"
+        "#;
+
+        let diagnostics = validate(src).unwrap();
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].line, Some(2));
+        assert!(diagnostics[0].message.contains("`output.hmtl`"));
+    }
+
+    #[test]
+    fn type_mismatches_are_reported() {
+        let src = r#"
+        [build]
+        create-missing = "yes"
+        "#;
+
+        let diagnostics = validate(src).unwrap();
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].line, Some(2));
+        assert!(diagnostics[0].message.starts_with("invalid value in `[build]`"));
+    }
+
+    #[test]
+    fn closest_match_only_suggests_likely_typos() {
+        assert_eq!(closest_match("hmtl", &["html"]), Some("html"));
+        assert_eq!(closest_match("buidl", KNOWN_TABLES), Some("build"));
+        assert_eq!(closest_match("foo", KNOWN_TABLES), None);
+        assert_eq!(closest_match("html", &["html"]), None);
+    }
 }
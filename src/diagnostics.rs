@@ -0,0 +1,150 @@
+//! Problems found while building a book which point at a particular file (and
+//! line), like a broken link or an include which couldn't be expanded.
+//!
+//! By default these are logged like any other message. Tools which would
+//! rather handle them themselves (e.g. to show them inline in an editor) can
+//! install a sink with [`set_sink()`].
+//!
+//! [`set_sink()`]: fn.set_sink.html
+
+use std::fmt::{self, Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use errors::{Error, ErrorKind};
+
+/// How bad a `Diagnostic` is.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Level {
+    /// Something which stops the book from being built correctly.
+    Error,
+    /// Something which is probably a mistake.
+    Warning,
+}
+
+/// A problem with a book, and where it is.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    /// How bad the problem is.
+    pub level: Level,
+    /// What the problem is.
+    pub message: String,
+    /// The file containing the problem.
+    pub file: Option<PathBuf>,
+    /// The line (counting from 1) the problem is on.
+    pub line: Option<usize>,
+}
+
+impl Diagnostic {
+    /// Create a `Diagnostic` which doesn't point at any file.
+    pub fn new<S: Into<String>>(level: Level, message: S) -> Diagnostic {
+        Diagnostic {
+            level,
+            message: message.into(),
+            file: None,
+            line: None,
+        }
+    }
+
+    /// Point the `Diagnostic` at a file, and optionally a line in it.
+    pub fn at<P: Into<PathBuf>>(mut self, file: P, line: Option<usize>) -> Diagnostic {
+        self.file = Some(file.into());
+        self.line = line;
+        self
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        if let Some(ref file) = self.file {
+            write!(f, "{}", file.display())?;
+            if let Some(line) = self.line {
+                write!(f, ":{}", line)?;
+            }
+            write!(f, ": ")?;
+        }
+
+        write!(f, "{}", self.message)
+    }
+}
+
+impl<'a> From<&'a Error> for Diagnostic {
+    fn from(e: &'a Error) -> Diagnostic {
+        let mut message = e.to_string();
+        for cause in e.iter().skip(1) {
+            message.push_str(&format!("\nCaused by: {}", cause));
+        }
+
+        let (file, line): (Option<&Path>, Option<usize>) = match *e.kind() {
+            // the summary parser doesn't know where SUMMARY.md lives
+            ErrorKind::ParseError(line, _, _) => (None, Some(line)),
+            ErrorKind::Config(ref file, _) => (Some(file), None),
+            ErrorKind::ChapterLoad(_, ref file) => (Some(file), None),
+            ErrorKind::ReservedFilenameError(ref file) => (Some(file), None),
+            _ => (None, None),
+        };
+
+        Diagnostic {
+            level: Level::Error,
+            message,
+            file: file.map(Path::to_path_buf),
+            line,
+        }
+    }
+}
+
+type Sink = Box<Fn(&Diagnostic) + Send + Sync>;
+
+lazy_static! {
+    static ref SINK: RwLock<Option<Sink>> = RwLock::new(None);
+}
+
+/// Send every `Diagnostic` reported from now on to `sink` instead of logging
+/// it.
+pub fn set_sink<F>(sink: F)
+where
+    F: Fn(&Diagnostic) + Send + Sync + 'static,
+{
+    *SINK.write().expect("the sink lock is never poisoned") = Some(Box::new(sink));
+}
+
+/// Report a problem with the book.
+pub fn report(diagnostic: Diagnostic) {
+    let sink = SINK.read().expect("the sink lock is never poisoned");
+
+    match *sink {
+        Some(ref sink) => sink(&diagnostic),
+        None => match diagnostic.level {
+            Level::Error => error!("{}", diagnostic),
+            Level::Warning => warn!("{}", diagnostic),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diagnostics_start_with_their_location() {
+        let diagnostic = Diagnostic::new(Level::Warning, "Oops").at("src/intro.md", Some(3));
+        assert_eq!(diagnostic.to_string(), "src/intro.md:3: Oops");
+
+        let diagnostic = Diagnostic::new(Level::Warning, "Oops");
+        assert_eq!(diagnostic.to_string(), "Oops");
+    }
+
+    #[test]
+    fn errors_keep_their_location() {
+        let e = Error::from(ErrorKind::ChapterLoad(
+            String::from("Intro"),
+            PathBuf::from("src/intro.md"),
+        ));
+
+        let got = Diagnostic::from(&e);
+
+        assert_eq!(got.level, Level::Error);
+        assert_eq!(got.file, Some(PathBuf::from("src/intro.md")));
+    }
+}
@@ -50,6 +50,36 @@
 //! md.build().expect("Building failed");
 //! ```
 //!
+//! Other tools can embed mdBook by registering their own preprocessors and
+//! renderers (see the [`Preprocessor`] and [`Renderer`] traits) before
+//! building:
+//!
+//! ```rust,no_run
+//! use mdbook::MDBook;
+//! # use mdbook::book::Book;
+//! # use mdbook::errors::Result;
+//! # use mdbook::preprocess::{Preprocessor, PreprocessorContext};
+//! # use mdbook::renderer::{RenderContext, Renderer};
+//! # struct MathJax;
+//! # impl Preprocessor for MathJax {
+//! #     fn name(&self) -> &str { "mathjax" }
+//! #     fn run(&self, _: &PreprocessorContext, _: &mut Book) -> Result<()> { Ok(()) }
+//! # }
+//! # struct Epub;
+//! # impl Renderer for Epub {
+//! #     fn name(&self) -> &str { "epub" }
+//! #     fn render(&self, _: &RenderContext) -> Result<()> { Ok(()) }
+//! # }
+//!
+//! MDBook::load("/path/to/book/root")
+//!     .expect("Unable to load the book")
+//!     .with_preprocessor(MathJax)
+//!     .with_renderer(Epub)
+//!     .with_build_dir("/tmp/book")
+//!     .build()
+//!     .expect("Building failed");
+//! ```
+//!
 //! ## Implementing a new Backend
 //!
 //! `mdbook` has a fairly flexible mechanism for creating additional backends 
@@ -72,11 +102,16 @@
 //! [`RenderContext`]: renderer/struct.RenderContext.html
 //! [relevant chapter]: https://rust-lang-nursery.github.io/mdBook/for_developers/backends.html
 //! [`Config`]: config/struct.Config.html
+//! [`Preprocessor`]: preprocess/trait.Preprocessor.html
+//! [`Renderer`]: renderer/trait.Renderer.html
 
 #![deny(missing_docs)]
 
+extern crate base64;
+extern crate chrono;
 #[macro_use]
 extern crate error_chain;
+extern crate glob;
 extern crate handlebars;
 extern crate itertools;
 #[macro_use]
@@ -85,12 +120,14 @@ extern crate lazy_static;
 extern crate log;
 extern crate memchr;
 extern crate pulldown_cmark;
+extern crate qrcode;
 extern crate regex;
 extern crate serde;
 #[macro_use]
 extern crate serde_derive;
 #[macro_use]
 extern crate serde_json;
+extern crate sha2;
 extern crate shlex;
 extern crate tempdir;
 extern crate toml;
@@ -102,10 +139,15 @@ extern crate pretty_assertions;
 
 pub mod preprocess;
 pub mod book;
+pub mod cache;
 pub mod config;
+pub mod diagnostics;
 pub mod renderer;
 pub mod theme;
+pub mod timings;
 pub mod utils;
+pub mod versions;
+pub mod workspace;
 
 pub use book::MDBook;
 pub use book::BookItem;
@@ -141,6 +183,30 @@ pub mod errors {
                 display("Error at line {}, column {}: {}", line, col, message)
             }
 
+            /// A configuration file couldn't be loaded.
+            Config(file: PathBuf, message: String) {
+                description("Invalid configuration")
+                display("Invalid configuration file {}: {}", file.display(), message)
+            }
+
+            /// A chapter's file couldn't be loaded.
+            ChapterLoad(name: String, file: PathBuf) {
+                description("Unable to load a chapter")
+                display("Unable to load \"{}\" ({})", name, file.display())
+            }
+
+            /// A preprocessor failed.
+            Preprocessor(name: String) {
+                description("A preprocessor failed")
+                display("The \"{}\" preprocessor failed", name)
+            }
+
+            /// A renderer failed.
+            Renderer(name: String) {
+                description("A renderer failed")
+                display("The \"{}\" renderer failed", name)
+            }
+
             /// The user tried to use a reserved filename.
             ReservedFilenameError(filename: PathBuf) {
                 description("Reserved Filename")
@@ -149,6 +215,26 @@ pub mod errors {
         }
     }
 
+    impl Error {
+        /// A suggestion on how to fix the error, for when it's shown to a
+        /// user.
+        pub fn hint(&self) -> Option<&'static str> {
+            match *self.kind() {
+                ErrorKind::ParseError(..) => {
+                    Some("see the \"SUMMARY.md\" chapter of the user guide for the format")
+                }
+                ErrorKind::ChapterLoad(..) => Some(
+                    "check the chapter's link in SUMMARY.md, or set `build.create-missing = true` \
+                     to create missing chapters",
+                ),
+                ErrorKind::ReservedFilenameError(..) => {
+                    Some("rename the chapter's file and update its link in SUMMARY.md")
+                }
+                _ => None,
+            }
+        }
+    }
+
     // Box to halve the size of Error
     impl From<::handlebars::TemplateError> for Error {
         fn from(e: ::handlebars::TemplateError) -> Error {
@@ -0,0 +1,143 @@
+use regex::{Captures, Regex};
+use utils::BuildInfo;
+use errors::*;
+
+use super::{Preprocessor, PreprocessorContext};
+use book::{is_closing_fence, opening_fence, Book, BookItem};
+
+/// A preprocessor for substituting the `{{ build_date }}`, `{{ git_commit }}`
+/// and `{{ mdbook_version }}` variables in a chapter.
+pub struct BuildInfoPreprocessor;
+
+impl BuildInfoPreprocessor {
+    /// Create a new `BuildInfoPreprocessor`.
+    pub fn new() -> Self {
+        BuildInfoPreprocessor
+    }
+}
+
+impl Preprocessor for BuildInfoPreprocessor {
+    fn name(&self) -> &str {
+        "build-info"
+    }
+
+    fn run(&self, ctx: &PreprocessorContext, book: &mut Book) -> Result<()> {
+        let info = match ctx.build_info {
+            Some(ref info) => info.clone(),
+            None => BuildInfo::collect(&ctx.root)?,
+        };
+
+        book.for_each_mut(|section: &mut BookItem| {
+            if let BookItem::Chapter(ref mut ch) = *section {
+                ch.content = replace_variables(&ch.content, &info);
+            }
+        });
+
+        Ok(())
+    }
+}
+
+/// Replace the variables everywhere but in fenced code blocks, which show
+/// code as it's written.
+fn replace_variables(s: &str, info: &BuildInfo) -> String {
+    let mut replaced = String::with_capacity(s.len());
+    let mut fence: Option<&str> = None;
+
+    for (i, line) in s.split('\n').enumerate() {
+        if i > 0 {
+            replaced.push('\n');
+        }
+        let trimmed = line.trim_left();
+
+        if let Some(marker) = fence {
+            if is_closing_fence(trimmed, marker) {
+                fence = None;
+            }
+            replaced.push_str(line);
+        } else if let Some(marker) = opening_fence(trimmed) {
+            fence = Some(marker);
+            replaced.push_str(line);
+        } else {
+            replaced.push_str(&replace_in_line(line, info));
+        }
+    }
+
+    replaced
+}
+
+fn replace_in_line(s: &str, info: &BuildInfo) -> String {
+    lazy_static! {
+        static ref RE: Regex =
+            Regex::new(r"\\?\{\{\s*(build_date|git_commit|mdbook_version)\s*\}\}").unwrap();
+    }
+
+    RE.replace_all(s, |caps: &Captures| {
+        let whole = &caps[0];
+
+        // an escaped variable is left as is, minus the backslash
+        if whole.starts_with('\\') {
+            return whole[1..].to_string();
+        }
+
+        match &caps[1] {
+            "build_date" => info.build_date.clone(),
+            "git_commit" => info.git_commit
+                .clone()
+                .unwrap_or_else(|| String::from("unknown")),
+            _ => info.mdbook_version.clone(),
+        }
+    }).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dummy_info() -> BuildInfo {
+        BuildInfo {
+            build_date: String::from("2018-02-14"),
+            git_commit: Some(String::from("abc123")),
+            mdbook_version: String::from("0.1.4"),
+        }
+    }
+
+    #[test]
+    fn variables_are_substituted() {
+        let src = "Built on {{ build_date }} from {{git_commit}} with mdBook {{ mdbook_version }}.";
+
+        let got = replace_variables(src, &dummy_info());
+
+        assert_eq!(got, "Built on 2018-02-14 from abc123 with mdBook 0.1.4.");
+    }
+
+    #[test]
+    fn escaped_and_unknown_variables_are_left_alone() {
+        let src = r"\{{ build_date }} {{ build_time }} {{#include foo.md}}";
+
+        let got = replace_variables(src, &dummy_info());
+
+        assert_eq!(got, "{{ build_date }} {{ build_time }} {{#include foo.md}}");
+    }
+
+    #[test]
+    fn code_blocks_are_left_alone() {
+        let src = "{{ build_date }}\n\n````markdown\n```\n{{ build_date }}\n```\n````\n{{ build_date }}";
+
+        let got = replace_variables(src, &dummy_info());
+
+        assert_eq!(
+            got,
+            "2018-02-14\n\n````markdown\n```\n{{ build_date }}\n```\n````\n2018-02-14"
+        );
+    }
+
+    #[test]
+    fn missing_git_commit_is_unknown() {
+        let info = BuildInfo {
+            git_commit: None,
+            ..dummy_info()
+        };
+
+        assert_eq!(replace_variables("{{ git_commit }}", &info), "unknown");
+    }
+}
@@ -0,0 +1,442 @@
+//! Translating a book with gettext: extracting its text into a `.pot`
+//! template, and building it with the translations from a `.po` file.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use book::{Book, BookItem};
+use utils::fs::file_to_string;
+use errors::*;
+
+use super::{Preprocessor, PreprocessorContext};
+
+/// A preprocessor which replaces the text of the book with its translation
+/// into `book.language`, taken from `<po-dir>/<language>.po`. Books without
+/// such a file are left alone.
+pub struct GettextPreprocessor;
+
+impl GettextPreprocessor {
+    /// Create a new `GettextPreprocessor`.
+    pub fn new() -> Self {
+        GettextPreprocessor
+    }
+}
+
+impl Preprocessor for GettextPreprocessor {
+    fn name(&self) -> &str {
+        "gettext"
+    }
+
+    fn run(&self, ctx: &PreprocessorContext, book: &mut Book) -> Result<()> {
+        let language = match ctx.config.book.language {
+            Some(ref language) => language,
+            None => return Ok(()),
+        };
+
+        let po = ctx.root
+            .join(&ctx.config.build.po_dir)
+            .join(format!("{}.po", language));
+        if !po.exists() {
+            return Ok(());
+        }
+
+        debug!("Translating the book with {}", po.display());
+        let catalog = Catalog::parse(&file_to_string(&po)?)
+            .chain_err(|| format!("Unable to parse {}", po.display()))?;
+
+        book.for_each_mut(|item| match *item {
+            BookItem::Chapter(ref mut ch) => {
+                ch.name = catalog.translate(&ch.name).to_string();
+                ch.content = translate(&ch.content, &catalog);
+            }
+            BookItem::PartTitle(ref mut title) => {
+                *title = catalog.translate(title).to_string();
+            }
+            BookItem::Separator => {}
+        });
+
+        Ok(())
+    }
+}
+
+/// Extract the translatable text of a book into the contents of a `.pot`
+/// file: the chapter and part titles, and each paragraph, heading, list and
+/// so on of the chapters. Code blocks aren't translated.
+///
+/// The references to where each message came from are relative to the
+/// book's root, with `chapter_dir` being the directory the chapters are in.
+pub fn xgettext(book: &Book, chapter_dir: &Path, title: Option<&str>) -> String {
+    let mut messages: Vec<(String, Vec<String>)> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    let summary = chapter_dir.join("SUMMARY.md").display().to_string();
+
+    {
+        let mut add = |msgid: &str, location: String| {
+            let ix = *index.entry(msgid.to_string()).or_insert_with(|| {
+                messages.push((msgid.to_string(), Vec::new()));
+                messages.len() - 1
+            });
+            messages[ix].1.push(location);
+        };
+
+        for item in book.iter() {
+            match *item {
+                BookItem::Chapter(ref ch) => {
+                    add(&ch.name, summary.clone());
+
+                    let path = chapter_dir.join(&ch.path).display().to_string();
+                    for block in translatable_blocks(&ch.content) {
+                        let line = block.line + ch.front_matter_lines;
+                        let location = format!("{}:{}", path, line);
+                        add(&ch.content[block.start..block.end], location);
+                    }
+                }
+                BookItem::PartTitle(ref title) => add(title, summary.clone()),
+                BookItem::Separator => {}
+            }
+        }
+    }
+
+    let mut pot = String::from("msgid \"\"\nmsgstr \"\"\n");
+    if let Some(title) = title {
+        pot.push_str(&format!("\"Project-Id-Version: {}\\n\"\n", escape(title)));
+    }
+    pot.push_str(
+        "\"MIME-Version: 1.0\\n\"\n\
+         \"Content-Type: text/plain; charset=UTF-8\\n\"\n\
+         \"Content-Transfer-Encoding: 8bit\\n\"\n",
+    );
+
+    for (msgid, locations) in messages {
+        pot.push('\n');
+        for location in locations {
+            pot.push_str(&format!("#: {}\n", location));
+        }
+        pot.push_str(&format!("msgid {}\nmsgstr \"\"\n", quote(&msgid)));
+    }
+
+    pot
+}
+
+/// Replace each block of a chapter which has a translation.
+fn translate(content: &str, catalog: &Catalog) -> String {
+    let mut translated = String::with_capacity(content.len());
+    let mut previous_end = 0;
+
+    for block in translatable_blocks(content) {
+        translated.push_str(&content[previous_end..block.start]);
+        translated.push_str(catalog.translate(&content[block.start..block.end]));
+        previous_end = block.end;
+    }
+
+    translated.push_str(&content[previous_end..]);
+    translated
+}
+
+/// A piece of a chapter which is translated as a whole, as a byte range
+/// (without the trailing newline) and the line it starts on.
+#[derive(Debug, Clone, PartialEq)]
+struct Block {
+    start: usize,
+    end: usize,
+    line: usize,
+}
+
+/// Split a chapter into the blocks which get translated: runs of lines
+/// separated by blank lines, with each heading on its own. Code blocks and
+/// lines which are just an `{{#include}}` (or similar) are left out.
+fn translatable_blocks(content: &str) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut current: Option<Block> = None;
+    let mut fence: Option<&str> = None;
+    let mut offset = 0;
+
+    for (ix, line) in content.split('\n').enumerate() {
+        let start = offset;
+        let end = start + line.trim_right().len();
+        offset += line.len() + 1;
+        let trimmed = line.trim();
+
+        if let Some(marker) = fence {
+            if trimmed.starts_with(marker) {
+                fence = None;
+            }
+            continue;
+        }
+
+        let is_fence = trimmed.starts_with("```") || trimmed.starts_with("~~~");
+        let is_helper = trimmed.starts_with("{{#") && trimmed.ends_with("}}");
+        let is_heading = trimmed.starts_with('#');
+
+        if is_fence || is_helper || is_heading || trimmed.is_empty() {
+            blocks.extend(current.take());
+        }
+
+        if is_fence {
+            fence = Some(&trimmed[..3]);
+        } else if is_heading {
+            blocks.push(Block {
+                start,
+                end,
+                line: ix + 1,
+            });
+        } else if !is_helper && !trimmed.is_empty() {
+            let block = current.get_or_insert(Block {
+                start,
+                end,
+                line: ix + 1,
+            });
+            block.end = end;
+        }
+    }
+
+    blocks.extend(current);
+    blocks
+        .into_iter()
+        .filter(|block| content[block.start..block.end].chars().any(char::is_alphabetic))
+        .collect()
+}
+
+/// The translations in a `.po` file, keyed by their `msgctxt` (if any) and
+/// `msgid`. Fuzzy translations and messages with plural forms are ignored.
+#[derive(Debug, Default, Clone, PartialEq)]
+struct Catalog {
+    translations: HashMap<(Option<String>, String), String>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Field {
+    Msgctxt,
+    Msgid,
+    Msgstr,
+    Other,
+}
+
+impl Catalog {
+    fn parse(src: &str) -> Result<Catalog> {
+        let mut catalog = Catalog::default();
+        let mut msgctxt: Option<String> = None;
+        let mut msgid = String::new();
+        let mut msgstr = String::new();
+        let mut field = Field::Other;
+        let mut fuzzy = false;
+        let mut plural = false;
+
+        for (ix, line) in src.lines().chain(Some("")).enumerate() {
+            let line = line.trim();
+            let parse_error = || format!("Invalid string on line {}", ix + 1);
+
+            // entries are usually separated by blank lines, but it's enough
+            // for the next one to start after a msgstr
+            let next_entry = line.starts_with('#') || line.starts_with("msgid ")
+                || line.starts_with("msgctxt");
+            if line.is_empty() || (next_entry && field == Field::Msgstr) {
+                if !fuzzy && !plural && !msgid.is_empty() && !msgstr.is_empty() {
+                    let key = (msgctxt.clone(), msgid.clone());
+                    catalog.translations.insert(key, msgstr.clone());
+                }
+                msgctxt = None;
+                msgid.clear();
+                msgstr.clear();
+                field = Field::Other;
+                fuzzy = false;
+                plural = false;
+            }
+
+            if line.starts_with("#,") {
+                fuzzy = fuzzy || line.contains("fuzzy");
+            } else if line.starts_with('#') || line.is_empty() {
+                continue;
+            } else if line.starts_with("msgid_plural") {
+                plural = true;
+                field = Field::Other;
+            } else if line.starts_with("msgstr[") {
+                plural = true;
+                field = Field::Msgstr;
+            } else if line.starts_with("msgctxt ") {
+                field = Field::Msgctxt;
+                msgctxt = Some(unquote(&line["msgctxt ".len()..]).ok_or_else(parse_error)?);
+            } else if line.starts_with("msgid ") {
+                field = Field::Msgid;
+                msgid = unquote(&line["msgid ".len()..]).ok_or_else(parse_error)?;
+            } else if line.starts_with("msgstr ") {
+                field = Field::Msgstr;
+                msgstr = unquote(&line["msgstr ".len()..]).ok_or_else(parse_error)?;
+            } else if line.starts_with('"') {
+                let continued = unquote(line).ok_or_else(parse_error)?;
+                match field {
+                    Field::Msgctxt => if let Some(ref mut msgctxt) = msgctxt {
+                        msgctxt.push_str(&continued);
+                    },
+                    Field::Msgid => msgid.push_str(&continued),
+                    Field::Msgstr => msgstr.push_str(&continued),
+                    Field::Other => {}
+                }
+            } else {
+                bail!("Unexpected line {}: {}", ix + 1, line);
+            }
+        }
+
+        Ok(catalog)
+    }
+
+    /// The translation of `msgid`, or `msgid` itself if it hasn't been
+    /// translated. The book's messages don't have a context, so translations
+    /// for the same `msgid` in some other context don't count.
+    fn translate<'a>(&'a self, msgid: &'a str) -> &'a str {
+        self.translations
+            .get(&(None, msgid.to_string()))
+            .map(|msgstr| msgstr.as_str())
+            .unwrap_or(msgid)
+    }
+}
+
+/// Quote a string for a `.po` file, splitting it over several lines after
+/// each newline.
+fn quote(text: &str) -> String {
+    if !text.contains('\n') {
+        return format!("\"{}\"", escape(text));
+    }
+
+    let mut quoted = String::from("\"\"");
+    for line in text.split_terminator('\n') {
+        quoted.push_str(&format!("\n\"{}\\n\"", escape(line)));
+    }
+    if !text.ends_with('\n') {
+        // the last line doesn't have a newline after all
+        let len = quoted.len();
+        quoted.truncate(len - 3);
+        quoted.push('"');
+    }
+    quoted
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+}
+
+/// The contents of a quoted `.po` string.
+fn unquote(quoted: &str) -> Option<String> {
+    let quoted = quoted.trim();
+    if quoted.len() < 2 || !quoted.starts_with('"') || !quoted.ends_with('"') {
+        return None;
+    }
+
+    let mut unquoted = String::new();
+    let mut chars = quoted[1..quoted.len() - 1].chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unquoted.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => unquoted.push('\n'),
+            Some('t') => unquoted.push('\t'),
+            Some('r') => unquoted.push('\r'),
+            Some(other) => unquoted.push(other),
+            None => return None,
+        }
+    }
+
+    Some(unquoted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use book::Chapter;
+
+    const CHAPTER: &str = "# Getting Started\n\nInstall the tool\nwith cargo:\n\n\
+                           ```sh\n$ cargo install mdbook\n\n# done\n```\n\n\
+                           {{#include example.rs}}\n\n- One\n- Two\n";
+
+    #[test]
+    fn chapters_are_split_into_paragraphs_and_headings() {
+        let got: Vec<&str> = translatable_blocks(CHAPTER)
+            .into_iter()
+            .map(|block| &CHAPTER[block.start..block.end])
+            .collect();
+
+        assert_eq!(
+            got,
+            vec![
+                "# Getting Started",
+                "Install the tool\nwith cargo:",
+                "- One\n- Two",
+            ]
+        );
+    }
+
+    #[test]
+    fn extract_a_template() {
+        let mut book = Book::new();
+        book.push_item(Chapter::new("Intro", CHAPTER.to_string(), "intro.md"));
+
+        let got = xgettext(&book, Path::new("src"), Some("My Book"));
+
+        assert!(got.starts_with("msgid \"\"\nmsgstr \"\"\n\"Project-Id-Version: My Book\\n\"\n"));
+        assert!(got.contains("#: src/SUMMARY.md\nmsgid \"Intro\"\nmsgstr \"\"\n"));
+        assert!(got.contains("#: src/intro.md:1\nmsgid \"# Getting Started\"\n"));
+        assert!(got.contains(
+            "#: src/intro.md:3\nmsgid \"\"\n\"Install the tool\\n\"\n\"with cargo:\"\nmsgstr \"\"\n"
+        ));
+        assert!(!got.contains("cargo install"));
+    }
+
+    #[test]
+    fn apply_a_translation() {
+        let po = r##"
+msgid ""
+msgstr ""
+"Content-Type: text/plain; charset=UTF-8\n"
+
+#: src/intro.md:1
+msgid "# Getting Started"
+msgstr "# Premiers pas"
+
+#: src/intro.md:3
+msgid ""
+"Install the tool\n"
+"with cargo:"
+msgstr ""
+"Installez l'outil\n"
+"avec cargo :"
+
+#, fuzzy
+msgid "- One\n- Two"
+msgstr "- Un\n- Deux"
+
+msgctxt "menu"
+msgid "# Getting Started"
+msgstr "# Démarrage"
+"##;
+        let catalog = Catalog::parse(po).unwrap();
+        assert_eq!(catalog.translations.len(), 3);
+
+        let got = translate(CHAPTER, &catalog);
+
+        assert_eq!(
+            got,
+            "# Premiers pas\n\nInstallez l'outil\navec cargo :\n\n\
+             ```sh\n$ cargo install mdbook\n\n# done\n```\n\n\
+             {{#include example.rs}}\n\n- One\n- Two\n"
+        );
+    }
+
+    #[test]
+    fn strings_survive_quoting() {
+        let text = "A \"quoted\"\n\tback\\slash";
+        let quoted = quote(text);
+
+        let got: String = quoted
+            .lines()
+            .map(|line| unquote(line).unwrap())
+            .collect();
+
+        assert_eq!(got, text);
+    }
+}
@@ -0,0 +1,212 @@
+use std::cmp;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use pulldown_cmark::{Event, Parser, Tag};
+use regex::Regex;
+use errors::*;
+
+use super::{Preprocessor, PreprocessorContext};
+use book::{Book, BookItem};
+use utils::links::is_external;
+
+/// A preprocessor for rendering a directory's `README.md` as its `index.html`,
+/// the way the source reads on GitHub, and fixing up the links to it.
+pub struct IndexPreprocessor;
+
+impl IndexPreprocessor {
+    /// Create a new `IndexPreprocessor`.
+    pub fn new() -> Self {
+        IndexPreprocessor
+    }
+}
+
+impl Preprocessor for IndexPreprocessor {
+    fn name(&self) -> &str {
+        "index"
+    }
+
+    fn run(&self, _ctx: &PreprocessorContext, book: &mut Book) -> Result<()> {
+        let renames = readme_renames(book);
+
+        book.for_each_mut(|section: &mut BookItem| {
+            if let BookItem::Chapter(ref mut ch) = *section {
+                if let Some(index) = renames.get(&ch.path) {
+                    ch.path = index.clone();
+                } else if is_readme(&ch.path) {
+                    warn!(
+                        "Not renaming {} because {} is already a chapter",
+                        ch.path.display(),
+                        ch.path.with_file_name("index.md").display()
+                    );
+                }
+
+                ch.content = replace_readme_links(&ch.content);
+            }
+        });
+
+        Ok(())
+    }
+
+    fn supports_renderer(&self, renderer: &str) -> bool {
+        // the `index.html` naming only matters for the HTML output
+        renderer == "html"
+    }
+}
+
+/// The new paths of the `README.md` chapters which get renamed to `index.md`,
+/// by their original path. A `README.md` keeps its name when there's already
+/// an `index.md` chapter next to it.
+pub(crate) fn readme_renames(book: &Book) -> HashMap<PathBuf, PathBuf> {
+    let paths: HashSet<PathBuf> = book.iter()
+        .filter_map(|item| match *item {
+            BookItem::Chapter(ref ch) => Some(ch.path.clone()),
+            _ => None,
+        })
+        .collect();
+
+    paths
+        .iter()
+        .filter(|path| is_readme(path))
+        .map(|path| (path.clone(), path.with_file_name("index.md")))
+        .filter(|&(_, ref index)| !paths.contains(index))
+        .collect()
+}
+
+fn is_readme(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .map(|name| name.eq_ignore_ascii_case("README.md"))
+        .unwrap_or(false)
+}
+
+/// Point the links to a `README.md` (or its rendered `README.html`) at the
+/// `index.html` in the same directory.
+///
+/// The links are found by parsing the markdown, so links to other sites and
+/// anything inside code are left alone.
+fn replace_readme_links(content: &str) -> String {
+    lazy_static! {
+        static ref DEFINITION: Regex =
+            Regex::new(r"(?m)^ {0,3}\[[^\]]+\]:[ \t]*<?([^\s>]+)").unwrap();
+    }
+
+    // the byte ranges of the destinations to replace, in order
+    let mut replacements: Vec<(usize, usize, String)> = Vec::new();
+    // destinations which aren't inline, so they come from a definition
+    let mut references = HashSet::new();
+    // the links of a paragraph come after its start
+    let mut cursor = 0;
+
+    let mut parser = Parser::new(content);
+    while let Some(event) = parser.next() {
+        match event {
+            Event::Start(Tag::Paragraph) |
+            Event::Start(Tag::Header(_)) |
+            Event::Start(Tag::Item) |
+            Event::Start(Tag::TableCell) => {
+                cursor = cmp::max(cursor, parser.get_offset());
+            }
+            Event::Start(Tag::Link(dest, _)) => {
+                let index = match index_link(&dest) {
+                    Some(index) => index,
+                    None => continue,
+                };
+
+                let inline = content[cursor..]
+                    .find(&format!("]({}", dest))
+                    .or_else(|| content[cursor..].find(&format!("](<{}", dest)));
+                match inline {
+                    Some(ix) => {
+                        let start = cursor + content[cursor + ix..].find(&*dest).unwrap() + ix;
+                        replacements.push((start, start + dest.len(), index));
+                        cursor = start + dest.len();
+                    }
+                    None => {
+                        references.insert(dest.into_owned());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for caps in DEFINITION.captures_iter(content) {
+        let dest = caps.get(1).expect("the destination is always there");
+        if references.contains(dest.as_str()) {
+            if let Some(index) = index_link(dest.as_str()) {
+                replacements.push((dest.start(), dest.end(), index));
+            }
+        }
+    }
+
+    replacements.sort_by_key(|&(start, _, _)| start);
+
+    let mut replaced = String::with_capacity(content.len());
+    let mut last = 0;
+    for (start, end, index) in replacements {
+        if start < last {
+            continue;
+        }
+        replaced.push_str(&content[last..start]);
+        replaced.push_str(&index);
+        last = end;
+    }
+    replaced.push_str(&content[last..]);
+    replaced
+}
+
+/// The link to the `index.html` for a link to a `README.md` or
+/// `README.html`, keeping its directory and anchor.
+fn index_link(dest: &str) -> Option<String> {
+    if is_external(dest) {
+        return None;
+    }
+
+    let (path, anchor) = match dest.find('#') {
+        Some(ix) => (&dest[..ix], &dest[ix..]),
+        None => (dest, ""),
+    };
+    let (dir, file) = match path.rfind('/') {
+        Some(ix) => (&path[..ix + 1], &path[ix + 1..]),
+        None => ("", path),
+    };
+
+    if file.eq_ignore_ascii_case("README.md") || file.eq_ignore_ascii_case("README.html") {
+        Some(format!("{}index.html{}", dir, anchor))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn links_to_readme_files_point_at_the_index() {
+        let src = "See [the guide](guide/README.md#usage), [home](README.html) or \
+                   [something else](guide/other.html), and [the parent][ref].\n\n\
+                   [ref]: ../README.md\n";
+        let should_be = "See [the guide](guide/index.html#usage), [home](index.html) or \
+                         [something else](guide/other.html), and [the parent][ref].\n\n\
+                         [ref]: ../index.html\n";
+
+        assert_eq!(replace_readme_links(src), should_be);
+    }
+
+    #[test]
+    fn links_to_other_sites_and_code_are_left_alone() {
+        let src = "See [upstream](https://github.com/x/y/blob/master/README.md).\n\n\
+                   Write `[home](README.md)` for a link home.\n\n\
+                   ```markdown\n[home](README.md)\n```\n";
+
+        assert_eq!(replace_readme_links(src), src);
+    }
+
+    #[test]
+    fn only_readme_files_are_renamed() {
+        assert!(is_readme(Path::new("guide/README.md")));
+        assert!(is_readme(Path::new("readme.md")));
+        assert!(!is_readme(Path::new("guide/not-a-README.md")));
+    }
+}
@@ -3,15 +3,23 @@ use std::path::{Path, PathBuf};
 use regex::{CaptureMatches, Captures, Regex};
 use utils::fs::file_to_string;
 use utils::take_lines;
+use diagnostics::{self, Diagnostic, Level};
 use errors::*;
 
 use super::{Preprocessor, PreprocessorContext};
-use book::{Book, BookItem};
+use super::qr::render_qrcode;
+use super::remote::{is_remote, RemoteIncludes};
+use super::table::render_table;
+use book::Book;
+use config::RawHtml;
 
 const ESCAPE_CHAR: char = '\\';
+/// How deeply included files may include other files, which stops an
+/// include cycle from going on forever.
+const MAX_LINK_NESTED_DEPTH: usize = 10;
 
-/// A preprocessor for expanding the `{{# playpen}}` and `{{# include}}`
-/// helpers in a chapter.
+/// A preprocessor for expanding the `{{# playpen}}`, `{{# include}}` and
+/// `{{# table}}` helpers in a chapter.
 pub struct LinkPreprocessor;
 
 impl LinkPreprocessor {
@@ -27,25 +35,51 @@ impl Preprocessor for LinkPreprocessor {
     }
 
     fn run(&self, ctx: &PreprocessorContext, book: &mut Book) -> Result<()> {
-        let src_dir = ctx.root.join(&ctx.config.book.src);
-
-        book.for_each_mut(|section: &mut BookItem| {
-            if let BookItem::Chapter(ref mut ch) = *section {
-                let base = ch.path
-                    .parent()
-                    .map(|dir| src_dir.join(dir))
-                    .expect("All book items have a parent");
+        let src_dir = ctx.root.join(ctx.config.book.chapter_dir());
+        let mounts = &ctx.mounts;
+        let settings = LinkSettings {
+            remote: RemoteIncludes::new(ctx.cache("remote-includes"), ctx.config.build.offline),
+            markdown_tables: ctx.config.book.markdown.tables,
+            raw_html: ctx.config.book.markdown.raw_html,
+        };
 
-                let content = replace_all(&ch.content, base);
-                ch.content = content;
-            }
+        book.for_each_chapter_mut(|ch, _| {
+            let source = match ch.source_path {
+                Some(ref path) => path.clone(),
+                None => mounts.source_path(&src_dir, &ch.path),
+            };
+            let base = source
+                .parent()
+                .map(|dir| dir.to_path_buf())
+                .expect("All book items have a parent");
+
+            let content =
+                replace_all(&ch.content, base, &source, ch.front_matter_lines, &settings, 0);
+            ch.content = content;
         });
 
         Ok(())
     }
 }
 
-fn replace_all<P: AsRef<Path>>(s: &str, path: P) -> String {
+/// The parts of the book's configuration which change what the links expand
+/// to.
+struct LinkSettings {
+    remote: RemoteIncludes,
+    /// Whether tables can be written in markdown (`book.markdown.tables`).
+    markdown_tables: bool,
+    /// What happens to raw HTML (`book.markdown.raw-html`).
+    raw_html: RawHtml,
+}
+
+fn replace_all<P: AsRef<Path>>(
+    s: &str,
+    path: P,
+    source: &Path,
+    front_matter_lines: usize,
+    settings: &LinkSettings,
+    depth: usize,
+) -> String {
     // When replacing one thing in a string by something with a different length,
     // the indices after that will not correspond,
     // we therefore have to store the difference to correct this
@@ -56,13 +90,37 @@ fn replace_all<P: AsRef<Path>>(s: &str, path: P) -> String {
     for playpen in find_links(s) {
         replaced.push_str(&s[previous_end_index..playpen.start_index]);
 
-        match playpen.render_with_path(&path) {
+        match playpen.render_with_path(&path, settings) {
             Ok(new_content) => {
-                replaced.push_str(&new_content);
+                // the links in an included file are relative to that file
+                match playpen.link.included_file(path) {
+                    Some(ref file) if depth < MAX_LINK_NESTED_DEPTH => {
+                        let dir = file.parent().unwrap_or(path);
+                        let expanded =
+                            replace_all(&new_content, dir, file, 0, settings, depth + 1);
+                        replaced.push_str(&expanded);
+                    }
+                    Some(_) => {
+                        let message = format!(
+                            "Not expanding the links in \"{}\", the includes are nested too deeply",
+                            playpen.link_text
+                        );
+                        diagnostics::report(
+                            Diagnostic::new(Level::Warning, message).at(source, None),
+                        );
+                        replaced.push_str(&new_content);
+                    }
+                    None => replaced.push_str(&new_content),
+                }
                 previous_end_index = playpen.end_index;
             }
             Err(e) => {
-                error!("Error updating \"{}\", {}", playpen.link_text, e);
+                let line =
+                    s[..playpen.start_index].matches('\n').count() + 1 + front_matter_lines;
+                let message = format!("Error updating \"{}\", {}", playpen.link_text, e);
+                diagnostics::report(
+                    Diagnostic::new(Level::Error, message).at(source, Some(line)),
+                );
                 // This should make sure we include the raw `{{# ... }}` snippet
                 // in the page content if there are any errors.
                 previous_end_index = playpen.start_index;
@@ -74,6 +132,56 @@ fn replace_all<P: AsRef<Path>>(s: &str, path: P) -> String {
     replaced
 }
 
+/// Something pulled into a chapter by one of its links.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Included {
+    /// A local file.
+    File(PathBuf),
+    /// The URL of a remote include, without its line selection.
+    Remote(String),
+}
+
+/// Everything pulled into a chapter by its `{{#include}}`, `{{#playpen}}` and
+/// `{{#table}}` links, including what the included files include in turn,
+/// where `base` is the directory containing the chapter.
+pub(crate) fn included_files(content: &str, base: &Path) -> Vec<Included> {
+    let mut included = Vec::new();
+    collect_included(content, base, 0, &mut included);
+    included
+}
+
+fn collect_included(content: &str, base: &Path, depth: usize, included: &mut Vec<Included>) {
+    for link in find_links(content) {
+        let item = match link.link {
+            LinkType::IncludeRange(ref path, _)
+            | LinkType::IncludeRangeFrom(ref path, _)
+            | LinkType::IncludeRangeTo(ref path, _)
+            | LinkType::IncludeRangeFull(ref path, _)
+            | LinkType::Playpen(ref path, _)
+            | LinkType::Table(ref path, _) => Included::File(base.join(path)),
+            LinkType::RemoteInclude(target, _) => {
+                let url = target.split('#').next().unwrap_or(target);
+                Included::Remote(url.to_string())
+            }
+            LinkType::Escaped | LinkType::QrCode(..) => continue,
+        };
+        // a file included more than once (or by itself) is only looked at once
+        if included.contains(&item) {
+            continue;
+        }
+        included.push(item);
+
+        if depth < MAX_LINK_NESTED_DEPTH {
+            if let Some(file) = link.link.included_file(base) {
+                if let Ok(nested) = file_to_string(&file) {
+                    let dir = file.parent().unwrap_or(base);
+                    collect_included(&nested, dir, depth + 1, included);
+                }
+            }
+        }
+    }
+}
+
 #[derive(PartialEq, Debug, Clone)]
 enum LinkType<'a> {
     Escaped,
@@ -82,6 +190,23 @@ enum LinkType<'a> {
     IncludeRangeTo(PathBuf, RangeTo<usize>),
     IncludeRangeFull(PathBuf, RangeFull),
     Playpen(PathBuf, Vec<&'a str>),
+    Table(PathBuf, Vec<&'a str>),
+    RemoteInclude(&'a str, Vec<&'a str>),
+    QrCode(&'a str, Vec<&'a str>),
+}
+
+impl<'a> LinkType<'a> {
+    /// The file an `{{#include}}` pulls in, whose own links get expanded
+    /// too. Playpens and tables are code and data, so their links aren't.
+    fn included_file(&self, base: &Path) -> Option<PathBuf> {
+        match *self {
+            LinkType::IncludeRange(ref path, _)
+            | LinkType::IncludeRangeFrom(ref path, _)
+            | LinkType::IncludeRangeTo(ref path, _)
+            | LinkType::IncludeRangeFull(ref path, _) => Some(base.join(path)),
+            _ => None,
+        }
+    }
 }
 
 fn parse_include_path(path: &str) -> LinkType<'static> {
@@ -140,8 +265,13 @@ impl<'a> Link<'a> {
                 let props: Vec<&str> = path_props.collect();
 
                 match (typ.as_str(), file_arg) {
+                    ("include", Some(pth)) if is_remote(pth) => {
+                        Some(LinkType::RemoteInclude(pth, props))
+                    }
                     ("include", Some(pth)) => Some(parse_include_path(pth)),
                     ("playpen", Some(pth)) => Some(LinkType::Playpen(pth.into(), props)),
+                    ("table", Some(pth)) => Some(LinkType::Table(pth.into(), props)),
+                    ("qrcode", Some(data)) => Some(LinkType::QrCode(data, props)),
                     _ => None,
                 }
             }
@@ -161,7 +291,7 @@ impl<'a> Link<'a> {
         })
     }
 
-    fn render_with_path<P: AsRef<Path>>(&self, base: P) -> Result<String> {
+    fn render_with_path<P: AsRef<Path>>(&self, base: P, settings: &LinkSettings) -> Result<String> {
         let base = base.as_ref();
         match self.link {
             // omit the escape char
@@ -188,6 +318,22 @@ impl<'a> Link<'a> {
                     contents
                 ))
             }
+            LinkType::Table(ref pat, ref props) => {
+                let contents = file_to_string(base.join(pat))
+                    .chain_err(|| format!("Could not read file for link {}", self.link_text))?;
+                let delimiter = match pat.extension().and_then(|ext| ext.to_str()) {
+                    Some("tsv") => '\t',
+                    _ => ',',
+                };
+                render_table(&contents, delimiter, props, settings.markdown_tables)
+                    .chain_err(|| format!("Could not render the table for link {}", self.link_text))
+            }
+            LinkType::RemoteInclude(url, ref props) => settings
+                .remote
+                .include(url, props)
+                .chain_err(|| format!("Could not fetch the file for link {}", self.link_text)),
+            LinkType::QrCode(data, ref props) => render_qrcode(data, props, settings.raw_html)
+                .chain_err(|| format!("Could not render the QR code for link {}", self.link_text)),
         }
     }
 }
@@ -216,7 +362,7 @@ fn find_links(contents: &str) -> LinkIter {
                     \{\{\s*                      # link opening parens and whitespace
                       \#([a-zA-Z0-9]+)           # link type
                       \s+                        # separating whitespace
-                      ([a-zA-Z0-9\s_.\-:/\\]+)   # link target path and space separated properties
+                      ([a-zA-Z0-9\s_.\-:/\\=,\#?&%~+@]+) # link target path (or URL) and space separated properties
                     \s*\}\}                      # whitespace and link closing parens
                                  ").unwrap();
     }
@@ -226,6 +372,57 @@ fn find_links(contents: &str) -> LinkIter {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use cache::Cache;
+    use tempdir::TempDir;
+    use utils::fs::write_files;
+
+    #[test]
+    fn included_files_are_relative_to_the_chapter() {
+        let s = "{{#include a.rs}}\n\\{{#include escaped.rs}}\n{{#playpen b.rs editable}}\n\
+                 {{#include c.rs:2:5}}\n";
+
+        let got = included_files(s, Path::new("src/nested"));
+
+        assert_eq!(
+            got,
+            vec![
+                Included::File(PathBuf::from("src/nested/a.rs")),
+                Included::File(PathBuf::from("src/nested/b.rs")),
+                Included::File(PathBuf::from("src/nested/c.rs")),
+            ]
+        );
+    }
+
+    #[test]
+    fn includes_of_included_files_are_followed() {
+        let temp = TempDir::new("mdbook").unwrap();
+        let files = [
+            ("src/chapter.md", "{{#include snippets/outer.md}}"),
+            ("src/snippets/outer.md", "Outer\n{{#include inner.md}}\n{{#include outer.md}}"),
+            ("src/snippets/inner.md", "Inner"),
+        ];
+        write_files(temp.path(), &files).unwrap();
+        let src = temp.path().join("src");
+        let settings = LinkSettings {
+            remote: RemoteIncludes::new(Cache::new(temp.path().join("cache")), true),
+            markdown_tables: true,
+            raw_html: RawHtml::Allow,
+        };
+
+        let got = included_files(files[0].1, &src);
+        assert_eq!(
+            got,
+            vec![
+                Included::File(src.join("snippets/outer.md")),
+                Included::File(src.join("snippets/inner.md")),
+            ]
+        );
+
+        // the cycle stops at the maximum depth rather than going on forever
+        let expanded = replace_all(files[0].1, &src, &src.join("chapter.md"), 0, &settings, 0);
+        assert!(expanded.starts_with("Outer\nInner\nOuter\nInner\n"));
+        assert_eq!(expanded.matches("Outer").count(), MAX_LINK_NESTED_DEPTH + 1);
+    }
 
     #[test]
     fn test_find_links_no_link() {
@@ -438,6 +635,72 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_find_tables_with_properties() {
+        let s = "Prices: {{#table data/prices.csv align=lrc no-header}}";
+
+        let res = find_links(s).collect::<Vec<_>>();
+        assert_eq!(
+            res,
+            vec![
+                Link {
+                    start_index: 8,
+                    end_index: 54,
+                    link: LinkType::Table(
+                        PathBuf::from("data/prices.csv"),
+                        vec!["align=lrc", "no-header"],
+                    ),
+                    link_text: "{{#table data/prices.csv align=lrc no-header}}",
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_find_remote_includes() {
+        let s = "{{#include https://example.com/a%20b/lib.rs?raw=true#L10-L30 sha256=abc123}}";
+
+        let res = find_links(s).collect::<Vec<_>>();
+
+        assert_eq!(
+            res,
+            vec![
+                Link {
+                    start_index: 0,
+                    end_index: 76,
+                    link: LinkType::RemoteInclude(
+                        "https://example.com/a%20b/lib.rs?raw=true#L10-L30",
+                        vec!["sha256=abc123"],
+                    ),
+                    link_text: s,
+                },
+            ]
+        );
+        assert_eq!(
+            included_files(s, Path::new("src")),
+            vec![Included::Remote(String::from("https://example.com/a%20b/lib.rs?raw=true"))]
+        );
+    }
+
+    #[test]
+    fn test_find_qrcode_links() {
+        let s = "Scan {{#qrcode https://example.com/book/ size=240}} to read online";
+
+        let res = find_links(s).collect::<Vec<_>>();
+
+        assert_eq!(
+            res,
+            vec![
+                Link {
+                    start_index: 5,
+                    end_index: 51,
+                    link: LinkType::QrCode("https://example.com/book/", vec!["size=240"]),
+                    link_text: "{{#qrcode https://example.com/book/ size=240}}",
+                },
+            ]
+        );
+    }
+
     #[test]
     fn test_find_all_link_types() {
         let s = "Some random text with escaped playpen {{#include file.rs}} and \\{{#contents are \
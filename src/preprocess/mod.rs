@@ -1,12 +1,25 @@
 //! Book preprocessing.
 
+pub use self::build_info::BuildInfoPreprocessor;
+pub use self::gettext::{xgettext, GettextPreprocessor};
+pub use self::index::IndexPreprocessor;
 pub use self::links::LinkPreprocessor;
+pub(crate) use self::index::readme_renames;
+pub(crate) use self::links::{included_files, Included};
 
+mod build_info;
+mod gettext;
+mod index;
 mod links;
+mod qr;
+mod remote;
+mod table;
 
-use book::Book;
+use book::{Book, MountedBooks};
+use cache::Cache;
 use config::Config;
 use errors::*;
+use utils::BuildInfo;
 
 use std::path::PathBuf;
 
@@ -17,12 +30,29 @@ pub struct PreprocessorContext {
     pub root: PathBuf,
     /// The book configuration (`book.toml`).
     pub config: Config,
+    /// The books mounted as parts of this one (`build.mounts`).
+    pub mounts: MountedBooks,
+    /// The build information, collected once per build. `None` means it
+    /// hasn't been collected yet.
+    pub build_info: Option<BuildInfo>,
 }
 
 impl PreprocessorContext {
     /// Create a new `PreprocessorContext`.
-    pub(crate) fn new(root: PathBuf, config: Config) -> Self {
-        PreprocessorContext { root, config }
+    pub(crate) fn new(root: PathBuf, config: Config, mounts: MountedBooks) -> Self {
+        PreprocessorContext {
+            root,
+            config,
+            mounts,
+            build_info: None,
+        }
+    }
+
+    /// A [`Cache`](../cache/struct.Cache.html) for memoizing expensive work
+    /// between builds. Each preprocessor should use its own `name`, which
+    /// becomes a subdirectory of the book's `build.cache-dir`.
+    pub fn cache(&self, name: &str) -> Cache {
+        Cache::new(self.root.join(&self.config.build.cache_dir).join(name))
     }
 }
 
@@ -35,4 +65,14 @@ pub trait Preprocessor {
     /// Run this `Preprocessor`, allowing it to update the book before it is
     /// given to a renderer.
     fn run(&self, ctx: &PreprocessorContext, book: &mut Book) -> Result<()>;
+
+    /// Should this `Preprocessor` be run for the renderer with the given
+    /// name? Preprocessors which only make sense for a particular output
+    /// format (e.g. rewriting links to `.html` files) can opt out of the
+    /// others, which then get the book without this preprocessor's changes.
+    ///
+    /// By default a preprocessor supports every renderer.
+    fn supports_renderer(&self, _renderer: &str) -> bool {
+        true
+    }
 }
\ No newline at end of file
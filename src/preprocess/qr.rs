@@ -0,0 +1,107 @@
+//! Rendering QR codes as SVG images, for the `{{#qrcode}}` link.
+
+use base64;
+use qrcode::QrCode;
+use qrcode::render::svg;
+
+use config::RawHtml;
+use utils::escape_html;
+use errors::*;
+
+/// The width and height of a QR code in pixels, unless the link gives a
+/// `size`.
+const DEFAULT_SIZE: u32 = 160;
+
+/// Render `data` (usually a URL) as a QR code. That's inline SVG when the
+/// book's `raw-html` allows it, and otherwise an `<img>` with the SVG image in
+/// its `src`, which makes it through `raw-html = "sanitize"`.
+///
+/// The properties of the link can give the `size` of the image in pixels,
+/// e.g. `size=240`.
+pub fn render_qrcode(data: &str, props: &[&str], raw_html: RawHtml) -> Result<String> {
+    let mut size = DEFAULT_SIZE;
+
+    for prop in props {
+        if prop.starts_with("size=") {
+            size = prop["size=".len()..]
+                .parse()
+                .chain_err(|| format!("Invalid QR code size \"{}\"", prop))?;
+        } else {
+            bail!("Unknown QR code property \"{}\"", prop);
+        }
+    }
+
+    let code = QrCode::new(data.as_bytes())
+        .map_err(|e| Error::from(format!("Unable to encode \"{}\" as a QR code, {:?}", data, e)))?;
+    let image = code.render::<svg::Color>()
+        .min_dimensions(size, size)
+        .dark_color(svg::Color("#000000"))
+        .light_color(svg::Color("#ffffff"))
+        .build();
+
+    let label = escape_html(data);
+
+    if raw_html == RawHtml::Allow {
+        // drop the XML declaration, which has no place in an HTML page
+        let svg = &image[image.find("<svg").unwrap_or(0) + "<svg".len()..];
+        return Ok(format!(
+            "<svg class=\"qrcode\" role=\"img\" aria-label=\"QR code for {}\"{}",
+            label,
+            svg.replacen(">", &format!("><title>{}</title>", label), 1)
+        ));
+    }
+
+    Ok(format!(
+        "<img class=\"qrcode\" src=\"data:image/svg+xml;base64,{}\" alt=\"QR code for {}\" \
+         title=\"{}\">",
+        base64::encode(&image),
+        label,
+        label
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::MarkdownConfig;
+    use utils::render_markdown_with;
+
+    #[test]
+    fn qr_codes_are_inline_svg_when_raw_html_is_allowed() {
+        let got = render_qrcode("https://example.com/?a=1&b=2", &[], RawHtml::Allow).unwrap();
+
+        assert!(got.starts_with("<svg class=\"qrcode\" role=\"img\""));
+        assert!(got.contains("<title>https://example.com/?a=1&amp;b=2</title>"));
+        assert!(!got.contains("<?xml"));
+        assert!(got.ends_with("</svg>"));
+    }
+
+    #[test]
+    fn qr_codes_are_svg_images_otherwise() {
+        let got =
+            render_qrcode("https://example.com/?a=1&b=2", &["size=200"], RawHtml::Strip).unwrap();
+
+        assert!(got.starts_with("<img class=\"qrcode\" src=\"data:image/svg+xml;base64,"));
+        assert!(got.contains("alt=\"QR code for https://example.com/?a=1&amp;b=2\""));
+        assert!(!got.contains("<svg"));
+    }
+
+    #[test]
+    fn qr_codes_survive_the_sanitizer() {
+        let markdown = MarkdownConfig {
+            raw_html: RawHtml::Sanitize,
+            ..Default::default()
+        };
+        let image = render_qrcode("https://example.com/", &[], RawHtml::Sanitize).unwrap();
+
+        let got = render_markdown_with(&image, false, &markdown);
+
+        assert!(got.contains("src=\"data:image/svg+xml;base64,"));
+    }
+
+    #[test]
+    fn unknown_properties_are_errors() {
+        assert!(render_qrcode("https://example.com/", &["color=red"], RawHtml::Allow).is_err());
+        assert!(render_qrcode("https://example.com/", &["size=big"], RawHtml::Allow).is_err());
+    }
+}
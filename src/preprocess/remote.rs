@@ -0,0 +1,253 @@
+//! Including files fetched over HTTP, e.g.
+//! `{{#include https://raw.githubusercontent.com/owner/repo/v1.0/src/lib.rs#L10-L30}}`.
+
+#[allow(unused_imports)] use std::ascii::AsciiExt;
+use std::ops::Range;
+use std::process::Command;
+use std::time::Duration;
+use sha2::{Digest, Sha256};
+
+use cache::Cache;
+use utils::take_lines;
+use errors::*;
+
+/// How long a cached copy of an include without a checksum is used before
+/// it's downloaded again.
+const REFRESH_AFTER_SECS: u64 = 24 * 60 * 60;
+
+/// Fetches the files for remote includes, keeping a copy of each in a
+/// [`Cache`](../../cache/struct.Cache.html) so the book can still be built
+/// offline.
+pub struct RemoteIncludes {
+    cache: Cache,
+    offline: bool,
+    refresh_after: Duration,
+}
+
+impl RemoteIncludes {
+    /// Create a `RemoteIncludes` which keeps its downloads in `cache`. When
+    /// `offline` is set nothing is downloaded, and every include must already
+    /// be in the cache.
+    pub fn new(cache: Cache, offline: bool) -> RemoteIncludes {
+        RemoteIncludes {
+            cache,
+            offline,
+            refresh_after: Duration::from_secs(REFRESH_AFTER_SECS),
+        }
+    }
+
+    /// The contents of the include for `target` (a URL with an optional
+    /// `#L10-L30` fragment), checked against the `sha256=...` in `props`.
+    pub fn include(&self, target: &str, props: &[&str]) -> Result<String> {
+        let (url, lines) = split_fragment(target)?;
+        let checksum = pinned_checksum(props)?;
+        let contents = self.fetch(url, checksum)?;
+
+        Ok(match lines {
+            Some(range) => take_lines(&contents, range),
+            None => contents,
+        })
+    }
+
+    /// Get the whole file at `url`. A pinned file is only downloaded when the
+    /// cached copy is missing or doesn't match, anything else when its cached
+    /// copy is more than a day old. If downloading it fails, an outdated copy
+    /// of a file which isn't pinned is used instead.
+    fn fetch(&self, url: &str, checksum: Option<&str>) -> Result<String> {
+        let cached = self.cache.get(url);
+
+        if let Some(ref contents) = cached {
+            match checksum {
+                Some(expected) if sha256(contents) == expected => return Ok(contents.clone()),
+                Some(_) if self.offline => bail!(
+                    "The cached copy of {} doesn't match its sha256 and mdBook is offline",
+                    url
+                ),
+                Some(_) => {}
+                None if self.offline || !self.is_outdated(url) => return Ok(contents.clone()),
+                None => {}
+            }
+        }
+
+        if self.offline {
+            bail!("{} isn't in the cache and mdBook is offline", url);
+        }
+
+        match self.download_and_cache(url, checksum) {
+            Ok(contents) => Ok(contents),
+            Err(e) => match cached {
+                Some(contents) if checksum.is_none() => {
+                    warn!("Using the cached copy of {}, {}", url, e);
+                    Ok(contents)
+                }
+                _ => Err(e),
+            },
+        }
+    }
+
+    fn is_outdated(&self, url: &str) -> bool {
+        self.cache
+            .age(url)
+            .map_or(true, |age| age >= self.refresh_after)
+    }
+
+    fn download_and_cache(&self, url: &str, checksum: Option<&str>) -> Result<String> {
+        let contents = download(url)?;
+
+        if let Some(expected) = checksum {
+            let actual = sha256(&contents);
+            if actual != expected {
+                bail!(
+                    "The sha256 of {} is {}, but the include is pinned to {}",
+                    url,
+                    actual,
+                    expected
+                );
+            }
+        }
+
+        self.cache.insert(url, &contents)?;
+        Ok(contents)
+    }
+}
+
+/// Is this include target a URL rather than a local file?
+pub fn is_remote(target: &str) -> bool {
+    target.starts_with("http://") || target.starts_with("https://")
+}
+
+/// Download a file with `curl`.
+fn download(url: &str) -> Result<String> {
+    debug!("Downloading {}", url);
+    let output = Command::new("curl")
+        .args(&["--fail", "--silent", "--show-error", "--location", url])
+        .output()
+        .chain_err(|| "Unable to run curl, which is needed for remote includes")?;
+
+    if !output.status.success() {
+        bail!(ErrorKind::Subprocess(
+            format!("Unable to download {}", url),
+            output
+        ));
+    }
+
+    String::from_utf8(output.stdout).chain_err(|| format!("{} isn't valid UTF-8", url))
+}
+
+fn sha256(contents: &str) -> String {
+    format!("{:x}", Sha256::digest(contents.as_bytes()))
+}
+
+/// Split the line selection (GitHub's `#L10-L30` or `#L10`) off a URL.
+fn split_fragment(target: &str) -> Result<(&str, Option<Range<usize>>)> {
+    let (url, fragment) = match target.find('#') {
+        Some(ix) => (&target[..ix], &target[ix + 1..]),
+        None => return Ok((target, None)),
+    };
+
+    let line = |s: &str| -> Option<usize> {
+        if s.len() > 1 && s[..1].eq_ignore_ascii_case("L") {
+            s[1..].parse::<usize>().ok().and_then(|n| n.checked_sub(1))
+        } else {
+            None
+        }
+    };
+
+    let mut parts = fragment.splitn(2, '-');
+    let start = parts.next().and_then(|s| line(s));
+    let end = match parts.next() {
+        Some(s) => line(s).map(|n| Some(n + 1)),
+        None => Some(None),
+    };
+
+    match (start, end) {
+        (Some(start), Some(Some(end))) if start < end => Ok((url, Some(start..end))),
+        (Some(start), Some(None)) => Ok((url, Some(start..start + 1))),
+        _ => bail!("\"#{}\" isn't a line selection like #L10-L30", fragment),
+    }
+}
+
+/// The checksum given with `sha256=...`, if any.
+fn pinned_checksum<'a>(props: &[&'a str]) -> Result<Option<&'a str>> {
+    let mut checksum = None;
+
+    for prop in props {
+        if prop.starts_with("sha256=") {
+            checksum = Some(&prop["sha256=".len()..]);
+        } else {
+            bail!("Unknown property \"{}\" for a remote include", prop);
+        }
+    }
+
+    Ok(checksum)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    const URL: &str = "https://example.com/src/lib.rs";
+
+    #[test]
+    fn line_selections_follow_github() {
+        let got = split_fragment("https://example.com/src/lib.rs#L10-L30").unwrap();
+        assert_eq!(got, (URL, Some(9..30)));
+
+        let got = split_fragment("https://example.com/src/lib.rs#L7").unwrap();
+        assert_eq!(got, (URL, Some(6..7)));
+
+        let got = split_fragment(URL).unwrap();
+        assert_eq!(got, (URL, None));
+
+        assert!(split_fragment("https://example.com/src/lib.rs#L30-L10").is_err());
+        assert!(split_fragment("https://example.com/src/lib.rs#readme").is_err());
+    }
+
+    #[test]
+    fn offline_includes_come_from_the_cache() {
+        let temp = TempDir::new("mdbook").unwrap();
+        let cache = Cache::new(temp.path());
+        cache.insert(URL, "one\ntwo\nthree\n").unwrap();
+        let remote = RemoteIncludes::new(cache, true);
+
+        let got = remote.include("https://example.com/src/lib.rs#L2-L3", &[]).unwrap();
+        assert_eq!(got, "two\nthree");
+
+        assert!(remote.include("https://example.com/missing.rs", &[]).is_err());
+    }
+
+    #[test]
+    fn outdated_includes_fall_back_to_the_cache_when_the_download_fails() {
+        let temp = TempDir::new("mdbook").unwrap();
+        let cache = Cache::new(temp.path());
+        let url = "https://example.invalid/src/lib.rs";
+        cache.insert(url, "cached").unwrap();
+        let remote = RemoteIncludes {
+            cache: cache,
+            offline: false,
+            refresh_after: Duration::from_secs(0),
+        };
+
+        assert!(remote.is_outdated(url));
+        assert_eq!(remote.include(url, &[]).unwrap(), "cached");
+
+        // a pinned include only accepts a copy which matches
+        let pin = "sha256=0000000000000000000000000000000000000000000000000000000000000000";
+        assert!(remote.include(url, &[pin]).is_err());
+    }
+
+    #[test]
+    fn pinned_includes_are_checked() {
+        let temp = TempDir::new("mdbook").unwrap();
+        let cache = Cache::new(temp.path());
+        cache.insert(URL, "abc").unwrap();
+        let remote = RemoteIncludes::new(cache, true);
+
+        let good = "sha256=ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+        assert_eq!(remote.include(URL, &[good]).unwrap(), "abc");
+
+        let bad = "sha256=0000000000000000000000000000000000000000000000000000000000000000";
+        assert!(remote.include(URL, &[bad]).is_err());
+    }
+}
@@ -0,0 +1,254 @@
+//! Rendering CSV (or TSV) files as tables, for the `{{#table}}` link.
+
+use utils::escape_html;
+use errors::*;
+
+/// Render the contents of a CSV file as a table.
+///
+/// The properties of the link can give the alignment of the columns (e.g.
+/// `align=lcr` for left, center and right) and `no-header` when the first
+/// row is data instead of the columns' titles.
+///
+/// The table is written in markdown when it has a header and
+/// `markdown_tables` is set, and in HTML otherwise. Either way the cells are
+/// shown as they are in the file.
+pub fn render_table(
+    contents: &str,
+    delimiter: char,
+    props: &[&str],
+    markdown_tables: bool,
+) -> Result<String> {
+    let rows = parse(contents, delimiter)?;
+    let columns = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+    if columns == 0 {
+        bail!("The table is empty");
+    }
+
+    let mut alignments = Vec::new();
+    let mut header = true;
+
+    for prop in props {
+        if prop.starts_with("align=") {
+            for letter in prop["align=".len()..].chars() {
+                alignments.push(match letter {
+                    'l' => Alignment::Left,
+                    'c' => Alignment::Center,
+                    'r' => Alignment::Right,
+                    '-' => Alignment::None,
+                    other => bail!("Unknown alignment '{}', expected one of l, c, r or -", other),
+                });
+            }
+        } else if *prop == "no-header" {
+            header = false;
+        } else {
+            bail!("Unknown table property \"{}\"", prop);
+        }
+    }
+    alignments.resize(columns, Alignment::None);
+
+    if header && markdown_tables {
+        Ok(markdown_table(&rows, &alignments))
+    } else {
+        // markdown tables always have a header
+        Ok(html_table(&rows, &alignments, header))
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Alignment {
+    None,
+    Left,
+    Center,
+    Right,
+}
+
+fn markdown_table(rows: &[Vec<String>], alignments: &[Alignment]) -> String {
+    let mut table = String::new();
+
+    for (i, row) in rows.iter().enumerate() {
+        table.push('|');
+        for column in 0..alignments.len() {
+            let cell = row.get(column).map(|c| c.as_str()).unwrap_or("");
+            table.push(' ');
+            table.push_str(&escape_markdown(cell));
+            table.push_str(" |");
+        }
+        table.push('\n');
+
+        if i == 0 {
+            table.push('|');
+            for alignment in alignments {
+                table.push_str(match *alignment {
+                    Alignment::None => "---|",
+                    Alignment::Left => ":--|",
+                    Alignment::Center => ":-:|",
+                    Alignment::Right => "--:|",
+                });
+            }
+            table.push('\n');
+        }
+    }
+
+    table
+}
+
+fn html_table(rows: &[Vec<String>], alignments: &[Alignment], header: bool) -> String {
+    let mut table = String::from("<table>\n");
+
+    for (i, row) in rows.iter().enumerate() {
+        let tag = if header && i == 0 { "th" } else { "td" };
+        table.push_str("<tr>");
+        for (column, alignment) in alignments.iter().enumerate() {
+            let cell = row.get(column).map(|c| c.as_str()).unwrap_or("");
+            let style = match *alignment {
+                Alignment::None => "",
+                Alignment::Left => " style=\"text-align: left\"",
+                Alignment::Center => " style=\"text-align: center\"",
+                Alignment::Right => " style=\"text-align: right\"",
+            };
+            table.push_str(&format!("<{0}{1}>{2}</{0}>", tag, style, escape(cell)));
+        }
+        table.push_str("</tr>\n");
+    }
+
+    table.push_str("</table>\n");
+    table
+}
+
+fn escape(text: &str) -> String {
+    escape_html(text).replace('\n', "<br>")
+}
+
+/// Escape a cell of a markdown table, so it's shown the same as in an HTML
+/// table instead of being rendered as markdown.
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::new();
+
+    for c in escape(text).chars() {
+        match c {
+            // a `|` would end the cell, even when it's escaped
+            '|' => escaped.push_str("&#124;"),
+            '\\' | '`' | '*' | '_' | '[' | ']' | '~' | '!' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            _ => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// Split CSV into rows of cells. Cells can be quoted (with `""` for a quote)
+/// to contain the delimiter or line breaks.
+fn parse(contents: &str, delimiter: char) -> Result<Vec<Vec<String>>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut cell = String::new();
+    let mut quoted = false;
+    let mut line = 1;
+    let mut chars = contents.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if quoted {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    cell.push('"');
+                }
+                '"' => quoted = false,
+                '\n' => {
+                    line += 1;
+                    cell.push(c);
+                }
+                _ => cell.push(c),
+            }
+        } else if c == delimiter {
+            row.push(cell.trim().to_string());
+            cell.clear();
+        } else if c == '"' && cell.trim().is_empty() {
+            quoted = true;
+            cell.clear();
+        } else if c == '\n' {
+            line += 1;
+            row.push(cell.trim().to_string());
+            cell.clear();
+            rows.push(row);
+            row = Vec::new();
+        } else if c != '\r' {
+            cell.push(c);
+        }
+    }
+
+    if quoted {
+        bail!("Unclosed quote at line {}", line);
+    }
+    if !cell.trim().is_empty() || !row.is_empty() {
+        row.push(cell.trim().to_string());
+        rows.push(row);
+    }
+
+    // blank lines don't make rows
+    rows.retain(|row| row.len() > 1 || row.iter().any(|cell| !cell.is_empty()));
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quoted_cells_can_contain_delimiters_and_quotes() {
+        let csv = "name,quote\r\n\"Doe, Jane\",\"She said \"\"hi\"\"\"\n\nBob,\"two\nlines\"\n";
+
+        let got = parse(csv, ',').unwrap();
+
+        assert_eq!(
+            got,
+            vec![
+                vec!["name".to_string(), "quote".to_string()],
+                vec!["Doe, Jane".to_string(), "She said \"hi\"".to_string()],
+                vec!["Bob".to_string(), "two\nlines".to_string()],
+            ]
+        );
+    }
+
+    #[test]
+    fn csv_becomes_a_markdown_table() {
+        let csv = "Item\tPrice\tNote\nTea\t2\ta|b\nCake\t3.50\t<b>*new*</b>\n";
+
+        let got = render_table(csv, '\t', &["align=lr"], true).unwrap();
+
+        assert_eq!(
+            got,
+            "| Item | Price | Note |\n|:--|--:|---|\n| Tea | 2 | a&#124;b |\n\
+             | Cake | 3.50 | &lt;b&gt;\\*new\\*&lt;/b&gt; |\n"
+        );
+    }
+
+    #[test]
+    fn tables_are_html_when_markdown_tables_are_disabled() {
+        let got = render_table("a,b\n1,2\n", ',', &[], false).unwrap();
+
+        assert_eq!(
+            got,
+            "<table>\n<tr><th>a</th><th>b</th></tr>\n<tr><td>1</td><td>2</td></tr>\n</table>\n"
+        );
+    }
+
+    #[test]
+    fn tables_without_a_header_are_html() {
+        let got = render_table("1,<2>\n", ',', &["no-header", "align=-c"], true).unwrap();
+
+        assert_eq!(
+            got,
+            "<table>\n<tr><td>1</td><td style=\"text-align: center\">&lt;2&gt;</td></tr>\n\
+             </table>\n"
+        );
+    }
+
+    #[test]
+    fn unclosed_quotes_are_an_error() {
+        assert!(parse("a,\"b\nc\n", ',').is_err());
+    }
+}
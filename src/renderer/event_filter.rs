@@ -0,0 +1,313 @@
+use std::borrow::Cow;
+use std::path::PathBuf;
+use std::process::Command;
+use pulldown_cmark::{Alignment, Event, Tag};
+use serde_json;
+
+use book::Chapter;
+use utils::process::{self, pipe_through};
+use errors::*;
+
+/// A transformation of a chapter's markdown, applied by the HTML renderer
+/// after the markdown is parsed and before it's turned into HTML. This makes
+/// it possible to support custom syntax without forking the renderer.
+///
+/// Filters are registered with [`HtmlHandlebars::with_event_filter()`], or
+/// given as commands in `output.html.event-filters` (see
+/// [`CmdEventFilter`]).
+///
+/// ```rust,no_run
+/// # extern crate mdbook;
+/// # extern crate pulldown_cmark;
+/// use std::borrow::Cow;
+/// use mdbook::MDBook;
+/// use mdbook::book::Chapter;
+/// use mdbook::errors::Result;
+/// use mdbook::renderer::{EventFilter, HtmlHandlebars};
+/// use pulldown_cmark::Event;
+///
+/// /// Shouts every piece of text.
+/// struct Shout;
+///
+/// impl EventFilter for Shout {
+///     fn name(&self) -> &str {
+///         "shout"
+///     }
+///
+///     fn filter<'a>(&self, _ch: &Chapter, events: Vec<Event<'a>>) -> Result<Vec<Event<'a>>> {
+///         Ok(events.into_iter()
+///             .map(|event| match event {
+///                 Event::Text(text) => Event::Text(Cow::from(text.to_uppercase())),
+///                 event => event,
+///             })
+///             .collect())
+///     }
+/// }
+///
+/// # fn main() {
+/// MDBook::load("/path/to/book")
+///     .unwrap()
+///     .with_renderer(HtmlHandlebars::new().with_event_filter(Shout))
+///     .build()
+///     .unwrap();
+/// # }
+/// ```
+///
+/// [`HtmlHandlebars::with_event_filter()`]: struct.HtmlHandlebars.html#method.with_event_filter
+/// [`CmdEventFilter`]: struct.CmdEventFilter.html
+pub trait EventFilter {
+    /// The filter's name.
+    fn name(&self) -> &str;
+
+    /// Transform the events of a chapter.
+    fn filter<'a>(&self, ch: &Chapter, events: Vec<Event<'a>>) -> Result<Vec<Event<'a>>>;
+}
+
+/// An `EventFilter` which shells out to a command.
+///
+/// The command is given a JSON object with the chapter's `name`, its `path`
+/// and its `events` on stdin, and has to print the transformed list of
+/// events as JSON. Each event is an object with a `type` (`start`, `end`,
+/// `text`, `html`, `inline-html`, `footnote-reference`, `soft-break` or
+/// `hard-break`). Text-like events have a `text` and `start`/`end` events
+/// have a `tag`, which is itself an object with a `type` (e.g. `paragraph`,
+/// `header` with a `level`, or `link` with a `dest` and `title`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct CmdEventFilter {
+    cmd: String,
+}
+
+impl CmdEventFilter {
+    /// Create a new `CmdEventFilter` which will invoke the provided `cmd`
+    /// string.
+    pub fn new(cmd: String) -> CmdEventFilter {
+        CmdEventFilter { cmd }
+    }
+
+    fn compose_command(&self) -> Result<Command> {
+        process::command(&self.cmd, &[])
+            .chain_err(|| format!("Invalid event filter \"{}\"", self.cmd))
+    }
+}
+
+impl EventFilter for CmdEventFilter {
+    fn name(&self) -> &str {
+        &self.cmd
+    }
+
+    fn filter<'a>(&self, ch: &Chapter, events: Vec<Event<'a>>) -> Result<Vec<Event<'a>>> {
+        let input = FilterInput {
+            name: &ch.name,
+            path: &ch.path,
+            events: events.iter().map(JsonEvent::from_event).collect(),
+        };
+
+        let input = serde_json::to_vec(&input).chain_err(|| "Unable to serialize the events")?;
+        let output = pipe_through(&mut self.compose_command()?, input)
+            .chain_err(|| format!("Unable to run the \"{}\" event filter", self.cmd))?;
+
+        if !output.status.success() {
+            bail!("The \"{}\" event filter failed on {}", self.cmd, ch.path.display());
+        }
+
+        let events: Vec<JsonEvent> = serde_json::from_slice(&output.stdout).chain_err(|| {
+            format!("The \"{}\" event filter printed invalid events", self.cmd)
+        })?;
+
+        Ok(events.into_iter().map(JsonEvent::into_event).collect())
+    }
+}
+
+#[derive(Serialize)]
+struct FilterInput<'a> {
+    name: &'a str,
+    path: &'a PathBuf,
+    events: Vec<JsonEvent>,
+}
+
+/// A markdown `Event`, the way it's given to (and read back from) event
+/// filter commands.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+enum JsonEvent {
+    Start { tag: JsonTag },
+    End { tag: JsonTag },
+    Text { text: String },
+    Html { text: String },
+    InlineHtml { text: String },
+    FootnoteReference { text: String },
+    SoftBreak,
+    HardBreak,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+enum JsonTag {
+    Paragraph,
+    Rule,
+    Header { level: i32 },
+    BlockQuote,
+    CodeBlock { info: String },
+    List { start: Option<usize> },
+    Item,
+    FootnoteDefinition { name: String },
+    Table { alignments: Vec<JsonAlignment> },
+    TableHead,
+    TableRow,
+    TableCell,
+    Emphasis,
+    Strong,
+    Code,
+    Link { dest: String, title: String },
+    Image { dest: String, title: String },
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum JsonAlignment {
+    None,
+    Left,
+    Center,
+    Right,
+}
+
+impl JsonEvent {
+    fn from_event(event: &Event) -> JsonEvent {
+        match *event {
+            Event::Start(ref tag) => JsonEvent::Start { tag: JsonTag::from_tag(tag) },
+            Event::End(ref tag) => JsonEvent::End { tag: JsonTag::from_tag(tag) },
+            Event::Text(ref text) => JsonEvent::Text { text: text.to_string() },
+            Event::Html(ref text) => JsonEvent::Html { text: text.to_string() },
+            Event::InlineHtml(ref text) => JsonEvent::InlineHtml { text: text.to_string() },
+            Event::FootnoteReference(ref text) => {
+                JsonEvent::FootnoteReference { text: text.to_string() }
+            }
+            Event::SoftBreak => JsonEvent::SoftBreak,
+            Event::HardBreak => JsonEvent::HardBreak,
+        }
+    }
+
+    fn into_event(self) -> Event<'static> {
+        match self {
+            JsonEvent::Start { tag } => Event::Start(tag.into_tag()),
+            JsonEvent::End { tag } => Event::End(tag.into_tag()),
+            JsonEvent::Text { text } => Event::Text(Cow::from(text)),
+            JsonEvent::Html { text } => Event::Html(Cow::from(text)),
+            JsonEvent::InlineHtml { text } => Event::InlineHtml(Cow::from(text)),
+            JsonEvent::FootnoteReference { text } => Event::FootnoteReference(Cow::from(text)),
+            JsonEvent::SoftBreak => Event::SoftBreak,
+            JsonEvent::HardBreak => Event::HardBreak,
+        }
+    }
+}
+
+impl JsonTag {
+    fn from_tag(tag: &Tag) -> JsonTag {
+        match *tag {
+            Tag::Paragraph => JsonTag::Paragraph,
+            Tag::Rule => JsonTag::Rule,
+            Tag::Header(level) => JsonTag::Header { level },
+            Tag::BlockQuote => JsonTag::BlockQuote,
+            Tag::CodeBlock(ref info) => JsonTag::CodeBlock { info: info.to_string() },
+            Tag::List(start) => JsonTag::List { start },
+            Tag::Item => JsonTag::Item,
+            Tag::FootnoteDefinition(ref name) => {
+                JsonTag::FootnoteDefinition { name: name.to_string() }
+            }
+            Tag::Table(ref alignments) => JsonTag::Table {
+                alignments: alignments.iter().map(JsonAlignment::from_alignment).collect(),
+            },
+            Tag::TableHead => JsonTag::TableHead,
+            Tag::TableRow => JsonTag::TableRow,
+            Tag::TableCell => JsonTag::TableCell,
+            Tag::Emphasis => JsonTag::Emphasis,
+            Tag::Strong => JsonTag::Strong,
+            Tag::Code => JsonTag::Code,
+            Tag::Link(ref dest, ref title) => JsonTag::Link {
+                dest: dest.to_string(),
+                title: title.to_string(),
+            },
+            Tag::Image(ref dest, ref title) => JsonTag::Image {
+                dest: dest.to_string(),
+                title: title.to_string(),
+            },
+        }
+    }
+
+    fn into_tag(self) -> Tag<'static> {
+        match self {
+            JsonTag::Paragraph => Tag::Paragraph,
+            JsonTag::Rule => Tag::Rule,
+            JsonTag::Header { level } => Tag::Header(level),
+            JsonTag::BlockQuote => Tag::BlockQuote,
+            JsonTag::CodeBlock { info } => Tag::CodeBlock(Cow::from(info)),
+            JsonTag::List { start } => Tag::List(start),
+            JsonTag::Item => Tag::Item,
+            JsonTag::FootnoteDefinition { name } => Tag::FootnoteDefinition(Cow::from(name)),
+            JsonTag::Table { alignments } => {
+                Tag::Table(alignments.into_iter().map(JsonAlignment::into_alignment).collect())
+            }
+            JsonTag::TableHead => Tag::TableHead,
+            JsonTag::TableRow => Tag::TableRow,
+            JsonTag::TableCell => Tag::TableCell,
+            JsonTag::Emphasis => Tag::Emphasis,
+            JsonTag::Strong => Tag::Strong,
+            JsonTag::Code => Tag::Code,
+            JsonTag::Link { dest, title } => Tag::Link(Cow::from(dest), Cow::from(title)),
+            JsonTag::Image { dest, title } => Tag::Image(Cow::from(dest), Cow::from(title)),
+        }
+    }
+}
+
+impl JsonAlignment {
+    fn from_alignment(alignment: &Alignment) -> JsonAlignment {
+        match *alignment {
+            Alignment::None => JsonAlignment::None,
+            Alignment::Left => JsonAlignment::Left,
+            Alignment::Center => JsonAlignment::Center,
+            Alignment::Right => JsonAlignment::Right,
+        }
+    }
+
+    fn into_alignment(self) -> Alignment {
+        match self {
+            JsonAlignment::None => Alignment::None,
+            JsonAlignment::Left => Alignment::Left,
+            JsonAlignment::Center => Alignment::Center,
+            JsonAlignment::Right => Alignment::Right,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pulldown_cmark::{Options, Parser, OPTION_ENABLE_FOOTNOTES, OPTION_ENABLE_TABLES};
+
+    #[test]
+    fn events_survive_the_round_trip_through_json() {
+        let src = "# Title\n\nSome *text* with [a link](x.html \"X\")[^1].\n\n\
+                   | a | b |\n|:--|--:|\n| 1 | 2 |\n\n```rust\nfn main() {}\n```\n\n\
+                   <div>html</div>\n\n[^1]: A footnote.\n";
+        let mut opts = Options::empty();
+        opts.insert(OPTION_ENABLE_TABLES);
+        opts.insert(OPTION_ENABLE_FOOTNOTES);
+        let events: Vec<Event> = Parser::new_ext(src, opts).collect();
+
+        let json: Vec<JsonEvent> = events.iter().map(JsonEvent::from_event).collect();
+        let json = serde_json::to_string(&json).unwrap();
+        let parsed: Vec<JsonEvent> = serde_json::from_str(&json).unwrap();
+        let got: Vec<Event> = parsed.into_iter().map(JsonEvent::into_event).collect();
+
+        assert_eq!(got, events);
+    }
+
+    #[test]
+    fn events_are_tagged_with_their_type() {
+        let event = Event::Start(Tag::Header(2));
+
+        let got = serde_json::to_value(&JsonEvent::from_event(&event)).unwrap();
+
+        assert_eq!(got, json!({"type": "start", "tag": {"type": "header", "level": 2}}));
+    }
+}
@@ -0,0 +1,240 @@
+//! External commands which turn some of the book's files into others while
+//! they're copied to the build directory, e.g. compiling `*.scss` to CSS.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use glob::Pattern;
+
+use config::AssetProcessorConfig;
+use utils::process;
+use errors::*;
+
+/// The processors of `output.html.asset-processors`, with their patterns
+/// parsed.
+pub struct AssetProcessors<'a> {
+    processors: Vec<(Pattern, &'a AssetProcessorConfig)>,
+}
+
+impl<'a> AssetProcessors<'a> {
+    pub fn new<I>(config: I) -> Result<AssetProcessors<'a>>
+    where
+        I: IntoIterator<Item = (&'a String, &'a AssetProcessorConfig)>,
+    {
+        let mut processors = Vec::new();
+        for (pattern, processor) in config {
+            let pattern = Pattern::new(pattern)
+                .chain_err(|| format!("Invalid asset processor pattern \"{}\"", pattern))?;
+            processors.push((pattern, processor));
+        }
+
+        Ok(AssetProcessors { processors: processors })
+    }
+
+    /// The processor for a file, given its path relative to the directory
+    /// being copied. When more than one pattern matches, the first one (in
+    /// alphabetical order) wins.
+    fn find(&self, path: &Path) -> Option<&'a AssetProcessorConfig> {
+        self.processors
+            .iter()
+            .find(|&&(ref pattern, _)| pattern.matches_path(path))
+            .map(|&(_, processor)| processor)
+    }
+
+    /// Run the processors over the files of `from` which match them, writing
+    /// their outputs to the same place in `to`, inside `prefix` (e.g. `theme`
+    /// for a theme directory outside the source directory). The files are
+    /// matched by their path in `to`, and removed from there if they were
+    /// copied along with the rest.
+    pub fn run(&self, from: &Path, to: &Path, prefix: &Path) -> Result<()> {
+        if self.processors.is_empty() || !from.is_dir() {
+            return Ok(());
+        }
+
+        let mut files = Vec::new();
+        list_files(from, to, &mut files)?;
+
+        for file in files {
+            let relative = match file.strip_prefix(from) {
+                Ok(relative) => prefix.join(relative),
+                Err(_) => continue,
+            };
+            if let Some(processor) = self.find(&relative) {
+                let output = output_path(&to.join(&relative), processor);
+                if is_up_to_date(&file, &output) {
+                    trace!("{} is up to date", output.display());
+                } else {
+                    run_processor(processor, &file, &output)?;
+                }
+
+                let copied = to.join(&relative);
+                if copied != output && copied.is_file() {
+                    fs::remove_file(&copied)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Where the output of a processed file goes, with the processor's
+/// `extension` if it has one.
+fn output_path(path: &Path, processor: &AssetProcessorConfig) -> PathBuf {
+    match processor.extension {
+        Some(ref extension) => path.with_extension(extension),
+        None => path.to_path_buf(),
+    }
+}
+
+/// Whether the output of a processed file was written after the file was
+/// last changed, the same way `copy_file_if_changed()` skips copying.
+fn is_up_to_date(input: &Path, output: &Path) -> bool {
+    match (fs::metadata(input), fs::metadata(output)) {
+        (Ok(input), Ok(output)) => match (input.modified(), output.modified()) {
+            (Ok(input), Ok(output)) => output >= input,
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+fn run_processor(processor: &AssetProcessorConfig, input: &Path, output: &Path) -> Result<()> {
+    if let Some(parent) = output.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let input_arg = input.to_string_lossy();
+    let output_arg = output.to_string_lossy();
+    let mut cmd = process::command(
+        &processor.command,
+        &[("{input}", &*input_arg), ("{output}", &*output_arg)],
+    ).chain_err(|| format!("Invalid asset processor for {}", input.display()))?;
+
+    debug!("Running {:?}", cmd);
+    let result = cmd.output()
+        .chain_err(|| format!("Unable to run `{}`", processor.command))?;
+    if !result.status.success() {
+        bail!(ErrorKind::Subprocess(
+            format!("`{}` failed for {}", processor.command, input.display()),
+            result
+        ));
+    }
+
+    Ok(())
+}
+
+/// The files in `dir`, leaving out the build directory if it's in there.
+fn list_files(dir: &Path, destination: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path == destination {
+            continue;
+        }
+        if path.is_dir() {
+            list_files(&path, destination, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::fs::File;
+    use std::io::{Read, Write};
+    use tempdir::TempDir;
+
+    #[test]
+    fn processors_are_picked_by_pattern() {
+        let mut config = BTreeMap::new();
+        config.insert(
+            String::from("*.scss"),
+            AssetProcessorConfig {
+                command: String::from("sass {input} {output}"),
+                extension: Some(String::from("css")),
+            },
+        );
+        config.insert(
+            String::from("scripts/*.ts"),
+            AssetProcessorConfig {
+                command: String::from("esbuild {input} --outfile={output}"),
+                extension: Some(String::from("js")),
+            },
+        );
+        let processors = AssetProcessors::new(&config).unwrap();
+
+        let scss = processors.find(Path::new("theme/style.scss")).unwrap();
+        assert_eq!(scss.command, "sass {input} {output}");
+        assert_eq!(
+            output_path(Path::new("book/theme/style.scss"), scss),
+            Path::new("book/theme/style.css")
+        );
+        assert!(processors.find(Path::new("scripts/main.ts")).is_some());
+        assert!(processors.find(Path::new("main.ts")).is_none());
+        assert!(processors.find(Path::new("style.css")).is_none());
+    }
+
+    #[test]
+    fn invalid_patterns_are_an_error() {
+        let mut config = BTreeMap::new();
+        config.insert(String::from("[*.scss"), AssetProcessorConfig::default());
+
+        assert!(AssetProcessors::new(&config).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn processed_files_replace_their_copies_unless_they_are_up_to_date() {
+        let temp = TempDir::new("mdbook").unwrap();
+        let src = temp.path().join("src");
+        let book = temp.path().join("book");
+        fs::create_dir_all(src.join("theme")).unwrap();
+        fs::create_dir_all(book.join("theme")).unwrap();
+        File::create(src.join("theme").join("style.scss"))
+            .unwrap()
+            .write_all(b"body {}")
+            .unwrap();
+        // copied over along with the other files
+        File::create(book.join("theme").join("style.scss")).unwrap();
+
+        let mut config = BTreeMap::new();
+        config.insert(
+            String::from("theme/*.scss"),
+            AssetProcessorConfig {
+                command: String::from("cp {input} {output}"),
+                extension: Some(String::from("css")),
+            },
+        );
+        let processors = AssetProcessors::new(&config).unwrap();
+        let read_output = || {
+            let mut output = String::new();
+            File::open(book.join("theme").join("style.css"))
+                .unwrap()
+                .read_to_string(&mut output)
+                .unwrap();
+            output
+        };
+
+        processors.run(&src, &book, Path::new("")).unwrap();
+        assert_eq!(read_output(), "body {}");
+        assert!(!book.join("theme").join("style.scss").exists());
+
+        File::create(book.join("theme").join("style.css"))
+            .unwrap()
+            .write_all(b"already built")
+            .unwrap();
+        processors.run(&src, &book, Path::new("")).unwrap();
+        assert_eq!(read_output(), "already built");
+
+        // a theme outside the source directory is matched as `theme/`
+        fs::remove_file(book.join("theme").join("style.css")).unwrap();
+        processors
+            .run(&src.join("theme"), &book, Path::new("theme"))
+            .unwrap();
+        assert_eq!(read_output(), "body {}");
+    }
+}
@@ -0,0 +1,253 @@
+//! Checking the images and other files which are copied along with the book.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use pulldown_cmark::{Event, Parser, Tag};
+use regex::{Captures, Regex};
+
+use book::{Chapter, MountedBooks};
+use diagnostics::{self, Diagnostic, Level};
+use utils::fs::file_to_string;
+use utils::links::{find_line, is_external, normalize};
+
+/// Warn about the local images used by a chapter which aren't in the source
+/// directory (or in the one of the book it's mounted from).
+pub fn check_images(ch: &Chapter, src_dir: &Path, mounts: &MountedBooks) {
+    let images = images(&ch.content);
+    if images.is_empty() {
+        return;
+    }
+
+    let source_path = mounts.source_path(src_dir, &ch.path);
+    let source = file_to_string(&source_path).unwrap_or_default();
+
+    for image in images {
+        let exists = resolve(&image)
+            .map(|path| mounts.source_path(src_dir, &path).is_file())
+            .unwrap_or(false);
+
+        if !exists {
+            let message = format!("image \"{}\" doesn't exist", image);
+            let line = find_line(&source, &image);
+            diagnostics::report(Diagnostic::new(Level::Warning, message).at(&source_path, line));
+        }
+    }
+}
+
+/// Warn about the files in the source directory (other than the chapters
+/// themselves) which are bigger than `max_size` kilobytes, skipping the
+/// `destination` in case it's inside the source directory.
+pub fn check_asset_sizes(src_dir: &Path, destination: &Path, max_size: u64) {
+    let entries = match fs::read_dir(src_dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        let metadata = match entry.metadata() {
+            Ok(metadata) => metadata,
+            Err(_) => continue,
+        };
+
+        if metadata.is_dir() {
+            if path != destination {
+                check_asset_sizes(&path, destination, max_size);
+            }
+        } else if path.extension().map_or(true, |ext| ext != "md") {
+            let size = metadata.len() / 1024;
+            if size > max_size {
+                let message = format!(
+                    "{} KB is more than the `output.html.max-asset-size` of {} KB",
+                    size, max_size
+                );
+                diagnostics::report(Diagnostic::new(Level::Warning, message).at(path, None));
+            }
+        }
+    }
+}
+
+/// Replace the `<img>` tags pointing at local SVG files with the SVG markup
+/// itself, so the images can be styled by the theme (e.g. with
+/// `currentColor`) and their text can be selected.
+pub fn inline_svgs(html: &str, src_dir: &Path) -> String {
+    lazy_static! {
+        static ref IMG: Regex = Regex::new(r"<img\s[^>]*>").unwrap();
+        static ref SRC: Regex = Regex::new(r#"\ssrc="([^"]*)""#).unwrap();
+        static ref ALT: Regex = Regex::new(r#"\salt="([^"]*)""#).unwrap();
+    }
+
+    IMG.replace_all(html, |caps: &Captures| {
+        let img = &caps[0];
+        let svg = SRC.captures(img).and_then(|src| {
+            let src = &src[1];
+            if is_external(src) || !src.to_lowercase().ends_with(".svg") {
+                return None;
+            }
+            resolve(src).and_then(|path| file_to_string(src_dir.join(path)).ok())
+        });
+
+        match svg.as_ref().and_then(|svg| svg.find("<svg").map(|start| &svg[start..])) {
+            Some(svg) => {
+                // the alt text describes the image to screen readers
+                let label = match ALT.captures(img) {
+                    Some(ref alt) if !alt[1].is_empty() => {
+                        format!(" role=\"img\" aria-label=\"{}\"", &alt[1])
+                    }
+                    _ => String::new(),
+                };
+                format!("<svg{}{}", label, &svg[4..].trim_right())
+            }
+            None => img.to_string(),
+        }
+    }).into_owned()
+}
+
+/// The URLs on other sites which a page loads when it's opened: scripts,
+/// images, stylesheets and the like, but not the pages it links to.
+pub fn external_resources(html: &str) -> Vec<String> {
+    lazy_static! {
+        static ref EMBED: Regex = Regex::new(
+            r#"(?i)<(?:img|script|iframe|frame|audio|video|source|track|embed|input)\s[^>]*?\bsrc\s*=\s*["']?([^"'\s>]+)"#
+        ).unwrap();
+        static ref OBJECT: Regex =
+            Regex::new(r#"(?i)<object\s[^>]*?\bdata\s*=\s*["']?([^"'\s>]+)"#).unwrap();
+        static ref SRCSET: Regex = Regex::new(r#"(?i)\ssrcset\s*=\s*"([^"]*)""#).unwrap();
+        static ref LINK: Regex = Regex::new(r"(?i)<link\s[^>]*>").unwrap();
+        static ref REL: Regex = Regex::new(r#"(?i)\srel\s*=\s*["']?([^"'>]+)"#).unwrap();
+        static ref HREF: Regex = Regex::new(r#"(?i)\shref\s*=\s*["']?([^"'\s>]+)"#).unwrap();
+        static ref CSS_URL: Regex =
+            Regex::new(r#"(?i)url\(\s*["']?([^"')\s]+)"#).unwrap();
+        static ref CSS_IMPORT: Regex =
+            Regex::new(r#"(?i)@import\s+["']([^"']+)"#).unwrap();
+    }
+    // the kinds of `<link>` which make the browser fetch something, unlike
+    // e.g. `canonical` or `next`
+    const FETCHING_RELS: &[&str] = &[
+        "stylesheet", "icon", "preload", "prefetch", "preconnect", "dns-prefetch",
+        "modulepreload", "manifest",
+    ];
+
+    let mut urls: Vec<String> = Vec::new();
+    for regex in &[&*EMBED, &*OBJECT, &*CSS_URL, &*CSS_IMPORT] {
+        urls.extend(regex.captures_iter(html).map(|caps| caps[1].to_string()));
+    }
+    for caps in SRCSET.captures_iter(html) {
+        urls.extend(
+            caps[1]
+                .split(',')
+                .filter_map(|candidate| candidate.split_whitespace().next())
+                .map(|url| url.to_string()),
+        );
+    }
+    for link in LINK.find_iter(html) {
+        let link = link.as_str();
+        let fetches = REL.captures(link)
+            .map(|rel| {
+                rel[1]
+                    .split_whitespace()
+                    .any(|kind| FETCHING_RELS.contains(&kind.to_lowercase().as_str()))
+            })
+            .unwrap_or(false);
+        if let (true, Some(href)) = (fetches, HREF.captures(link)) {
+            urls.push(href[1].to_string());
+        }
+    }
+
+    urls.retain(|url| is_external(url) && !url.starts_with("data:"));
+    urls.sort();
+    urls.dedup();
+    urls
+}
+
+/// The destinations of the images in some markdown which point inside the
+/// book.
+fn images(content: &str) -> Vec<String> {
+    Parser::new(content)
+        .filter_map(|event| match event {
+            Event::Start(Tag::Image(dest, _)) => Some(dest.into_owned()),
+            _ => None,
+        })
+        .filter(|dest| !dest.is_empty() && !is_external(dest))
+        .collect()
+}
+
+/// Where an image is, relative to the source directory. Like every other
+/// link, images are relative to the root of the book (the theme sets a
+/// `<base>`).
+fn resolve(image: &str) -> Option<PathBuf> {
+    let path = image
+        .split(|c| c == '?' || c == '#')
+        .next()
+        .unwrap_or(image)
+        .trim_left_matches('/');
+
+    normalize(Path::new(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::io::Write;
+    use tempdir::TempDir;
+
+    #[test]
+    fn resources_from_other_sites_are_found() {
+        let html = r#"<link rel="stylesheet" href="book.css">
+<link href="https://fonts.example.com/css" rel="stylesheet">
+<link rel="canonical" href="https://example.com/book/">
+<script src="https://cdn.example.com/lib.js"></script>
+<script src="book.js"></script>
+<img src="//images.example.com/a.png" alt="">
+<img src="data:image/png;base64,AAAA">
+<img srcset="small.png 1x, https://images.example.com/big.png 2x">
+<div style="background: url('https://images.example.com/bg.png')"></div>
+<a href="https://example.com/elsewhere.html">a link</a>"#;
+
+        let got = external_resources(html);
+
+        assert_eq!(
+            got,
+            vec![
+                "//images.example.com/a.png",
+                "https://cdn.example.com/lib.js",
+                "https://fonts.example.com/css",
+                "https://images.example.com/bg.png",
+                "https://images.example.com/big.png",
+            ]
+        );
+    }
+
+    #[test]
+    fn only_local_images_are_checked() {
+        let content = "![local](img/cat.png) ![remote](https://example.com/dog.png)\n\n\
+                       [a link](not-an-image.png) ![inline](data:image/png;base64,AAAA)\n";
+
+        assert_eq!(images(content), vec![String::from("img/cat.png")]);
+    }
+
+    #[test]
+    fn local_svgs_are_inlined() {
+        let temp = TempDir::new("mdbook").unwrap();
+        let mut f = File::create(temp.path().join("logo.svg")).unwrap();
+        f.write_all(b"<?xml version=\"1.0\"?>\n<svg viewBox=\"0 0 1 1\"><path/></svg>\n")
+            .unwrap();
+
+        let html = "<p><img src=\"logo.svg\" alt=\"Logo\" /> \
+                    <img src=\"missing.svg\" alt=\"\" /> <img src=\"photo.png\" alt=\"\" /></p>";
+
+        assert_eq!(
+            inline_svgs(html, temp.path()),
+            "<p><svg role=\"img\" aria-label=\"Logo\" viewBox=\"0 0 1 1\"><path/></svg> \
+             <img src=\"missing.svg\" alt=\"\" /> <img src=\"photo.png\" alt=\"\" /></p>"
+        );
+    }
+
+    #[test]
+    fn images_are_relative_to_the_book() {
+        assert_eq!(resolve("/img/./cat.png?v=2"), Some(PathBuf::from("img/cat.png")));
+        assert_eq!(resolve("img/../dog.png#top"), Some(PathBuf::from("dog.png")));
+        assert_eq!(resolve("../outside.png"), None);
+    }
+}
@@ -0,0 +1,192 @@
+//! Finding the people who contributed to a chapter from the git history of
+//! its source file.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Someone who committed changes to a chapter's source file.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Contributor {
+    pub name: String,
+    pub email: String,
+    /// How many commits they made to the file.
+    pub commits: usize,
+}
+
+/// The git history of the directories a book's chapters are in (its source
+/// directory and the books mounted in it), read with a single `git log` per
+/// directory the first time one of its chapters is asked about.
+#[derive(Debug, Default)]
+pub struct History {
+    dirs: Vec<PathBuf>,
+    /// The authors of each file's commits, newest first, keyed by the
+    /// directory and the file's path in it.
+    logs: RefCell<HashMap<PathBuf, HashMap<String, Vec<(String, String)>>>>,
+}
+
+impl History {
+    pub fn new(dirs: Vec<PathBuf>) -> History {
+        History {
+            dirs: dirs,
+            logs: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// The contributors to the file at `path`, following it across renames,
+    /// with the ones who made the most commits first.
+    ///
+    /// Files which aren't tracked by git (or when git isn't installed) simply
+    /// don't have any contributors.
+    pub fn contributors(&self, path: &Path) -> Vec<Contributor> {
+        // the innermost directory, in case a mounted book is inside the
+        // source directory
+        let dir = match self.dirs
+            .iter()
+            .filter(|dir| path.starts_with(dir))
+            .max_by_key(|dir| dir.components().count())
+        {
+            Some(dir) => dir,
+            None => return Vec::new(),
+        };
+        let relative = path.strip_prefix(dir)
+            .expect("The directory contains the path")
+            .to_string_lossy()
+            .replace('\\', "/");
+
+        let mut logs = self.logs.borrow_mut();
+        let log = logs.entry(dir.clone()).or_insert_with(|| read_log(dir));
+        match log.get(&relative) {
+            Some(authors) => tally(authors),
+            None => Vec::new(),
+        }
+    }
+}
+
+/// The history of every file in `dir`, with their paths relative to it.
+fn read_log(dir: &Path) -> HashMap<String, Vec<(String, String)>> {
+    let mut cmd = Command::new("git");
+    cmd.args(&["-c", "core.quotepath=off", "log", "--format=%x00%aN%x09%aE"])
+        .args(&["--name-status", "-M", "--relative", "--", "."])
+        .current_dir(dir);
+    debug!("Running {:?}", cmd);
+
+    match cmd.output() {
+        Ok(ref output) if output.status.success() => {
+            parse_log(&String::from_utf8_lossy(&output.stdout))
+        }
+        Ok(output) => {
+            debug!(
+                "Unable to get the history of {}, {}",
+                dir.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+            HashMap::new()
+        }
+        Err(e) => {
+            debug!("Unable to run git, {}", e);
+            HashMap::new()
+        }
+    }
+}
+
+/// Parse the output of `git log --format=%x00%aN%x09%aE --name-status -M`,
+/// newest commit first, into the authors of each file. Commits from before a
+/// file was renamed count towards its current name.
+fn parse_log(log: &str) -> HashMap<String, Vec<(String, String)>> {
+    let mut files: HashMap<String, Vec<(String, String)>> = HashMap::new();
+    // the names files had further back in history, and their current ones
+    let mut renamed: HashMap<String, String> = HashMap::new();
+    let mut author: Option<(String, String)> = None;
+
+    for line in log.lines() {
+        if line.starts_with('\0') {
+            let mut parts = line[1..].splitn(2, '\t');
+            let name = parts.next().unwrap_or("").trim();
+            let email = parts.next().unwrap_or("").trim();
+            author = if name.is_empty() {
+                None
+            } else {
+                Some((name.to_string(), email.to_string()))
+            };
+            continue;
+        }
+
+        let author = match author {
+            Some(ref author) => author,
+            None => continue,
+        };
+        let fields: Vec<&str> = line.split('\t').collect();
+        let (old, new) = match fields.len() {
+            2 => (None, fields[1]),
+            3 if fields[0].starts_with('R') => (Some(fields[1]), fields[2]),
+            _ => continue,
+        };
+
+        let current = renamed.get(new).cloned().unwrap_or_else(|| new.to_string());
+        files
+            .entry(current.clone())
+            .or_insert_with(Vec::new)
+            .push(author.clone());
+        if let Some(old) = old {
+            renamed.insert(old.to_string(), current);
+        }
+    }
+
+    files
+}
+
+/// Count the commits of each author, most commits first.
+fn tally(authors: &[(String, String)]) -> Vec<Contributor> {
+    let mut contributors: Vec<Contributor> = Vec::new();
+
+    for &(ref name, ref email) in authors {
+        if let Some(contributor) = contributors.iter_mut().find(|c| c.name == *name) {
+            contributor.commits += 1;
+            continue;
+        }
+        contributors.push(Contributor {
+            name: name.clone(),
+            email: email.clone(),
+            commits: 1,
+        });
+    }
+
+    // a stable sort, so people with as many commits stay in the order of
+    // their latest change
+    contributors.sort_by(|a, b| b.commits.cmp(&a.commits));
+    contributors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn contributors_are_counted_and_ordered_by_commits() {
+        let log = "\0Ada\tada@example.com\n\
+                   \n\
+                   M\tintro.md\n\
+                   \0Grace\tgrace@example.com\n\
+                   \n\
+                   M\tintro.md\n\
+                   M\tsetup.md\n\
+                   \0Linus\tlinus@example.com\n\
+                   \n\
+                   R087\tintroduction.md\tintro.md\n\
+                   \0Grace\tgrace@example.com\n\
+                   \n\
+                   A\tintroduction.md\n";
+
+        let files = parse_log(log);
+        let got = tally(&files["intro.md"]);
+
+        let names: Vec<_> = got.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["Grace", "Ada", "Linus"]);
+        assert_eq!(got[0].commits, 2);
+        assert_eq!(got[0].email, "grace@example.com");
+        assert_eq!(files["setup.md"].len(), 1);
+        assert!(!files.contains_key("introduction.md"));
+    }
+}
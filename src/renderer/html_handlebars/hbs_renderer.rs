@@ -1,29 +1,46 @@
-use renderer::html_handlebars::helpers;
-use renderer::{RenderContext, Renderer};
-use book::{Book, BookItem, Chapter};
-use config::{Config, HtmlConfig, Playpen};
-use {theme, utils};
+use renderer::html_handlebars::{assets, contributors, helpers, offline, parts, robots,
+                                social_cards, theme_colors, web_app};
+use renderer::html_handlebars::asset_processors::AssetProcessors;
+use renderer::html_handlebars::repository::Repository;
+use renderer::{CmdEventFilter, EventFilter, RenderContext, Renderer};
+use book::{converted_chapters, Book, BookItem, Chapter, MountedBooks, SectionNumber, Stats};
+use config::{AnalyticsProvider, Config, HtmlConfig, MarkdownConfig, PartNavigation, Playpen,
+             RawHtml, SlugStyle};
+use {theme, timings, utils};
+use utils::escape_html;
 use theme::{playpen_editor, Theme};
 use errors::*;
 use regex::{Captures, Regex};
 
 #[allow(unused_imports)] use std::ascii::AsciiExt;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 use std::fs::{self, File};
-use std::io::{Read, Write};
+use std::io::{BufWriter, Read, Write};
 use std::collections::BTreeMap;
 use std::collections::HashMap;
 
 use handlebars::Handlebars;
+use pulldown_cmark::Event;
 
 use serde_json;
+use toml;
 
 #[derive(Default)]
-pub struct HtmlHandlebars;
+pub struct HtmlHandlebars {
+    event_filters: Vec<Box<EventFilter>>,
+}
 
 impl HtmlHandlebars {
     pub fn new() -> Self {
-        HtmlHandlebars
+        HtmlHandlebars::default()
+    }
+
+    /// Transform the markdown of every chapter with an `EventFilter`, after
+    /// the ones registered before it and before those from
+    /// `output.html.event-filters`.
+    pub fn with_event_filter<F: EventFilter + 'static>(mut self, filter: F) -> Self {
+        self.event_filters.push(Box::new(filter));
+        self
     }
 
     fn write_file<P: AsRef<Path>>(
@@ -34,23 +51,63 @@ impl HtmlHandlebars {
     ) -> Result<()> {
         let path = build_dir.join(filename);
 
-        utils::fs::create_file(&path)?
-            .write_all(content)
-            .map_err(|e| e.into())
+        // unchanged files are left alone, so watch-mode rebuilds only touch
+        // what actually changed
+        utils::fs::write_file_if_changed(&path, content)?;
+        Ok(())
     }
 
     fn render_item(
         &self,
                    item: &BookItem,
                    mut ctx: RenderItemContext,
-        print_content: &mut String,
+        print_page: &mut PrintPage,
     ) -> Result<()> {
         // FIXME: This should be made DRY-er and rely less on mutable state
         match *item {
             BookItem::Chapter(ref ch) => {
-                let content = ch.content.clone();
-                let content = utils::render_markdown(&content, ctx.html_config.curly_quotes);
-                print_content.push_str(&content);
+                let event_filters = ctx.event_filters;
+                let content = utils::render_markdown_filtered(
+                    &ch.content,
+                    ctx.html_config.curly_quotes,
+                    &ctx.markdown,
+                    |events| apply_event_filters(event_filters, ch, events),
+                )?;
+                // the SVG files haven't been through the sanitizer
+                let content = if ctx.html_config.inline_svg
+                    && ctx.markdown.raw_html == RawHtml::Allow
+                {
+                    assets::inline_svgs(&content, &ctx.src_dir)
+                } else {
+                    content
+                };
+                if !ch.hidden {
+                    print_page.push(&content)?;
+                }
+                if !ctx.render_page {
+                    return Ok(());
+                }
+
+                let reading_time = Stats::from_markdown(&ch.content)
+                    .reading_time(ctx.html_config.words_per_minute);
+                let content = if ctx.html_config.reading_time {
+                    add_reading_time(&content, reading_time)
+                } else {
+                    content
+                };
+
+                let contributors =
+                    if ctx.html_config.contributors || ctx.html_config.contributor_byline {
+                        ctx.history
+                            .contributors(&ctx.mounts.source_path(&ctx.src_dir, &ch.path))
+                    } else {
+                        Vec::new()
+                    };
+                let content = if ctx.html_config.contributor_byline {
+                    add_byline(&content, &contributors)
+                } else {
+                    content
+                };
 
                 // Update the context with data for this file
                 let path = ch.path
@@ -69,50 +126,136 @@ impl HtmlHandlebars {
                                         .get("book_title")
                                         .and_then(serde_json::Value::as_str)
                                         .unwrap_or("");
-                    title = ch.name.clone() + " - " + book_title;
+                    title = match visible_number(ch, ctx.number_depth) {
+                        Some(number) if ctx.html_config.section_label_in_title => {
+                            let label =
+                                section_label(number, &ctx.html_config.section_label_separator);
+                            format!("{} {} - {}", label, ch.name, book_title)
+                        }
+                        _ => ch.name.clone() + " - " + book_title,
+                    };
                 }
 
+                let filepath = output_path(&ch.path, ctx.html_config.pretty_urls);
+
                 ctx.data.insert("path".to_owned(), json!(path));
+                let source = ctx.book_src.join(&ch.path);
+                match ctx.repository {
+                    Some(repository) => {
+                        ctx.data.insert("source_path".to_owned(),
+                                        json!(repository.source_path(&source)));
+                        ctx.data.insert("source_url".to_owned(),
+                                        json!(repository.source_url(&source)));
+                    }
+                    None => {
+                        ctx.data.insert("source_path".to_owned(),
+                                        json!(normalize_path(&source.to_string_lossy())));
+                    }
+                }
                 ctx.data.insert("content".to_owned(), json!(content));
                 ctx.data.insert("chapter_title".to_owned(), json!(ch.name));
                 ctx.data.insert("title".to_owned(), json!(title));
                 ctx.data.insert("path_to_root".to_owned(),
-                                json!(utils::fs::path_to_root(&ch.path)));
+                                json!(utils::fs::path_to_root(&filepath)));
+                ctx.data.insert("front_matter".to_owned(), json!(ch.front_matter));
+                ctx.data.insert("reading_time".to_owned(), json!(reading_time));
+                ctx.data.insert("contributors".to_owned(), json!(contributors));
+                if let Some(&toml::Value::Boolean(false)) = ch.front_matter.get("comments") {
+                    ctx.data.remove("comments");
+                }
+                if noindex(ch) {
+                    ctx.data.insert("noindex".to_owned(), json!(true));
+                }
+
+                add_language_links(&mut ctx.data, &filepath);
+                add_version_links(&mut ctx.data);
+
+                if let Some(ref cards) = ctx.html_config.social_cards {
+                    let book_title = ctx.data
+                        .get("book_title")
+                        .and_then(serde_json::Value::as_str)
+                        .unwrap_or("")
+                        .to_string();
+                    social_cards::write_card(
+                        &ctx.destination,
+                        &ctx.cache_dir,
+                        &filepath,
+                        &ch.name,
+                        &book_title,
+                        cards,
+                    )?;
+
+                    // crawlers need absolute URLs, which is why make_data()
+                    // insists on the site's URL
+                    let card = normalize_path(&social_cards::card_path(&filepath).to_string_lossy());
+                    if let Some(ref url) = ctx.html_config.site_url {
+                        let card = format!("{}/{}", url.trim_right_matches('/'), card);
+                        ctx.data.insert("social_card".to_owned(), json!(card));
+                    }
+                }
+
+                if let Some(number) = visible_number(ch, ctx.number_depth) {
+                    let label = section_label(number, &ctx.html_config.section_label_separator);
+                    ctx.data.insert("chapter_section".to_owned(), json!(number.to_string()));
+                    ctx.data.insert("chapter_section_label".to_owned(), json!(label));
+                }
 
                 // Render the handlebars template with the data
                 debug!("Render template");
-                let rendered = ctx.handlebars.render("index", &ctx.data)?;
+                let template = chapter_template(ch)?.unwrap_or("index");
+                let rendered = ctx.handlebars.render(template, &ctx.data)?;
+                if ctx.html_config.privacy {
+                    check_privacy(&rendered, &filepath)?;
+                }
 
-                let filepath = Path::new(&ch.path).with_extension("html");
                 let rendered = self.post_process(
                     rendered,
                     &normalize_path(filepath.to_str().ok_or_else(|| {
                         Error::from(format!("Bad file name: {}", filepath.display()))
                     })?),
                     &ctx.html_config.playpen,
+                    ctx.html_config.slug_style,
                 );
 
                 // Write to file
                 debug!("Creating {} ✓", filepath.display());
-                self.write_file(&ctx.destination, filepath, &rendered.into_bytes())?;
+                self.write_file(&ctx.destination, &filepath, &rendered.into_bytes())?;
+
+                // Keep the old `.html` URL working when using pretty URLs
+                let old_filepath = ch.path.with_extension("html");
+                if old_filepath != filepath {
+                    let stem = ch.path
+                        .file_stem()
+                        .and_then(|stem| stem.to_str())
+                        .chain_err(|| "Could not convert path to str")?;
+                    let redirect = redirect_page(&format!("{}/", stem));
+                    self.write_file(&ctx.destination, &old_filepath, redirect.as_bytes())?;
+                }
 
                 if ctx.is_index {
-                    self.render_index(ch, &ctx.destination)?;
+                    self.render_index(&filepath, &ctx.destination)?;
                 }
             }
-            _ => {}
+            BookItem::PartTitle(ref title) => {
+                print_page.push(&format!(
+                    "<h1 class=\"part-title\">{}</h1>\n",
+                    escape_html(title)
+                ))?;
+            }
+            BookItem::Separator => {}
         }
 
         Ok(())
     }
 
-    /// Create an index.html from the first element in SUMMARY.md
-    fn render_index(&self, ch: &Chapter, destination: &Path) -> Result<()> {
+    /// Create an index.html from the first element in SUMMARY.md, which was
+    /// rendered to `filepath`.
+    fn render_index(&self, filepath: &Path, destination: &Path) -> Result<()> {
         debug!("index.html");
 
         let mut content = String::new();
 
-        File::open(destination.join(&ch.path.with_extension("html")))?
+        File::open(destination.join(filepath))?
             .read_to_string(&mut content)?;
 
         // This could cause a problem when someone displays
@@ -127,24 +270,60 @@ impl HtmlHandlebars {
 
         debug!(
             "Creating index.html from {} ✓",
-            destination.join(&ch.path.with_extension("html")).display()
+            destination.join(filepath).display()
         );
 
         Ok(())
     }
 
-    #[cfg_attr(feature = "cargo-clippy", allow(let_and_return))]
     fn post_process(&self,
                     rendered: String,
                     filepath: &str,
-                    playpen_config: &Playpen)
+                    playpen_config: &Playpen,
+                    slug_style: SlugStyle)
                     -> String {
-        let rendered = build_header_links(&rendered, filepath);
-        let rendered = fix_anchor_links(&rendered, filepath);
-        let rendered = fix_code_blocks(&rendered);
-        let rendered = add_playpen_pre(&rendered, playpen_config);
+        post_process_part(&rendered, filepath, playpen_config, slug_style, &mut HashMap::new())
+    }
 
-        rendered
+    /// Render the generated page of a part which doesn't have a chapter of
+    /// its own to link to.
+    fn render_part_page(
+        &self,
+        part: &parts::Part,
+        handlebars: &Handlebars,
+        data: &serde_json::Map<String, serde_json::Value>,
+        destination: &Path,
+        html_config: &HtmlConfig,
+    ) -> Result<()> {
+        let pretty_urls = html_config.pretty_urls;
+        let content = parts::page_content(part, |ch| chapter_link(&ch.path, pretty_urls));
+        let filepath = output_path(&part.path, pretty_urls);
+
+        let mut data = data.clone();
+        let book_title = data.get("book_title")
+            .and_then(serde_json::Value::as_str)
+            .unwrap_or("")
+            .to_string();
+        data.insert("path".to_owned(), json!(normalize_path(&part.path.to_string_lossy())));
+        data.insert("content".to_owned(), json!(content));
+        data.insert("chapter_title".to_owned(), json!(part.title));
+        data.insert("title".to_owned(), json!(format!("{} - {}", part.title, book_title)));
+        data.insert("path_to_root".to_owned(), json!(utils::fs::path_to_root(&filepath)));
+        data.remove("comments");
+
+        let rendered = handlebars.render("index", &data)?;
+        if html_config.privacy {
+            check_privacy(&rendered, &filepath)?;
+        }
+        let rendered = self.post_process(
+            rendered,
+            &normalize_path(&filepath.to_string_lossy()),
+            &html_config.playpen,
+            html_config.slug_style,
+        );
+
+        debug!("Creating {} ✓", filepath.display());
+        self.write_file(destination, &filepath, &rendered.into_bytes())
     }
 
     fn copy_static_files(
@@ -161,6 +340,18 @@ impl HtmlHandlebars {
         self.write_file(destination, "ayu-highlight.css", &theme.ayu_highlight_css)?;
         self.write_file(destination, "highlight.js", &theme.highlight_js)?;
         self.write_file(destination, "clipboard.min.js", &theme.clipboard_js)?;
+        if html_config.reader_notes {
+            self.write_file(destination, "notes.js", &theme.notes_js)?;
+        }
+        if let Some(ref config) = html_config.robots {
+            let robots = robots::robots_txt(config);
+            self.write_file(destination, robots::ROBOTS_TXT, robots.as_bytes())?;
+        }
+        if let Some(ref colors) = html_config.theme_colors {
+            if let Some(stylesheet) = theme_colors::stylesheet(colors)? {
+                self.write_file(destination, theme_colors::STYLESHEET, stylesheet.as_bytes())?;
+            }
+        }
         self.write_file(
             destination,
             "_FontAwesome/css/font-awesome.css",
@@ -196,6 +387,11 @@ impl HtmlHandlebars {
             "_FontAwesome/fonts/FontAwesome.ttf",
             theme::FONT_AWESOME_TTF,
         )?;
+        if html_config.privacy {
+            for &(name, contents) in theme::FONTS {
+                self.write_file(destination, name, contents)?;
+            }
+        }
 
         let playpen_config = &html_config.playpen;
 
@@ -223,17 +419,21 @@ impl HtmlHandlebars {
         // Make sure that the Print chapter does not display the title from
         // the last rendered chapter by removing it from its context
         data.remove("title");
+        data.remove("comments");
         data.insert("is_print".to_owned(), json!(true));
         data.insert("path".to_owned(), json!("print.md"));
         data.insert("content".to_owned(), json!(print_content));
         data.insert("path_to_root".to_owned(),
                     json!(utils::fs::path_to_root(Path::new("print.md"))));
+        add_language_links(data, Path::new("print.md"));
+        add_version_links(data);
     }
 
     fn register_hbs_helpers(&self, handlebars: &mut Handlebars, html_config: &HtmlConfig) {
         handlebars.register_helper("toc", Box::new(helpers::toc::RenderToc {no_section_label: html_config.no_section_label}));
         handlebars.register_helper("previous", Box::new(helpers::navigation::previous));
         handlebars.register_helper("next", Box::new(helpers::navigation::next));
+        handlebars.register_helper("json", Box::new(helpers::json::json));
     }
 
     /// Copy across any additional CSS and JavaScript files which the book
@@ -250,13 +450,7 @@ impl HtmlHandlebars {
                 fs::create_dir_all(parent)
                     .chain_err(|| format!("Unable to create {}", parent.display()))?;
             }
-            debug!(
-                "Copying {} -> {}",
-                input_location.display(),
-                output_location.display()
-            );
-
-            fs::copy(&input_location, &output_location).chain_err(|| {
+            utils::fs::copy_file_if_changed(&input_location, &output_location).chain_err(|| {
                 format!(
                     "Unable to copy {} to {}",
                     input_location.display(),
@@ -274,6 +468,10 @@ impl Renderer for HtmlHandlebars {
         "html"
     }
 
+    fn supports_incremental(&self) -> bool {
+        true
+    }
+
     fn render(&self, ctx: &RenderContext) -> Result<()> {
         let html_config = ctx.config.html_config().unwrap_or_default();
         let src_dir = ctx.root.join(&ctx.config.book.src);
@@ -288,7 +486,7 @@ impl Renderer for HtmlHandlebars {
             None => src_dir.join("theme"),
         };
 
-        let theme = theme::Theme::new(theme_dir);
+        let theme = theme::Theme::new(&theme_dir);
 
         debug!("Register the index handlebars template");
         handlebars.register_template_string("index", String::from_utf8(theme.index.clone())?)?;
@@ -296,46 +494,161 @@ impl Renderer for HtmlHandlebars {
         debug!("Register the header handlebars template");
         handlebars.register_partial("header", String::from_utf8(theme.header.clone())?)?;
 
+        debug!("Register the comments handlebars template");
+        handlebars.register_partial("comments", String::from_utf8(theme.comments.clone())?)?;
+
+        // the parts of index.hbs, which themes can override one at a time
+        debug!("Register the sidebar, menu, footer and scripts handlebars templates");
+        handlebars.register_partial("sidebar", String::from_utf8(theme.sidebar.clone())?)?;
+        handlebars.register_partial("menu", String::from_utf8(theme.menu.clone())?)?;
+        handlebars.register_partial("footer", String::from_utf8(theme.footer.clone())?)?;
+        handlebars.register_partial("scripts", String::from_utf8(theme.scripts.clone())?)?;
+
+        debug!("Register the chapters' own templates");
+        for item in book.iter() {
+            if let BookItem::Chapter(ref ch) = *item {
+                if let Some(name) = chapter_template(ch)? {
+                    if handlebars.get_template(name).is_some() {
+                        continue;
+                    }
+                    let path = theme_dir.join(name);
+                    if !path.is_file() {
+                        bail!(
+                            "\"{}\" uses the template {}, which isn't in the theme ({})",
+                            ch.name,
+                            name,
+                            path.display()
+                        );
+                    }
+                    let template = utils::fs::file_to_string(&path)?;
+                    handlebars
+                        .register_template_string(name, template)
+                        .chain_err(|| format!("Unable to parse the template {}", name))?;
+                }
+            }
+        }
+
+        if html_config.inline_svg && ctx.config.book.markdown.raw_html != RawHtml::Allow {
+            warn!("SVG images aren't inlined because the raw HTML of the book isn't allowed");
+        }
+
         debug!("Register handlebars helpers");
         self.register_hbs_helpers(&mut handlebars, &html_config);
 
-        let mut data = make_data(&ctx.root, &book, &ctx.config, &html_config)?;
-
-        // Print version
-        let mut print_content = String::new();
+        let build_info = match ctx.build_info {
+            Some(ref info) => info.clone(),
+            None => utils::BuildInfo::collect(&ctx.root)?,
+        };
+        let data = make_data(&ctx.root, &book, &ctx.config, &html_config, &build_info)?;
 
         fs::create_dir_all(&destination)
             .chain_err(|| "Unexpected error when constructing destination path")?;
 
+        // Print version, which is written out a chapter at a time instead of
+        // collecting the whole book's HTML in memory first
+        let mut print_data = data.clone();
+        self.configure_print_version(&mut print_data, PRINT_CONTENT_MARKER);
+        if let Some(ref title) = ctx.config.book.title {
+            print_data.insert("title".to_owned(), json!(title));
+        }
+        // the print page would otherwise get chapters indexed after all
+        let has_noindex_chapters = book.iter().any(|item| match *item {
+            BookItem::Chapter(ref ch) => !ch.hidden && noindex(ch),
+            _ => false,
+        });
+        if has_noindex_chapters {
+            print_data.insert("noindex".to_owned(), json!(true));
+        }
+        let rendered = handlebars.render("index", &print_data)?;
+        if html_config.privacy {
+            check_privacy(&rendered, Path::new("print.html"))?;
+        }
+        let mut print_page =
+            PrintPage::create(
+                &destination.join("print.html"),
+                &rendered,
+                &html_config.playpen,
+                html_config.slug_style,
+            )?;
+
+        // The index page is the first visible chapter, which isn't
+        // necessarily the first item (e.g. when the book starts with a part
+        // title).
+        let index = book.iter().position(|item| match *item {
+            BookItem::Chapter(ref ch) => !ch.hidden,
+            _ => false,
+        });
+
+        let repository = Repository::from_config(&ctx.root, &html_config);
+        let mounts = &ctx.mounts;
+        let history = contributors::History::new(
+            Some(src_dir.clone())
+                .into_iter()
+                .chain(mounts.iter().map(|(_, dir)| dir.clone()))
+                .collect(),
+        );
+
+        let cmd_filters: Vec<CmdEventFilter> = html_config
+            .event_filters
+            .iter()
+            .map(|cmd| CmdEventFilter::new(cmd.clone()))
+            .collect();
+        let event_filters: Vec<&EventFilter> = self.event_filters
+            .iter()
+            .map(|filter| &**filter)
+            .chain(cmd_filters.iter().map(|filter| filter as &EventFilter))
+            .collect();
+
         for (i, item) in book.iter().enumerate() {
+            let render_page = match (item, ctx.changed_chapters.as_ref()) {
+                (&BookItem::Chapter(ref ch), Some(changed)) => changed.contains(&ch.path),
+                _ => true,
+            };
+            if let BookItem::Chapter(ref ch) = *item {
+                if render_page {
+                    assets::check_images(ch, &src_dir, &mounts);
+                }
+            }
+
             let ctx = RenderItemContext {
                 handlebars: &handlebars,
                 destination: destination.to_path_buf(),
+                src_dir: src_dir.clone(),
+                mounts: &mounts,
+                history: &history,
+                cache_dir: ctx.root.join(&ctx.config.build.cache_dir),
+                book_src: ctx.config.book.src.clone(),
+                repository: repository.as_ref(),
                 data: data.clone(),
-                is_index: i == 0,
+                is_index: Some(i) == index,
                 html_config: html_config.clone(),
+                markdown: ctx.config.book.markdown.clone(),
+                event_filters: &event_filters,
+                number_depth: ctx.config.book.number_depth,
+                render_page,
             };
-            self.render_item(item, ctx, &mut print_content)?;
-        }
-
-        // Print version
-        self.configure_print_version(&mut data, &print_content);
-        if let Some(ref title) = ctx.config.book.title {
-            data.insert("title".to_owned(), json!(title));
+            match *item {
+                BookItem::Chapter(ref ch) => timings::time_chapter(
+                    || String::from("render html"),
+                    Some(&ch.path),
+                    || self.render_item(item, ctx, &mut print_page),
+                )?,
+                _ => self.render_item(item, ctx, &mut print_page)?,
+            }
         }
 
-        // Render the handlebars template with the data
-        debug!("Render template");
-
-        let rendered = handlebars.render("index", &data)?;
-
-        let rendered = self.post_process(rendered,
-                                         "print.html",
-                                         &html_config.playpen);
-
-        self.write_file(&destination, "print.html", &rendered.into_bytes())?;
+        print_page.finish()?;
         debug!("Creating print.html ✓");
 
+        if html_config.part_pages {
+            for part in parts::parts(&book) {
+                if part.index.is_none() {
+                    self.render_part_page(&part, &handlebars, &data, &destination, &html_config)
+                        .chain_err(|| format!("Unable to render the page of part \"{}\"", part.title))?;
+                }
+            }
+        }
+
         debug!("Copy static files");
         self.copy_static_files(&destination, &theme, &html_config)
             .chain_err(|| "Unable to copy across static files")?;
@@ -343,18 +656,77 @@ impl Renderer for HtmlHandlebars {
             .chain_err(|| "Unable to copy across additional CSS and JS")?;
 
         // Copy all remaining files
-        utils::fs::copy_files_except_ext(&src_dir, &destination, true, &["md"])?;
+        let chapter_dir = ctx.root.join(ctx.config.book.chapter_dir());
+        let converted = converted_chapters(book, &chapter_dir, &mounts);
+        utils::fs::copy_files_except(&src_dir, &destination, true, &["md"], &converted)?;
+        for (name, mounted_src) in mounts.iter() {
+            let mounted_destination = destination.join(name);
+            fs::create_dir_all(&mounted_destination)?;
+            utils::fs::copy_files_except(
+                mounted_src,
+                &mounted_destination,
+                true,
+                &["md"],
+                &converted,
+            ).chain_err(|| format!("Unable to copy the files of the book mounted as \"{}\"", name))?;
+        }
+
+        debug!("Run the asset processors");
+        let asset_processors = AssetProcessors::new(&html_config.asset_processors)?;
+        asset_processors
+            .run(&src_dir, &destination, Path::new(""))
+            .chain_err(|| "Unable to process the assets")?;
+        if !theme_dir.starts_with(&src_dir) {
+            asset_processors
+                .run(&theme_dir, &destination, Path::new("theme"))
+                .chain_err(|| "Unable to process the theme's assets")?;
+        }
+        for (name, mounted_src) in mounts.iter() {
+            asset_processors
+                .run(mounted_src, &destination, Path::new(name))
+                .chain_err(|| "Unable to process the assets")?;
+        }
+
+        if let Some(max_size) = html_config.max_asset_size {
+            assets::check_asset_sizes(&src_dir, destination, max_size);
+        }
+
+        if let Some(ref web_app) = html_config.web_app {
+            let config = &ctx.config.book;
+            web_app::write_manifest(
+                destination,
+                config.title.as_ref().map(|title| title.as_str()).unwrap_or(""),
+                config.description.as_ref().map(|desc| desc.as_str()).unwrap_or(""),
+                web_app,
+            ).chain_err(|| "Unable to write the web app manifest")?;
+        }
+
+        // the service worker needs the list of every file, so it comes last
+        if html_config.service_worker {
+            offline::write_service_worker(
+                destination,
+                &theme.service_worker,
+                html_config.precache_book,
+            ).chain_err(|| "Unable to write the service worker")?;
+        }
 
         Ok(())
     }
 }
 
-fn make_data(root: &Path, book: &Book, config: &Config, html_config: &HtmlConfig) -> Result<serde_json::Map<String, serde_json::Value>> {
+fn make_data(
+    root: &Path,
+    book: &Book,
+    config: &Config,
+    html_config: &HtmlConfig,
+    build_info: &utils::BuildInfo,
+) -> Result<serde_json::Map<String, serde_json::Value>> {
     trace!("make_data");
     let html = config.html_config().unwrap_or_default();
 
     let mut data = serde_json::Map::new();
-    data.insert("language".to_owned(), json!("en"));
+    let language = config.book.language_or_default();
+    data.insert("language".to_owned(), json!(language));
     data.insert("book_title".to_owned(), json!(config.book.title.clone().unwrap_or_default()));
     data.insert("description".to_owned(), json!(config.book.description.clone().unwrap_or_default()));
     data.insert("favicon".to_owned(), json!("favicon.png"));
@@ -362,15 +734,124 @@ fn make_data(root: &Path, book: &Book, config: &Config, html_config: &HtmlConfig
         data.insert("livereload".to_owned(), json!(livereload));
     }
 
-    // Add google analytics tag
-    if let Some(ref ga) = config.html_config().and_then(|html| html.google_analytics) {
-        data.insert("google_analytics".to_owned(), json!(ga));
+    // Add the analytics snippet
+    if let Some(analytics) = analytics(&html)? {
+        // older themes only know about google analytics
+        if analytics.provider == AnalyticsProvider::Google {
+            data.insert("google_analytics".to_owned(), json!(analytics.id));
+        }
+        data.insert("analytics".to_owned(), json!(analytics));
     }
 
     if html.mathjax_support {
+        if html.privacy {
+            bail!(
+                "MathJax is loaded from a CDN, so output.html.mathjax-support can't be used with \
+                 output.html.privacy"
+            );
+        }
         data.insert("mathjax_support".to_owned(), json!(true));
     }
 
+    if html.privacy {
+        data.insert("privacy".to_owned(), json!(true));
+    }
+
+    if html.reader_notes {
+        data.insert("reader_notes".to_owned(), json!(true));
+    }
+
+    data.insert("font_size".to_owned(), json!(font_size(&html)?));
+
+    let sidebar = &html.sidebar;
+    if sidebar.min_width > sidebar.width || sidebar.width > sidebar.max_width {
+        bail!(
+            "output.html.sidebar.width ({}) should be between its min-width ({}) and max-width ({})",
+            sidebar.width,
+            sidebar.min_width,
+            sidebar.max_width
+        );
+    }
+    data.insert("sidebar_width".to_owned(), json!(sidebar.width));
+    data.insert("sidebar_min_width".to_owned(), json!(sidebar.min_width));
+    data.insert("sidebar_max_width".to_owned(), json!(sidebar.max_width));
+
+    if html.part_navigation == PartNavigation::PartPage && !html.part_pages {
+        bail!("output.html.part-navigation = \"part-page\" needs output.html.part-pages");
+    }
+    data.insert("part_navigation".to_owned(), json!(html.part_navigation.as_str()));
+
+    if html.social_cards.is_some() && html.site_url.is_none() {
+        bail!("output.html.social-cards needs output.html.site-url, for the cards' absolute URLs");
+    }
+
+    if html.print.page_breaks {
+        data.insert("print_page_breaks".to_owned(), json!(true));
+    }
+    if html.print.expand_links {
+        data.insert("print_expand_links".to_owned(), json!(true));
+    }
+
+    if let Some(ref colors) = html.theme_colors {
+        if theme_colors::stylesheet(colors)?.is_some() {
+            data.insert("theme_colors".to_owned(), json!(theme_colors::STYLESHEET));
+        }
+    }
+
+    if let Some(ref web_app) = html.web_app {
+        data.insert("web_app_manifest".to_owned(), json!(web_app::MANIFEST));
+        data.insert("theme_color".to_owned(), json!(web_app.theme_color));
+    }
+
+    // a service worker would keep serving old files while the book is being
+    // worked on with `mdbook serve`
+    if html.service_worker && html_config.livereload_url.is_none() {
+        data.insert("service_worker".to_owned(), json!(offline::SERVICE_WORKER));
+    }
+
+    // The comment system shown after every chapter
+    if let Some(ref snippet) = html.comments {
+        let snippet = root.join(snippet);
+        let comments = utils::fs::file_to_string(&snippet)
+            .chain_err(|| format!("Unable to read the comments snippet {}", snippet.display()))?;
+        data.insert("comments".to_owned(), json!(comments));
+    }
+
+    // The language switcher of a multilingual book
+    if config.book.multilingual {
+        let languages: Vec<_> = config
+            .languages()
+            .into_iter()
+            .map(|(code, lang)| {
+                let name = lang.name.unwrap_or_else(|| code.clone());
+                json!({"code": code, "name": name, "current": code == language})
+            })
+            .collect();
+
+        if languages.len() > 1 {
+            data.insert("languages".to_owned(), json!(languages));
+        }
+    }
+
+    // The version switcher of a versioned book
+    if let Some(ref current) = config.book.version {
+        // the versions are next to each other, above the languages of a
+        // multilingual book
+        let up = if config.book.multilingual { "../../" } else { "../" };
+        let versions: Vec<_> = config
+            .versions()
+            .into_iter()
+            .map(|version| {
+                let dir = format!("{}{}/", up, version.name);
+                json!({"name": version.name, "dir": dir, "current": version.name == *current})
+            })
+            .collect();
+
+        if versions.len() > 1 {
+            data.insert("versions".to_owned(), json!(versions));
+        }
+    }
+
     // Add check to see if there is an additional style
     if !html.additional_css.is_empty() {
         let mut css = Vec::new();
@@ -405,8 +886,28 @@ fn make_data(root: &Path, book: &Book, config: &Config, html_config: &HtmlConfig
         data.insert("additional_js".to_owned(), json!(js));
     }
 
+    data.insert("vars".to_owned(), json!(html.vars));
+
+    data.insert("build_date".to_owned(), json!(build_info.build_date));
+    data.insert("mdbook_version".to_owned(), json!(build_info.mdbook_version));
+    if let Some(ref commit) = build_info.git_commit {
+        data.insert("git_commit".to_owned(), json!(commit));
+    }
+    if let Some(ref url) = html.git_repository_url {
+        data.insert("git_repository_url".to_owned(), json!(url.trim_right_matches('/')));
+    }
+
+    data.insert("keyboard_shortcuts".to_owned(), json!(html.keyboard_shortcuts));
+
+    data.insert("playpen_url".to_owned(),
+                json!(html.playpen.url.trim_right_matches('/')));
+
     if html.playpen.editable {
         data.insert("playpens_editable".to_owned(), json!(true));
+        data.insert("playpen_line_wrapping".to_owned(), json!(html.playpen.line_wrapping));
+        if let Some(ref theme) = html.playpen.editor_theme {
+            data.insert("playpen_editor_theme".to_owned(), json!(theme));
+        }
         data.insert("editor_js".to_owned(), json!("editor.js"));
         data.insert("ace_js".to_owned(), json!("ace.js"));
         data.insert("mode_rust_js".to_owned(), json!("mode-rust.js"));
@@ -415,6 +916,13 @@ fn make_data(root: &Path, book: &Book, config: &Config, html_config: &HtmlConfig
                     json!("theme-tomorrow_night.js"));
     }
 
+    let book_parts = if html.part_pages {
+        parts::parts(book)
+    } else {
+        Vec::new()
+    };
+    let mut book_parts = book_parts.iter();
+
     let mut chapters = vec![];
 
     for item in book.iter() {
@@ -422,9 +930,21 @@ fn make_data(root: &Path, book: &Book, config: &Config, html_config: &HtmlConfig
         let mut chapter = BTreeMap::new();
 
         match *item {
+            // Hidden chapters are left out of the TOC and the previous/next
+            // links, which are all built from this list
+            BookItem::Chapter(ref ch) if ch.hidden => continue,
             BookItem::Chapter(ref ch) => {
                 if let Some(ref section) = ch.number {
+                    // The TOC still needs to know how deeply the chapter is
+                    // nested when its number isn't shown
+                    chapter.insert("level".to_owned(), json!(section.len().to_string()));
+                }
+                if let Some(section) = visible_number(ch, config.book.number_depth) {
                     chapter.insert("section".to_owned(), json!(section.to_string()));
+                    chapter.insert(
+                        "section_label".to_owned(),
+                        json!(section_label(section, &html.section_label_separator)),
+                    );
                 }
 
                 chapter.insert("name".to_owned(), json!(ch.name));
@@ -432,10 +952,21 @@ fn make_data(root: &Path, book: &Book, config: &Config, html_config: &HtmlConfig
                     .to_str()
                     .chain_err(|| "Could not convert path to str")?;
                 chapter.insert("path".to_owned(), json!(path));
+                chapter.insert("link".to_owned(), json!(chapter_link(&ch.path, html.pretty_urls)));
             }
             BookItem::Separator => {
                 chapter.insert("spacer".to_owned(), json!("_spacer_"));
             }
+            BookItem::PartTitle(ref title) => {
+                chapter.insert("part_title".to_owned(), json!(title));
+                // not a "path", which would make the page part of the
+                // previous and next links
+                if let Some(part) = book_parts.next() {
+                    let page = part.page();
+                    chapter.insert("part_path".to_owned(), json!(normalize_path(&page.to_string_lossy())));
+                    chapter.insert("part_link".to_owned(), json!(chapter_link(page, html.pretty_urls)));
+                }
+            }
         }
 
         chapters.push(chapter);
@@ -447,29 +978,197 @@ fn make_data(root: &Path, book: &Book, config: &Config, html_config: &HtmlConfig
     Ok(data)
 }
 
-/// Goes through the rendered HTML, making sure all header tags are wrapped in
-/// an anchor so people can link to sections directly.
-fn build_header_links(html: &str, filepath: &str) -> String {
+/// Where a chapter is rendered to, relative to the build directory. With
+/// pretty URLs every chapter (except for index pages) gets a directory of its
+/// own, so `foo/bar.md` becomes `foo/bar/index.html`.
+pub(crate) fn output_path(path: &Path, pretty_urls: bool) -> PathBuf {
+    let is_index = path.file_stem().map(|stem| stem == "index").unwrap_or(false);
+
+    if pretty_urls && !is_index {
+        path.with_extension("").join("index.html")
+    } else {
+        path.with_extension("html")
+    }
+}
+
+/// The URL of a chapter, relative to the book's root.
+fn chapter_link(path: &Path, pretty_urls: bool) -> String {
+    let output = normalize_path(&output_path(path, pretty_urls).to_string_lossy());
+
+    if !pretty_urls {
+        output
+    } else if output == "index.html" {
+        String::from("./")
+    } else {
+        output.trim_right_matches("index.html").to_string()
+    }
+}
+
+/// A page sending the reader on to `url`.
+fn redirect_page(url: &str) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+    <meta charset="UTF-8">
+    <meta http-equiv="refresh" content="0; url={0}">
+    <link rel="canonical" href="{0}">
+</head>
+<body>
+    <p>This page has moved to <a href="{0}">{0}</a>.</p>
+</body>
+</html>
+"#,
+        url
+    )
+}
+
+/// The chapter's section number, unless it's nested deeper than the book's
+/// `number-depth`.
+fn visible_number(ch: &Chapter, number_depth: Option<usize>) -> Option<&SectionNumber> {
+    match (ch.number.as_ref(), number_depth) {
+        (Some(number), Some(depth)) if number.len() > depth => None,
+        (number, _) => number,
+    }
+}
+
+/// Format a section number for display, using the configured separator.
+///
+/// The default `"."` separator keeps the trailing dot (`1.2.`), any other
+/// separator is only placed between the numbers (`1-2`).
+fn section_label(number: &SectionNumber, separator: &str) -> String {
+    if separator == "." {
+        number.to_string()
+    } else {
+        number
+            .iter()
+            .map(|n| n.to_string())
+            .collect::<Vec<_>>()
+            .join(separator)
+    }
+}
+
+/// Insert the reading time under the chapter's title (its first top-level
+/// header), or at the very top if it doesn't have one.
+fn add_reading_time(html: &str, minutes: usize) -> String {
+    let reading_time = format!(
+        "<p class=\"reading-time\">{} min read</p>\n",
+        ::std::cmp::max(minutes, 1)
+    );
+
+    match html.find("</h1>") {
+        Some(ix) => {
+            let (title, rest) = html.split_at(ix + "</h1>".len());
+            format!("{}\n{}{}", title, reading_time, rest.trim_left_matches('\n'))
+        }
+        None => reading_time + html,
+    }
+}
+
+/// List the chapter's contributors at the bottom of its page.
+fn add_byline(html: &str, contributors: &[contributors::Contributor]) -> String {
+    let names: Vec<String> = contributors
+        .iter()
+        .map(|contributor| escape_html(&contributor.name))
+        .collect();
+
+    let names = match names.split_last() {
+        None => return html.to_string(),
+        Some((last, rest)) if rest.is_empty() => last.clone(),
+        Some((last, rest)) => format!("{} and {}", rest.join(", "), last),
+    };
+
+    format!(
+        "{}\n<footer class=\"contributors\">Written by {}</footer>\n",
+        html.trim_right_matches('\n'),
+        names
+    )
+}
+
+fn apply_event_filters<'a>(
+    filters: &[&EventFilter],
+    ch: &Chapter,
+    mut events: Vec<Event<'a>>,
+) -> Result<Vec<Event<'a>>> {
+    for filter in filters {
+        events = filter
+            .filter(ch, events)
+            .chain_err(|| format!("The \"{}\" event filter failed", filter.name()))?;
+    }
+
+    Ok(events)
+}
+
+/// Post-process a page, or one part of it, making sure its header and
+/// footnote IDs don't clash with the ones already handed out in `id_counter`.
+fn post_process_part(
+    rendered: &str,
+    filepath: &str,
+    playpen_config: &Playpen,
+    slug_style: SlugStyle,
+    id_counter: &mut HashMap<String, usize>,
+) -> String {
+    let rendered = build_header_links(rendered, filepath, slug_style, id_counter);
+    let rendered = unique_footnote_ids(&rendered, id_counter);
+    let rendered = fix_anchor_links(&rendered, filepath);
+    let rendered = fix_code_blocks(&rendered);
+    add_playpen_pre(&rendered, playpen_config)
+}
+
+fn build_header_links(
+    html: &str,
+    filepath: &str,
+    slug_style: SlugStyle,
+    id_counter: &mut HashMap<String, usize>,
+) -> String {
     let regex = Regex::new(r"<h(\d)>(.*?)</h\d>").unwrap();
-    let mut id_counter = HashMap::new();
 
     regex.replace_all(html, |caps: &Captures| {
         let level = caps[1].parse()
                            .expect("Regex should ensure we only ever get numbers here");
 
-        wrap_header_with_link(level, &caps[2], &mut id_counter, filepath)
+        wrap_header_with_link(level, &caps[2], slug_style, id_counter, filepath)
     })
          .into_owned()
 }
 
+/// Number the IDs of footnotes (and of the references to them) which were
+/// already handed out in `id_counter`, so the footnotes of different chapters
+/// don't clash on the print page.
+fn unique_footnote_ids(html: &str, id_counter: &mut HashMap<String, usize>) -> String {
+    let id_regex = Regex::new(r#"id="(footnote-[^"]*)""#).unwrap();
+
+    let mut renamed = HashMap::new();
+    for caps in id_regex.captures_iter(html) {
+        let id_count = id_counter.entry(caps[1].to_string()).or_insert(0);
+        if *id_count > 0 {
+            renamed.insert(caps[1].to_string(), format!("{}-{}", &caps[1], id_count));
+        }
+        *id_count += 1;
+    }
+
+    if renamed.is_empty() {
+        return html.to_string();
+    }
+
+    let regex = Regex::new(r##"(id="|href="#)(footnote-[^"]*)""##).unwrap();
+    regex
+        .replace_all(html, |caps: &Captures| match renamed.get(&caps[2]) {
+            Some(id) => format!("{}{}\"", &caps[1], id),
+            None => caps[0].to_string(),
+        })
+        .into_owned()
+}
+
 /// Wraps a single header tag with a link, making sure each tag gets its own
 /// unique ID by appending an auto-incremented number (if necessary).
 fn wrap_header_with_link(level: usize,
                          content: &str,
+                         slug_style: SlugStyle,
                          id_counter: &mut HashMap<String, usize>,
                          filepath: &str)
                          -> String {
-    let raw_id = id_from_content(content);
+    let raw_id = id_from_content(content, slug_style);
 
     let id_count = id_counter.entry(raw_id.clone()).or_insert(0);
 
@@ -491,7 +1190,7 @@ fn wrap_header_with_link(level: usize,
 
 /// Generate an id for use with anchors which is derived from a "normalised"
 /// string.
-fn id_from_content(content: &str) -> String {
+fn id_from_content(content: &str, slug_style: SlugStyle) -> String {
     let mut content = content.to_string();
 
     // Skip any tags or html-encoded stuff
@@ -513,7 +1212,7 @@ fn id_from_content(content: &str) -> String {
     // Remove spaces and hastags indicating a header
     let trimmed = content.trim().trim_left_matches('#').trim();
 
-    normalize_id(trimmed)
+    utils::slugify(trimmed, slug_style)
 }
 
 // anchors to the same page (href="#anchor") do not work because of
@@ -616,12 +1315,184 @@ fn partition_source(s: &str) -> (String, String) {
     (before, after)
 }
 
+/// Stands in for the content when rendering the print page's template, so the
+/// chapters can be written out in its place one at a time.
+const PRINT_CONTENT_MARKER: &str = "mdbook-print-content-goes-here";
+
+/// The print page, written out a chapter at a time rather than being
+/// assembled in memory.
+struct PrintPage {
+    writer: BufWriter<File>,
+    /// Everything after the content, or `None` if the theme's template
+    /// doesn't show the content at all.
+    tail: Option<String>,
+    playpen: Playpen,
+    slug_style: SlugStyle,
+    id_counter: HashMap<String, usize>,
+}
+
+impl PrintPage {
+    /// Start writing the print page, given its template rendered with
+    /// `PRINT_CONTENT_MARKER` as the content.
+    fn create(
+        path: &Path,
+        rendered: &str,
+        playpen: &Playpen,
+        slug_style: SlugStyle,
+    ) -> Result<PrintPage> {
+        let (head, tail) = match rendered.find(PRINT_CONTENT_MARKER) {
+            Some(i) => (
+                &rendered[..i],
+                Some(rendered[i + PRINT_CONTENT_MARKER.len()..].to_string()),
+            ),
+            None => (rendered, None),
+        };
+
+        let mut page = PrintPage {
+            writer: BufWriter::new(utils::fs::create_file(path)?),
+            tail,
+            playpen: playpen.clone(),
+            slug_style,
+            id_counter: HashMap::new(),
+        };
+        page.write_part(head)?;
+
+        Ok(page)
+    }
+
+    /// Append some content.
+    fn push(&mut self, content: &str) -> Result<()> {
+        if self.tail.is_some() {
+            self.write_part(content)?;
+        }
+
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<()> {
+        if let Some(tail) = self.tail.take() {
+            self.write_part(&tail)?;
+        }
+        self.writer.flush()?;
+
+        Ok(())
+    }
+
+    fn write_part(&mut self, part: &str) -> Result<()> {
+        let part = post_process_part(
+            part,
+            "print.html",
+            &self.playpen,
+            self.slug_style,
+            &mut self.id_counter,
+        );
+        self.writer.write_all(part.as_bytes())?;
+
+        Ok(())
+    }
+}
+
+/// Make sure a page doesn't load anything from another site, for
+/// `output.html.privacy`.
+fn check_privacy(html: &str, page: &Path) -> Result<()> {
+    let external = assets::external_resources(html);
+    if !external.is_empty() {
+        bail!(
+            "{} loads {} from another site, which output.html.privacy doesn't allow",
+            page.display(),
+            external.join(", ")
+        );
+    }
+    Ok(())
+}
+
+/// The analytics added to every page, as the theme sees them.
+#[derive(Debug, PartialEq, Serialize)]
+struct Analytics {
+    provider: AnalyticsProvider,
+    id: String,
+    google: bool,
+    plausible: bool,
+}
+
+/// Figure out which analytics to add to the pages, if any. The older
+/// `google-analytics` key is the same as using Google as the provider.
+fn analytics(html: &HtmlConfig) -> Result<Option<Analytics>> {
+    if !html.analytics.enabled || html.privacy {
+        return Ok(None);
+    }
+
+    let provider = match (html.analytics.provider, &html.google_analytics) {
+        (Some(provider), _) => Some(provider),
+        (None, &Some(_)) => Some(AnalyticsProvider::Google),
+        (None, &None) => None,
+    };
+    let id = html.analytics.id.as_ref().or(html.google_analytics.as_ref());
+
+    match (provider, id) {
+        (Some(provider), Some(id)) => Ok(Some(Analytics {
+            provider: provider,
+            id: id.clone(),
+            google: provider == AnalyticsProvider::Google,
+            plausible: provider == AnalyticsProvider::Plausible,
+        })),
+        (None, None) => Ok(None),
+        (Some(_), None) => bail!("output.html.analytics needs the `id` to use with the provider"),
+        (None, Some(_)) => bail!("output.html.analytics needs a `provider` for the id"),
+    }
+}
+
 struct RenderItemContext<'a> {
     handlebars: &'a Handlebars,
     destination: PathBuf,
+    src_dir: PathBuf,
+    mounts: &'a MountedBooks,
+    /// The git history of the chapters, for their contributors.
+    history: &'a contributors::History,
+    /// The book's cache directory, for things which are expensive to render.
+    cache_dir: PathBuf,
+    /// The source directory as it is in the configuration, relative to the
+    /// book's root.
+    book_src: PathBuf,
+    repository: Option<&'a Repository>,
     data: serde_json::Map<String, serde_json::Value>,
     is_index: bool,
     html_config: HtmlConfig,
+    markdown: MarkdownConfig,
+    event_filters: &'a [&'a EventFilter],
+    number_depth: Option<usize>,
+    /// Whether to write the chapter's page, or only add it to the print page
+    /// because its page is still up to date.
+    render_page: bool,
+}
+
+/// Point each entry of the language switcher at the page for `path` in that
+/// language.
+fn add_language_links(data: &mut serde_json::Map<String, serde_json::Value>, path: &Path) {
+    if let Some(&mut serde_json::Value::Array(ref mut languages)) = data.get_mut("languages") {
+        let html_path = normalize_path(&path.with_extension("html").to_string_lossy());
+
+        // relative to the book's root, which every page sets as its `<base>`
+        for language in languages.iter_mut() {
+            let link = format!(
+                "../{}/{}",
+                language["code"].as_str().unwrap_or_default(),
+                html_path
+            );
+            language["link"] = json!(link);
+        }
+    }
+}
+
+/// Point each entry of the version switcher at the index of that version.
+fn add_version_links(data: &mut serde_json::Map<String, serde_json::Value>) {
+    if let Some(&mut serde_json::Value::Array(ref mut versions)) = data.get_mut("versions") {
+        // relative to the book's root, which every page sets as its `<base>`
+        for version in versions.iter_mut() {
+            let link = format!("{}index.html", version["dir"].as_str().unwrap_or_default());
+            version["link"] = json!(link);
+        }
+    }
 }
 
 pub fn normalize_path(path: &str) -> String {
@@ -632,21 +1503,72 @@ pub fn normalize_path(path: &str) -> String {
 }
 
 pub fn normalize_id(content: &str) -> String {
-    content.chars()
-           .filter_map(|ch| if ch.is_alphanumeric() || ch == '_' || ch == '-' {
-                           Some(ch.to_ascii_lowercase())
-                       } else if ch.is_whitespace() {
-                           Some('-')
-                       } else {
-                           None
-                       })
-           .collect::<String>()
+    utils::slugify(content, SlugStyle::Github)
 }
 
 
+/// The template a chapter is rendered with instead of `index.hbs`, as set
+/// with `template = "landing.hbs"` in its front matter. The name is the path
+/// of the template in the theme directory.
+fn chapter_template(ch: &Chapter) -> Result<Option<&str>> {
+    let name = match ch.front_matter.get("template") {
+        Some(&toml::Value::String(ref name)) => name.as_str(),
+        Some(_) => bail!("The template of \"{}\" should be a file name", ch.name),
+        None => return Ok(None),
+    };
+
+    let inside_theme = Path::new(name)
+        .components()
+        .all(|component| match component {
+            Component::Normal(_) => true,
+            _ => false,
+        });
+    if name.is_empty() || !inside_theme {
+        bail!(
+            "The template of \"{}\" should be a file in the theme directory, not {}",
+            ch.name,
+            name
+        );
+    }
+
+    if name == "index.hbs" {
+        Ok(None)
+    } else {
+        Ok(Some(name))
+    }
+}
+
+/// Whether search engines should leave the chapter out, as set with
+/// `noindex = true` in its front matter.
+fn noindex(ch: &Chapter) -> bool {
+    match ch.front_matter.get("noindex") {
+        Some(&toml::Value::Boolean(noindex)) => noindex,
+        _ => false,
+    }
+}
+
+/// The smallest and largest font sizes, in percent, which the A- and A+
+/// buttons go to. These have to match the ones in `book.js`.
+const FONT_SIZES: (u32, u32) = (50, 200);
+
+fn font_size(html: &HtmlConfig) -> Result<u32> {
+    let (min, max) = FONT_SIZES;
+    if html.font_size < min || html.font_size > max {
+        bail!(
+            "output.html.font-size should be between {}% and {}%, not {}%",
+            min,
+            max,
+            html.font_size
+        );
+    }
+
+    Ok(html.font_size)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use tempdir::TempDir;
 
     #[test]
     fn original_build_header_links() {
@@ -679,7 +1601,7 @@ mod tests {
 
         for (src, should_be) in inputs {
             let filepath = "./some_chapter/some_section.html";
-            let got = build_header_links(&src, filepath);
+            let got = build_header_links(&src, filepath, SlugStyle::Github, &mut HashMap::new());
             assert_eq!(got, should_be);
 
             // This is redundant for most cases
@@ -688,11 +1610,209 @@ mod tests {
         }
     }
 
+    #[test]
+    fn section_labels_use_the_configured_separator() {
+        let number = SectionNumber(vec![1, 2, 3]);
+
+        assert_eq!(section_label(&number, "."), "1.2.3.");
+        assert_eq!(section_label(&number, "–"), "1–2–3");
+        assert_eq!(section_label(&SectionNumber(vec![4]), "-"), "4");
+    }
+
+    #[test]
+    fn pretty_urls_give_every_chapter_its_own_directory() {
+        assert_eq!(output_path(Path::new("foo/bar.md"), false), PathBuf::from("foo/bar.html"));
+        assert_eq!(
+            output_path(Path::new("foo/bar.md"), true),
+            PathBuf::from("foo/bar/index.html")
+        );
+        assert_eq!(
+            output_path(Path::new("foo/index.md"), true),
+            PathBuf::from("foo/index.html")
+        );
+
+        assert_eq!(chapter_link(Path::new("foo/bar.md"), false), "foo/bar.html");
+        assert_eq!(chapter_link(Path::new("foo/bar.md"), true), "foo/bar/");
+        assert_eq!(chapter_link(Path::new("foo/index.md"), true), "foo/");
+        assert_eq!(chapter_link(Path::new("index.md"), true), "./");
+    }
+
+    #[test]
+    fn chapters_nested_past_the_number_depth_have_no_number() {
+        let mut ch = Chapter::new("Nested", String::new(), "nested.md");
+        ch.number = Some(SectionNumber(vec![1, 2, 3]));
+
+        assert_eq!(visible_number(&ch, None), ch.number.as_ref());
+        assert_eq!(visible_number(&ch, Some(3)), ch.number.as_ref());
+        assert_eq!(visible_number(&ch, Some(2)), None);
+    }
+
+    #[test]
+    fn the_byline_lists_the_contributors() {
+        let contributor = |name: &str| contributors::Contributor {
+            name: name.to_string(),
+            email: String::new(),
+            commits: 1,
+        };
+
+        let got = add_byline("<p>Text</p>\n", &[contributor("Ada")]);
+        assert_eq!(got, "<p>Text</p>\n<footer class=\"contributors\">Written by Ada</footer>\n");
+
+        let everyone = [contributor("Ada"), contributor("Grace"), contributor("R&D")];
+        let got = add_byline("<p>Text</p>\n", &everyone);
+        assert!(got.contains("Written by Ada, Grace and R&amp;D</footer>"));
+
+        assert_eq!(add_byline("<p>Text</p>\n", &[]), "<p>Text</p>\n");
+    }
+
+    #[test]
+    fn chapters_pick_templates_from_the_theme() {
+        let mut ch = Chapter::new("Landing", String::new(), "landing.md");
+        assert_eq!(chapter_template(&ch).unwrap(), None);
+
+        ch.front_matter.insert("template".to_string(), toml::Value::from("landing.hbs"));
+        assert_eq!(chapter_template(&ch).unwrap(), Some("landing.hbs"));
+
+        ch.front_matter.insert("template".to_string(), toml::Value::from("index.hbs"));
+        assert_eq!(chapter_template(&ch).unwrap(), None);
+
+        for &bad in &["../secret.hbs", "/etc/passwd", ""] {
+            ch.front_matter.insert("template".to_string(), toml::Value::from(bad));
+            assert!(chapter_template(&ch).is_err(), "{}", bad);
+        }
+
+        ch.front_matter.insert("template".to_string(), toml::Value::from(true));
+        assert!(chapter_template(&ch).is_err());
+    }
+
+    #[test]
+    fn the_font_size_has_to_be_reasonable() {
+        let mut html = HtmlConfig::default();
+        assert_eq!(font_size(&html).unwrap(), 100);
+
+        html.font_size = 150;
+        assert_eq!(font_size(&html).unwrap(), 150);
+
+        html.font_size = 0;
+        assert!(font_size(&html).is_err());
+
+        html.font_size = 400;
+        assert!(font_size(&html).is_err());
+    }
+
+    #[test]
+    fn the_google_analytics_key_still_works() {
+        let mut html = HtmlConfig::default();
+        assert_eq!(analytics(&html).unwrap(), None);
+
+        html.google_analytics = Some(String::from("UA-123"));
+        let got = analytics(&html).unwrap().unwrap();
+        assert_eq!(got.provider, AnalyticsProvider::Google);
+        assert_eq!(got.id, "UA-123");
+
+        html.analytics.provider = Some(AnalyticsProvider::Plausible);
+        html.analytics.id = Some(String::from("example.com"));
+        let got = analytics(&html).unwrap().unwrap();
+        assert!(got.plausible);
+        assert_eq!(got.id, "example.com");
+
+        html.privacy = true;
+        assert_eq!(analytics(&html).unwrap(), None);
+
+        html.privacy = false;
+        html.analytics.enabled = false;
+        assert_eq!(analytics(&html).unwrap(), None);
+    }
+
+    #[test]
+    fn analytics_need_a_provider_and_an_id() {
+        let mut html = HtmlConfig::default();
+        html.analytics.provider = Some(AnalyticsProvider::Plausible);
+        assert!(analytics(&html).is_err());
+
+        html.analytics.provider = None;
+        html.analytics.id = Some(String::from("example.com"));
+        assert!(analytics(&html).is_err());
+    }
+
+    #[test]
+    fn the_reading_time_goes_under_the_title() {
+        let got = add_reading_time("<h1>Title</h1>\n<p>Text</p>\n", 3);
+        assert_eq!(got, "<h1>Title</h1>\n<p class=\"reading-time\">3 min read</p>\n<p>Text</p>\n");
+
+        let got = add_reading_time("<p>Text</p>\n", 0);
+        assert_eq!(got, "<p class=\"reading-time\">1 min read</p>\n<p>Text</p>\n");
+    }
+
     #[test]
     fn anchor_generation() {
-        assert_eq!(id_from_content("## `--passes`: add more rustdoc passes"),
+        assert_eq!(id_from_content("## `--passes`: add more rustdoc passes", SlugStyle::Github),
                    "--passes-add-more-rustdoc-passes");
-        assert_eq!(id_from_content("## Method-call expressions"),
+        assert_eq!(id_from_content("## Method-call expressions", SlugStyle::Github),
                    "method-call-expressions");
     }
+
+    #[test]
+    fn the_print_page_is_written_a_chapter_at_a_time() {
+        let temp = TempDir::new("mdbook").unwrap();
+        let path = temp.path().join("print.html");
+        let template = format!("<main>{}</main>", PRINT_CONTENT_MARKER);
+
+        let mut page = PrintPage::create(&path, &template, &Playpen::default(), SlugStyle::Github).unwrap();
+        page.push("<h1>Foo</h1>").unwrap();
+        page.push("<h1>Foo</h1>").unwrap();
+        page.finish().unwrap();
+
+        let got = utils::fs::file_to_string(&path).unwrap();
+        assert_eq!(
+            got,
+            "<main><a class=\"header\" href=\"print.html#foo\" id=\"foo\"><h1>Foo</h1></a>\
+             <a class=\"header\" href=\"print.html#foo-1\" id=\"foo-1\"><h1>Foo</h1></a></main>"
+        );
+    }
+
+    #[test]
+    fn footnotes_of_different_chapters_get_their_own_ids_on_the_print_page() {
+        let chapter = "<p>Text<sup class=\"footnote-reference\" id=\"footnote-ref-1\">\
+                       <a href=\"#footnote-1\">1</a></sup></p>\
+                       <ol class=\"footnotes\"><li id=\"footnote-1\"><p>Note. \
+                       <a href=\"#footnote-ref-1\" class=\"footnote-backref\">↩</a></p></li></ol>";
+        let mut id_counter = HashMap::new();
+
+        let first = post_process_part(chapter, "print.html", &Playpen::default(), SlugStyle::Github, &mut id_counter);
+        let second = post_process_part(chapter, "print.html", &Playpen::default(), SlugStyle::Github, &mut id_counter);
+
+        assert!(first.contains("id=\"footnote-1\""));
+        assert!(first.contains("href=\"print.html#footnote-1\""));
+        assert!(second.contains("id=\"footnote-ref-1-1\""));
+        assert!(second.contains("href=\"print.html#footnote-1-1\""));
+        assert!(second.contains("id=\"footnote-1-1\""));
+        assert!(second.contains("href=\"print.html#footnote-ref-1-1\""));
+        assert!(!second.contains("\"footnote-1\""));
+    }
+
+    #[test]
+    fn language_links_are_relative_to_the_root_of_the_book() {
+        let mut data = serde_json::Map::new();
+        data.insert("languages".to_owned(), json!([{"code": "en"}, {"code": "fr"}]));
+
+        add_language_links(&mut data, Path::new("guide/install.md"));
+
+        assert_eq!(data["languages"][0]["link"], json!("../en/guide/install.html"));
+        assert_eq!(data["languages"][1]["link"], json!("../fr/guide/install.html"));
+    }
+
+    #[test]
+    fn version_links_point_at_the_index_of_each_version() {
+        let mut data = serde_json::Map::new();
+        data.insert(
+            "versions".to_owned(),
+            json!([{"name": "v2.0", "dir": "../v2.0/"}, {"name": "v1.0", "dir": "../v1.0/"}]),
+        );
+
+        add_version_links(&mut data);
+
+        assert_eq!(data["versions"][0]["link"], json!("../v2.0/index.html"));
+        assert_eq!(data["versions"][1]["link"], json!("../v1.0/index.html"));
+    }
 }
@@ -0,0 +1,42 @@
+use serde_json;
+use handlebars::{Handlebars, Helper, RenderContext, RenderError};
+
+/// Write a value as a JavaScript literal, for the configuration which ends up
+/// in an inline `<script>`, e.g. `url: {{json playpen_url}}`.
+///
+/// Strings are quoted and escaped like in JSON, and `</` is written as `<\/`
+/// so a value can't close the `<script>` it's in.
+pub fn json(h: &Helper, _r: &Handlebars, rc: &mut RenderContext) -> Result<(), RenderError> {
+    trace!("json (handlebars helper)");
+
+    let value = h.param(0)
+        .ok_or_else(|| RenderError::new("Param 0 is required for json helper"))?
+        .value();
+    let literal = serde_json::to_string(value)
+        .map_err(|e| RenderError::new(format!("Unable to serialize the value, {}", e)))?;
+
+    rc.writer.write_all(literal.replace("</", "<\\/").as_bytes())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_cant_break_out_of_the_script() {
+        let data = json!({
+            "url": "https://example.com/\"</script><script>alert(1)</script>",
+        });
+
+        let mut h = Handlebars::new();
+        h.register_helper("json", Box::new(json));
+
+        let got = h.template_render("url: {{json url}}", &data).unwrap();
+
+        assert_eq!(
+            got,
+            r#"url: "https://example.com/\"<\/script><script>alert(1)<\/script>""#
+        );
+    }
+}
@@ -59,20 +59,63 @@ fn find_chapter(
                       .ok_or_else(|| RenderError::new("Type error for `path`, string expected"))?
                       .replace("\"", "");
 
+    // what happens at the end of a part, see `PartNavigation`
+    let part_navigation = rc.evaluate_absolute("part_navigation")
+        .ok()
+        .and_then(|mode| mode.as_str().map(String::from))
+        .unwrap_or_default();
+
+    // a part's page can be one of its chapters, which then only shows up
+    // where the part starts
+    let part_pages: Vec<String> = if part_navigation == "part-page" {
+        chapters
+            .iter()
+            .filter(|item| item.contains_key("part_title") && item.contains_key("part_link"))
+            .filter_map(|item| item.get("part_path").cloned())
+            .collect()
+    } else {
+        Vec::new()
+    };
+
     let mut previous: Option<StringMap> = None;
+    let mut crossed_part = false;
 
     debug!("Search for chapter");
 
-    for item in chapters {
-        match item.get("path") {
+    for mut item in chapters {
+        let is_part = item.contains_key("part_title");
+        if let Some(title) = item.get("part_title").cloned() {
+            match (item.get("part_path").cloned(), item.get("part_link").cloned()) {
+                // the part's page comes between its chapters and the ones before
+                (Some(path), Some(link)) if part_navigation == "part-page" => {
+                    item.insert("name".to_owned(), title);
+                    item.insert("path".to_owned(), path);
+                    item.insert("link".to_owned(), link);
+                }
+                _ => {
+                    crossed_part = true;
+                    continue;
+                }
+            }
+        }
+
+        match item.get("path").cloned() {
             Some(path) if !path.is_empty() => {
-                if let Some(previous) = previous {
-                    if let Some(item) = target.find(&base_path, &path, &item, &previous)? {
-                        return Ok(Some(item));
+                if !is_part && part_pages.contains(&path) {
+                    continue;
+                }
+
+                if let Some(ref previous) = previous {
+                    let stop = part_navigation == "stop" && crossed_part;
+                    if !stop {
+                        if let Some(item) = target.find(&base_path, &path, &item, previous)? {
+                            return Ok(Some(item));
+                        }
                     }
                 }
 
                 previous = Some(item.clone());
+                crossed_part = false;
             }
             _ => continue,
         }
@@ -95,14 +138,18 @@ fn render(
             .ok_or_else(|| RenderError::new("No title found for chapter in JSON data"))
             .map(|name| context.insert("title".to_owned(), json!(name)))?;
 
-    chapter.get("path")
-            .ok_or_else(|| RenderError::new("No path found for chapter in JSON data"))
-            .and_then(|p| {
-                    Path::new(p).with_extension("html")
-                    .to_str()
-                    .ok_or_else(|| RenderError::new("Link could not be converted to str"))
-                    .map(|p| context.insert("link".to_owned(), json!(p.replace("\\", "/"))))
-            })?;
+    let link = match chapter.get("link") {
+        Some(link) => link.clone(),
+        None => {
+            let path = chapter.get("path")
+                .ok_or_else(|| RenderError::new("No path found for chapter in JSON data"))?;
+            Path::new(path).with_extension("html")
+                .to_str()
+                .ok_or_else(|| RenderError::new("Link could not be converted to str"))?
+                .replace("\\", "/")
+        }
+    };
+    context.insert("link".to_owned(), json!(link));
 
     trace!("Render template");
 
@@ -231,4 +278,48 @@ mod tests {
          h.template_render(TEMPLATE, &data).unwrap(),
          "two: two.html|");
    }
+
+   #[test]
+   fn navigation_across_parts() {
+      let chapters = json!([
+         {"name": "one", "path": "one.path"},
+         {"part_title": "Part II", "part_path": "parts/part-ii.md", "part_link": "parts/part-ii.html"},
+         {"name": "two", "path": "two.path"}
+      ]);
+
+      let mut h = Handlebars::new();
+      h.register_helper("previous", Box::new(previous));
+      h.register_helper("next", Box::new(next));
+
+      let render = |path: &str, mode: &str| {
+         let data = json!({"path": path, "part_navigation": mode, "chapters": chapters});
+         h.template_render(TEMPLATE, &data).unwrap()
+      };
+
+      assert_eq!(render("one.path", "continue"), "|two: two.html");
+      assert_eq!(render("two.path", "continue"), "one: one.html|");
+      assert_eq!(render("one.path", "stop"), "|");
+      assert_eq!(render("two.path", "stop"), "|");
+      assert_eq!(render("one.path", "part-page"), "|Part II: parts/part-ii.html");
+      assert_eq!(render("parts/part-ii.md", "part-page"), "one: one.html|two: two.html");
+      assert_eq!(render("two.path", "part-page"), "Part II: parts/part-ii.html|");
+
+      // a chapter which is also its part's page, but not the part's first one
+      let chapters = json!([
+         {"name": "one", "path": "one.path"},
+         {"part_title": "Part II", "part_path": "three.path", "part_link": "three.html"},
+         {"name": "two", "path": "two.path"},
+         {"name": "three", "path": "three.path"},
+         {"name": "four", "path": "four.path"}
+      ]);
+      let render = |path: &str| {
+         let data = json!({"path": path, "part_navigation": "part-page", "chapters": chapters});
+         h.template_render(TEMPLATE, &data).unwrap()
+      };
+
+      assert_eq!(render("one.path"), "|Part II: three.html");
+      assert_eq!(render("three.path"), "one: one.html|two: two.html");
+      assert_eq!(render("two.path"), "Part II: three.html|four: four.html");
+      assert_eq!(render("four.path"), "two: two.html|");
+   }
 }
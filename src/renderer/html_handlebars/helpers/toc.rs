@@ -1,3 +1,4 @@
+use std::iter;
 use std::path::Path;
 use std::collections::BTreeMap;
 
@@ -30,13 +31,50 @@ impl HelperDef for RenderToc {
         let mut current_level = 1;
 
         for item in chapters {
+            // Spacers and part titles always sit between top-level chapters,
+            // so they span the whole sidebar instead of being indented along
+            // with the nested chapters before them.
+            if item.contains_key("spacer") || item.contains_key("part_title") {
+                while current_level > 1 {
+                    rc.writer.write_all(b"</ol>")?;
+                    rc.writer.write_all(b"</li>")?;
+                    current_level -= 1;
+                }
+            }
+
             // Spacer
             if item.get("spacer").is_some() {
                 rc.writer.write_all(b"<li class=\"spacer\"></li>")?;
                 continue;
             }
 
-            let level = if let Some(s) = item.get("section") {
+            // Part title
+            if let Some(title) = item.get("part_title") {
+                let mut escaped = String::with_capacity(title.len());
+                html::push_html(&mut escaped, iter::once(Event::Text(title.as_str().into())));
+
+                rc.writer.write_all(b"<li class=\"part-title\">")?;
+                match (item.get("part_link"), item.get("part_path")) {
+                    (Some(link), Some(path)) => {
+                        rc.writer.write_all(b"<a href=\"")?;
+                        rc.writer.write_all(link.as_bytes())?;
+                        rc.writer.write_all(b"\"")?;
+                        if path == &current {
+                            rc.writer.write_all(b" class=\"active\"")?;
+                        }
+                        rc.writer.write_all(b">")?;
+                        rc.writer.write_all(escaped.as_bytes())?;
+                        rc.writer.write_all(b"</a>")?;
+                    }
+                    _ => rc.writer.write_all(escaped.as_bytes())?,
+                }
+                rc.writer.write_all(b"</li>")?;
+                continue;
+            }
+
+            let level = if let Some(level) = item.get("level").and_then(|l| l.parse().ok()) {
+                level
+            } else if let Some(s) = item.get("section") {
                 s.matches('.').count()
             } else {
                 1
@@ -58,7 +96,7 @@ impl HelperDef for RenderToc {
                 rc.writer.write_all(b"<li>")?;
             } else {
                 rc.writer.write_all(b"<li")?;
-                if item.get("section").is_none() {
+                if item.get("level").is_none() && item.get("section").is_none() {
                     rc.writer.write_all(b" class=\"affix\"")?;
                 }
                 rc.writer.write_all(b">")?;
@@ -69,12 +107,15 @@ impl HelperDef for RenderToc {
                 if !path.is_empty() {
                     rc.writer.write_all(b"<a href=\"")?;
 
-                    let tmp = Path::new(item.get("path").expect("Error: path should be Some(_)"))
-                        .with_extension("html")
-                        .to_str()
-                        .unwrap()
-                        // Hack for windows who tends to use `\` as separator instead of `/`
-                        .replace("\\", "/");
+                    let tmp = match item.get("link") {
+                        Some(link) => link.clone(),
+                        None => Path::new(path)
+                            .with_extension("html")
+                            .to_str()
+                            .unwrap()
+                            // Hack for windows who tends to use `\` as separator instead of `/`
+                            .replace("\\", "/"),
+                    };
 
                     // Add link
                     rc.writer.write_all(tmp.as_bytes())?;
@@ -95,7 +136,7 @@ impl HelperDef for RenderToc {
 
             if !self.no_section_label {
                 // Section does not necessarily exist
-                if let Some(section) = item.get("section") {
+                if let Some(section) = item.get("section_label").or_else(|| item.get("section")) {
                     rc.writer.write_all(b"<strong aria-hidden=\"true\">")?;
                     rc.writer.write_all(section.as_bytes())?;
                     rc.writer.write_all(b"</strong> ")?;
@@ -1,6 +1,17 @@
 #![allow(missing_docs)] // FIXME: Document this
 
 pub use self::hbs_renderer::HtmlHandlebars;
+pub(crate) use self::hbs_renderer::output_path;
 
+mod asset_processors;
+mod assets;
+mod contributors;
 mod hbs_renderer;
 mod helpers;
+mod offline;
+mod parts;
+mod repository;
+mod robots;
+mod social_cards;
+mod theme_colors;
+mod web_app;
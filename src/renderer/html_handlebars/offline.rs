@@ -0,0 +1,133 @@
+//! The service worker which lets readers open the book while offline.
+
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::Path;
+use serde_json;
+use sha2::{Digest, Sha256};
+
+use utils::fs::create_file;
+use errors::*;
+
+/// The file the service worker is written to, at the top of the book.
+pub const SERVICE_WORKER: &str = "sw.js";
+
+/// The list of the book's files, which the service worker uses to pre-cache
+/// them and to notice a new build.
+pub const ASSET_MANIFEST: &str = "asset-manifest.json";
+
+#[derive(Debug, PartialEq, Serialize)]
+struct AssetManifest {
+    /// Changes whenever any of the files do.
+    version: String,
+    /// Whether the service worker caches every file as soon as it's
+    /// installed, instead of the pages as they're visited.
+    precache: bool,
+    files: Vec<String>,
+}
+
+/// Write the asset manifest for everything in `destination` and the service
+/// worker (from the theme's `sw.js`) with the manifest built in, so it
+/// changes, and browsers update it, with every build which changes the book.
+pub fn write_service_worker(destination: &Path, service_worker: &[u8], precache: bool) -> Result<()> {
+    let mut files = Vec::new();
+    list_files(destination, destination, &mut files)?;
+    files.retain(|file| file != SERVICE_WORKER && file != ASSET_MANIFEST);
+    files.sort();
+
+    let mut hasher = Sha256::default();
+    for file in &files {
+        let mut contents = Vec::new();
+        File::open(destination.join(file))?.read_to_end(&mut contents)?;
+        hasher.input(file.as_bytes());
+        hasher.input(&contents);
+    }
+    let version = format!("{:x}", hasher.result());
+
+    let manifest = AssetManifest {
+        version: version[..16].to_string(),
+        precache: precache,
+        files: files,
+    };
+    let manifest = serde_json::to_string_pretty(&manifest)
+        .chain_err(|| format!("Unable to serialize {}", ASSET_MANIFEST))?;
+
+    create_file(&destination.join(ASSET_MANIFEST))?.write_all(manifest.as_bytes())?;
+
+    let mut sw = create_file(&destination.join(SERVICE_WORKER))?;
+    writeln!(sw, "var ASSET_MANIFEST = {};\n", manifest)?;
+    sw.write_all(service_worker)?;
+
+    Ok(())
+}
+
+/// The files in `dir`, relative to `root` and with `/` separators, since
+/// they're used as URLs.
+fn list_files(root: &Path, dir: &Path, files: &mut Vec<String>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            list_files(root, &path, files)?;
+        } else if let Ok(relative) = path.strip_prefix(root) {
+            let parts: Vec<_> = relative
+                .components()
+                .map(|part| part.as_os_str().to_string_lossy().into_owned())
+                .collect();
+            files.push(parts.join("/"));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utils::fs::file_to_string;
+    use tempdir::TempDir;
+
+    #[test]
+    fn the_manifest_lists_every_file_of_the_book() {
+        let temp = TempDir::new("mdbook").unwrap();
+        for name in &["index.html", "guide/caching.html", "book.css"] {
+            create_file(&temp.path().join(name)).unwrap();
+        }
+
+        write_service_worker(temp.path(), b"// the worker\n", true).unwrap();
+        let manifest: serde_json::Value =
+            serde_json::from_str(&file_to_string(temp.path().join(ASSET_MANIFEST)).unwrap())
+                .unwrap();
+
+        assert_eq!(
+            manifest["files"],
+            json!(["book.css", "guide/caching.html", "index.html"])
+        );
+        assert_eq!(manifest["precache"], json!(true));
+
+        let sw = file_to_string(temp.path().join(SERVICE_WORKER)).unwrap();
+        assert!(sw.starts_with("var ASSET_MANIFEST = {"));
+        assert!(sw.ends_with("// the worker\n"));
+    }
+
+    #[test]
+    fn the_version_changes_with_the_book() {
+        let temp = TempDir::new("mdbook").unwrap();
+        let version = || {
+            write_service_worker(temp.path(), b"", false).unwrap();
+            let manifest: serde_json::Value =
+                serde_json::from_str(&file_to_string(temp.path().join(ASSET_MANIFEST)).unwrap())
+                    .unwrap();
+            manifest["version"].clone()
+        };
+
+        create_file(&temp.path().join("index.html")).unwrap();
+        let first = version();
+        assert_eq!(first, version());
+
+        create_file(&temp.path().join("index.html"))
+            .unwrap()
+            .write_all(b"<p>Changed</p>")
+            .unwrap();
+        assert_ne!(first, version());
+    }
+}
@@ -0,0 +1,195 @@
+//! Pages for the parts of a book (the `# Part` titles in `SUMMARY.md`),
+//! listing the chapters in them, so the part titles in the sidebar can link
+//! somewhere.
+
+use std::path::{Path, PathBuf};
+use toml;
+
+use book::{Book, BookItem, Chapter};
+use config::SlugStyle;
+use utils;
+use utils::escape_html;
+
+/// The directory the generated pages go in.
+pub const PARTS_DIR: &str = "parts";
+
+/// A part title and the top-level chapters up to the next one.
+#[derive(Debug)]
+pub struct Part<'a> {
+    pub title: &'a str,
+    pub chapters: Vec<&'a Chapter>,
+    /// The chapter which the part title links to instead of a generated
+    /// page, marked with `part-index = true` in its front matter.
+    pub index: Option<&'a Chapter>,
+    /// The source path of the generated page, e.g. `parts/getting-started.md`.
+    pub path: PathBuf,
+}
+
+impl<'a> Part<'a> {
+    /// The path of the page the part title links to.
+    pub fn page(&self) -> &Path {
+        match self.index {
+            Some(ch) => &ch.path,
+            None => &self.path,
+        }
+    }
+}
+
+/// The parts of the book, in order. Hidden chapters are left out.
+///
+/// A generated page never takes the path of one of the book's chapters (say,
+/// a `src/parts/guide.md` which is in `SUMMARY.md`), it gets a number added
+/// to its name instead.
+pub fn parts(book: &Book) -> Vec<Part> {
+    let chapters: Vec<&Path> = book.iter()
+        .filter_map(|item| match *item {
+            BookItem::Chapter(ref ch) => Some(ch.path.as_path()),
+            _ => None,
+        })
+        .collect();
+    let mut parts: Vec<Part> = Vec::new();
+
+    for item in book.sections() {
+        match *item {
+            BookItem::PartTitle(ref title) => {
+                let mut slug = utils::slugify(title, SlugStyle::Ascii);
+                if slug.is_empty() {
+                    slug = format!("part-{}", parts.len() + 1);
+                }
+                let mut path = Path::new(PARTS_DIR).join(format!("{}.md", slug));
+                let mut n = 1;
+                while is_taken(&path, &chapters, &parts) {
+                    n += 1;
+                    path = Path::new(PARTS_DIR).join(format!("{}-{}.md", slug, n));
+                }
+
+                parts.push(Part {
+                    title: title,
+                    chapters: Vec::new(),
+                    index: None,
+                    path: path,
+                });
+            }
+            BookItem::Chapter(ref ch) if !ch.hidden => {
+                if let Some(part) = parts.last_mut() {
+                    part.chapters.push(ch);
+                    if part.index.is_none() && is_part_index(ch) {
+                        part.index = Some(ch);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    parts
+}
+
+/// Whether a generated page can't go at `path`, because a chapter or another
+/// part's page is already there.
+fn is_taken(path: &Path, chapters: &[&Path], parts: &[Part]) -> bool {
+    chapters.contains(&path) || parts.iter().any(|part| part.path == path)
+}
+
+fn is_part_index(ch: &Chapter) -> bool {
+    match ch.front_matter.get("part-index") {
+        Some(&toml::Value::Boolean(index)) => index,
+        _ => false,
+    }
+}
+
+/// The HTML of a part's generated page, listing its chapters with the
+/// `description` from their front matter. `link` gives the URL of a chapter.
+pub fn page_content<F>(part: &Part, link: F) -> String
+where
+    F: Fn(&Chapter) -> String,
+{
+    let mut content = format!(
+        "<h1>{}</h1>\n<ul class=\"part-chapters\">\n",
+        escape_html(part.title)
+    );
+
+    for &ch in &part.chapters {
+        content.push_str(&format!(
+            "<li><a href=\"{}\">{}</a>",
+            escape_html(&link(ch)),
+            escape_html(&ch.name)
+        ));
+        if let Some(&toml::Value::String(ref description)) = ch.front_matter.get("description") {
+            content.push_str(&format!("<p>{}</p>", escape_html(description)));
+        }
+        content.push_str("</li>\n");
+    }
+
+    content.push_str("</ul>\n");
+    content
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn book() -> Book {
+        let mut intro = Chapter::new("Intro", String::new(), "intro.md");
+        intro
+            .front_matter
+            .insert("description".to_string(), toml::Value::from("Where to start"));
+        let mut overview = Chapter::new("Overview", String::new(), "guide/index.md");
+        overview
+            .front_matter
+            .insert("part-index".to_string(), toml::Value::from(true));
+
+        let mut book = Book::new();
+        book.push_item(Chapter::new("Preface", String::new(), "preface.md"))
+            .push_item(BookItem::PartTitle(String::from("Getting Started")))
+            .push_item(intro)
+            .push_item(Chapter::new("Setup & <Tools>", String::new(), "setup.md"))
+            .push_item(BookItem::PartTitle(String::from("Guide")))
+            .push_item(overview);
+        book
+    }
+
+    #[test]
+    fn chapters_are_grouped_under_their_part_title() {
+        let book = book();
+        let parts = parts(&book);
+
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].title, "Getting Started");
+        assert_eq!(parts[0].chapters.len(), 2);
+        assert!(parts[0].index.is_none());
+        assert_eq!(parts[0].page(), Path::new("parts/getting-started.md"));
+
+        assert_eq!(parts[1].index.map(|ch| ch.name.as_str()), Some("Overview"));
+        assert_eq!(parts[1].page(), Path::new("guide/index.md"));
+    }
+
+    #[test]
+    fn generated_pages_dont_replace_chapters() {
+        let mut book = Book::new();
+        book.push_item(BookItem::PartTitle(String::from("Guide")))
+            .push_item(Chapter::new("Guide", String::new(), "parts/guide.md"))
+            .push_item(BookItem::PartTitle(String::from("Guide")));
+
+        let parts = parts(&book);
+
+        assert_eq!(parts[0].path, Path::new("parts/guide-2.md"));
+        assert_eq!(parts[1].path, Path::new("parts/guide-3.md"));
+    }
+
+    #[test]
+    fn the_page_lists_the_chapters_with_their_descriptions() {
+        let book = book();
+        let parts = parts(&book);
+
+        let got = page_content(&parts[0], |ch| ch.path.with_extension("html").display().to_string());
+
+        assert_eq!(
+            got,
+            "<h1>Getting Started</h1>\n<ul class=\"part-chapters\">\n\
+             <li><a href=\"intro.html\">Intro</a><p>Where to start</p></li>\n\
+             <li><a href=\"setup.html\">Setup &amp; &lt;Tools&gt;</a></li>\n\
+             </ul>\n"
+        );
+    }
+}
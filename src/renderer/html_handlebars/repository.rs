@@ -0,0 +1,115 @@
+//! Links to the book's git repository, so themes can have "view source" and
+//! "report issue" links on every chapter.
+
+use std::path::Path;
+use std::process::Command;
+
+use config::HtmlConfig;
+
+/// The source URL of a chapter when there's no `source-url-template`, which
+/// works for GitHub, GitLab and Gitea.
+const DEFAULT_SOURCE_URL: &str = "{url}/blob/master/{path}";
+
+/// The book's repository, from `output.html.git-repository-url`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Repository {
+    url: String,
+    source_url: String,
+    /// Where the book's root is in the repository, e.g. `docs/book/`.
+    prefix: String,
+}
+
+impl Repository {
+    /// The repository of the book in `root`, or `None` if the configuration
+    /// doesn't have one.
+    pub fn from_config(root: &Path, html: &HtmlConfig) -> Option<Repository> {
+        html.git_repository_url.as_ref().map(|url| {
+            let template = html.source_url_template.as_ref().map(|t| t.as_str());
+            Repository::new(url, template, git_prefix(root))
+        })
+    }
+
+    pub fn new(url: &str, source_url: Option<&str>, prefix: String) -> Repository {
+        let url = url.trim_right_matches('/');
+        let source_url = source_url.unwrap_or(DEFAULT_SOURCE_URL).replace("{url}", url);
+
+        Repository {
+            url: url.to_string(),
+            source_url: source_url,
+            prefix: prefix,
+        }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// The path of a file in the repository, given its path from the book's
+    /// root.
+    pub fn source_path(&self, path: &Path) -> String {
+        let path = path.to_string_lossy().replace('\\', "/");
+        format!("{}{}", self.prefix, path.trim_left_matches("./"))
+    }
+
+    /// The URL of a file in the repository, given its path from the book's
+    /// root.
+    pub fn source_url(&self, path: &Path) -> String {
+        self.source_url.replace("{path}", &self.source_path(path))
+    }
+}
+
+/// The path from the top of the git repository to `root`, ending in a `/`
+/// (or empty when the book is at the top, or isn't in a repository).
+fn git_prefix(root: &Path) -> String {
+    let output = Command::new("git")
+        .arg("rev-parse")
+        .arg("--show-prefix")
+        .current_dir(root)
+        .output();
+
+    match output {
+        Ok(ref output) if output.status.success() => {
+            String::from_utf8_lossy(&output.stdout).trim().to_string()
+        }
+        _ => {
+            debug!("Unable to find where {} is in its git repository", root.display());
+            String::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn source_urls_point_into_the_repository() {
+        let repository = Repository::new(
+            "https://github.com/rust-lang/book/",
+            None,
+            String::from("second-edition/"),
+        );
+        let path = Path::new("src").join("intro.md");
+
+        assert_eq!(repository.url(), "https://github.com/rust-lang/book");
+        assert_eq!(repository.source_path(&path), "second-edition/src/intro.md");
+        assert_eq!(
+            repository.source_url(&path),
+            "https://github.com/rust-lang/book/blob/master/second-edition/src/intro.md"
+        );
+    }
+
+    #[test]
+    fn the_source_url_can_follow_a_template() {
+        let repository = Repository::new(
+            "https://git.example.com/book",
+            Some("{url}/src/branch/main/{path}?plain=1"),
+            String::new(),
+        );
+
+        assert_eq!(
+            repository.source_url(Path::new("./src/intro.md")),
+            "https://git.example.com/book/src/branch/main/src/intro.md?plain=1"
+        );
+    }
+}
@@ -0,0 +1,49 @@
+//! The `robots.txt` telling search engines which parts of the site they may
+//! crawl.
+
+use config::RobotsConfig;
+
+/// The file the rules are written to, at the top of the book.
+pub const ROBOTS_TXT: &str = "robots.txt";
+
+/// The contents of `robots.txt` for the given configuration.
+pub fn robots_txt(config: &RobotsConfig) -> String {
+    let mut robots = String::from("User-agent: *\n");
+
+    if config.disallow.is_empty() {
+        // an empty rule allows everything
+        robots.push_str("Disallow:\n");
+    }
+    for path in &config.disallow {
+        robots.push_str(&format!("Disallow: {}\n", path));
+    }
+
+    if let Some(ref sitemap) = config.sitemap {
+        robots.push_str(&format!("\nSitemap: {}\n", sitemap));
+    }
+
+    robots
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_disallowed_path_gets_a_rule() {
+        assert_eq!(
+            robots_txt(&RobotsConfig::default()),
+            "User-agent: *\nDisallow:\n"
+        );
+
+        let config = RobotsConfig {
+            disallow: vec![String::from("/drafts/"), String::from("/internal.html")],
+            sitemap: Some(String::from("https://example.com/sitemap.xml")),
+        };
+        assert_eq!(
+            robots_txt(&config),
+            "User-agent: *\nDisallow: /drafts/\nDisallow: /internal.html\n\
+             \nSitemap: https://example.com/sitemap.xml\n"
+        );
+    }
+}
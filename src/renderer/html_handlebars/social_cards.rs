@@ -0,0 +1,208 @@
+//! The preview images shown for links to the book's chapters on social
+//! media, rendered as SVG and turned into PNG by an external converter.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use config::SocialCardsConfig;
+use utils::{escape_html, process};
+use utils::fs::{copy_file_if_changed, write_file_if_changed};
+use errors::*;
+
+/// The directory the cards are put in, at the top of the book.
+pub const CARDS_DIR: &str = "cards";
+
+const WIDTH: usize = 1200;
+const HEIGHT: usize = 630;
+/// How many characters of the title fit on one line of the card.
+const LINE_LENGTH: usize = 28;
+const MAX_LINES: usize = 3;
+
+/// The background, text and accent colors of each of the built-in themes.
+fn palette(theme: &str) -> Option<(&'static str, &'static str, &'static str)> {
+    match theme {
+        "light" => Some(("#ffffff", "#333333", "#4183c4")),
+        "rust" => Some(("#e1e1db", "#262625", "#2b79a2")),
+        "coal" => Some(("#141617", "#98a3ad", "#2b79a2")),
+        "navy" => Some(("#161923", "#bcbdd0", "#2b79a2")),
+        "ayu" => Some(("#0f1419", "#c5c5c5", "#0096cf")),
+        _ => None,
+    }
+}
+
+/// Where the card of the chapter rendered to `page` goes, relative to the
+/// top of the book.
+pub fn card_path(page: &Path) -> PathBuf {
+    Path::new(CARDS_DIR).join(page.with_extension("png"))
+}
+
+/// Render the card for a chapter into `destination`.
+///
+/// The cards are kept in the `social-cards` directory of `cache_dir` as well,
+/// along with the SVG they were converted from, so the (slow) converter only
+/// runs for the cards which changed since they were last converted.
+pub fn write_card(
+    destination: &Path,
+    cache_dir: &Path,
+    page: &Path,
+    title: &str,
+    book_title: &str,
+    config: &SocialCardsConfig,
+) -> Result<()> {
+    let svg = card_svg(title, book_title, &config.theme)?;
+    let png = cache_dir.join("social-cards").join(card_path(page));
+    let svg_path = png.with_extension("svg");
+
+    let changed = write_file_if_changed(&svg_path, svg.as_bytes())?;
+    if changed || !is_newer(&png, &svg_path) {
+        convert(&svg_path, &png, page, config)?;
+    } else {
+        trace!("The card of {} is up to date", page.display());
+    }
+
+    let card = destination.join(card_path(page));
+    if let Some(parent) = card.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    copy_file_if_changed(&png, &card)?;
+
+    Ok(())
+}
+
+/// Whether `path` was modified after `than`.
+fn is_newer(path: &Path, than: &Path) -> bool {
+    match (fs::metadata(path), fs::metadata(than)) {
+        (Ok(path), Ok(than)) => match (path.modified(), than.modified()) {
+            (Ok(path), Ok(than)) => path >= than,
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// Turn the SVG of the card for `page` into a PNG with the converter.
+fn convert(svg: &Path, png: &Path, page: &Path, config: &SocialCardsConfig) -> Result<()> {
+    let input_arg = svg.to_string_lossy();
+    let output_arg = png.to_string_lossy();
+    let mut cmd = process::command(
+        &config.converter,
+        &[("{input}", &*input_arg), ("{output}", &*output_arg)],
+    ).chain_err(|| "Invalid social card converter")?;
+
+    debug!("Running {:?}", cmd);
+    let output = cmd.output().chain_err(|| {
+        format!(
+            "Unable to run `{}`, which turns the social cards into PNG images",
+            config.converter
+        )
+    })?;
+    if !output.status.success() {
+        bail!(ErrorKind::Subprocess(
+            format!("The social card converter failed for {}", page.display()),
+            output
+        ));
+    }
+
+    Ok(())
+}
+
+/// The card as an SVG image: the chapter's title in large letters and the
+/// book's title underneath, in the colors of one of the themes.
+fn card_svg(title: &str, book_title: &str, theme: &str) -> Result<String> {
+    let (background, color, accent) = match palette(theme) {
+        Some(palette) => palette,
+        None => bail!("\"{}\" isn't one of the built-in themes", theme),
+    };
+
+    let lines = wrap(title, LINE_LENGTH, MAX_LINES);
+    let mut text = String::new();
+    for (i, line) in lines.iter().enumerate() {
+        text.push_str(&format!(
+            "  <text x=\"80\" y=\"{}\" font-size=\"72\" font-weight=\"bold\">{}</text>\n",
+            220 + i * 90,
+            escape_html(line)
+        ));
+    }
+
+    Ok(format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" \
+         viewBox=\"0 0 {width} {height}\">\n\
+         <g font-family=\"Open Sans, sans-serif\" fill=\"{color}\">\n\
+         \x20 <rect width=\"{width}\" height=\"{height}\" fill=\"{background}\"/>\n\
+         \x20 <rect width=\"{width}\" height=\"16\" fill=\"{accent}\"/>\n\
+         {text}\
+         \x20 <text x=\"80\" y=\"{footer}\" font-size=\"40\" fill=\"{accent}\">{book}</text>\n\
+         </g>\n\
+         </svg>\n",
+        width = WIDTH,
+        height = HEIGHT,
+        background = background,
+        color = color,
+        accent = accent,
+        text = text,
+        footer = HEIGHT - 80,
+        book = escape_html(book_title),
+    ))
+}
+
+/// Break `text` into lines of at most `width` characters (unless a single
+/// word is longer), cutting it short with an ellipsis after `max_lines`.
+fn wrap(text: &str, width: usize, max_lines: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut current = String::new();
+
+    for word in text.split_whitespace() {
+        let length = current.chars().count();
+        if length > 0 && length + 1 + word.chars().count() > width {
+            lines.push(current);
+            current = String::new();
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(word);
+    }
+    if !current.is_empty() {
+        lines.push(current);
+    }
+
+    if lines.len() > max_lines {
+        lines.truncate(max_lines);
+        if let Some(last) = lines.last_mut() {
+            last.push('…');
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn long_titles_are_wrapped_and_cut_short() {
+        let got = wrap("Configuring the build of a book with many chapters", 20, 2);
+        assert_eq!(got, vec!["Configuring the", "build of a book with…"]);
+
+        let got = wrap("Introduction", 20, 2);
+        assert_eq!(got, vec!["Introduction"]);
+    }
+
+    #[test]
+    fn cards_use_the_colors_of_the_theme() {
+        let got = card_svg("Rust & <You>", "The Book", "navy").unwrap();
+
+        assert!(got.contains("fill=\"#161923\""));
+        assert!(got.contains(">Rust &amp; &lt;You&gt;</text>"));
+        assert!(got.contains(">The Book</text>"));
+        assert!(card_svg("Title", "Book", "purple").is_err());
+    }
+
+    #[test]
+    fn every_chapter_has_its_own_card() {
+        assert_eq!(
+            card_path(Path::new("guide/caching.html")),
+            PathBuf::from("cards/guide/caching.png")
+        );
+    }
+}
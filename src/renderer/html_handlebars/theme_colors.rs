@@ -0,0 +1,112 @@
+//! The stylesheet generated from `output.html.theme-colors`, which puts the
+//! configured colors in CSS variables and uses them instead of the colors of
+//! the built-in themes.
+
+#[allow(unused_imports)] use std::ascii::AsciiExt;
+
+use config::ThemeColorsConfig;
+use errors::*;
+
+/// The file the stylesheet is written to, at the top of the book.
+pub const STYLESHEET: &str = "theme-colors.css";
+
+/// The rules using each color. Every selector starts with `:root` where the
+/// themes start with their class (e.g. `.light`), so the rules win over the
+/// themes' ones as the stylesheet comes after `book.css`.
+const ACCENT: &str = "\
+:root .content a:link,
+:root a:visited,
+:root a > .hljs,
+:root .chapter li .active,
+:root .chapter li a:hover {
+    color: var(--accent);
+}";
+
+const SIDEBAR_BACKGROUND: &str = "\
+:root .sidebar,
+:root .sidebar::-webkit-scrollbar,
+:root .mobile-nav-chapters {
+    background-color: var(--sidebar-background);
+}";
+
+const CODE_BACKGROUND: &str = "\
+:root .hljs {
+    background: var(--code-background);
+}";
+
+/// The contents of the stylesheet for the given colors, or `None` when none
+/// of them is set.
+pub fn stylesheet(colors: &ThemeColorsConfig) -> Result<Option<String>> {
+    let colors = [
+        ("accent", &colors.accent, ACCENT),
+        ("sidebar-background", &colors.sidebar_background, SIDEBAR_BACKGROUND),
+        ("code-background", &colors.code_background, CODE_BACKGROUND),
+    ];
+
+    let mut variables = String::new();
+    let mut rules = String::new();
+    for &(name, color, rule) in &colors {
+        if let Some(ref color) = *color {
+            if !is_color(color) {
+                bail!(
+                    "\"{}\" isn't a valid color for output.html.theme-colors.{}",
+                    color,
+                    name
+                );
+            }
+            variables.push_str(&format!("    --{}: {};\n", name, color.trim()));
+            rules.push_str(&format!("\n{}\n", rule));
+        }
+    }
+
+    if variables.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(format!(
+        "/* Generated from output.html.theme-colors in book.toml */\n\
+         :root {{\n{}}}\n{}",
+        variables, rules
+    )))
+}
+
+/// Whether a color can be put into the stylesheet as it is. This doesn't try
+/// to parse CSS colors, it only keeps out whatever could end the declaration.
+fn is_color(color: &str) -> bool {
+    !color.trim().is_empty()
+        && color
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || " #(),.%-".contains(c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_set_colors_end_up_in_the_stylesheet() {
+        assert_eq!(stylesheet(&ThemeColorsConfig::default()).unwrap(), None);
+
+        let colors = ThemeColorsConfig {
+            accent: Some(String::from("#c0392b")),
+            code_background: Some(String::from("rgb(250, 250, 245)")),
+            ..Default::default()
+        };
+        let got = stylesheet(&colors).unwrap().unwrap();
+
+        assert!(got.contains("--accent: #c0392b;"));
+        assert!(got.contains("--code-background: rgb(250, 250, 245);"));
+        assert!(got.contains("color: var(--accent);"));
+        assert!(!got.contains("sidebar-background"));
+    }
+
+    #[test]
+    fn colors_cant_sneak_in_other_css() {
+        let colors = ThemeColorsConfig {
+            accent: Some(String::from("red; } body { display: none")),
+            ..Default::default()
+        };
+
+        assert!(stylesheet(&colors).is_err());
+    }
+}
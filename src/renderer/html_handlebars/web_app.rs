@@ -0,0 +1,169 @@
+//! The web app manifest, which lets readers install the book on their phones
+//! like an app.
+
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+use serde_json;
+
+use config::WebAppConfig;
+use utils::fs::create_file;
+use errors::*;
+
+/// The file the manifest is written to, at the top of the book.
+pub const MANIFEST: &str = "manifest.webmanifest";
+
+/// Write the book's web app manifest into `destination`, after the book's
+/// images (including the favicon) were copied there.
+pub fn write_manifest(
+    destination: &Path,
+    title: &str,
+    description: &str,
+    config: &WebAppConfig,
+) -> Result<()> {
+    let name = config.name.clone().unwrap_or_else(|| title.to_string());
+    let mut manifest = json!({
+        "name": name,
+        "short_name": config.short_name.clone().unwrap_or_else(|| name.clone()),
+        "start_url": "index.html",
+        "scope": "./",
+        "display": config.display,
+        "theme_color": config.theme_color,
+        "background_color": config.background_color,
+    });
+    if !description.is_empty() {
+        manifest["description"] = json!(description);
+    }
+
+    // fall back to the favicon, which is better than no icon at all
+    let icons: Vec<_> = if config.icons.is_empty() {
+        vec![icon(destination, "favicon.png", None, None)]
+    } else {
+        config
+            .icons
+            .iter()
+            .map(|i| icon(destination, &i.src, i.sizes.as_ref(), i.purpose.as_ref()))
+            .collect()
+    };
+    manifest["icons"] = json!(icons);
+
+    let manifest = serde_json::to_string_pretty(&manifest)
+        .chain_err(|| format!("Unable to serialize {}", MANIFEST))?;
+    create_file(&destination.join(MANIFEST))?.write_all(manifest.as_bytes())?;
+
+    Ok(())
+}
+
+/// An icon's entry in the manifest. Its size is read from the image when it
+/// isn't given.
+fn icon(
+    destination: &Path,
+    src: &str,
+    sizes: Option<&String>,
+    purpose: Option<&String>,
+) -> serde_json::Value {
+    let mut icon = json!({ "src": src });
+
+    let extension = Path::new(src)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let mime_type = match extension.as_str() {
+        "png" => Some("image/png"),
+        "svg" => Some("image/svg+xml"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "webp" => Some("image/webp"),
+        "ico" => Some("image/x-icon"),
+        _ => None,
+    };
+    if let Some(mime_type) = mime_type {
+        icon["type"] = json!(mime_type);
+    }
+
+    let sizes = match sizes {
+        Some(sizes) => Some(sizes.clone()),
+        None if extension == "svg" => Some(String::from("any")),
+        None if extension == "png" => png_size(&destination.join(src))
+            .map(|(width, height)| format!("{}x{}", width, height)),
+        None => None,
+    };
+    if let Some(sizes) = sizes {
+        icon["sizes"] = json!(sizes);
+    }
+    if let Some(purpose) = purpose {
+        icon["purpose"] = json!(purpose);
+    }
+
+    icon
+}
+
+/// The width and height of a PNG image, from its header.
+fn png_size(path: &Path) -> Option<(u32, u32)> {
+    let mut header = [0; 24];
+    File::open(path).and_then(|mut f| f.read_exact(&mut header)).ok()?;
+
+    if &header[..8] != b"\x89PNG\r\n\x1a\n" || &header[12..16] != b"IHDR" {
+        return None;
+    }
+    let number = |bytes: &[u8]| {
+        bytes
+            .iter()
+            .fold(0u32, |number, &byte| (number << 8) | u32::from(byte))
+    };
+
+    Some((number(&header[16..20]), number(&header[20..24])))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::WebAppIcon;
+    use theme;
+    use utils::fs::file_to_string;
+    use tempdir::TempDir;
+
+    #[test]
+    fn the_size_of_png_icons_is_filled_in() {
+        let temp = TempDir::new("mdbook").unwrap();
+        create_file(&temp.path().join("favicon.png"))
+            .unwrap()
+            .write_all(theme::FAVICON)
+            .unwrap();
+
+        let got = icon(temp.path(), "favicon.png", None, None);
+
+        assert_eq!(got["type"], json!("image/png"));
+        assert!(got["sizes"].as_str().unwrap().contains('x'));
+        assert_eq!(icon(temp.path(), "missing.png", None, None).get("sizes"), None);
+    }
+
+    #[test]
+    fn the_manifest_defaults_to_the_book_title() {
+        let temp = TempDir::new("mdbook").unwrap();
+        let config = WebAppConfig {
+            icons: vec![WebAppIcon {
+                src: String::from("icons/logo.svg"),
+                sizes: None,
+                purpose: Some(String::from("maskable")),
+            }],
+            ..Default::default()
+        };
+
+        write_manifest(temp.path(), "My Book", "", &config).unwrap();
+        let got: serde_json::Value =
+            serde_json::from_str(&file_to_string(temp.path().join(MANIFEST)).unwrap()).unwrap();
+
+        assert_eq!(got["name"], json!("My Book"));
+        assert_eq!(got["short_name"], json!("My Book"));
+        assert_eq!(got.get("description"), None);
+        assert_eq!(
+            got["icons"],
+            json!([{
+                "src": "icons/logo.svg",
+                "type": "image/svg+xml",
+                "sizes": "any",
+                "purpose": "maskable"
+            }])
+        );
+    }
+}
@@ -11,8 +11,11 @@
 //! [For Developers]: https://rust-lang-nursery.github.io/mdBook/lib/index.html
 //! [RenderContext]: struct.RenderContext.html
 
+pub use self::event_filter::{CmdEventFilter, EventFilter};
 pub use self::html_handlebars::HtmlHandlebars;
+pub(crate) use self::html_handlebars::output_path as html_output_path;
 
+mod event_filter;
 mod html_handlebars;
 
 use std::fs;
@@ -24,7 +27,8 @@ use shlex::Shlex;
 
 use errors::*;
 use config::Config;
-use book::Book;
+use book::{Book, MountedBooks};
+use utils::BuildInfo;
 
 const MDBOOK_VERSION: &str = env!("CARGO_PKG_VERSION");
 
@@ -40,6 +44,41 @@ const MDBOOK_VERSION: &str = env!("CARGO_PKG_VERSION");
 ///
 /// [HtmlHandlebars]: struct.HtmlHandlebars.html
 /// [CmdRenderer]: struct.CmdRenderer.html
+///
+/// Any other implementation can be registered with
+/// [`MDBook::with_renderer()`], which runs it alongside (or, when it has the
+/// same name, instead of) the renderers from `book.toml`:
+///
+/// ```rust,no_run
+/// # extern crate mdbook;
+/// use mdbook::MDBook;
+/// use mdbook::errors::Result;
+/// use mdbook::renderer::{RenderContext, Renderer};
+///
+/// struct WordCount;
+///
+/// impl Renderer for WordCount {
+///     fn name(&self) -> &str {
+///         "wordcount"
+///     }
+///
+///     fn render(&self, ctx: &RenderContext) -> Result<()> {
+///         // count the words in ctx.book and write them to ctx.destination
+///         Ok(())
+///     }
+/// }
+///
+/// # fn main() {
+/// MDBook::load("/path/to/book")
+///     .unwrap()
+///     .without_renderer("html")
+///     .with_renderer(WordCount)
+///     .build()
+///     .unwrap();
+/// # }
+/// ```
+///
+/// [`MDBook::with_renderer()`]: ../book/struct.MDBook.html#method.with_renderer
 pub trait Renderer {
     /// The `Renderer`'s name.
     fn name(&self) -> &str;
@@ -47,6 +86,15 @@ pub trait Renderer {
     /// Invoke the `Renderer`, passing in all the necessary information for
     /// describing a book.
     fn render(&self, ctx: &RenderContext) -> Result<()>;
+
+    /// Can this `Renderer` update a previous build by only rendering the
+    /// chapters listed in `RenderContext::changed_chapters`? If not, books
+    /// using it are always built in full.
+    ///
+    /// By default renderers don't support incremental builds.
+    fn supports_incremental(&self) -> bool {
+        false
+    }
 }
 
 /// The context provided to all renderers.
@@ -66,6 +114,19 @@ pub struct RenderContext {
     /// renderers to cache intermediate results, this directory is not
     /// guaranteed to be empty or even exist.
     pub destination: PathBuf,
+    /// For an incremental build, the paths of the chapters which changed
+    /// since the book was last rendered into `destination`. The other
+    /// chapters' pages are still up to date. `None` means everything needs
+    /// to be rendered.
+    #[serde(default)]
+    pub changed_chapters: Option<Vec<PathBuf>>,
+    /// The books mounted as parts of this one (`build.mounts`).
+    #[serde(default)]
+    pub mounts: MountedBooks,
+    /// The build information, collected once per build. `None` means it
+    /// hasn't been collected yet.
+    #[serde(default)]
+    pub build_info: Option<BuildInfo>,
 }
 
 impl RenderContext {
@@ -81,6 +142,9 @@ impl RenderContext {
             version: MDBOOK_VERSION.to_string(),
             root: root.into(),
             destination: destination.into(),
+            changed_chapters: None,
+            mounts: MountedBooks::default(),
+            build_info: None,
         }
     }
 
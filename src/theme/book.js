@@ -16,7 +16,10 @@ function playpen_text(playpen) {
 (function codeSnippets() {
     // Hide Rust code lines prepended with a specific character
     var hiding_character = "#";
-    var request = fetch("https://play.rust-lang.org/meta/crates", {
+    var playpen_config = window.playpen_config || {};
+    var playpen_url = playpen_config.url || "https://play.rust-lang.org";
+    // without the playground, the play buttons simply stay hidden
+    var request = playpen_config.disabled ? null : fetch(playpen_url + "/meta/crates", {
         headers: {
             'Content-Type': "application/json",
         },
@@ -41,7 +44,7 @@ function playpen_text(playpen) {
     }
 
     // updates the visibility of play button based on `no_run` class and
-    // used crates vs ones available on http://play.rust-lang.org
+    // used crates vs ones available on the configured playground
     function update_play_button(pre_block, playground_crates) {
         var play_button = pre_block.querySelector(".play-button");
 
@@ -96,7 +99,7 @@ function playpen_text(playpen) {
 
         result_block.innerText = "Running...";
 
-        var request = fetch("https://play.rust-lang.org/execute", {
+        var request = fetch(playpen_url + "/execute", {
             headers: {
                 'Content-Type': "application/json",
             },
@@ -272,15 +275,17 @@ function playpen_text(playpen) {
         }
     });
 
-    request
-        .then(function (response) { return response.json(); })
-        .then(function (response) {
-            // get list of crates available in the rust playground
-            let playground_crates = response.crates.map(function (item) { return item["id"]; });
-            Array.from(document.querySelectorAll(".playpen")).forEach(function (block) {
-                handle_crate_list_update(block, playground_crates);
+    if (request) {
+        request
+            .then(function (response) { return response.json(); })
+            .then(function (response) {
+                // get list of crates available in the rust playground
+                let playground_crates = response.crates.map(function (item) { return item["id"]; });
+                Array.from(document.querySelectorAll(".playpen")).forEach(function (block) {
+                    handle_crate_list_update(block, playground_crates);
+                });
             });
-        });
+    }
 
 })();
 
@@ -330,6 +335,10 @@ function playpen_text(playpen) {
             ace_theme = "ace/theme/dawn";
         }
 
+        if (window.playpen_config && window.playpen_config.editorTheme) {
+            ace_theme = "ace/theme/" + window.playpen_config.editorTheme;
+        }
+
         setTimeout(function () {
             themeColorMetaTag.content = getComputedStyle(document.body).backgroundColor;
         }, 1);
@@ -412,6 +421,43 @@ function playpen_text(playpen) {
     });
 })();
 
+(function fontSize() {
+    var html = document.querySelector('html');
+    var decreaseButton = document.getElementById('font-size-decrease');
+    var increaseButton = document.getElementById('font-size-increase');
+    // the same limits as the `font-size` setting in book.toml
+    var MIN = 50;
+    var MAX = 200;
+    var STEP = 10;
+
+    if (!decreaseButton || !increaseButton) {
+        return;
+    }
+
+    function set_font_size(size) {
+        size = Math.min(Math.max(size, MIN), MAX);
+        html.style.fontSize = size + '%';
+        decreaseButton.disabled = size <= MIN;
+        increaseButton.disabled = size >= MAX;
+        try { localStorage.setItem('mdbook-font-size', size); } catch (e) { }
+    }
+
+    function current() {
+        return parseInt(html.style.fontSize, 10) || 100;
+    }
+
+    decreaseButton.addEventListener('click', function () {
+        set_font_size(current() - STEP);
+    });
+
+    increaseButton.addEventListener('click', function () {
+        set_font_size(current() + STEP);
+    });
+
+    decreaseButton.disabled = current() <= MIN;
+    increaseButton.disabled = current() >= MAX;
+})();
+
 (function sidebar() {
     var html = document.querySelector("html");
     var sidebar = document.getElementById("sidebar");
@@ -481,6 +527,44 @@ function playpen_text(playpen) {
         }
     }, { passive: true });
 
+    // Resize the sidebar by dragging its edge
+    var resizeHandle = document.getElementById('sidebar-resize-handle');
+    var widths = window.sidebar_width || { default: 300, min: 150, max: 600 };
+
+    function setSidebarWidth(width, remember) {
+        width = Math.min(Math.max(width, widths.min), widths.max);
+        html.style.setProperty('--sidebar-width', width + 'px');
+        if (remember) {
+            try { localStorage.setItem('mdbook-sidebar-width', width); } catch (e) { }
+        }
+    }
+
+    function resize(e) {
+        setSidebarWidth(e.clientX, false);
+    }
+
+    function stopResizing(e) {
+        html.classList.remove('sidebar-resizing');
+        document.removeEventListener('mousemove', resize);
+        document.removeEventListener('mouseup', stopResizing);
+        setSidebarWidth(e.clientX, true);
+    }
+
+    if (resizeHandle) {
+        resizeHandle.addEventListener('mousedown', function (e) {
+            e.preventDefault();
+            // the sliding animations would lag behind the mouse
+            html.classList.add('sidebar-resizing');
+            document.addEventListener('mousemove', resize);
+            document.addEventListener('mouseup', stopResizing);
+        });
+
+        resizeHandle.addEventListener('dblclick', function () {
+            setSidebarWidth(widths.default, false);
+            try { localStorage.removeItem('mdbook-sidebar-width'); } catch (e) { }
+        });
+    }
+
     // Scroll sidebar to current active section
     var activeSection = sidebar.querySelector(".active");
     if (activeSection) {
@@ -489,24 +573,50 @@ function playpen_text(playpen) {
 })();
 
 (function chapterNavigation() {
+    var shortcuts = window.keyboard_shortcuts || {
+        "previous": ["ArrowLeft"],
+        "next": ["ArrowRight"],
+        "toggle-sidebar": [],
+        "enabled": true,
+        "ignore": []
+    };
+    var enabled = shortcuts.enabled !== false;
+    // don't steal keys from text inputs and editable code blocks
+    var ignored = ['input', 'textarea', '.ace_editor'].concat(shortcuts.ignore || []);
+
+    function matches(action, key) {
+        return (shortcuts[action] || []).indexOf(key) !== -1;
+    }
+
+    // Lets scripts in the book turn the shortcuts off while a widget needs
+    // the keys, or keep them away from parts of the page
+    window.keyboard_navigation = {
+        enable: function () { enabled = true; },
+        disable: function () { enabled = false; },
+        ignore: function (selector) { ignored.push(selector); },
+        isEnabled: function () { return enabled; }
+    };
+
     document.addEventListener('keydown', function (e) {
+        if (!enabled || e.defaultPrevented) { return; }
         if (e.altKey || e.ctrlKey || e.metaKey || e.shiftKey) { return; }
+        if (e.target.closest && e.target.closest(ignored.join(', '))) { return; }
 
-        switch (e.key) {
-            case 'ArrowRight':
-                e.preventDefault();
-                var nextButton = document.querySelector('.nav-chapters.next');
-                if (nextButton) {
-                    window.location.href = nextButton.href;
-                }
-                break;
-            case 'ArrowLeft':
-                e.preventDefault();
-                var previousButton = document.querySelector('.nav-chapters.previous');
-                if (previousButton) {
-                    window.location.href = previousButton.href;
-                }
-                break;
+        if (matches('next', e.key)) {
+            e.preventDefault();
+            var nextButton = document.querySelector('.nav-chapters.next');
+            if (nextButton) {
+                window.location.href = nextButton.href;
+            }
+        } else if (matches('previous', e.key)) {
+            e.preventDefault();
+            var previousButton = document.querySelector('.nav-chapters.previous');
+            if (previousButton) {
+                window.location.href = previousButton.href;
+            }
+        } else if (matches('toggle-sidebar', e.key)) {
+            e.preventDefault();
+            document.getElementById('sidebar-toggle').click();
         }
     });
 })();
@@ -556,6 +666,66 @@ function playpen_text(playpen) {
     });
 })();
 
+(function readingPosition() {
+    var STORAGE_KEY = 'mdbook-scroll';
+    // a heading counts as the current section once it's this close to the top
+    var OFFSET = 80;
+    var headers = Array.from(document.querySelectorAll('#content main a.header[id]'));
+    var page = window.location.pathname;
+
+    function positions() {
+        try {
+            return JSON.parse(localStorage.getItem(STORAGE_KEY)) || {};
+        } catch (e) {
+            return {};
+        }
+    }
+
+    // Come back to where the reader left the chapter, unless the link which
+    // got them here points at a section
+    if (!window.location.hash) {
+        var top = positions()[page];
+        if (top) {
+            document.scrollingElement.scrollTop = top;
+        }
+    }
+
+    function currentSection() {
+        var current = null;
+        for (var i = 0; i < headers.length; i++) {
+            if (headers[i].getBoundingClientRect().top > OFFSET) {
+                break;
+            }
+            current = headers[i];
+        }
+        return current;
+    }
+
+    var pending = null;
+
+    function update() {
+        pending = null;
+
+        var saved = positions();
+        saved[page] = document.scrollingElement.scrollTop;
+        try { localStorage.setItem(STORAGE_KEY, JSON.stringify(saved)); } catch (e) { }
+
+        // replaceState changes the URL without scrolling or adding to the
+        // history, so copying the URL links to the section being read
+        var section = currentSection();
+        var hash = section ? '#' + section.id : '';
+        if (window.history.replaceState && hash !== window.location.hash) {
+            window.history.replaceState(null, '', window.location.pathname + window.location.search + hash);
+        }
+    }
+
+    document.addEventListener('scroll', function () {
+        if (pending === null) {
+            pending = setTimeout(update, 200);
+        }
+    }, { passive: true });
+})();
+
 (function autoHideMenu() {
     var menu = document.getElementById('menu-bar');
 
@@ -579,3 +749,26 @@ function playpen_text(playpen) {
         previousScrollTop = document.scrollingElement.scrollTop;
     }, { passive: true });
 })();
+
+(function embed() {
+    if (!document.documentElement.classList.contains('embed')) {
+        return;
+    }
+
+    // the page the book is embedded in most likely needs the keys itself
+    if (window.keyboard_navigation) {
+        window.keyboard_navigation.disable();
+    }
+
+    // stay embedded when following links to other chapters
+    Array.from(document.querySelectorAll('#content main a[href]')).forEach(function (link) {
+        var href = link.getAttribute('href');
+        if (/^([a-z][a-z0-9+.-]*:|\/\/|#)/i.test(href) || /[?&]embed(=|&|$)/.test(href)) {
+            return;
+        }
+        var hash = href.indexOf('#');
+        var path = hash === -1 ? href : href.slice(0, hash);
+        var fragment = hash === -1 ? '' : href.slice(hash);
+        link.setAttribute('href', path + (path.indexOf('?') === -1 ? '?' : '&') + 'embed' + fragment);
+    });
+})();
@@ -9,6 +9,11 @@ use errors::*;
 
 pub static INDEX: &'static [u8] = include_bytes!("index.hbs");
 pub static HEADER: &'static [u8] = include_bytes!("header.hbs");
+pub static COMMENTS: &'static [u8] = include_bytes!("comments.hbs");
+pub static SIDEBAR: &'static [u8] = include_bytes!("sidebar.hbs");
+pub static MENU: &'static [u8] = include_bytes!("menu.hbs");
+pub static FOOTER: &'static [u8] = include_bytes!("footer.hbs");
+pub static SCRIPTS: &'static [u8] = include_bytes!("scripts.hbs");
 pub static CSS: &'static [u8] = include_bytes!("book.css");
 pub static FAVICON: &'static [u8] = include_bytes!("favicon.png");
 pub static JS: &'static [u8] = include_bytes!("book.js");
@@ -17,6 +22,8 @@ pub static TOMORROW_NIGHT_CSS: &'static [u8] = include_bytes!("tomorrow-night.cs
 pub static HIGHLIGHT_CSS: &'static [u8] = include_bytes!("highlight.css");
 pub static AYU_HIGHLIGHT_CSS: &'static [u8] = include_bytes!("ayu-highlight.css");
 pub static CLIPBOARD_JS: &'static [u8] = include_bytes!("clipboard.min.js");
+pub static SERVICE_WORKER: &'static [u8] = include_bytes!("sw.js");
+pub static NOTES_JS: &'static [u8] = include_bytes!("notes.js");
 pub static FONT_AWESOME: &'static [u8] = include_bytes!("_FontAwesome/css/font-awesome.min.css");
 pub static FONT_AWESOME_EOT: &'static [u8] =
     include_bytes!("_FontAwesome/fonts/fontawesome-webfont.eot");
@@ -30,6 +37,25 @@ pub static FONT_AWESOME_WOFF2: &'static [u8] =
     include_bytes!("_FontAwesome/fonts/fontawesome-webfont.woff2");
 pub static FONT_AWESOME_OTF: &'static [u8] = include_bytes!("_FontAwesome/fonts/FontAwesome.otf");
 
+/// The Open Sans and Source Code Pro fonts (with their licenses), for books
+/// which can't load them from Google Fonts.
+pub static FONTS: &'static [(&'static str, &'static [u8])] = &[
+    ("fonts/fonts.css", include_bytes!("fonts/fonts.css")),
+    ("fonts/OPEN-SANS-LICENSE.txt", include_bytes!("fonts/OPEN-SANS-LICENSE.txt")),
+    ("fonts/SOURCE-CODE-PRO-LICENSE.txt", include_bytes!("fonts/SOURCE-CODE-PRO-LICENSE.txt")),
+    ("fonts/open-sans-v17-all-charsets-300.woff2", include_bytes!("fonts/open-sans-v17-all-charsets-300.woff2")),
+    ("fonts/open-sans-v17-all-charsets-300italic.woff2", include_bytes!("fonts/open-sans-v17-all-charsets-300italic.woff2")),
+    ("fonts/open-sans-v17-all-charsets-600.woff2", include_bytes!("fonts/open-sans-v17-all-charsets-600.woff2")),
+    ("fonts/open-sans-v17-all-charsets-600italic.woff2", include_bytes!("fonts/open-sans-v17-all-charsets-600italic.woff2")),
+    ("fonts/open-sans-v17-all-charsets-700.woff2", include_bytes!("fonts/open-sans-v17-all-charsets-700.woff2")),
+    ("fonts/open-sans-v17-all-charsets-700italic.woff2", include_bytes!("fonts/open-sans-v17-all-charsets-700italic.woff2")),
+    ("fonts/open-sans-v17-all-charsets-800.woff2", include_bytes!("fonts/open-sans-v17-all-charsets-800.woff2")),
+    ("fonts/open-sans-v17-all-charsets-800italic.woff2", include_bytes!("fonts/open-sans-v17-all-charsets-800italic.woff2")),
+    ("fonts/open-sans-v17-all-charsets-italic.woff2", include_bytes!("fonts/open-sans-v17-all-charsets-italic.woff2")),
+    ("fonts/open-sans-v17-all-charsets-regular.woff2", include_bytes!("fonts/open-sans-v17-all-charsets-regular.woff2")),
+    ("fonts/source-code-pro-v11-all-charsets-500.woff2", include_bytes!("fonts/source-code-pro-v11-all-charsets-500.woff2")),
+];
+
 
 /// The `Theme` struct should be used instead of the static variables because
 /// the `new()` method will look if the user has a theme directory in his
@@ -41,6 +67,11 @@ pub static FONT_AWESOME_OTF: &'static [u8] = include_bytes!("_FontAwesome/fonts/
 pub struct Theme {
     pub index: Vec<u8>,
     pub header: Vec<u8>,
+    pub comments: Vec<u8>,
+    pub sidebar: Vec<u8>,
+    pub menu: Vec<u8>,
+    pub footer: Vec<u8>,
+    pub scripts: Vec<u8>,
     pub css: Vec<u8>,
     pub favicon: Vec<u8>,
     pub js: Vec<u8>,
@@ -49,6 +80,8 @@ pub struct Theme {
     pub ayu_highlight_css: Vec<u8>,
     pub highlight_js: Vec<u8>,
     pub clipboard_js: Vec<u8>,
+    pub service_worker: Vec<u8>,
+    pub notes_js: Vec<u8>,
 }
 
 impl Theme {
@@ -66,11 +99,18 @@ impl Theme {
             let files = vec![
                 (theme_dir.join("index.hbs"), &mut theme.index),
                 (theme_dir.join("header.hbs"), &mut theme.header),
+                (theme_dir.join("comments.hbs"), &mut theme.comments),
+                (theme_dir.join("sidebar.hbs"), &mut theme.sidebar),
+                (theme_dir.join("menu.hbs"), &mut theme.menu),
+                (theme_dir.join("footer.hbs"), &mut theme.footer),
+                (theme_dir.join("scripts.hbs"), &mut theme.scripts),
                 (theme_dir.join("book.js"), &mut theme.js),
                 (theme_dir.join("book.css"), &mut theme.css),
                 (theme_dir.join("favicon.png"), &mut theme.favicon),
                 (theme_dir.join("highlight.js"), &mut theme.highlight_js),
                 (theme_dir.join("clipboard.min.js"), &mut theme.clipboard_js),
+                (theme_dir.join("sw.js"), &mut theme.service_worker),
+                (theme_dir.join("notes.js"), &mut theme.notes_js),
                 (theme_dir.join("highlight.css"), &mut theme.highlight_css),
                 (theme_dir.join("tomorrow-night.css"), &mut theme.tomorrow_night_css),
                 (theme_dir.join("ayu-highlight.css"), &mut theme.ayu_highlight_css),
@@ -96,6 +136,11 @@ impl Default for Theme {
         Theme {
             index: INDEX.to_owned(),
             header: HEADER.to_owned(),
+            comments: COMMENTS.to_owned(),
+            sidebar: SIDEBAR.to_owned(),
+            menu: MENU.to_owned(),
+            footer: FOOTER.to_owned(),
+            scripts: SCRIPTS.to_owned(),
             css: CSS.to_owned(),
             favicon: FAVICON.to_owned(),
             js: JS.to_owned(),
@@ -104,6 +149,8 @@ impl Default for Theme {
             ayu_highlight_css: AYU_HIGHLIGHT_CSS.to_owned(),
             highlight_js: HIGHLIGHT_JS.to_owned(),
             clipboard_js: CLIPBOARD_JS.to_owned(),
+            service_worker: SERVICE_WORKER.to_owned(),
+            notes_js: NOTES_JS.to_owned(),
         }
     }
 }
@@ -166,6 +213,11 @@ mod tests {
         let empty = Theme {
             index: Vec::new(),
             header: Vec::new(),
+            comments: Vec::new(),
+            sidebar: Vec::new(),
+            menu: Vec::new(),
+            footer: Vec::new(),
+            scripts: Vec::new(),
             css: Vec::new(),
             favicon: Vec::new(),
             js: Vec::new(),
@@ -174,6 +226,8 @@ mod tests {
             ayu_highlight_css: Vec::new(),
             highlight_js: Vec::new(),
             clipboard_js: Vec::new(),
+            service_worker: Vec::new(),
+            notes_js: Vec::new(),
         };
 
         assert_eq!(got, empty);
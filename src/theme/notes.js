@@ -0,0 +1,273 @@
+// Highlights and notes, which readers make by selecting text in a chapter.
+// They're kept in the browser's localStorage, one list per page, and can be
+// exported to (and imported from) a JSON file.
+(function readerNotes() {
+    var STORAGE_KEY = 'mdbook-notes';
+    // how much of the text around a highlight is kept, to tell apart the
+    // places where the same words appear
+    var CONTEXT = 32;
+
+    var main = document.querySelector('#content main');
+    var page = window.reader_notes_page;
+    if (!main || !page) {
+        return;
+    }
+
+    function load() {
+        try {
+            return JSON.parse(localStorage.getItem(STORAGE_KEY)) || {};
+        } catch (e) {
+            return {};
+        }
+    }
+
+    function save(notes) {
+        // storage can be full, or unavailable in private browsing
+        try { localStorage.setItem(STORAGE_KEY, JSON.stringify(notes)); } catch (e) { }
+    }
+
+    function pageNotes() {
+        return load()[page] || [];
+    }
+
+    function savePageNotes(list) {
+        var notes = load();
+        if (list.length > 0) {
+            notes[page] = list;
+        } else {
+            delete notes[page];
+        }
+        save(notes);
+    }
+
+    // All the text of the chapter, with where each text node starts in it
+    function textNodes() {
+        var walker = document.createTreeWalker(main, NodeFilter.SHOW_TEXT, null, false);
+        var nodes = [];
+        var text = '';
+        while (walker.nextNode()) {
+            nodes.push({ node: walker.currentNode, start: text.length });
+            text += walker.currentNode.nodeValue;
+        }
+        return { nodes: nodes, text: text };
+    }
+
+    function offsetOf(all, container, offset) {
+        for (var i = 0; i < all.nodes.length; i++) {
+            if (all.nodes[i].node === container) {
+                return all.nodes[i].start + offset;
+            }
+        }
+        return -1;
+    }
+
+    // Wrap the text between two offsets in <mark>s, one per text node
+    function wrap(all, start, end, highlight) {
+        all.nodes.forEach(function (entry) {
+            var node = entry.node;
+            var nodeEnd = entry.start + node.nodeValue.length;
+            if (nodeEnd <= start || entry.start >= end || !node.nodeValue.trim()) {
+                return;
+            }
+
+            var from = Math.max(start - entry.start, 0);
+            var to = Math.min(end - entry.start, node.nodeValue.length);
+            var middle = node.splitText(from);
+            middle.splitText(to - from);
+
+            var mark = document.createElement('mark');
+            mark.className = 'reader-highlight';
+            mark.setAttribute('data-highlight', highlight.id);
+            if (highlight.note) {
+                mark.title = highlight.note;
+                mark.classList.add('has-note');
+            }
+            middle.parentNode.insertBefore(mark, middle);
+            mark.appendChild(middle);
+        });
+    }
+
+    function unwrap(id) {
+        Array.from(main.querySelectorAll('mark[data-highlight="' + id + '"]')).forEach(function (mark) {
+            var parent = mark.parentNode;
+            while (mark.firstChild) {
+                parent.insertBefore(mark.firstChild, mark);
+            }
+            parent.removeChild(mark);
+            parent.normalize();
+        });
+    }
+
+    // Find a stored highlight in the chapter, preferring the place whose
+    // surrounding text matches best
+    function locate(all, highlight) {
+        var best = -1;
+        var bestScore = -1;
+        var index = all.text.indexOf(highlight.text);
+        while (index !== -1) {
+            var score = 0;
+            if (all.text.slice(Math.max(index - CONTEXT, 0), index) === highlight.prefix) {
+                score += 1;
+            }
+            if (all.text.slice(index + highlight.text.length, index + highlight.text.length + CONTEXT) === highlight.suffix) {
+                score += 1;
+            }
+            if (score > bestScore) {
+                best = index;
+                bestScore = score;
+            }
+            index = all.text.indexOf(highlight.text, index + 1);
+        }
+        return best;
+    }
+
+    function restore() {
+        pageNotes().forEach(function (highlight) {
+            var all = textNodes();
+            var start = locate(all, highlight);
+            if (start !== -1) {
+                wrap(all, start, start + highlight.text.length, highlight);
+            }
+        });
+    }
+
+    // The button which shows up next to a selection
+    var highlightButton = document.createElement('button');
+    highlightButton.className = 'reader-notes-button';
+    highlightButton.innerHTML = '<i class="fa fa-pencil"></i> Highlight';
+    highlightButton.hidden = true;
+    document.body.appendChild(highlightButton);
+
+    var pendingRange = null;
+
+    document.addEventListener('mouseup', function (e) {
+        if (e.target === highlightButton || highlightButton.contains(e.target)) {
+            return;
+        }
+        var selection = window.getSelection();
+        if (selection.isCollapsed || selection.rangeCount === 0 ||
+            !main.contains(selection.getRangeAt(0).commonAncestorContainer)) {
+            highlightButton.hidden = true;
+            pendingRange = null;
+            return;
+        }
+
+        pendingRange = selection.getRangeAt(0);
+        var rect = pendingRange.getBoundingClientRect();
+        highlightButton.style.top = (window.scrollY + rect.bottom + 6) + 'px';
+        highlightButton.style.left = (window.scrollX + rect.left) + 'px';
+        highlightButton.hidden = false;
+    });
+
+    highlightButton.addEventListener('click', function () {
+        highlightButton.hidden = true;
+        if (!pendingRange) {
+            return;
+        }
+
+        var all = textNodes();
+        var start = offsetOf(all, pendingRange.startContainer, pendingRange.startOffset);
+        var end = offsetOf(all, pendingRange.endContainer, pendingRange.endOffset);
+        pendingRange = null;
+        window.getSelection().removeAllRanges();
+        if (start === -1 || end === -1 || end <= start) {
+            return;
+        }
+
+        var highlight = {
+            id: Date.now().toString(36) + Math.random().toString(36).slice(2, 6),
+            text: all.text.slice(start, end),
+            prefix: all.text.slice(Math.max(start - CONTEXT, 0), start),
+            suffix: all.text.slice(end, end + CONTEXT),
+            note: '',
+            created: new Date().toISOString()
+        };
+        var list = pageNotes();
+        list.push(highlight);
+        savePageNotes(list);
+        wrap(all, start, end, highlight);
+    });
+
+    // Clicking a highlight edits its note, and an empty note removes it
+    main.addEventListener('click', function (e) {
+        var mark = e.target.closest('mark.reader-highlight');
+        if (!mark) {
+            return;
+        }
+
+        var id = mark.getAttribute('data-highlight');
+        var list = pageNotes();
+        var highlight = list.filter(function (h) { return h.id === id; })[0];
+        if (!highlight) {
+            return;
+        }
+
+        var note = window.prompt('Note (leave it empty to remove the highlight)', highlight.note);
+        if (note === null) {
+            return;
+        }
+
+        unwrap(id);
+        if (note.trim() === '') {
+            savePageNotes(list.filter(function (h) { return h.id !== id; }));
+        } else {
+            highlight.note = note;
+            savePageNotes(list);
+            var all = textNodes();
+            var start = locate(all, highlight);
+            if (start !== -1) {
+                wrap(all, start, start + highlight.text.length, highlight);
+            }
+        }
+    });
+
+    // Exporting and importing all the notes of the book
+    var exportButton = document.getElementById('notes-export');
+    var importButton = document.getElementById('notes-import');
+    var importFile = document.getElementById('notes-import-file');
+
+    if (exportButton) {
+        exportButton.addEventListener('click', function () {
+            var blob = new Blob([JSON.stringify(load(), null, 2)], { type: 'application/json' });
+            var link = document.createElement('a');
+            link.href = URL.createObjectURL(blob);
+            link.download = 'notes.json';
+            document.body.appendChild(link);
+            link.click();
+            document.body.removeChild(link);
+        });
+    }
+
+    if (importButton && importFile) {
+        importButton.addEventListener('click', function () {
+            importFile.click();
+        });
+        importFile.addEventListener('change', function () {
+            var file = importFile.files[0];
+            if (!file) {
+                return;
+            }
+            var reader = new FileReader();
+            reader.onload = function () {
+                try {
+                    var imported = JSON.parse(reader.result);
+                    var notes = load();
+                    Object.keys(imported).forEach(function (path) {
+                        var existing = notes[path] || [];
+                        var ids = existing.map(function (h) { return h.id; });
+                        notes[path] = existing.concat(imported[path].filter(function (h) {
+                            return ids.indexOf(h.id) === -1;
+                        }));
+                    });
+                    save(notes);
+                    window.location.reload();
+                } catch (e) {
+                    window.alert('Unable to import the notes: ' + e.message);
+                }
+            };
+            reader.readAsText(file);
+        });
+    }
+
+    restore();
+})();
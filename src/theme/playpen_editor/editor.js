@@ -4,6 +4,8 @@ window.editors = [];
         return;
     }
 
+    var config = window.playpen_config || {};
+
     Array.from(document.querySelectorAll('.editable')).forEach(function(editable) {
         let editor = ace.edit(editable);
             editor.setOptions({
@@ -11,7 +13,8 @@ window.editors = [];
             showPrintMargin: false,
             showLineNumbers: false,
             showGutter: false,
-            maxLines: Infinity
+            maxLines: Infinity,
+            wrap: !!config.lineWrapping
         });
 
         editor.$blockScrolling = Infinity;
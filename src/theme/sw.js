@@ -0,0 +1,60 @@
+// A service worker keeping the book readable offline. mdBook puts the
+// book's `ASSET_MANIFEST` (a version and the list of files) above this.
+var CACHE_PREFIX = 'mdbook-' + self.registration.scope + '-';
+var CACHE = CACHE_PREFIX + ASSET_MANIFEST.version;
+
+self.addEventListener('install', function (event) {
+    event.waitUntil(
+        caches.open(CACHE)
+            .then(function (cache) {
+                // with `precache-book`, the whole book is available offline
+                // straight away, otherwise only the pages which were visited
+                return ASSET_MANIFEST.precache ? cache.addAll(ASSET_MANIFEST.files) : null;
+            })
+            .then(function () { return self.skipWaiting(); })
+    );
+});
+
+self.addEventListener('activate', function (event) {
+    // throw away what was cached for older builds of the book
+    event.waitUntil(
+        caches.keys()
+            .then(function (keys) {
+                return Promise.all(keys.filter(function (key) {
+                    return key.indexOf(CACHE_PREFIX) === 0 && key !== CACHE;
+                }).map(function (key) {
+                    return caches.delete(key);
+                }));
+            })
+            .then(function () { return self.clients.claim(); })
+    );
+});
+
+function fetchAndCache(request) {
+    return fetch(request).then(function (response) {
+        if (response.ok) {
+            var copy = response.clone();
+            caches.open(CACHE).then(function (cache) { cache.put(request, copy); });
+        }
+        return response;
+    });
+}
+
+self.addEventListener('fetch', function (event) {
+    var request = event.request;
+    if (request.method !== 'GET' || request.url.indexOf(self.registration.scope) !== 0) {
+        return;
+    }
+
+    if (request.mode === 'navigate') {
+        // pages come from the network while online, so they're up to date
+        event.respondWith(fetchAndCache(request).catch(function () {
+            return caches.match(request, { ignoreSearch: true });
+        }));
+    } else {
+        // everything else only changes with a new build of the book
+        event.respondWith(caches.match(request).then(function (cached) {
+            return cached || fetchAndCache(request);
+        }));
+    }
+});
@@ -0,0 +1,166 @@
+//! Measuring how long each stage of a build takes, to find out what's slowing
+//! it down.
+//!
+//! Nothing is recorded until [`enable()`] is called, which is what
+//! `mdbook build --timings` does.
+//!
+//! [`enable()`]: fn.enable.html
+
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long something took.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Timing {
+    /// The stage of the build, e.g. `"render html"`.
+    pub stage: String,
+    /// The chapter, for timings of a single chapter within a stage.
+    pub chapter: Option<PathBuf>,
+    /// How long it took.
+    pub duration: Duration,
+}
+
+lazy_static! {
+    static ref TIMINGS: Mutex<Option<Vec<Timing>>> = Mutex::new(None);
+}
+
+/// Start recording timings.
+pub fn enable() {
+    let mut timings = TIMINGS.lock().expect("the timings lock is never poisoned");
+    if timings.is_none() {
+        *timings = Some(Vec::new());
+    }
+}
+
+/// Are timings being recorded?
+pub fn is_enabled() -> bool {
+    TIMINGS
+        .lock()
+        .expect("the timings lock is never poisoned")
+        .is_some()
+}
+
+/// Record a timing, if timings are being recorded.
+pub fn record(timing: Timing) {
+    let mut timings = TIMINGS.lock().expect("the timings lock is never poisoned");
+    if let Some(ref mut timings) = *timings {
+        timings.push(timing);
+    }
+}
+
+/// Run `f`, recording how long it took as the given stage. The name is only
+/// built when timings are being recorded.
+pub fn time<S, F, T>(stage: S, f: F) -> T
+where
+    S: FnOnce() -> String,
+    F: FnOnce() -> T,
+{
+    time_chapter(stage, None, f)
+}
+
+/// Run `f`, recording how long it took to process a chapter as part of the
+/// given stage.
+pub fn time_chapter<S, F, T>(stage: S, chapter: Option<&Path>, f: F) -> T
+where
+    S: FnOnce() -> String,
+    F: FnOnce() -> T,
+{
+    if !is_enabled() {
+        return f();
+    }
+
+    let start = Instant::now();
+    let value = f();
+    record(Timing {
+        stage: stage(),
+        chapter: chapter.map(Path::to_path_buf),
+        duration: start.elapsed(),
+    });
+
+    value
+}
+
+/// Take the timings recorded so far.
+pub fn take() -> Vec<Timing> {
+    let mut timings = TIMINGS.lock().expect("the timings lock is never poisoned");
+    match *timings {
+        Some(ref mut timings) => timings.drain(..).collect(),
+        None => Vec::new(),
+    }
+}
+
+/// How many of the slowest chapters `report()` lists for each stage.
+const SLOWEST_CHAPTERS: usize = 10;
+
+/// A human readable breakdown of the timings: each stage in the order it
+/// ran, followed by the slowest chapters of the stages which timed them.
+pub fn report(timings: &[Timing]) -> String {
+    let mut report = String::from("Timings:\n");
+
+    for timing in timings.iter().filter(|t| t.chapter.is_none()) {
+        writeln!(report, "  {:>9}  {}", seconds(timing.duration), timing.stage).unwrap();
+    }
+
+    let mut stages: Vec<&str> = Vec::new();
+    for timing in timings.iter().filter(|t| t.chapter.is_some()) {
+        if !stages.contains(&timing.stage.as_str()) {
+            stages.push(&timing.stage);
+        }
+    }
+
+    for stage in stages {
+        let mut chapters: Vec<&Timing> = timings
+            .iter()
+            .filter(|t| t.chapter.is_some() && t.stage == stage)
+            .collect();
+        chapters.sort_by(|a, b| b.duration.cmp(&a.duration));
+
+        writeln!(report, "\nSlowest chapters ({}):", stage).unwrap();
+        for timing in chapters.into_iter().take(SLOWEST_CHAPTERS) {
+            if let Some(ref chapter) = timing.chapter {
+                let duration = seconds(timing.duration);
+                writeln!(report, "  {:>9}  {}", duration, chapter.display()).unwrap();
+            }
+        }
+    }
+
+    report
+}
+
+fn seconds(duration: Duration) -> String {
+    let secs = duration.as_secs() as f64 + f64::from(duration.subsec_nanos()) / 1e9;
+    format!("{:.3}s", secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timing(stage: &str, chapter: Option<&str>, millis: u64) -> Timing {
+        Timing {
+            stage: stage.to_string(),
+            chapter: chapter.map(PathBuf::from),
+            duration: Duration::from_millis(millis),
+        }
+    }
+
+    #[test]
+    fn stages_are_listed_in_order_followed_by_the_slowest_chapters() {
+        let timings = vec![
+            timing("load", None, 12),
+            timing("render html", Some("fast.md"), 1),
+            timing("render html", Some("slow.md"), 250),
+            timing("render html", None, 1500),
+        ];
+
+        let got = report(&timings);
+
+        assert_eq!(
+            got,
+            "Timings:\n     0.012s  load\n     1.500s  render html\n\
+             \nSlowest chapters (render html):\n     0.250s  slow.md\n     0.001s  fast.md\n"
+        );
+    }
+}
@@ -0,0 +1,87 @@
+use std::env;
+use std::path::Path;
+use std::process::Command;
+use chrono::{TimeZone, Utc};
+use errors::*;
+
+/// Information about the current build, so a book can show exactly which
+/// revision it documents.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BuildInfo {
+    /// The date the book was built on, formatted as `YYYY-MM-DD`.
+    pub build_date: String,
+    /// The commit checked out in the book's git repository, if any.
+    pub git_commit: Option<String>,
+    /// The version of mdBook doing the build.
+    pub mdbook_version: String,
+}
+
+impl BuildInfo {
+    /// Gather the build information for the book in `root`.
+    ///
+    /// The `SOURCE_DATE_EPOCH` environment variable is honoured for the build
+    /// date, so reproducible builds stay reproducible. It's an error for it
+    /// not to be a valid timestamp.
+    pub fn collect(root: &Path) -> Result<BuildInfo> {
+        Ok(BuildInfo {
+            build_date: build_date(env::var("SOURCE_DATE_EPOCH").ok())?,
+            git_commit: git_commit(root),
+            mdbook_version: String::from(env!("CARGO_PKG_VERSION")),
+        })
+    }
+}
+
+fn build_date(source_date_epoch: Option<String>) -> Result<String> {
+    let now = match source_date_epoch {
+        Some(epoch) => epoch
+            .trim()
+            .parse()
+            .ok()
+            .and_then(|seconds| Utc.timestamp_opt(seconds, 0).single())
+            .ok_or_else(|| format!("SOURCE_DATE_EPOCH isn't a valid timestamp: {:?}", epoch))?,
+        None => Utc::now(),
+    };
+
+    Ok(now.format("%Y-%m-%d").to_string())
+}
+
+fn git_commit(root: &Path) -> Option<String> {
+    let output = Command::new("git")
+        .arg("rev-parse")
+        .arg("HEAD")
+        .current_dir(root)
+        .output();
+
+    match output {
+        Ok(ref output) if output.status.success() => {
+            let commit = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            if commit.is_empty() {
+                None
+            } else {
+                Some(commit)
+            }
+        }
+        _ => {
+            debug!("Unable to find the current git commit of {}", root.display());
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_build_date_honours_source_date_epoch() {
+        let got = build_date(Some(String::from("1518566400"))).unwrap();
+
+        assert_eq!(got, "2018-02-14");
+    }
+
+    #[test]
+    fn an_invalid_source_date_epoch_is_an_error() {
+        assert!(build_date(Some(String::from("yesterday"))).is_err());
+        assert!(build_date(Some(String::from("99999999999999999"))).is_err());
+    }
+}
@@ -0,0 +1,481 @@
+//! The markdown extensions which pulldown-cmark doesn't support itself.
+
+use std::borrow::Cow;
+use pulldown_cmark::{Event, Tag};
+
+use super::math_start;
+
+/// Merge adjacent text events, which pulldown-cmark splits wherever some
+/// other markup might have started.
+pub fn merge_text<'a>(events: Vec<Event<'a>>) -> Vec<Event<'a>> {
+    let mut merged: Vec<Event<'a>> = Vec::with_capacity(events.len());
+
+    for event in events {
+        if let Event::Text(text) = event {
+            if let Some(&mut Event::Text(ref mut previous)) = merged.last_mut() {
+                previous.to_mut().push_str(&text);
+                continue;
+            }
+            merged.push(Event::Text(text));
+        } else {
+            merged.push(event);
+        }
+    }
+
+    merged
+}
+
+/// Turn text surrounded by `~~` into `<del>` elements. Both ends have to be
+/// in the same paragraph (or heading, table cell, etc.).
+pub fn strikethrough<'a>(events: Vec<Event<'a>>) -> Vec<Event<'a>> {
+    let mut out: Vec<Event<'a>> = Vec::with_capacity(events.len());
+    // where the unclosed `~~` is, if there is one
+    let mut open: Option<usize> = None;
+    let mut in_code = false;
+
+    for event in events {
+        match event {
+            Event::Text(ref text) if !in_code && text.contains("~~") => {
+                for (i, part) in text.split("~~").enumerate() {
+                    if i > 0 {
+                        match open.take() {
+                            Some(ix) => {
+                                out[ix] = Event::Html(Cow::from("<del>"));
+                                out.push(Event::Html(Cow::from("</del>")));
+                            }
+                            None => {
+                                open = Some(out.len());
+                                out.push(Event::Text(Cow::from("~~")));
+                            }
+                        }
+                    }
+                    if !part.is_empty() {
+                        out.push(Event::Text(Cow::from(part.to_string())));
+                    }
+                }
+            }
+            Event::Start(Tag::Code) | Event::Start(Tag::CodeBlock(_)) => {
+                in_code = true;
+                out.push(event);
+            }
+            Event::End(Tag::Code) | Event::End(Tag::CodeBlock(_)) => {
+                in_code = false;
+                out.push(event);
+            }
+            Event::End(_) if !is_inline(&event) => {
+                open = None;
+                out.push(event);
+            }
+            event => out.push(event),
+        }
+    }
+
+    out
+}
+
+/// Turn list items starting with `[ ]` or `[x]` into checkboxes.
+pub fn task_lists<'a>(events: Vec<Event<'a>>) -> Vec<Event<'a>> {
+    let mut out: Vec<Event<'a>> = Vec::with_capacity(events.len());
+    let mut item_started = false;
+
+    for event in events {
+        match event {
+            Event::Start(Tag::Item) => {
+                item_started = true;
+                out.push(event);
+            }
+            // a loose list's items start with a paragraph
+            Event::Start(Tag::Paragraph) if item_started => out.push(event),
+            Event::Text(ref text) if item_started => {
+                item_started = false;
+
+                let checked = if text.starts_with("[ ] ") {
+                    Some(false)
+                } else if text.starts_with("[x] ") || text.starts_with("[X] ") {
+                    Some(true)
+                } else {
+                    None
+                };
+
+                match checked {
+                    Some(checked) => {
+                        let checkbox = if checked {
+                            "<input type=\"checkbox\" disabled=\"\" checked=\"\" /> "
+                        } else {
+                            "<input type=\"checkbox\" disabled=\"\" /> "
+                        };
+                        out.push(Event::Html(Cow::from(checkbox)));
+                        out.push(Event::Text(Cow::from(text[4..].to_string())));
+                    }
+                    None => out.push(Event::Text(text.clone())),
+                }
+            }
+            event => {
+                item_started = false;
+                out.push(event);
+            }
+        }
+    }
+
+    out
+}
+
+/// Turn text between `^`s into superscript (e.g. `m^2^`), the way pandoc
+/// does. The text can't contain whitespace, and math is left alone.
+pub fn superscript<'a>(events: Vec<Event<'a>>) -> Vec<Event<'a>> {
+    wrap_delimited(events, '^', "sup")
+}
+
+/// Turn text between `~`s into subscript (e.g. `H~2~O`), the way pandoc does.
+/// The text can't contain whitespace, and math is left alone.
+pub fn subscript<'a>(events: Vec<Event<'a>>) -> Vec<Event<'a>> {
+    wrap_delimited(events, '~', "sub")
+}
+
+fn wrap_delimited<'a>(events: Vec<Event<'a>>, delimiter: char, tag: &str) -> Vec<Event<'a>> {
+    let mut out: Vec<Event<'a>> = Vec::with_capacity(events.len());
+    let mut in_code = false;
+    // the delimiter ending the math we're in, if we are
+    let mut math_end = None;
+
+    for event in events {
+        match event {
+            Event::Text(ref text) if !in_code && text.contains(delimiter) => {
+                for (part, is_math) in split_math(text, &mut math_end) {
+                    if is_math {
+                        out.push(Event::Text(Cow::from(part.to_string())));
+                    } else {
+                        split_delimited(part, delimiter, tag, &mut out);
+                    }
+                }
+            }
+            Event::Text(ref text) if !in_code => {
+                split_math(text, &mut math_end);
+                out.push(event.clone());
+            }
+            Event::Start(Tag::Code) | Event::Start(Tag::CodeBlock(_)) => {
+                in_code = true;
+                out.push(event);
+            }
+            Event::End(Tag::Code) | Event::End(Tag::CodeBlock(_)) => {
+                in_code = false;
+                out.push(event);
+            }
+            // math can't carry on into the next paragraph
+            Event::End(Tag::Paragraph) | Event::End(Tag::Header(_)) | Event::End(Tag::Item) => {
+                math_end = None;
+                out.push(event);
+            }
+            event => out.push(event),
+        }
+    }
+
+    out
+}
+
+/// Split `text` into the parts outside and inside of math (the latter marked
+/// `true`), finding the math the same way `convert_quotes_outside_math()`
+/// does. `math_end` is the delimiter ending the math the text starts in, if
+/// it does.
+fn split_math<'t>(text: &'t str, math_end: &mut Option<&'static str>) -> Vec<(&'t str, bool)> {
+    let mut parts = Vec::new();
+    let mut part_start = 0;
+    let mut preceded_by_whitespace = true;
+    let mut ix = 0;
+
+    while let Some(c) = text[ix..].chars().next() {
+        let rest = &text[ix..];
+        let mut len = c.len_utf8();
+
+        match *math_end {
+            Some(end) => {
+                // a closing `$` has to come straight after the math
+                if rest.starts_with(end) && !(end == "$" && preceded_by_whitespace) {
+                    *math_end = None;
+                    len = end.len();
+                    parts.push((&text[part_start..ix + len], true));
+                    part_start = ix + len;
+                }
+            }
+            None => if let Some((start, end)) = math_start(rest) {
+                if ix > part_start {
+                    parts.push((&text[part_start..ix], false));
+                }
+                *math_end = Some(end);
+                len = start.len();
+                part_start = ix;
+            },
+        }
+
+        preceded_by_whitespace = c.is_whitespace();
+        ix += len;
+    }
+
+    if part_start < text.len() {
+        parts.push((&text[part_start..], math_end.is_some()));
+    }
+    parts
+}
+
+fn split_delimited<'a>(text: &str, delimiter: char, tag: &str, out: &mut Vec<Event<'a>>) {
+    let mut plain = String::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find(delimiter) {
+        // a run of delimiters (e.g. the `~~` of strikethrough) isn't ours
+        let run = rest[start..].chars().take_while(|&c| c == delimiter).count();
+        if run > 1 {
+            plain.push_str(&rest[..start + run]);
+            rest = &rest[start + run..];
+            continue;
+        }
+
+        let after = &rest[start + 1..];
+        match after.find(|c: char| c == delimiter || c.is_whitespace()) {
+            Some(end) if end > 0 && after[end..].starts_with(delimiter) => {
+                plain.push_str(&rest[..start]);
+                if !plain.is_empty() {
+                    out.push(Event::Text(Cow::from(plain)));
+                    plain = String::new();
+                }
+                out.push(Event::Html(Cow::from(format!("<{}>", tag))));
+                out.push(Event::Text(Cow::from(after[..end].to_string())));
+                out.push(Event::Html(Cow::from(format!("</{}>", tag))));
+                rest = &after[end + 1..];
+            }
+            _ => {
+                plain.push_str(&rest[..start + 1]);
+                rest = after;
+            }
+        }
+    }
+
+    plain.push_str(rest);
+    if !plain.is_empty() {
+        out.push(Event::Text(Cow::from(plain)));
+    }
+}
+
+/// Turn paragraphs made of terms followed by definitions starting with `: `
+/// into definition lists, the way PHP Markdown Extra does:
+///
+/// ```text
+/// Apple
+/// : A fruit.
+/// : A company.
+/// ```
+///
+/// A definition can also be separated from its term by a blank line.
+pub fn definition_lists<'a>(events: Vec<Event<'a>>) -> Vec<Event<'a>> {
+    let mut out: Vec<Event<'a>> = Vec::with_capacity(events.len());
+    // where the previous paragraph starts in `out` and its lines, in case it's
+    // the term for a definition which comes after a blank line
+    let mut previous: Option<(usize, Vec<Vec<Event<'a>>>)> = None;
+    let mut paragraph: Option<Vec<Event<'a>>> = None;
+
+    for event in events {
+        match event {
+            Event::Start(Tag::Paragraph) if paragraph.is_none() => paragraph = Some(Vec::new()),
+            Event::End(Tag::Paragraph) if paragraph.is_some() => {
+                let mut lines = split_lines(paragraph.take().unwrap_or_default());
+                let first_definition = lines.iter().position(|line| is_definition(line));
+
+                match first_definition {
+                    Some(0) => match previous.take() {
+                        Some((start, terms)) => {
+                            out.truncate(start);
+                            push_definition_list(&mut out, terms, lines);
+                        }
+                        None if ends_definition_list(&out) => {
+                            push_definition_list(&mut out, Vec::new(), lines)
+                        }
+                        None => push_paragraph(&mut out, lines),
+                    },
+                    Some(ix) => {
+                        let definitions = lines.split_off(ix);
+                        push_definition_list(&mut out, lines, definitions);
+                    }
+                    None => {
+                        previous = Some((out.len(), lines.clone()));
+                        push_paragraph(&mut out, lines);
+                    }
+                }
+            }
+            event => match paragraph {
+                Some(ref mut paragraph) => paragraph.push(event),
+                None => {
+                    previous = None;
+                    out.push(event);
+                }
+            },
+        }
+    }
+
+    out
+}
+
+fn split_lines<'a>(events: Vec<Event<'a>>) -> Vec<Vec<Event<'a>>> {
+    let mut lines = vec![Vec::new()];
+
+    for event in events {
+        match event {
+            Event::SoftBreak => lines.push(Vec::new()),
+            event => lines.last_mut().expect("there's always a line").push(event),
+        }
+    }
+
+    lines
+}
+
+fn join_lines<'a>(lines: Vec<Vec<Event<'a>>>) -> Vec<Event<'a>> {
+    let mut events = Vec::new();
+
+    for (i, line) in lines.into_iter().enumerate() {
+        if i > 0 {
+            events.push(Event::SoftBreak);
+        }
+        events.extend(line);
+    }
+
+    events
+}
+
+fn is_definition(line: &[Event]) -> bool {
+    match line.first() {
+        Some(&Event::Text(ref text)) => text.starts_with(": ") || text.as_ref() == ":",
+        _ => false,
+    }
+}
+
+fn ends_definition_list(events: &[Event]) -> bool {
+    match events.last() {
+        Some(&Event::Html(ref html)) => html.as_ref() == "</dl>\n",
+        _ => false,
+    }
+}
+
+fn push_paragraph<'a>(out: &mut Vec<Event<'a>>, lines: Vec<Vec<Event<'a>>>) {
+    out.push(Event::Start(Tag::Paragraph));
+    out.extend(join_lines(lines));
+    out.push(Event::End(Tag::Paragraph));
+}
+
+fn push_definition_list<'a>(
+    out: &mut Vec<Event<'a>>,
+    terms: Vec<Vec<Event<'a>>>,
+    definitions: Vec<Vec<Event<'a>>>,
+) {
+    // a list straight after another one carries it on
+    if ends_definition_list(out) {
+        out.pop();
+    } else {
+        out.push(Event::Html(Cow::from("<dl>\n")));
+    }
+
+    for term in terms {
+        out.push(Event::Html(Cow::from("<dt>")));
+        out.extend(term);
+        out.push(Event::Html(Cow::from("</dt>\n")));
+    }
+
+    // lines which don't start with `: ` carry on the previous definition
+    let mut grouped: Vec<Vec<Vec<Event<'a>>>> = Vec::new();
+    for mut line in definitions {
+        if is_definition(&line) {
+            let rest = match line.remove(0) {
+                Event::Text(text) => text[1..].trim_left().to_string(),
+                _ => unreachable!(),
+            };
+            if !rest.is_empty() {
+                line.insert(0, Event::Text(Cow::from(rest)));
+            }
+            grouped.push(vec![line]);
+        } else if let Some(definition) = grouped.last_mut() {
+            definition.push(line);
+        }
+    }
+
+    for definition in grouped {
+        out.push(Event::Html(Cow::from("<dd>")));
+        out.extend(join_lines(definition));
+        out.push(Event::Html(Cow::from("</dd>\n")));
+    }
+
+    out.push(Event::Html(Cow::from("</dl>\n")));
+}
+
+fn is_inline(event: &Event) -> bool {
+    match *event {
+        Event::End(Tag::Emphasis) |
+        Event::End(Tag::Strong) |
+        Event::End(Tag::Link(..)) |
+        Event::End(Tag::Image(..)) => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pulldown_cmark::{html, Parser};
+
+    fn render(text: &str, f: fn(Vec<Event>) -> Vec<Event>) -> String {
+        let events = merge_text(Parser::new(text).collect());
+        let mut s = String::new();
+        html::push_html(&mut s, f(events).into_iter());
+        s
+    }
+
+    #[test]
+    fn strikethrough_can_span_other_markup() {
+        assert_eq!(
+            render("~~gone *for* good~~ and `~~kept~~` ~~unclosed\n", strikethrough),
+            "<p><del>gone <em>for</em> good</del> and <code>~~kept~~</code> ~~unclosed</p>\n"
+        );
+    }
+
+    #[test]
+    fn terms_and_their_definitions_become_definition_lists() {
+        let input = "Apple\nPear\n: A fruit.\n  Tasty too.\n: A *company*.\n\n\
+                     Banana\n\n: Yellow.\n\nNot: a definition\n";
+
+        assert_eq!(
+            render(input, definition_lists),
+            "<dl>\n<dt>Apple</dt>\n<dt>Pear</dt>\n<dd>A fruit.\nTasty too.</dd>\n\
+             <dd>A <em>company</em>.</dd>\n<dt>Banana</dt>\n<dd>Yellow.</dd>\n</dl>\n\
+             <p>Not: a definition</p>\n"
+        );
+    }
+
+    #[test]
+    fn superscript_and_subscript_need_both_delimiters() {
+        assert_eq!(
+            render("m^2^ and H~2~O, but not 2 ^ 3 or ~~this~~ or `x^2^`\n", |events| {
+                subscript(superscript(events))
+            }),
+            "<p>m<sup>2</sup> and H<sub>2</sub>O, but not 2 ^ 3 or ~~this~~ or <code>x^2^</code></p>\n"
+        );
+    }
+
+    #[test]
+    fn superscript_and_subscript_leave_math_alone() {
+        let input = "$x^2^$ and \\\\(a~i~ + b~i~\\\\), but m^2^ and $$\\sum\nx^k^$$\n";
+
+        assert_eq!(
+            render(input, |events| subscript(superscript(events))),
+            "<p>$x^2^$ and \\(a~i~ + b~i~\\), but m<sup>2</sup> and $$\\sum\nx^k^$$</p>\n"
+        );
+    }
+
+    #[test]
+    fn task_list_items_get_checkboxes() {
+        assert_eq!(
+            render("- [ ] todo\n- [x] done\n- [link]\n", task_lists),
+            "<ul>\n\
+             <li><input type=\"checkbox\" disabled=\"\" /> todo</li>\n\
+             <li><input type=\"checkbox\" disabled=\"\" checked=\"\" /> done</li>\n\
+             <li>[link]</li>\n\
+             </ul>\n"
+        );
+    }
+}
@@ -0,0 +1,171 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use pulldown_cmark::{Event, Tag};
+
+/// Move a chapter's footnote definitions into a numbered list at its end.
+///
+/// Footnotes are numbered in the order they're first referenced, and each
+/// one links back to the places it was referenced from. Definitions which
+/// are never referenced are dropped, while references to footnotes which
+/// don't exist are left as the text which was written.
+pub fn collect_footnotes<'a, I>(events: I) -> Vec<Event<'a>>
+where
+    I: Iterator<Item = Event<'a>>,
+{
+    let mut body = Vec::new();
+    let mut definitions: HashMap<String, Vec<Event<'a>>> = HashMap::new();
+    let mut current: Option<(String, Vec<Event<'a>>)> = None;
+
+    for event in events {
+        match event {
+            Event::Start(Tag::FootnoteDefinition(name)) => {
+                current = Some((name.into_owned(), Vec::new()));
+            }
+            Event::End(Tag::FootnoteDefinition(_)) => if let Some((name, events)) =
+                current.take()
+            {
+                definitions.insert(name, events);
+            },
+            event => match current {
+                Some((_, ref mut events)) => events.push(event),
+                None => body.push(event),
+            },
+        }
+    }
+
+    // the name of each referenced footnote, in order, and how many times
+    // it was referenced
+    let mut referenced: Vec<(String, usize)> = Vec::new();
+
+    let mut events: Vec<Event<'a>> = body.into_iter()
+        .map(|event| match event {
+            Event::FootnoteReference(ref name) if definitions.contains_key(name.as_ref()) => {
+                let number = match referenced.iter().position(|r| r.0 == name.as_ref()) {
+                    Some(ix) => ix,
+                    None => {
+                        referenced.push((name.to_string(), 0));
+                        referenced.len() - 1
+                    }
+                };
+                referenced[number].1 += 1;
+
+                Event::Html(Cow::from(format!(
+                    "<sup class=\"footnote-reference\" id=\"{}\"><a href=\"#{}\">{}</a></sup>",
+                    reference_id(name, referenced[number].1),
+                    definition_id(name),
+                    number + 1
+                )))
+            }
+            Event::FootnoteReference(name) => Event::Text(Cow::from(format!("[^{}]", name))),
+            event => event,
+        })
+        .collect();
+
+    if referenced.is_empty() {
+        return events;
+    }
+
+    events.push(Event::Html(Cow::from(
+        "<hr class=\"footnotes-separator\" />\n<ol class=\"footnotes\">\n",
+    )));
+
+    for (name, count) in referenced {
+        let mut definition = definitions.remove(&name).unwrap_or_default();
+
+        let backlinks: String = (1..count + 1)
+            .map(|n| {
+                format!(
+                    " <a href=\"#{}\" class=\"footnote-backref\">↩</a>",
+                    reference_id(&name, n)
+                )
+            })
+            .collect();
+        let backlinks = Event::Html(Cow::from(backlinks));
+
+        // put the backlinks at the end of the footnote's last paragraph
+        match definition.pop() {
+            Some(Event::End(Tag::Paragraph)) => {
+                definition.push(backlinks);
+                definition.push(Event::End(Tag::Paragraph));
+            }
+            Some(event) => {
+                definition.push(event);
+                definition.push(backlinks);
+            }
+            None => definition.push(backlinks),
+        }
+
+        events.push(Event::Html(Cow::from(format!("<li id=\"{}\">", definition_id(&name)))));
+        events.extend(definition);
+        events.push(Event::Html(Cow::from("</li>\n")));
+    }
+
+    events.push(Event::Html(Cow::from("</ol>\n")));
+    events
+}
+
+fn definition_id(name: &str) -> String {
+    format!("footnote-{}", id_safe(name))
+}
+
+fn reference_id(name: &str, n: usize) -> String {
+    if n == 1 {
+        format!("footnote-ref-{}", id_safe(name))
+    } else {
+        format!("footnote-ref-{}-{}", id_safe(name), n)
+    }
+}
+
+fn id_safe(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' {
+            c
+        } else {
+            '-'
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pulldown_cmark::{html, Options, Parser, OPTION_ENABLE_FOOTNOTES};
+
+    fn render_markdown(text: &str) -> String {
+        let mut opts = Options::empty();
+        opts.insert(OPTION_ENABLE_FOOTNOTES);
+
+        let mut s = String::new();
+        html::push_html(&mut s, collect_footnotes(Parser::new_ext(text, opts)).into_iter());
+        s
+    }
+
+    #[test]
+    fn footnotes_are_numbered_and_moved_to_the_end() {
+        let input = "[^b]: Second.\n\nOne[^a], two[^b] and one again[^a].\n\n[^a]: First.\n";
+
+        let got = render_markdown(input);
+
+        assert_eq!(
+            got,
+            "<p>One<sup class=\"footnote-reference\" id=\"footnote-ref-a\">\
+             <a href=\"#footnote-a\">1</a></sup>, \
+             two<sup class=\"footnote-reference\" id=\"footnote-ref-b\">\
+             <a href=\"#footnote-b\">2</a></sup> and \
+             one again<sup class=\"footnote-reference\" id=\"footnote-ref-a-2\">\
+             <a href=\"#footnote-a\">1</a></sup>.</p>\n\
+             <hr class=\"footnotes-separator\" />\n<ol class=\"footnotes\">\n\
+             <li id=\"footnote-a\">\n<p>First. \
+             <a href=\"#footnote-ref-a\" class=\"footnote-backref\">↩</a> \
+             <a href=\"#footnote-ref-a-2\" class=\"footnote-backref\">↩</a></p>\n</li>\n\
+             <li id=\"footnote-b\">\n<p>Second. \
+             <a href=\"#footnote-ref-b\" class=\"footnote-backref\">↩</a></p>\n</li>\n\
+             </ol>\n"
+        );
+    }
+
+    #[test]
+    fn missing_footnotes_are_left_alone() {
+        assert_eq!(render_markdown("Nothing[^x] here.\n"), "<p>Nothing[^x] here.</p>\n");
+    }
+}
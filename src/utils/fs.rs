@@ -1,6 +1,6 @@
 use std::path::{Component, Path, PathBuf};
 use errors::*;
-use std::io::Read;
+use std::io::{Read, Write};
 use std::fs::{self, File};
 
 /// Takes a path to a file and try to read the file into a String
@@ -75,6 +75,60 @@ pub fn create_file(path: &Path) -> Result<File> {
     File::create(path).map_err(|e| e.into())
 }
 
+/// Create each of `files`, given as their path relative to `root` and their
+/// contents, along with any missing directories. Handy for setting up a book
+/// (or a template) to try something out on.
+pub fn write_files(root: &Path, files: &[(&str, &str)]) -> Result<()> {
+    for &(name, content) in files {
+        create_file(&root.join(name))?.write_all(content.as_bytes())?;
+    }
+
+    Ok(())
+}
+
+/// Write `content` to a file (creating any missing directories), unless it
+/// already contains exactly that. Returns whether the file was written.
+///
+/// Leaving unchanged files alone keeps their modification times, so tools
+/// syncing the build directory elsewhere don't see them as changed.
+pub fn write_file_if_changed(path: &Path, content: &[u8]) -> Result<bool> {
+    if let Ok(metadata) = fs::metadata(path) {
+        if metadata.len() == content.len() as u64 {
+            let mut existing = Vec::with_capacity(content.len());
+            File::open(path)?.read_to_end(&mut existing)?;
+
+            if existing == content {
+                trace!("{} is unchanged", path.display());
+                return Ok(false);
+            }
+        }
+    }
+
+    create_file(path)?.write_all(content)?;
+    Ok(true)
+}
+
+/// Copy a file, unless the destination is already up to date: it has the
+/// same size and was modified after the source. Returns whether the file was
+/// copied.
+pub fn copy_file_if_changed(from: &Path, to: &Path) -> Result<bool> {
+    if let (Ok(source), Ok(dest)) = (fs::metadata(from), fs::metadata(to)) {
+        let dest_is_newer = match (source.modified(), dest.modified()) {
+            (Ok(source), Ok(dest)) => dest >= source,
+            _ => false,
+        };
+
+        if source.len() == dest.len() && dest_is_newer {
+            trace!("{} is up to date", to.display());
+            return Ok(false);
+        }
+    }
+
+    debug!("Copying {} to {}", from.display(), to.display());
+    fs::copy(from, to)?;
+    Ok(true)
+}
+
 /// Removes all the content of a directory but not the directory itself
 
 pub fn remove_dir_content(dir: &Path) -> Result<()> {
@@ -99,6 +153,17 @@ pub fn copy_files_except_ext(
     to: &Path,
     recursive: bool,
     ext_blacklist: &[&str],
+) -> Result<()> {
+    copy_files_except(from, to, recursive, ext_blacklist, &[])
+}
+
+/// Like `copy_files_except_ext()`, also leaving out the files in `skipped`.
+pub fn copy_files_except(
+    from: &Path,
+    to: &Path,
+    recursive: bool,
+    ext_blacklist: &[&str],
+    skipped: &[PathBuf],
 ) -> Result<()> {
     debug!(
         "Copying all files from {} to {} (blacklist: {:?})",
@@ -127,48 +192,30 @@ pub fn copy_files_except_ext(
                 fs::create_dir(&to.join(entry.file_name()))?;
             }
 
-            copy_files_except_ext(
+            copy_files_except(
                 &from.join(entry.file_name()),
                 &to.join(entry.file_name()),
                 true,
                 ext_blacklist,
+                skipped,
             )?;
         } else if metadata.is_file() {
+            if skipped.contains(&entry.path()) {
+                continue;
+            }
             // Check if it is in the blacklist
             if let Some(ext) = entry.path().extension() {
                 if ext_blacklist.contains(&ext.to_str().unwrap()) {
                     continue;
                 }
             }
-            debug!(
-                "creating path for file: {:?}",
-                &to.join(
-                    entry
-                        .path()
-                        .file_name()
-                        .expect("a file should have a file name...")
-                )
-            );
-
-            debug!(
-                "Copying {:?} to {:?}",
-                entry.path(),
-                &to.join(
-                    entry
-                        .path()
-                        .file_name()
-                        .expect("a file should have a file name...")
-                )
+            let dest = to.join(
+                entry
+                    .path()
+                    .file_name()
+                    .expect("a file should have a file name..."),
             );
-            fs::copy(
-                entry.path(),
-                &to.join(
-                    entry
-                        .path()
-                        .file_name()
-                        .expect("a file should have a file name..."),
-                ),
-            )?;
+            copy_file_if_changed(&entry.path(), &dest)?;
         }
     }
     Ok(())
@@ -178,7 +225,7 @@ pub fn copy_files_except_ext(
 mod tests {
     extern crate tempdir;
 
-    use super::copy_files_except_ext;
+    use super::{copy_file_if_changed, copy_files_except_ext, write_file_if_changed};
     use std::fs;
 
     #[test]
@@ -241,4 +288,18 @@ mod tests {
             panic!("output/sub_dir/file.png should exist")
         }
     }
+
+    #[test]
+    fn unchanged_files_are_left_alone() {
+        let tmp = tempdir::TempDir::new("").unwrap();
+        let file = tmp.path().join("nested/book.css");
+
+        assert!(write_file_if_changed(&file, b"body {}").unwrap());
+        assert!(!write_file_if_changed(&file, b"body {}").unwrap());
+        assert!(write_file_if_changed(&file, b"body { margin: 0 }").unwrap());
+
+        let copy = tmp.path().join("copy.css");
+        assert!(copy_file_if_changed(&file, &copy).unwrap());
+        assert!(!copy_file_if_changed(&file, &copy).unwrap());
+    }
 }
@@ -0,0 +1,69 @@
+//! Helpers for the links in a book's markdown, shared by the link and image
+//! checks.
+
+use std::path::{Component, Path, PathBuf};
+
+/// Does the link point somewhere outside the book?
+pub fn is_external(link: &str) -> bool {
+    link.contains("://") || link.starts_with("//") || link.starts_with("mailto:")
+        || link.starts_with("data:")
+}
+
+/// Resolve the `.` and `..` in a path relative to the root of the book,
+/// returning `None` if it points outside of it.
+pub fn normalize(path: &Path) -> Option<PathBuf> {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => if !normalized.pop() {
+                return None;
+            },
+            Component::Normal(part) => normalized.push(part),
+            Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+
+    Some(normalized)
+}
+
+/// The (1-based) line of a chapter's source which has the link (or image) to
+/// `dest`, either inline or as a reference definition.
+pub fn find_line(source: &str, dest: &str) -> Option<usize> {
+    let inline = format!("]({}", dest);
+    let reference = format!("]: {}", dest);
+
+    source
+        .lines()
+        .position(|line| line.contains(&inline) || line.contains(&reference))
+        .map(|ix| ix + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_relative_paths() {
+        assert_eq!(normalize(Path::new("./a/../b/c.html")), Some(PathBuf::from("b/c.html")));
+        assert_eq!(normalize(Path::new("../outside.html")), None);
+    }
+
+    #[test]
+    fn links_to_other_sites_are_external() {
+        assert!(is_external("https://example.com"));
+        assert!(is_external("//cdn.example.com/lib.js"));
+        assert!(is_external("mailto:someone@example.com"));
+        assert!(!is_external("chapter.html#usage"));
+    }
+
+    #[test]
+    fn links_are_found_inline_and_in_definitions() {
+        let source = "# Title\n\nSee [here](a.html).\n\n[ref]: b.html\n";
+
+        assert_eq!(find_line(source, "a.html"), Some(3));
+        assert_eq!(find_line(source, "b.html"), Some(5));
+        assert_eq!(find_line(source, "c.html"), None);
+    }
+}
@@ -1,35 +1,100 @@
 #![allow(missing_docs)] // FIXME: Document this
 
 pub mod fs;
+pub mod links;
+pub mod process;
+mod build_info;
+mod extensions;
+mod footnotes;
+mod sanitize;
+mod slug;
 mod string;
-use errors::Error;
+use config::MarkdownConfig;
+use errors::{Error, Result};
 
 use pulldown_cmark::{html, Event, Options, Parser, Tag, OPTION_ENABLE_FOOTNOTES,
                      OPTION_ENABLE_TABLES};
 use std::borrow::Cow;
 
-pub use self::string::{RangeArgument, take_lines};
+pub use self::build_info::BuildInfo;
+pub use self::sanitize::Sanitizer;
+pub use self::slug::slugify;
+pub use self::string::{escape_html, RangeArgument, take_lines};
 
 /// Wrapper around the pulldown-cmark parser for rendering markdown to HTML.
 pub fn render_markdown(text: &str, curly_quotes: bool) -> String {
+    render_markdown_with(text, curly_quotes, &MarkdownConfig::default())
+}
+
+/// Render markdown to HTML using the extensions enabled in the book's
+/// `[book.markdown]` table.
+pub fn render_markdown_with(text: &str, curly_quotes: bool, markdown: &MarkdownConfig) -> String {
+    render_markdown_filtered(text, curly_quotes, markdown, Ok)
+        .expect("rendering without a filter never fails")
+}
+
+/// Like `render_markdown_with()`, passing the parsed markdown through
+/// `filter` before it's turned into HTML.
+pub fn render_markdown_filtered<'a, F>(
+    text: &'a str,
+    curly_quotes: bool,
+    markdown: &MarkdownConfig,
+    filter: F,
+) -> Result<String>
+where
+    F: FnOnce(Vec<Event<'a>>) -> Result<Vec<Event<'a>>>,
+{
     let mut s = String::with_capacity(text.len() * 3 / 2);
 
     let mut opts = Options::empty();
-    opts.insert(OPTION_ENABLE_TABLES);
+    if markdown.tables {
+        opts.insert(OPTION_ENABLE_TABLES);
+    }
     opts.insert(OPTION_ENABLE_FOOTNOTES);
 
     let p = Parser::new_ext(text, opts);
+    let events = p.map(clean_codeblock_headers).collect();
+
+    let events = match Sanitizer::new(markdown) {
+        Some(sanitizer) => sanitizer.sanitize_events(events),
+        None => events,
+    };
+
+    let events = filter(extensions::merge_text(events))?;
+
+    // the quotes are converted once the text is in one piece, so the
+    // delimiters of any math can be found
     let mut converter = EventQuoteConverter::new(curly_quotes);
-    let events = p.map(clean_codeblock_headers)
-                  .map(|event| converter.convert(event));
+    let mut events: Vec<Event> = extensions::merge_text(events)
+        .into_iter()
+        .map(|event| converter.convert(event))
+        .collect();
+    if markdown.strikethrough {
+        events = extensions::strikethrough(events);
+    }
+    if markdown.superscript {
+        events = extensions::superscript(events);
+    }
+    if markdown.subscript {
+        events = extensions::subscript(events);
+    }
+    if markdown.task_lists {
+        events = extensions::task_lists(events);
+    }
+    if markdown.definition_lists {
+        events = extensions::definition_lists(events);
+    }
+    let events = footnotes::collect_footnotes(events.into_iter());
 
-    html::push_html(&mut s, events);
-    s
+    html::push_html(&mut s, events.into_iter());
+    Ok(s)
 }
 
 struct EventQuoteConverter {
     enabled: bool,
     convert_text: bool,
+    /// The delimiter which ends the math we're in, if we are.
+    math_end: Option<&'static str>,
 }
 
 impl EventQuoteConverter {
@@ -37,6 +102,7 @@ impl EventQuoteConverter {
         EventQuoteConverter {
             enabled: enabled,
             convert_text: true,
+            math_end: None,
         }
     }
 
@@ -55,7 +121,12 @@ impl EventQuoteConverter {
                 event
             }
             Event::Text(ref text) if self.convert_text => {
-                Event::Text(Cow::from(convert_quotes_to_curly(text)))
+                Event::Text(Cow::from(convert_quotes_outside_math(text, &mut self.math_end)))
+            }
+            // math can't carry on into the next paragraph
+            Event::End(Tag::Paragraph) | Event::End(Tag::Header(_)) | Event::End(Tag::Item) => {
+                self.math_end = None;
+                event
             }
             _ => event,
         }
@@ -75,34 +146,77 @@ fn clean_codeblock_headers(event: Event) -> Event {
 
 
 fn convert_quotes_to_curly(original_text: &str) -> String {
+    convert_quotes_outside_math(original_text, &mut None)
+}
+
+/// Convert straight quotes to curly ones, leaving the math which MathJax
+/// renders (e.g. `\\( f'(x) \\)` or `$f'(x)$`) alone. `math_end` is the
+/// delimiter ending the math the text starts in, if it does.
+fn convert_quotes_outside_math(original_text: &str, math_end: &mut Option<&'static str>) -> String {
+    let mut converted = String::with_capacity(original_text.len());
     // We'll consider the start to be "whitespace".
     let mut preceded_by_whitespace = true;
+    let mut rest = original_text;
 
-    original_text.chars()
-                 .map(|original_char| {
-        let converted_char = match original_char {
-            '\'' => {
-                if preceded_by_whitespace {
-                    '‘'
-                } else {
-                    '’'
+    while let Some(original_char) = rest.chars().next() {
+        let mut len = original_char.len_utf8();
+
+        match *math_end {
+            Some(end) => {
+                // a closing `$` has to come straight after the math
+                if rest.starts_with(end) && !(end == "$" && preceded_by_whitespace) {
+                    *math_end = None;
+                    len = end.len();
                 }
+                converted.push_str(&rest[..len]);
             }
-            '"' => {
-                if preceded_by_whitespace {
-                    '“'
-                } else {
-                    '”'
+            None => match math_start(rest) {
+                Some((start, end)) => {
+                    *math_end = Some(end);
+                    len = start.len();
+                    converted.push_str(start);
                 }
-            }
-            _ => original_char,
-        };
+                None => converted.push(match original_char {
+                    '\'' => if preceded_by_whitespace { '‘' } else { '’' },
+                    '"' => if preceded_by_whitespace { '“' } else { '”' },
+                    _ => original_char,
+                }),
+            },
+        }
 
         preceded_by_whitespace = original_char.is_whitespace();
+        rest = &rest[len..];
+    }
 
-        converted_char
-    })
-                 .collect()
+    converted
+}
+
+/// The delimiters of the math starting at the beginning of `text`, if any.
+/// Like pandoc, a single `$` only starts inline math when it's followed by
+/// something other than whitespace and closed later on (so prices like
+/// `$5` aren't math).
+fn math_start(text: &str) -> Option<(&'static str, &'static str)> {
+    if text.starts_with("\\(") {
+        Some(("\\(", "\\)"))
+    } else if text.starts_with("\\[") {
+        Some(("\\[", "\\]"))
+    } else if text.starts_with("$$") {
+        Some(("$$", "$$"))
+    } else if text.starts_with('$') {
+        let inner = &text[1..];
+        let starts_math = inner.chars().next().map_or(false, |c| !c.is_whitespace())
+            && inner.find('$').map_or(false, |ix| {
+                !inner[..ix].ends_with(char::is_whitespace)
+            });
+
+        if starts_math {
+            Some(("$", "$"))
+        } else {
+            None
+        }
+    } else {
+        None
+    }
 }
 
 /// Prints a "backtrace" of some `Error`.
@@ -112,6 +226,10 @@ pub fn log_backtrace(e: &Error) {
     for cause in e.iter().skip(1) {
         error!("\tCaused By: {}", cause);
     }
+
+    if let Some(hint) = e.hint() {
+        error!("help: {}", hint);
+    }
 }
 
 #[cfg(test)]
@@ -231,5 +349,17 @@ more text with spaces
         fn it_treats_tab_as_whitespace() {
             assert_eq!(convert_quotes_to_curly("\t'one'"), "\t‘one’");
         }
+
+        #[test]
+        fn it_leaves_math_alone() {
+            assert_eq!(convert_quotes_to_curly(r"'f' is \( f'(x) \) or $f'(x)$"),
+                       r"‘f’ is \( f'(x) \) or $f'(x)$");
+        }
+
+        #[test]
+        fn it_does_not_mistake_prices_for_math() {
+            assert_eq!(convert_quotes_to_curly("$5 or 'cheaper' at $4"),
+                       "$5 or ‘cheaper’ at $4");
+        }
     }
 }
@@ -0,0 +1,86 @@
+//! Running the commands given in the configuration.
+
+use std::io::{self, Write};
+use std::process::{Command, Output, Stdio};
+use std::thread;
+use shlex::Shlex;
+
+use errors::*;
+
+/// Split a command from the configuration into its program and arguments the
+/// way a shell would, replacing the placeholders (e.g. `{destination}`) in
+/// them with their values.
+pub fn command(command: &str, substitutions: &[(&str, &str)]) -> Result<Command> {
+    let mut words = Shlex::new(command).map(|word| {
+        substitutions
+            .iter()
+            .fold(word, |word, &(placeholder, value)| word.replace(placeholder, value))
+    });
+
+    let mut cmd = match words.next() {
+        Some(program) => Command::new(program),
+        None => bail!("The command is empty"),
+    };
+    cmd.args(words);
+
+    Ok(cmd)
+}
+
+/// Run `cmd` with `input` on its stdin, collecting what it prints to stdout
+/// (its stderr goes straight to ours).
+///
+/// The input is written from another thread while the output is read, so a
+/// command which prints as it goes can't fill up its stdout pipe while we're
+/// still blocked on writing to it.
+pub fn pipe_through(cmd: &mut Command, input: Vec<u8>) -> io::Result<Output> {
+    let mut child = cmd.stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()?;
+
+    let mut stdin = child.stdin.take().expect("Child has stdin");
+    let writer = thread::spawn(move || stdin.write_all(&input).and_then(|_| stdin.flush()));
+
+    let output = child.wait_with_output()?;
+
+    match writer.join() {
+        Ok(Ok(())) => {}
+        // the command doesn't have to read all of its input
+        Ok(Err(ref e)) if e.kind() == io::ErrorKind::BrokenPipe => {}
+        Ok(Err(e)) => warn!("Error writing to {:?}, {}", cmd, e),
+        Err(_) => warn!("Writing to {:?} panicked", cmd),
+    }
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn placeholders_are_substituted_in_each_word() {
+        let cmd = command(
+            "rsync -a '{destination}/' staging:/{renderer}",
+            &[("{destination}", "my book"), ("{renderer}", "html")],
+        ).unwrap();
+
+        assert_eq!(
+            format!("{:?}", cmd),
+            r#""rsync" "-a" "my book/" "staging:/html""#
+        );
+        assert!(command("  ", &[]).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn big_inputs_dont_deadlock() {
+        // far more than fits in a pipe's buffer
+        let input = vec![b'x'; 1024 * 1024];
+
+        let output = pipe_through(&mut Command::new("cat"), input.clone()).unwrap();
+
+        assert!(output.status.success());
+        assert_eq!(output.stdout, input);
+    }
+}
@@ -0,0 +1,337 @@
+//! Sanitizing the raw HTML in chapters, for books which can't trust everyone
+//! who contributes to them.
+
+#[allow(unused_imports)] use std::ascii::AsciiExt;
+use std::borrow::Cow;
+use pulldown_cmark::{Event, Tag};
+use regex::{Captures, Regex};
+
+use config::{MarkdownConfig, RawHtml};
+
+/// The tags kept by `raw-html = "sanitize"` unless `allowed-tags` is set.
+const DEFAULT_TAGS: &[&str] = &[
+    "a", "abbr", "b", "blockquote", "br", "code", "dd", "del", "details", "div", "dl", "dt",
+    "em", "figcaption", "figure", "h1", "h2", "h3", "h4", "h5", "h6", "hr", "i", "img", "ins",
+    "kbd", "li", "mark", "ol", "p", "pre", "q", "s", "samp", "small", "span", "strong", "sub",
+    "summary", "sup", "table", "tbody", "td", "tfoot", "th", "thead", "tr", "u", "ul", "var",
+];
+
+/// The attributes kept by `raw-html = "sanitize"` unless `allowed-attributes`
+/// is set.
+const DEFAULT_ATTRIBUTES: &[&str] = &[
+    "align", "alt", "class", "colspan", "height", "href", "id", "lang", "rowspan", "src",
+    "start", "title", "width",
+];
+
+/// Cleans up the raw HTML of a chapter according to its `raw-html` setting.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sanitizer {
+    strip: bool,
+    tags: Vec<String>,
+    attributes: Vec<String>,
+}
+
+impl Sanitizer {
+    /// The sanitizer for a book, or `None` when its raw HTML is allowed
+    /// through untouched.
+    pub fn new(config: &MarkdownConfig) -> Option<Sanitizer> {
+        let strip = match config.raw_html {
+            RawHtml::Allow => return None,
+            RawHtml::Sanitize => false,
+            RawHtml::Strip => true,
+        };
+
+        Some(Sanitizer {
+            strip,
+            tags: allowlist(&config.allowed_tags, DEFAULT_TAGS),
+            attributes: allowlist(&config.allowed_attributes, DEFAULT_ATTRIBUTES),
+        })
+    }
+
+    /// Sanitize the raw HTML among some markdown events, along with the
+    /// destinations of links and images.
+    pub fn sanitize_events<'a>(&self, events: Vec<Event<'a>>) -> Vec<Event<'a>> {
+        let mut out: Vec<Event<'a>> = Vec::with_capacity(events.len());
+
+        for event in events {
+            match event {
+                // pulldown-cmark gives each line of an HTML block separately,
+                // but a tag can span several of them
+                Event::Html(html) => {
+                    if let Some(&mut Event::Html(ref mut previous)) = out.last_mut() {
+                        previous.to_mut().push_str(&html);
+                        continue;
+                    }
+                    out.push(Event::Html(html));
+                }
+                Event::Start(Tag::Link(dest, title)) => {
+                    out.push(Event::Start(Tag::Link(safe_url(dest, false), title)))
+                }
+                Event::Start(Tag::Image(dest, title)) => {
+                    out.push(Event::Start(Tag::Image(safe_url(dest, true), title)))
+                }
+                event => out.push(event),
+            }
+        }
+
+        out.into_iter()
+            .map(|event| match event {
+                Event::Html(html) => Event::Html(Cow::from(self.sanitize(&html))),
+                Event::InlineHtml(html) => Event::InlineHtml(Cow::from(self.sanitize(&html))),
+                event => event,
+            })
+            .collect()
+    }
+
+    /// Sanitize a piece of HTML.
+    pub fn sanitize(&self, html: &str) -> String {
+        lazy_static! {
+            static ref DROPPED: Regex =
+                Regex::new(r"(?is)<!--.*?(?:-->|$)|<script\b.*?(?:</script\s*>|$)|<style\b.*?(?:</style\s*>|$)")
+                    .unwrap();
+            static ref TAG: Regex =
+                Regex::new(r#"<(/?)([a-zA-Z][a-zA-Z0-9-]*)((?:[^>"']|"[^"]*"|'[^']*')*?)(/?)>"#)
+                    .unwrap();
+        }
+
+        let html = DROPPED.replace_all(html, "");
+        let mut clean = String::with_capacity(html.len());
+        let mut last = 0;
+
+        for caps in TAG.captures_iter(&html) {
+            let whole = caps.get(0).expect("the whole match is always there");
+            // anything which looks like the start of a tag but isn't one could
+            // combine with what follows, so it gets escaped
+            clean.push_str(&html[last..whole.start()].replace('<', "&lt;"));
+            last = whole.end();
+
+            if let Some(tag) = self.sanitize_tag(&caps) {
+                clean.push_str(&tag);
+            }
+        }
+
+        clean.push_str(&html[last..].replace('<', "&lt;"));
+        clean
+    }
+
+    fn sanitize_tag(&self, caps: &Captures) -> Option<String> {
+        let name = caps[2].to_ascii_lowercase();
+        if self.strip || !self.tags.contains(&name) {
+            return None;
+        }
+
+        let closing = &caps[1];
+        if !closing.is_empty() {
+            return Some(format!("</{}>", name));
+        }
+
+        lazy_static! {
+            static ref ATTRIBUTE: Regex = Regex::new(
+                r#"([a-zA-Z_:][-a-zA-Z0-9_:.]*)(?:\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'=<>`]+)))?"#
+            ).unwrap();
+        }
+
+        let mut tag = format!("<{}", name);
+
+        for attr in ATTRIBUTE.captures_iter(&caps[3]) {
+            let attr_name = attr[1].to_ascii_lowercase();
+            if attr_name.starts_with("on") || !self.attributes.contains(&attr_name) {
+                continue;
+            }
+
+            let value = attr.get(2)
+                .or_else(|| attr.get(3))
+                .or_else(|| attr.get(4))
+                .map(|m| m.as_str())
+                .unwrap_or("");
+            if (attr_name == "href" || attr_name == "src")
+                && !is_safe_url(value, attr_name == "src" && name == "img")
+            {
+                continue;
+            }
+
+            tag.push_str(&format!(" {}=\"{}\"", attr_name, value.replace('"', "&quot;")));
+        }
+
+        tag.push_str(&caps[4]);
+        tag.push('>');
+        Some(tag)
+    }
+}
+
+fn allowlist(configured: &Option<Vec<String>>, default: &[&str]) -> Vec<String> {
+    match *configured {
+        Some(ref items) => items.iter().map(|item| item.to_ascii_lowercase()).collect(),
+        None => default.iter().map(|item| item.to_string()).collect(),
+    }
+}
+
+fn safe_url(url: Cow<str>, image: bool) -> Cow<str> {
+    if is_safe_url(&url, image) {
+        url
+    } else {
+        Cow::from("")
+    }
+}
+
+/// Is this a URL which can't run a script when it's followed? Images may
+/// use `data:` URLs.
+fn is_safe_url(url: &str, image: bool) -> bool {
+    // browsers decode character references in attributes, and ignore
+    // whitespace and control characters in the scheme
+    let url: String = decode_character_references(url)
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .take(16)
+        .collect::<String>()
+        .to_ascii_lowercase();
+
+    // whatever reference is left could still turn into part of the scheme,
+    // as long as it comes before anything which ends one (like the `?` of a
+    // query string)
+    let scheme: String = url.chars()
+        .take_while(|&c| c.is_ascii_alphanumeric() || "+-.&;#".contains(c))
+        .collect();
+    if scheme.contains('&') {
+        return false;
+    }
+
+    if url.starts_with("data:") {
+        image && url.starts_with("data:image/")
+    } else {
+        !url.starts_with("javascript:") && !url.starts_with("vbscript:")
+    }
+}
+
+/// Decode the numeric character references (`&#106;`, `&#x6A`, with or
+/// without the `;`) and the named ones which can end up in a URL's scheme.
+fn decode_character_references(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        match decode_reference(rest) {
+            Some((c, len)) => {
+                decoded.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    decoded.push_str(rest);
+    decoded
+}
+
+/// The character a reference at the start of `text` stands for, and how long
+/// the reference is.
+fn decode_reference(text: &str) -> Option<(char, usize)> {
+    const NAMED: &[(&str, char)] = &[
+        ("&colon;", ':'),
+        ("&tab;", '\t'),
+        ("&newline;", '\n'),
+        ("&amp;", '&'),
+    ];
+
+    let lowercase = text.chars().take(10).collect::<String>().to_ascii_lowercase();
+    for &(name, c) in NAMED {
+        if lowercase.starts_with(name) {
+            return Some((c, name.len()));
+        }
+    }
+
+    if !text.starts_with("&#") {
+        return None;
+    }
+    let (radix, digits_start) = match text[2..].chars().next() {
+        Some('x') | Some('X') => (16, 3),
+        _ => (10, 2),
+    };
+    let digits = text[digits_start..]
+        .chars()
+        .take_while(|c| c.is_digit(radix))
+        .count();
+    if digits == 0 {
+        return None;
+    }
+
+    let end = digits_start + digits;
+    let c = u32::from_str_radix(&text[digits_start..end], radix)
+        .ok()
+        .and_then(::std::char::from_u32)
+        .unwrap_or('\u{fffd}');
+    let len = if text[end..].starts_with(';') { end + 1 } else { end };
+    Some((c, len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sanitizer(raw_html: RawHtml) -> Sanitizer {
+        let config = MarkdownConfig {
+            raw_html,
+            ..Default::default()
+        };
+        Sanitizer::new(&config).unwrap()
+    }
+
+    #[test]
+    fn scripts_and_event_handlers_are_removed() {
+        let html = "<div class=\"note\" onclick=\"evil()\"><script>evil()</script>\
+                    <a href=\"javascript:evil()\" title='x'>link</a><iframe src=\"x\"></iframe>\
+                    <img src=x onerror=evil()></div>";
+
+        assert_eq!(
+            sanitizer(RawHtml::Sanitize).sanitize(html),
+            "<div class=\"note\"><a title=\"x\">link</a><img src=\"x\"></div>"
+        );
+    }
+
+    #[test]
+    fn character_references_cant_hide_a_script_url() {
+        let sanitizer = sanitizer(RawHtml::Sanitize);
+
+        for href in &[
+            "&#106;avascript:evil()",
+            "&#x6A;avascript:evil()",
+            "&#0000106avascript:evil()",
+            "javascript&colon;evil()",
+            "java&Tab;script:evil()",
+            "java&unknown;script:evil()",
+        ] {
+            let html = format!("<a href=\"{}\">link</a>", href);
+            assert_eq!(sanitizer.sanitize(&html), "<a>link</a>", "{}", href);
+        }
+
+        assert_eq!(
+            sanitizer.sanitize("<a href=\"search.html?q=a&amp;page=2\">link</a>"),
+            "<a href=\"search.html?q=a&amp;page=2\">link</a>"
+        );
+    }
+
+    #[test]
+    fn stripping_only_keeps_the_text() {
+        let html = "<p>Some <b>bold</b> text</p><style>p {}</style><!-- a comment -->";
+
+        assert_eq!(sanitizer(RawHtml::Strip).sanitize(html), "Some bold text");
+    }
+
+    #[test]
+    fn a_tag_split_over_several_lines_is_sanitized_as_a_whole() {
+        let events = vec![
+            Event::Html(Cow::from("<img\n")),
+            Event::Html(Cow::from("  onerror=\"evil()\" src=\"cat.png\">\n")),
+        ];
+
+        let got = sanitizer(RawHtml::Sanitize).sanitize_events(events);
+
+        assert_eq!(got, vec![Event::Html(Cow::from("<img src=\"cat.png\">\n"))]);
+    }
+}
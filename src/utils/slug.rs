@@ -0,0 +1,122 @@
+#[allow(unused_imports)] use std::ascii::AsciiExt;
+use config::SlugStyle;
+
+/// Turn the text of a heading into the ID used to link to it.
+pub fn slugify(text: &str, style: SlugStyle) -> String {
+    match style {
+        SlugStyle::Github => github(text),
+        SlugStyle::Ascii => ascii(text),
+        SlugStyle::Unicode => unicode(text),
+    }
+}
+
+fn github(text: &str) -> String {
+    text.chars()
+        .filter_map(|ch| if ch.is_alphanumeric() || ch == '_' || ch == '-' {
+            Some(ch.to_ascii_lowercase())
+        } else if ch.is_whitespace() {
+            Some('-')
+        } else {
+            None
+        })
+        .collect()
+}
+
+fn ascii(text: &str) -> String {
+    let mut slug = String::with_capacity(text.len());
+
+    for ch in text.chars() {
+        if ch.is_ascii() {
+            slug.push(ch);
+        } else if let Some(replacement) = transliterate(ch) {
+            slug.push_str(replacement);
+        } else if ch.is_whitespace() {
+            slug.push(' ');
+        }
+    }
+
+    github(&slug)
+}
+
+fn unicode(text: &str) -> String {
+    text.chars()
+        .filter_map(|ch| if ch.is_whitespace() {
+            Some('-')
+        } else if ch.is_control() || "\"'<>&#%?/\\`{}|^[]".contains(ch) {
+            None
+        } else {
+            Some(ch)
+        })
+        .collect()
+}
+
+/// The ASCII spelling of the common accented (and otherwise non-ASCII) latin
+/// letters.
+fn transliterate(ch: char) -> Option<&'static str> {
+    let replacement = match ch {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => "a",
+        'À' | 'Á' | 'Â' | 'Ã' | 'Ä' | 'Å' | 'Ā' | 'Ă' | 'Ą' => "A",
+        'æ' => "ae",
+        'Æ' => "AE",
+        'ç' | 'ć' | 'ĉ' | 'ċ' | 'č' => "c",
+        'Ç' | 'Ć' | 'Ĉ' | 'Ċ' | 'Č' => "C",
+        'ď' | 'đ' | 'ð' => "d",
+        'Ď' | 'Đ' | 'Ð' => "D",
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ĕ' | 'ė' | 'ę' | 'ě' => "e",
+        'È' | 'É' | 'Ê' | 'Ë' | 'Ē' | 'Ĕ' | 'Ė' | 'Ę' | 'Ě' => "E",
+        'ĝ' | 'ğ' | 'ġ' | 'ģ' => "g",
+        'Ĝ' | 'Ğ' | 'Ġ' | 'Ģ' => "G",
+        'ĥ' | 'ħ' => "h",
+        'Ĥ' | 'Ħ' => "H",
+        'ì' | 'í' | 'î' | 'ï' | 'ĩ' | 'ī' | 'ĭ' | 'į' | 'ı' => "i",
+        'Ì' | 'Í' | 'Î' | 'Ï' | 'Ĩ' | 'Ī' | 'Ĭ' | 'Į' | 'İ' => "I",
+        'ĵ' => "j",
+        'Ĵ' => "J",
+        'ķ' => "k",
+        'Ķ' => "K",
+        'ĺ' | 'ļ' | 'ľ' | 'ŀ' | 'ł' => "l",
+        'Ĺ' | 'Ļ' | 'Ľ' | 'Ŀ' | 'Ł' => "L",
+        'ñ' | 'ń' | 'ņ' | 'ň' => "n",
+        'Ñ' | 'Ń' | 'Ņ' | 'Ň' => "N",
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ŏ' | 'ő' => "o",
+        'Ò' | 'Ó' | 'Ô' | 'Õ' | 'Ö' | 'Ø' | 'Ō' | 'Ŏ' | 'Ő' => "O",
+        'œ' => "oe",
+        'Œ' => "OE",
+        'ŕ' | 'ŗ' | 'ř' => "r",
+        'Ŕ' | 'Ŗ' | 'Ř' => "R",
+        'ś' | 'ŝ' | 'ş' | 'š' => "s",
+        'Ś' | 'Ŝ' | 'Ş' | 'Š' => "S",
+        'ß' => "ss",
+        'ţ' | 'ť' | 'ŧ' => "t",
+        'Ţ' | 'Ť' | 'Ŧ' => "T",
+        'þ' => "th",
+        'Þ' => "TH",
+        'ù' | 'ú' | 'û' | 'ü' | 'ũ' | 'ū' | 'ŭ' | 'ů' | 'ű' | 'ų' => "u",
+        'Ù' | 'Ú' | 'Û' | 'Ü' | 'Ũ' | 'Ū' | 'Ŭ' | 'Ů' | 'Ű' | 'Ų' => "U",
+        'ŵ' => "w",
+        'Ŵ' => "W",
+        'ý' | 'ÿ' | 'ŷ' => "y",
+        'Ý' | 'Ÿ' | 'Ŷ' => "Y",
+        'ź' | 'ż' | 'ž' => "z",
+        'Ź' | 'Ż' | 'Ž' => "Z",
+        _ => return None,
+    };
+
+    Some(replacement)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_style_slugifies_differently() {
+        let heading = "Über die Größe von Äpfeln?";
+
+        assert_eq!(slugify(heading, SlugStyle::Github), "Über-die-größe-von-Äpfeln");
+        assert_eq!(slugify(heading, SlugStyle::Ascii), "uber-die-grosse-von-apfeln");
+        assert_eq!(slugify(heading, SlugStyle::Unicode), "Über-die-Größe-von-Äpfeln");
+        assert_eq!(slugify("Hello, World!", SlugStyle::Unicode), "Hello,-World!");
+        assert_eq!(slugify("Hello, World!", SlugStyle::Github), "hello-world");
+    }
+}
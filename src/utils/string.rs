@@ -55,9 +55,18 @@ pub fn take_lines<R: RangeArgument<usize>>(s: &str, range: R) -> String {
     }
 }
 
+/// Escape the characters which have a meaning in HTML, so `text` can be put
+/// in an element or a (quoted) attribute.
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
 #[cfg(test)]
 mod tests {
-    use super::take_lines;
+    use super::{escape_html, take_lines};
 
     #[test]
     fn take_lines_test() {
@@ -70,4 +79,12 @@ mod tests {
         assert_eq!(take_lines(s, 4..3), "");
         assert_eq!(take_lines(s, ..100), s);
     }
+
+    #[test]
+    fn escape_html_test() {
+        assert_eq!(
+            escape_html(r#"<a href="?a=1&b=2">"#),
+            "&lt;a href=&quot;?a=1&amp;b=2&quot;&gt;"
+        );
+    }
 }
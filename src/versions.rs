@@ -0,0 +1,254 @@
+//! Building several versions of a book into a combined site.
+//!
+//! The versions are listed in the book's `book.toml`, newest first:
+//!
+//! ```toml
+//! [[version]]
+//! name = "v2.0"
+//! rev = "v2.0.0"
+//!
+//! [[version]]
+//! name = "v1.0"
+//! rev = "v1.0.3"
+//!
+//! [[version]]
+//! name = "dev"
+//! ```
+//!
+//! Each version is checked out from git (or taken from the working tree when
+//! it has no `rev`) and built into its own directory, e.g. `book/v2.0/`. The
+//! latest version is also built into `book/latest/`, and a `versions.json`
+//! lists all of them for other tools.
+
+use std::fs;
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+use serde_json;
+use tempdir::TempDir;
+
+use book::{load_config, MDBook};
+use config::{Config, VersionConfig};
+use utils::fs::{copy_files_except_ext, create_file};
+use errors::*;
+
+/// The directory the latest version is also built into.
+pub const LATEST: &str = "latest";
+
+/// The name of the manifest listing the versions, at the top of the build
+/// directory.
+pub const MANIFEST: &str = "versions.json";
+
+/// A version's entry in `versions.json`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Entry {
+    name: String,
+    path: String,
+    latest: bool,
+}
+
+/// Build every version of the book in `root` into its own subdirectory of
+/// `build_dir`, then copy the latest one into `latest/` and write the
+/// `versions.json` manifest.
+pub fn build_versions(root: &Path, config: &Config, build_dir: &Path) -> Result<()> {
+    let versions = config.versions();
+    if versions.is_empty() {
+        bail!("book.toml doesn't list any versions");
+    }
+    for version in &versions {
+        if !is_usable_name(&version.name) {
+            bail!("\"{}\" can't be used as the name of a version", version.name);
+        }
+    }
+
+    let latest = versions
+        .iter()
+        .find(|version| version.latest)
+        .unwrap_or(&versions[0])
+        .name
+        .clone();
+
+    for version in &versions {
+        info!("Building version \"{}\"", version.name);
+        build_version(root, config, version, &versions, &build_dir.join(&version.name))
+            .chain_err(|| format!("Unable to build version \"{}\"", version.name))?;
+    }
+
+    let latest_dir = build_dir.join(LATEST);
+    if latest_dir.exists() {
+        fs::remove_dir_all(&latest_dir)?;
+    }
+    fs::create_dir_all(&latest_dir)?;
+    copy_files_except_ext(&build_dir.join(&latest), &latest_dir, true, &[])?;
+
+    let entries: Vec<Entry> = versions
+        .iter()
+        .map(|version| Entry {
+            name: version.name.clone(),
+            path: format!("{}/", version.name),
+            latest: version.name == latest,
+        })
+        .collect();
+    let manifest = serde_json::to_string_pretty(&entries)
+        .chain_err(|| format!("Unable to serialize {}", MANIFEST))?;
+    create_file(&build_dir.join(MANIFEST))?.write_all(manifest.as_bytes())?;
+
+    // send readers who end up at the root to the latest version
+    let mut f = create_file(&build_dir.join("index.html"))?;
+    writeln!(
+        f,
+        "<!DOCTYPE html>\n<meta charset=\"utf-8\">\n\
+         <meta http-equiv=\"refresh\" content=\"0; url={}/index.html\">",
+        LATEST
+    )?;
+
+    Ok(())
+}
+
+/// Is the name a single directory in the build directory, other than
+/// `latest`?
+fn is_usable_name(name: &str) -> bool {
+    let mut components = Path::new(name).components();
+    let single_dir = match (components.next(), components.next()) {
+        (Some(Component::Normal(_)), None) => true,
+        _ => false,
+    };
+
+    single_dir && name != LATEST && !name.contains(|c| c == '/' || c == '\\')
+}
+
+fn build_version(
+    root: &Path,
+    config: &Config,
+    version: &VersionConfig,
+    versions: &[VersionConfig],
+    destination: &Path,
+) -> Result<()> {
+    let (book_root, _checkout) = match version.rev {
+        Some(ref rev) => {
+            let checkout = Checkout::new(root, rev)?;
+            (checkout.book_root.clone(), Some(checkout))
+        }
+        None => (root.to_path_buf(), None),
+    };
+
+    // older versions use their own book.toml, but every version needs to know
+    // about all the others for the version switcher
+    let mut version_config = if version.rev.is_some() {
+        load_config(&book_root, None)?
+    } else {
+        config.clone()
+    };
+    version_config.set("version", versions)?;
+    version_config.book.version = Some(version.name.clone());
+    version_config.build.build_dir = destination.to_path_buf();
+
+    MDBook::load_with_config(book_root, version_config)?.build()
+}
+
+/// A temporary git worktree with a revision of the book's repository checked
+/// out, which is removed again when dropped.
+struct Checkout {
+    repository: PathBuf,
+    book_root: PathBuf,
+    dir: TempDir,
+}
+
+impl Checkout {
+    fn new(root: &Path, rev: &str) -> Result<Checkout> {
+        let repository = git(root, &["rev-parse", "--show-toplevel"])?;
+        let prefix = git(root, &["rev-parse", "--show-prefix"])?;
+
+        let dir = TempDir::new("mdbook-version")?;
+        let worktree = dir.path().join("checkout");
+        let mut add = Command::new("git");
+        add.args(&["worktree", "add", "--detach"])
+            .arg(&worktree)
+            .arg(rev)
+            .current_dir(root);
+        run(add)?;
+
+        Ok(Checkout {
+            repository: PathBuf::from(repository),
+            book_root: worktree.join(prefix),
+            dir: dir,
+        })
+    }
+}
+
+impl Drop for Checkout {
+    fn drop(&mut self) {
+        // git forgets about a worktree once its directory is gone
+        if let Err(e) = fs::remove_dir_all(self.dir.path().join("checkout")) {
+            warn!("Unable to remove the checkout in {}, {}", self.dir.path().display(), e);
+        }
+
+        let mut prune = Command::new("git");
+        prune.args(&["worktree", "prune"]).current_dir(&self.repository);
+        if let Err(e) = run(prune) {
+            warn!("Unable to prune the git worktrees, {}", e);
+        }
+    }
+}
+
+/// Run a git command in `dir`, returning what it printed.
+fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let mut cmd = Command::new("git");
+    cmd.args(args).current_dir(dir);
+    run(cmd)
+}
+
+fn run(mut cmd: Command) -> Result<String> {
+    debug!("Running {:?}", cmd);
+    let output = cmd.output()
+        .chain_err(|| "Unable to run git, which is needed to build versions")?;
+
+    if !output.status.success() {
+        bail!(ErrorKind::Subprocess(format!("{:?} failed", cmd), output));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_need_usable_names() {
+        let temp = TempDir::new("mdbook").unwrap();
+        let mut config = Config::default();
+        config
+            .set(
+                "version",
+                vec![VersionConfig {
+                    name: String::from(LATEST),
+                    ..Default::default()
+                }],
+            )
+            .unwrap();
+
+        let got = build_versions(temp.path(), &config, &temp.path().join("book"));
+
+        assert!(got.is_err());
+    }
+
+    #[test]
+    fn version_names_stay_inside_the_build_directory() {
+        assert!(is_usable_name("v2.0"));
+        assert!(is_usable_name("dev"));
+
+        for name in &["", ".", "..", "../v1", "v1/beta", "/tmp", "latest"] {
+            assert!(!is_usable_name(name), "{}", name);
+        }
+    }
+
+    #[test]
+    fn a_book_without_versions_cant_be_built_as_one() {
+        let temp = TempDir::new("mdbook").unwrap();
+
+        let got = build_versions(temp.path(), &Config::default(), &temp.path().join("book"));
+
+        assert!(got.is_err());
+    }
+}
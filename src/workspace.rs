@@ -0,0 +1,306 @@
+//! Building several books at once into a combined site.
+//!
+//! A workspace is a directory containing a `mdbook-workspace.toml` manifest
+//! which lists the roots of the books it contains:
+//!
+//! ```toml
+//! title = "Project Documentation"
+//! books = ["user-guide", "developer-guide"]
+//! build-dir = "site"
+//! ```
+//!
+//! Each book is built into its own directory inside the workspace's build
+//! directory (e.g. `site/user-guide/`), next to a generated landing page
+//! linking to all of them.
+
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::thread;
+use toml;
+
+use book::{load_config, MDBook};
+use utils;
+use utils::escape_html;
+use utils::fs::create_file;
+use errors::*;
+
+/// The name of the manifest file at the top of a workspace.
+pub const MANIFEST: &str = "mdbook-workspace.toml";
+
+/// The contents of a workspace's manifest.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct WorkspaceConfig {
+    /// The title used for the landing page.
+    pub title: String,
+    /// The root directories of the books, relative to the workspace.
+    pub books: Vec<PathBuf>,
+    /// Where to put the combined site, relative to the workspace.
+    pub build_dir: PathBuf,
+}
+
+impl Default for WorkspaceConfig {
+    fn default() -> WorkspaceConfig {
+        WorkspaceConfig {
+            title: String::from("Books"),
+            books: Vec::new(),
+            build_dir: PathBuf::from("book"),
+        }
+    }
+}
+
+impl WorkspaceConfig {
+    /// Load a `WorkspaceConfig` from some string.
+    pub fn from_str(src: &str) -> Result<WorkspaceConfig> {
+        toml::from_str(src).chain_err(|| Error::from("Invalid workspace manifest"))
+    }
+}
+
+/// A book's entry on the landing page.
+#[derive(Debug, Clone, PartialEq)]
+struct Entry {
+    link: String,
+    title: String,
+    description: Option<String>,
+}
+
+/// Several books which are built together.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Workspace {
+    /// The directory containing the manifest.
+    pub root: PathBuf,
+    /// The parsed manifest.
+    pub config: WorkspaceConfig,
+    /// The configuration profile applied to every book.
+    pub profile: Option<String>,
+    /// `key=value` overrides applied to every book's configuration, as passed
+    /// to `--set` on the command line.
+    pub overrides: Vec<String>,
+}
+
+impl Workspace {
+    /// Load the workspace whose manifest is in `root`.
+    pub fn load<P: Into<PathBuf>>(root: P) -> Result<Workspace> {
+        let root = root.into();
+        let manifest = root.join(MANIFEST);
+
+        let mut src = String::new();
+        File::open(&manifest)
+            .chain_err(|| format!("Unable to open {}", manifest.display()))?
+            .read_to_string(&mut src)?;
+        let config = WorkspaceConfig::from_str(&src)?;
+
+        if config.books.is_empty() {
+            bail!("{} doesn't list any books", manifest.display());
+        }
+
+        Ok(Workspace {
+            root,
+            config,
+            profile: None,
+            overrides: Vec::new(),
+        })
+    }
+
+    /// The directory the combined site is written to.
+    pub fn build_dir(&self) -> PathBuf {
+        self.root.join(&self.config.build_dir)
+    }
+
+    /// The directory a particular book is written to.
+    ///
+    /// Books end up as directories in the build directory, so this is an
+    /// error unless the book's entry is a plain directory name.
+    pub fn build_dir_for(&self, book: &Path) -> Result<PathBuf> {
+        let mut components = book.components();
+        match (components.next(), components.next()) {
+            (Some(Component::Normal(_)), None) => Ok(self.build_dir().join(book)),
+            _ => bail!(
+                "The workspace's book \"{}\" has to be a plain directory name",
+                book.display()
+            ),
+        }
+    }
+
+    /// Build every book, then write the landing page.
+    ///
+    /// With `parallel` each book is built on its own thread. Either way every
+    /// book is attempted, and the build fails if any of them did.
+    pub fn build(&self, parallel: bool) -> Result<()> {
+        let mut jobs = Vec::new();
+        for book in &self.config.books {
+            jobs.push((self.root.join(book), self.build_dir_for(book)?));
+        }
+
+        let results: Vec<(PathBuf, Result<()>)> = if parallel {
+            let handles: Vec<_> = jobs.into_iter()
+                .map(|(root, dest)| {
+                    let name = root.clone();
+                    let profile = self.profile.clone();
+                    let overrides = self.overrides.clone();
+                    let handle = thread::spawn(move || {
+                        build_book(root, dest, profile.as_ref().map(|p| p.as_str()), &overrides)
+                    });
+                    (name, handle)
+                })
+                .collect();
+
+            handles
+                .into_iter()
+                .map(|(name, handle)| {
+                    let result = handle
+                        .join()
+                        .unwrap_or_else(|_| Err(Error::from("The build panicked")));
+                    (name, result)
+                })
+                .collect()
+        } else {
+            jobs.into_iter()
+                .map(|(root, dest)| {
+                    let profile = self.profile.as_ref().map(|p| p.as_str());
+                    (root.clone(), build_book(root, dest, profile, &self.overrides))
+                })
+                .collect()
+        };
+
+        let mut failures = 0;
+        for (root, result) in results {
+            if let Err(e) = result {
+                error!("Unable to build {}", root.display());
+                utils::log_backtrace(&e);
+                failures += 1;
+            }
+        }
+        if failures > 0 {
+            bail!("{} of the workspace's books failed to build", failures);
+        }
+
+        self.write_landing_page()
+    }
+
+    fn write_landing_page(&self) -> Result<()> {
+        let mut entries = Vec::new();
+        for book in &self.config.books {
+            let profile = self.profile.as_ref().map(|p| p.as_str());
+            let config = load_config(&self.root.join(book), profile)?;
+            let link = book.to_string_lossy().replace('\\', "/");
+            entries.push(Entry {
+                title: config.book.title.unwrap_or_else(|| link.clone()),
+                description: config.book.description,
+                link,
+            });
+        }
+
+        let index = self.build_dir().join("index.html");
+        debug!("Writing {}", index.display());
+        create_file(&index)?.write_all(landing_page(&self.config.title, &entries).as_bytes())?;
+
+        Ok(())
+    }
+}
+
+fn build_book(
+    root: PathBuf,
+    dest: PathBuf,
+    profile: Option<&str>,
+    overrides: &[String],
+) -> Result<()> {
+    info!("Building {}", root.display());
+
+    let mut config = load_config(&root, profile)?;
+    for assignment in overrides {
+        config.apply_override(assignment)?;
+    }
+    config.build.build_dir = dest;
+
+    MDBook::load_with_config(root, config)?.build()
+}
+
+fn landing_page(title: &str, entries: &[Entry]) -> String {
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n\
+         <meta charset=\"UTF-8\">\n<title>{0}</title>\n</head>\n<body>\n\
+         <h1>{0}</h1>\n<ul class=\"books\">\n",
+        escape_html(title)
+    );
+
+    for entry in entries {
+        html.push_str(&format!(
+            "<li><a href=\"{}/index.html\">{}</a>",
+            escape_html(&entry.link),
+            escape_html(&entry.title)
+        ));
+        if let Some(ref description) = entry.description {
+            html.push_str(&format!("<p>{}</p>", escape_html(description)));
+        }
+        html.push_str("</li>\n");
+    }
+
+    html.push_str("</ul>\n</body>\n</html>\n");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_a_workspace_manifest() {
+        let src = r#"
+        title = "Project Documentation"
+        books = ["user-guide", "developer-guide"]
+        "#;
+
+        let got = WorkspaceConfig::from_str(src).unwrap();
+
+        assert_eq!(got.title, "Project Documentation");
+        assert_eq!(
+            got.books,
+            vec![PathBuf::from("user-guide"), PathBuf::from("developer-guide")]
+        );
+        assert_eq!(got.build_dir, PathBuf::from("book"));
+    }
+
+    #[test]
+    fn books_have_to_be_plain_directory_names() {
+        let workspace = Workspace {
+            root: PathBuf::from("/workspace"),
+            config: WorkspaceConfig::default(),
+            profile: None,
+            overrides: Vec::new(),
+        };
+
+        assert_eq!(
+            workspace.build_dir_for(Path::new("guide")).unwrap(),
+            PathBuf::from("/workspace/book/guide")
+        );
+        for bad in &["../other", "/elsewhere", "nested/guide", "."] {
+            assert!(workspace.build_dir_for(Path::new(bad)).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn the_landing_page_links_to_each_book() {
+        let entries = vec![
+            Entry {
+                link: String::from("user-guide"),
+                title: String::from("User <Guide>"),
+                description: Some(String::from("For users")),
+            },
+            Entry {
+                link: String::from("api"),
+                title: String::from("API"),
+                description: None,
+            },
+        ];
+
+        let got = landing_page("Docs", &entries);
+
+        assert!(got.contains("<title>Docs</title>"));
+        assert!(got.contains(
+            "<li><a href=\"user-guide/index.html\">User &lt;Guide&gt;</a><p>For users</p></li>"
+        ));
+        assert!(got.contains("<li><a href=\"api/index.html\">API</a></li>"));
+    }
+}
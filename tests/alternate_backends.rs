@@ -81,3 +81,177 @@ fn dummy_book_with_backend(name: &str, command: &str) -> (MDBook, TempDir) {
 
     (md, temp)
 }
+
+#[test]
+fn renderers_can_have_their_own_build_dir() {
+    let temp = TempDir::new("mdbook").unwrap();
+
+    let mut config = Config::default();
+    config.set("output.html.build-dir", "site").unwrap();
+    config.set("output.passing.command", "true").unwrap();
+    config.set("output.passing.build-dir", "passing-output").unwrap();
+
+    let md = MDBook::init(temp.path())
+        .with_config(config)
+        .build()
+        .unwrap();
+
+    assert_eq!(md.build_dir_for("html"), temp.path().join("site"));
+    assert_eq!(md.build_dir_for("passing"), temp.path().join("passing-output"));
+
+    md.build().unwrap();
+    assert!(temp.path().join("site").join("index.html").exists());
+}
+
+#[test]
+fn renderers_cant_share_a_build_dir() {
+    let temp = TempDir::new("mdbook").unwrap();
+
+    let mut config = Config::default();
+    config.set("output.html.build-dir", "out").unwrap();
+    config.set("output.passing.command", "true").unwrap();
+    config.set("output.passing.build-dir", "out/passing").unwrap();
+
+    let md = MDBook::init(temp.path())
+        .with_config(config)
+        .build()
+        .unwrap();
+
+    md.build().unwrap_err();
+}
+
+#[test]
+fn renderers_can_be_registered_from_code() {
+    use std::sync::{Arc, Mutex};
+    use mdbook::errors::Result;
+    use mdbook::renderer::Renderer;
+
+    struct Recorder(Arc<Mutex<Option<RenderContext>>>);
+
+    impl Renderer for Recorder {
+        fn name(&self) -> &str {
+            "recorder"
+        }
+
+        fn render(&self, ctx: &RenderContext) -> Result<()> {
+            *self.0.lock().unwrap() = Some(ctx.clone());
+            Ok(())
+        }
+    }
+
+    let temp = TempDir::new("mdbook").unwrap();
+    MDBook::init(temp.path()).build().unwrap();
+    let got = Arc::new(Mutex::new(None));
+
+    MDBook::load(temp.path())
+        .unwrap()
+        .with_renderer(Recorder(Arc::clone(&got)))
+        .with_build_dir("out")
+        .build()
+        .unwrap();
+
+    let ctx = got.lock().unwrap().take().expect("The renderer wasn't run");
+    assert_eq!(ctx.destination, temp.path().join("out").join("recorder"));
+    assert!(temp.path().join("out").join("html").join("index.html").exists());
+}
+
+#[test]
+fn registered_renderers_replace_ones_with_the_same_name() {
+    use mdbook::errors::Result;
+    use mdbook::renderer::Renderer;
+
+    struct Named(&'static str);
+
+    impl Renderer for Named {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        fn render(&self, _ctx: &RenderContext) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    let temp = TempDir::new("mdbook").unwrap();
+    let mut config = Config::default();
+    config.set("output.html.curly-quotes", true).unwrap();
+    config.set("output.passing.command", "true").unwrap();
+
+    let mut md = MDBook::init(temp.path())
+        .with_config(config)
+        .build()
+        .unwrap();
+    assert_eq!(md.renderer_names(), vec!["html", "passing"]);
+
+    md.with_renderer(Named("passing")).with_renderer(Named("custom"));
+    assert_eq!(md.renderer_names(), vec!["html", "passing", "custom"]);
+
+    md.without_renderer("html");
+    assert_eq!(md.renderer_names(), vec!["passing", "custom"]);
+}
+
+#[test]
+fn hooks_are_run_at_each_stage_of_the_build() {
+    use std::sync::{Arc, Mutex};
+    use mdbook::book::BuildStage;
+
+    let temp = TempDir::new("mdbook").unwrap();
+    MDBook::init(temp.path()).build().unwrap();
+    let stages = Arc::new(Mutex::new(Vec::new()));
+
+    let mut md = MDBook::load(temp.path()).unwrap();
+    for &stage in &[
+        BuildStage::AfterRender,
+        BuildStage::BeforeBuild,
+        BuildStage::AfterPreprocess,
+    ] {
+        let stages = Arc::clone(&stages);
+        md.with_hook(stage, move |ctx| {
+            stages
+                .lock()
+                .unwrap()
+                .push((ctx.stage, ctx.renderer.map(String::from)));
+            Ok(())
+        });
+    }
+    md.build().unwrap();
+
+    let html = Some(String::from("html"));
+    assert_eq!(
+        *stages.lock().unwrap(),
+        vec![
+            (BuildStage::BeforeBuild, None),
+            (BuildStage::AfterPreprocess, html.clone()),
+            (BuildStage::AfterRender, html),
+        ]
+    );
+}
+
+#[test]
+#[cfg(not(windows))]
+fn hook_commands_can_post_process_the_output() {
+    let temp = TempDir::new("mdbook").unwrap();
+
+    let mut config = Config::default();
+    config
+        .set("build.hooks.after-render", "touch {destination}/{renderer}.txt")
+        .unwrap();
+
+    let md = MDBook::init(temp.path())
+        .with_config(config)
+        .build()
+        .unwrap();
+    md.build().unwrap();
+
+    assert!(temp.path().join("book").join("html.txt").exists());
+}
+
+#[test]
+fn a_failing_hook_fails_the_build() {
+    let (mut md, _temp) = dummy_book_with_backend("passing", "true");
+    md.with_hook(mdbook::book::BuildStage::BeforeBuild, |_| {
+        Err("Something went wrong".into())
+    });
+
+    md.build().unwrap_err();
+}
@@ -0,0 +1,36 @@
+//! Integration tests for reporting problems through a diagnostics sink.
+
+extern crate mdbook;
+extern crate tempdir;
+
+use std::fs::File;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use mdbook::MDBook;
+use mdbook::diagnostics::{self, Level};
+use tempdir::TempDir;
+
+#[test]
+fn missing_includes_are_reported_with_their_location() {
+    let temp = TempDir::new("mdbook").unwrap();
+    let md = MDBook::init(temp.path()).build().unwrap();
+
+    let chapter = md.source_dir().join("chapter_1.md");
+    File::create(&chapter)
+        .unwrap()
+        .write_all(b"+++\ntags = [\"intro\"]\n+++\n# Chapter 1\n\n{{#include missing.rs}}\n")
+        .unwrap();
+
+    let reported = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&reported);
+    diagnostics::set_sink(move |diagnostic| sink.lock().unwrap().push(diagnostic.clone()));
+
+    MDBook::load(temp.path()).unwrap().build().unwrap();
+
+    let reported = reported.lock().unwrap();
+    assert_eq!(reported.len(), 1);
+    assert_eq!(reported[0].level, Level::Error);
+    assert_eq!(reported[0].file, Some(chapter));
+    // counting the front matter
+    assert_eq!(reported[0].line, Some(6));
+}
@@ -2,9 +2,11 @@ extern crate mdbook;
 extern crate tempdir;
 
 use std::path::PathBuf;
-use std::fs;
+use std::fs::{self, File};
+use std::io::Write;
 use mdbook::MDBook;
 use mdbook::config::Config;
+use mdbook::utils::fs::{file_to_string, write_files};
 use tempdir::TempDir;
 
 
@@ -68,3 +70,106 @@ fn book_toml_isnt_required() {
 
     md.build().unwrap();
 }
+
+#[test]
+fn init_wont_overwrite_existing_files_unless_forced() {
+    let temp = TempDir::new("mdbook").unwrap();
+    File::create(temp.path().join("book.toml"))
+        .unwrap()
+        .write_all(b"[book]\ntitle = \"Mine\"\n")
+        .unwrap();
+
+    assert!(MDBook::init(temp.path()).build().is_err());
+
+    let mut cfg = Config::default();
+    cfg.book.title = Some(String::from("Forced"));
+    let md = MDBook::init(temp.path())
+        .with_config(cfg)
+        .overwrite_existing(true)
+        .build()
+        .unwrap();
+
+    assert_eq!(md.config.book.title, Some(String::from("Forced")));
+}
+
+#[test]
+fn init_keeps_an_existing_summary() {
+    let temp = TempDir::new("mdbook").unwrap();
+    let summary = "# Summary\n\n- [Getting Started](getting-started.md)\n";
+    fs::create_dir(temp.path().join("src")).unwrap();
+    File::create(temp.path().join("src/SUMMARY.md"))
+        .unwrap()
+        .write_all(summary.as_bytes())
+        .unwrap();
+
+    MDBook::init(temp.path()).build().unwrap();
+
+    let got = file_to_string(temp.path().join("src/SUMMARY.md")).unwrap();
+    assert_eq!(got, summary);
+    assert!(temp.path().join("src/getting-started.md").exists());
+    assert!(!temp.path().join("src/chapter_1.md").exists());
+}
+
+#[test]
+fn init_from_a_template() {
+    let template = TempDir::new("template").unwrap();
+    let files = vec![
+        (
+            "book.toml",
+            "# Starter book\n[book]\nsrc = \"content\"\ntitle = \"Template\"\n\n\
+             [output.html]\n# keep the default theme\n",
+        ),
+        ("content/SUMMARY.md", "# Summary\n\n- [Welcome](welcome.md)\n"),
+        ("content/welcome.md", "# Welcome\n"),
+        (".git/HEAD", "ref: refs/heads/master\n"),
+    ];
+    write_files(template.path(), &files).unwrap();
+
+    let temp = TempDir::new("mdbook").unwrap();
+    let mut cfg = Config::default();
+    cfg.book.title = Some(String::from("My Book"));
+
+    let md = MDBook::init(temp.path())
+        .with_config(cfg)
+        .with_template(template.path())
+        .build()
+        .unwrap();
+
+    assert_eq!(md.config.book.src, PathBuf::from("content"));
+    assert_eq!(md.config.book.title, Some(String::from("My Book")));
+    assert!(temp.path().join("content/welcome.md").exists());
+    assert!(!temp.path().join("content/chapter_1.md").exists());
+    assert!(!temp.path().join(".git").exists());
+
+    let book_toml = file_to_string(temp.path().join("book.toml")).unwrap();
+    assert_eq!(
+        book_toml,
+        "# Starter book\n[book]\nsrc = \"content\"\ntitle = \"My Book\"\n\n\
+         [output.html]\n# keep the default theme\n"
+    );
+}
+
+#[test]
+fn a_template_which_makes_a_broken_book_is_an_error() {
+    let template = TempDir::new("template").unwrap();
+    let files = vec![("book.toml", "[build]\npreprocess = [\"nonsense\"]\n")];
+    write_files(template.path(), &files).unwrap();
+
+    let temp = TempDir::new("mdbook").unwrap();
+    let got = MDBook::init(temp.path())
+        .with_template(template.path())
+        .build();
+
+    assert!(got.is_err());
+}
+
+#[test]
+fn init_can_copy_the_theme() {
+    let temp = TempDir::new("mdbook").unwrap();
+
+    MDBook::init(temp.path()).copy_theme(true).build().unwrap();
+
+    for file in &["index.hbs", "book.css", "book.js", "highlight.js"] {
+        assert!(temp.path().join("src/theme").join(file).exists(), "{} is missing", file);
+    }
+}
@@ -18,15 +18,17 @@ use select::document::Document;
 use select::predicate::{Class, Name, Predicate};
 use tempdir::TempDir;
 use mdbook::errors::*;
-use mdbook::utils::fs::file_to_string;
+use mdbook::utils::fs::{file_to_string, write_files};
 use mdbook::config::Config;
-use mdbook::MDBook;
+use mdbook::{BookItem, MDBook};
+use mdbook::book::BuildState;
 
 const BOOK_ROOT: &'static str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/dummy_book");
 const TOC_TOP_LEVEL: &[&'static str] = &[
     "1. First Chapter",
     "2. Second Chapter",
     "Conclusion",
+    "Front Matter",
     "Introduction",
 ];
 const TOC_SECOND_LEVEL: &[&'static str] = &["1.1. Nested Chapter", "1.2. Includes"];
@@ -251,6 +253,55 @@ fn check_spacers() {
     assert_eq!(num_spacers, should_be);
 }
 
+#[test]
+fn spacers_after_nested_chapters_are_not_indented() {
+    let temp = DummyBook::new().build().unwrap();
+    let summary = "# Summary\n\n- [First Chapter](first/index.md)\n    \
+                   - [Nested Chapter](first/nested.md)\n\n---\n\n- [Second Chapter](second.md)\n";
+    fs::File::create(temp.path().join("src").join("SUMMARY.md"))
+        .unwrap()
+        .write_all(summary.as_bytes())
+        .unwrap();
+
+    let md = MDBook::load(temp.path()).unwrap();
+    md.build().unwrap();
+
+    let index = file_to_string(md.build_dir_for("html").join("index.html")).unwrap();
+    let doc = Document::from(index.as_str());
+
+    let top_level_spacers = doc.find(
+        Name("ol")
+            .and(Class("chapter"))
+            .child(Name("li").and(Class("spacer"))),
+    ).count();
+    assert_eq!(top_level_spacers, 1);
+
+    let chapters: Vec<_> = doc.find(Class("chapter").descendant(Name("a")))
+        .map(|elem| elem.text().trim().to_string())
+        .collect();
+    assert!(chapters.contains(&String::from("2. Second Chapter")));
+}
+
+#[test]
+fn hidden_chapters_are_rendered_but_left_out_of_the_sidebar() {
+    let temp = DummyBook::new().build().unwrap();
+    let summary = "# Summary\n\n- [First Chapter](first/index.md)\n\
+                   - [Second Chapter](second.md \"hidden\")\n";
+    fs::File::create(temp.path().join("src").join("SUMMARY.md"))
+        .unwrap()
+        .write_all(summary.as_bytes())
+        .unwrap();
+
+    let md = MDBook::load(temp.path()).unwrap();
+    md.build().unwrap();
+
+    let html_dir = md.build_dir_for("html");
+    assert!(html_dir.join("second.html").exists());
+
+    assert_doesnt_contain_strings(html_dir.join("first").join("index.html"), &["second.html"]);
+    assert_doesnt_contain_strings(html_dir.join("print.html"), &["Second Chapter"]);
+}
+
 /// Ensure building fails if `create-missing` is false and one of the files does
 /// not exist.
 #[test]
@@ -313,6 +364,24 @@ fn able_to_include_files_in_chapters() {
     assert_doesnt_contain_strings(&includes, &["{{#include ../SUMMARY.md::}}"]);
 }
 
+#[test]
+fn the_preprocessed_book_has_its_includes_filled_in() {
+    let temp = DummyBook::new().build().unwrap();
+    let md = MDBook::load(temp.path()).unwrap();
+
+    let book = md.preprocessed_book("html").unwrap();
+
+    let includes = book.iter()
+        .filter_map(|item| match *item {
+            BookItem::Chapter(ref ch) if ch.path.ends_with("includes.md") => Some(ch),
+            _ => None,
+        })
+        .next()
+        .unwrap();
+    assert!(includes.content.contains("[First Chapter]"));
+    assert!(!includes.content.contains("{{#include"));
+}
+
 #[test]
 fn example_book_can_build() {
     let example_book_dir = dummy_book::new_copy_of_example_book().unwrap();
@@ -339,3 +408,229 @@ fn book_with_a_reserved_filename_does_not_build() {
     let got = md.build();
     assert!(got.is_err());
 }
+
+#[test]
+fn front_matter_is_stripped_from_the_rendered_chapter() {
+    let temp = DummyBook::new().build().unwrap();
+    let md = MDBook::load(temp.path()).unwrap();
+
+    let chapter = md.iter()
+        .filter_map(|item| match *item {
+            BookItem::Chapter(ref ch) => Some(ch),
+            _ => None,
+        })
+        .find(|ch| ch.name == "Front Matter")
+        .unwrap();
+    assert_eq!(
+        chapter.front_matter["author"].as_str(),
+        Some("Michael-F-Bryan")
+    );
+
+    md.build().unwrap();
+
+    let chapter_html = temp.path().join("book").join("front_matter.html");
+    assert_contains_strings(&chapter_html, &["some front matter"]);
+    assert_doesnt_contain_strings(&chapter_html, &["+++", "author ="]);
+}
+
+#[test]
+fn multilingual_books_are_built_per_language() {
+    let temp = TempDir::new("mdbook").unwrap();
+    let files = [
+        (
+            "book.toml",
+            "[book]\nmultilingual = true\nlanguage = \"en\"\n\n\
+             [language.en]\nname = \"English\"\n\n\
+             [language.fr]\nname = \"Français\"\ntitle = \"Mon Livre\"\n",
+        ),
+        ("src/en/SUMMARY.md", "- [First](first.md)\n- [Second](second.md)\n"),
+        ("src/en/first.md", "# First"),
+        ("src/en/second.md", "# Second\n\n{{#include snippet.txt}}\n"),
+        ("src/en/snippet.txt", "A shared snippet"),
+        ("src/fr/first.md", "# Premier"),
+    ];
+    write_files(temp.path(), &files).unwrap();
+
+    let md = MDBook::load(temp.path()).unwrap();
+    md.build().unwrap();
+
+    let book_dir = temp.path().join("book");
+    assert_contains_strings(book_dir.join("index.html"), &["en/index.html"]);
+    assert_contains_strings(book_dir.join("en/first.html"), &["First", r#"lang="en""#]);
+    assert_contains_strings(
+        book_dir.join("fr/first.html"),
+        &["Premier", "Mon Livre", r#"value="../fr/first.html""#],
+    );
+    // untranslated chapters fall back to the main language
+    assert_contains_strings(
+        book_dir.join("fr/second.html"),
+        &["Second", "A shared snippet", r#"lang="fr""#],
+    );
+    assert!(!temp.path().join("src/fr/second.md").exists());
+}
+
+#[test]
+fn broken_links_in_included_files_fail_a_strict_build() {
+    let temp = TempDir::new("mdbook").unwrap();
+    let files = [
+        ("src/SUMMARY.md", "- [First](first.md)\n"),
+        ("src/first.md", "# First\n\n{{#include snippet.md}}\n"),
+        ("src/snippet.md", "[Nowhere](missing.md)\n"),
+    ];
+    write_files(temp.path(), &files).unwrap();
+
+    let mut md = MDBook::load(temp.path()).unwrap();
+    md.build().unwrap();
+    assert!(md.with_strict_links(true).build().is_err());
+}
+
+#[test]
+fn the_comments_snippet_is_rendered_after_each_chapter() {
+    let temp = TempDir::new("mdbook").unwrap();
+    let files = [
+        ("book.toml", "[output.html]\ncomments = \"comments.html\"\n"),
+        ("comments.html", "<script src=\"https://giscus.app/client.js\"></script>"),
+        ("src/SUMMARY.md", "- [First](first.md)\n- [Quiet](quiet.md)\n"),
+        ("src/first.md", "# First"),
+        ("src/quiet.md", "+++\ncomments = false\n+++\n# Quiet"),
+    ];
+    write_files(temp.path(), &files).unwrap();
+
+    let md = MDBook::load(temp.path()).unwrap();
+    md.build().unwrap();
+
+    let book_dir = temp.path().join("book");
+    assert_contains_strings(book_dir.join("first.html"), &["giscus.app/client.js"]);
+    assert_doesnt_contain_strings(book_dir.join("quiet.html"), &["giscus.app"]);
+    assert_doesnt_contain_strings(book_dir.join("print.html"), &["giscus.app"]);
+}
+
+#[test]
+fn books_in_privacy_mode_only_load_their_own_files() {
+    let temp = TempDir::new("mdbook").unwrap();
+    let files = [
+        ("book.toml", "[output.html]\nprivacy = true\ngoogle-analytics = \"UA-123\"\n"),
+        ("src/SUMMARY.md", "- [First](first.md)\n"),
+        ("src/first.md", "# First\n\n[A link elsewhere](https://example.com/)"),
+    ];
+    write_files(temp.path(), &files).unwrap();
+
+    let md = MDBook::load(temp.path()).unwrap();
+    md.build().unwrap();
+
+    let book_dir = temp.path().join("book");
+    assert_contains_strings(book_dir.join("first.html"), &["fonts/fonts.css"]);
+    assert_doesnt_contain_strings(
+        book_dir.join("first.html"),
+        &["fonts.googleapis.com", "cdn.jsdelivr.net", "googletagmanager"],
+    );
+    assert!(book_dir.join("fonts/open-sans-v17-all-charsets-regular.woff2").exists());
+
+    // an image from another site is a request to it
+    fs::File::create(temp.path().join("src/first.md"))
+        .unwrap()
+        .write_all(b"# First\n\n![Logo](https://example.com/logo.png)")
+        .unwrap();
+    let md = MDBook::load(temp.path()).unwrap();
+    assert!(md.build().is_err());
+}
+
+#[test]
+fn mathjax_cant_be_used_in_privacy_mode() {
+    let temp = TempDir::new("mdbook").unwrap();
+    let files = [
+        ("book.toml", "[output.html]\nprivacy = true\nmathjax-support = true\n"),
+        ("src/SUMMARY.md", "- [First](first.md)\n"),
+        ("src/first.md", "# First\n\n\\\\( x^2 \\\\)"),
+    ];
+    write_files(temp.path(), &files).unwrap();
+
+    let md = MDBook::load(temp.path()).unwrap();
+    let err = md.build().unwrap_err();
+
+    let message = err.iter().map(|e| e.to_string()).collect::<Vec<_>>().join(": ");
+    assert!(message.contains("mathjax-support"), "{}", message);
+}
+
+#[test]
+fn load_the_outline_without_building_or_creating_anything() {
+    let temp = DummyBook::new().build().unwrap();
+    fs::remove_file(temp.path().join("src/second.md")).unwrap();
+
+    // a missing chapter is an error rather than being created
+    assert!(mdbook::book::load_outline(temp.path()).is_err());
+    assert!(!temp.path().join("src/second.md").exists());
+
+    let temp = DummyBook::new().build().unwrap();
+    let outline = mdbook::book::load_outline(temp.path()).unwrap();
+
+    let names: Vec<_> = outline.chapters.iter().map(|ch| ch.name.as_str()).collect();
+    assert_eq!(
+        names,
+        vec![
+            "Introduction",
+            "First Chapter",
+            "Nested Chapter",
+            "Includes",
+            "Second Chapter",
+            "Conclusion",
+            "Front Matter",
+        ]
+    );
+
+    let nested = &outline.chapters[2];
+    assert_eq!(nested.depth, 1);
+    assert_eq!(nested.parent, Some(1));
+    assert_eq!(nested.number.as_ref().unwrap().to_string(), "1.1.");
+    assert_eq!(nested.source_path, temp.path().join("src/first/nested.md"));
+}
+
+#[test]
+fn incremental_builds_only_rerender_changed_chapters() {
+    let temp = DummyBook::new().build().unwrap();
+    let mut state = BuildState::new();
+    MDBook::load(temp.path()).unwrap().build_incremental(&mut state).unwrap();
+
+    // mark a page which should be left alone by the next build
+    let intro = temp.path().join("book/intro.html");
+    let mut f = fs::OpenOptions::new().append(true).open(&intro).unwrap();
+    writeln!(f, "<!-- left alone -->").unwrap();
+
+    let mut f = fs::OpenOptions::new()
+        .append(true)
+        .open(temp.path().join("src/second.md"))
+        .unwrap();
+    writeln!(f, "\nSome freshly written text").unwrap();
+
+    MDBook::load(temp.path()).unwrap().build_incremental(&mut state).unwrap();
+
+    assert_contains_strings(&intro, &["<!-- left alone -->"]);
+    let second = temp.path().join("book/second.html");
+    assert_contains_strings(&second, &["Some freshly written text"]);
+    let print = temp.path().join("book/print.html");
+    assert_contains_strings(&print, &["Some freshly written text"]);
+}
+
+#[test]
+fn chapters_are_found_where_the_html_renderer_put_them() {
+    let temp = TempDir::new("mdbook").unwrap();
+    let files = [
+        ("book.toml", "[output.html]\npretty-urls = true\n"),
+        ("src/SUMMARY.md", "- [Intro](README.md)\n- [Guide](guide/usage.md)\n"),
+        ("src/README.md", "# Intro"),
+        ("src/guide/usage.md", "# Usage"),
+    ];
+    write_files(temp.path(), &files).unwrap();
+
+    let md = MDBook::load(temp.path()).unwrap();
+    md.build().unwrap();
+
+    for (chapter, page) in vec![
+        ("README.md", "index.html"),
+        ("guide/usage.md", "guide/usage/index.html"),
+    ] {
+        let page_for = md.html_page_for(Path::new(chapter));
+        assert_eq!(page_for, Path::new(page));
+        assert!(temp.path().join("book").join(page_for).exists());
+    }
+}
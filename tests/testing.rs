@@ -28,6 +28,24 @@ fn mdbook_detects_book_with_failing_tests() {
     assert!(md.test(vec![]).is_err());
 }
 
+#[test]
+fn externs_need_a_name_and_a_path() {
+    let temp = DummyBook::new().with_passing_test(true).build().unwrap();
+    let mut md = MDBook::load(temp.path()).unwrap();
+
+    assert!(md.test_with(vec![], vec!["serde"], None).is_err());
+}
+
+#[test]
+fn test_a_single_chapter() {
+    let temp = DummyBook::new().with_passing_test(false).build().unwrap();
+    let mut md = MDBook::load(temp.path()).unwrap();
+
+    assert!(md.test_with(vec![], vec![], Some("Introduction")).is_ok());
+    assert!(md.test_with(vec![], vec![], Some("src/intro.md")).is_ok());
+    assert!(md.test_with(vec![], vec![], Some("Nonexistent")).is_err());
+}
+
 #[test]
 fn mdbook_runs_preprocessors() {
 
@@ -50,8 +68,39 @@ fn mdbook_runs_preprocessors() {
     let cfg = Config::default();
 
     let mut book = MDBook::load_with_config(temp.path(), cfg).unwrap();
-    book.with_preprecessor(DummyPreprocessor(Arc::clone(&has_run)));
+    book.with_preprocessor(DummyPreprocessor(Arc::clone(&has_run)));
     book.build().unwrap();
 
     assert!(*has_run.lock().unwrap())
 }
+
+#[test]
+fn preprocessors_are_skipped_for_renderers_they_dont_support() {
+    struct HtmlOnly(Arc<Mutex<bool>>);
+
+    impl Preprocessor for HtmlOnly {
+        fn name(&self) -> &str {
+            "html-only"
+        }
+
+        fn run(&self, _ctx: &PreprocessorContext, _book: &mut Book) -> Result<()> {
+            *self.0.lock().unwrap() = true;
+            Ok(())
+        }
+
+        fn supports_renderer(&self, renderer: &str) -> bool {
+            renderer == "html"
+        }
+    }
+
+    let has_run: Arc<Mutex<bool>> = Arc::new(Mutex::new(false));
+    let temp = DummyBook::new().build().unwrap();
+    let mut cfg = Config::default();
+    cfg.set("output.passing.command", "true").unwrap();
+
+    let mut book = MDBook::load_with_config(temp.path(), cfg).unwrap();
+    book.with_preprocessor(HtmlOnly(Arc::clone(&has_run)));
+    book.build().unwrap();
+
+    assert!(!*has_run.lock().unwrap())
+}
@@ -0,0 +1,73 @@
+//! Integration tests for building several books as a workspace.
+
+extern crate mdbook;
+extern crate tempdir;
+
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use mdbook::MDBook;
+use mdbook::config::Config;
+use mdbook::utils::fs::file_to_string;
+use mdbook::workspace::{Workspace, MANIFEST};
+use tempdir::TempDir;
+
+fn workspace_with_books(names: &[&str]) -> TempDir {
+    let temp = TempDir::new("mdbook").unwrap();
+
+    for name in names {
+        let mut cfg = Config::default();
+        cfg.book.title = Some(format!("The {} book", name));
+        MDBook::init(temp.path().join(name))
+            .with_config(cfg)
+            .build()
+            .unwrap();
+    }
+
+    let books: Vec<String> = names.iter().map(|name| format!("{:?}", name)).collect();
+    let manifest = format!(
+        "title = \"Docs\"\nbooks = [{}]\nbuild-dir = \"site\"\n",
+        books.join(", ")
+    );
+    File::create(temp.path().join(MANIFEST))
+        .unwrap()
+        .write_all(manifest.as_bytes())
+        .unwrap();
+
+    temp
+}
+
+fn assert_workspace_was_built(root: &Path) {
+    let site = root.join("site");
+    assert!(site.join("first/index.html").exists());
+    assert!(site.join("second/index.html").exists());
+
+    let landing_page = file_to_string(site.join("index.html")).unwrap();
+    assert!(landing_page.contains("<a href=\"first/index.html\">The first book</a>"));
+    assert!(landing_page.contains("<a href=\"second/index.html\">The second book</a>"));
+}
+
+#[test]
+fn build_every_book_in_a_workspace() {
+    let temp = workspace_with_books(&["first", "second"]);
+
+    Workspace::load(temp.path()).unwrap().build(false).unwrap();
+
+    assert_workspace_was_built(temp.path());
+}
+
+#[test]
+fn build_a_workspace_in_parallel() {
+    let temp = workspace_with_books(&["first", "second"]);
+
+    Workspace::load(temp.path()).unwrap().build(true).unwrap();
+
+    assert_workspace_was_built(temp.path());
+}
+
+#[test]
+fn a_workspace_needs_at_least_one_book() {
+    let temp = workspace_with_books(&[]);
+
+    assert!(Workspace::load(temp.path()).is_err());
+}